@@ -0,0 +1,555 @@
+use crate::ffmpeg::{FFmpegError, ffmpeg_output, format_seconds};
+use anyhow::{Result, bail};
+use regex::Regex;
+
+/// Offsets (in milliseconds) below this are treated as already in sync by default.
+pub const DEFAULT_IN_SYNC_THRESHOLD_MS: f64 = 20.0;
+
+/// Sample rate used when decoding audio for analysis (mono, 32-bit float).
+pub const ANALYSIS_SAMPLE_RATE: u32 = 8000;
+
+/// How much of each track (in seconds, from the start) is decoded for alignment.
+pub const ALIGNMENT_WINDOW_SECONDS: f64 = 600.0;
+
+/// Length of one energy window used by the transient detector.
+const TRANSIENT_WINDOW_MS: f64 = 10.0;
+/// Number of preceding windows used as the background level for a transient.
+const TRANSIENT_BACKGROUND_WINDOWS: usize = 20;
+/// Minimum jump (in dB) over the background level to count as a transient.
+const TRANSIENT_MIN_JUMP_DB: f64 = 12.0;
+/// Transients quieter than this (in dBFS) are ignored.
+const TRANSIENT_FLOOR_DB: f64 = -45.0;
+/// Minimum distance between two transients.
+const TRANSIENT_REFRACTORY_MS: f64 = 250.0;
+/// Only the strongest transients of each track take part in matching.
+const MAX_TRANSIENTS: usize = 60;
+/// Two transients match if they are at most this far apart after shifting.
+const TRANSIENT_MATCH_TOLERANCE_MS: f64 = 20.0;
+/// Largest offset that is considered when matching transients.
+const MAX_ALIGNMENT_OFFSET_SECONDS: f64 = 30.0;
+/// Minimum number of matched transients for an offset to be trusted.
+const MIN_TRANSIENT_MATCHES: usize = 3;
+/// Length of one loudness window of the envelopes that are cross-correlated.
+const CORRELATION_WINDOW_MS: f64 = 10.0;
+/// Envelope levels below this (in dBFS) are clamped, so silence of any depth looks the same.
+const CORRELATION_FLOOR_DB: f64 = -90.0;
+/// Minimum share of the shorter envelope that has to overlap at a candidate offset.
+const MIN_CORRELATION_OVERLAP: f64 = 0.5;
+/// Minimum correlation of the envelopes for an offset to be trusted.
+const MIN_CORRELATION: f64 = 0.4;
+/// Audio (in seconds) around the coarse offset whose samples refine it below one window.
+const CORRELATION_REFINE_SECONDS: f64 = 60.0;
+/// Audio louder than this (in dBFS) counts as the start of the content.
+const AUDIBLE_FLOOR_DB: f64 = -50.0;
+/// Minimum length of leading black video (in seconds) to be treated as the picture start.
+const MIN_BLACK_SECONDS: f64 = 0.1;
+
+/// A sharp onset (clap, beep, slate) found in a track.
+#[derive(Debug, Clone, Copy)]
+pub struct Transient {
+    pub time: f64,
+    /// Jump over the background level in dB
+    pub strength: f64,
+}
+
+/// Result of matching the transients of a target against a reference track.
+#[derive(Debug)]
+pub struct TransientAlignment {
+    /// Delay (in milliseconds) to apply to the target so it lines up with the reference
+    pub offset_ms: f64,
+    pub matches: usize,
+    pub target_transients: usize,
+    pub reference_transients: usize,
+}
+
+/// Decode `duration` seconds of an audio stream, starting at `start`, to mono PCM samples.
+/// `map` is an ffmpeg stream specifier for input 0 (e.g. `0:6` or `0:a:0`).
+pub fn decode_pcm_mono(
+    input: &str,
+    map: &str,
+    start: f64,
+    duration: f64,
+) -> Result<Vec<f32>, FFmpegError> {
+    decode_pcm_mono_at(input, map, start, duration, ANALYSIS_SAMPLE_RATE)
+}
+
+/// Like `decode_pcm_mono`, but resampled to `sample_rate` instead of the analysis rate.
+pub fn decode_pcm_mono_at(
+    input: &str,
+    map: &str,
+    start: f64,
+    duration: f64,
+    sample_rate: u32,
+) -> Result<Vec<f32>, FFmpegError> {
+    let output = ffmpeg_output(
+        &[
+            "-v",
+            "error",
+            "-ss",
+            &format_seconds(start),
+            "-t",
+            &format_seconds(duration),
+            "-i",
+            input,
+            "-map",
+            map,
+            "-ac",
+            "1",
+            "-ar",
+            &sample_rate.to_string(),
+            "-f",
+            "f32le",
+            "-",
+        ],
+        false,
+    )?;
+
+    if !output.status.success() {
+        return Err(FFmpegError::CommandFailed(
+            "decode_pcm_mono".to_string(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// Find sharp onsets (claps, beeps, slates) in mono PCM samples.
+/// Returns at most the strongest `MAX_TRANSIENTS` transients, sorted by time.
+pub fn detect_transients(samples: &[f32], sample_rate: u32) -> Vec<Transient> {
+    let window = ((sample_rate as f64 * TRANSIENT_WINDOW_MS / 1000.0) as usize).max(1);
+    let energies: Vec<f64> = samples
+        .chunks(window)
+        .map(|w| w.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>() / w.len() as f64)
+        .collect();
+
+    let to_db = |e: f64| 10.0 * e.max(1e-12).log10();
+    let refractory = (TRANSIENT_REFRACTORY_MS / TRANSIENT_WINDOW_MS) as usize;
+
+    let mut transients: Vec<Transient> = Vec::new();
+    let mut last_onset: Option<usize> = None;
+    for i in TRANSIENT_BACKGROUND_WINDOWS..energies.len() {
+        if last_onset.is_some_and(|last| i - last < refractory) {
+            continue;
+        }
+        let background = energies[i - TRANSIENT_BACKGROUND_WINDOWS..i]
+            .iter()
+            .sum::<f64>()
+            / TRANSIENT_BACKGROUND_WINDOWS as f64;
+        let level = to_db(energies[i]);
+        let jump = level - to_db(background);
+        if level > TRANSIENT_FLOOR_DB && jump >= TRANSIENT_MIN_JUMP_DB {
+            transients.push(Transient {
+                time: (i * window) as f64 / sample_rate as f64,
+                strength: jump,
+            });
+            last_onset = Some(i);
+        }
+    }
+
+    transients.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+    transients.truncate(MAX_TRANSIENTS);
+    transients.sort_by(|a, b| a.time.total_cmp(&b.time));
+    transients
+}
+
+/// Count how many target transients have a reference transient within tolerance after shifting by `offset`.
+/// Returns the number of matches and the sum of the exact pair offsets.
+fn score_offset(target: &[Transient], reference: &[Transient], offset: f64) -> (usize, f64) {
+    let tolerance = TRANSIENT_MATCH_TOLERANCE_MS / 1000.0;
+    let mut matches = 0;
+    let mut offset_sum = 0.0;
+    for t in target {
+        let shifted = t.time + offset;
+        if let Some(r) = reference
+            .iter()
+            .filter(|r| (r.time - shifted).abs() <= tolerance)
+            .min_by(|a, b| {
+                (a.time - shifted)
+                    .abs()
+                    .total_cmp(&(b.time - shifted).abs())
+            })
+        {
+            matches += 1;
+            offset_sum += r.time - t.time;
+        }
+    }
+    (matches, offset_sum)
+}
+
+/// Find the offset that lines up the most transients of `target` with those of `reference`.
+pub fn match_transients(
+    target: &[Transient],
+    reference: &[Transient],
+) -> Result<TransientAlignment> {
+    let mut best: Option<(usize, f64, f64)> = None;
+    for t in target {
+        for r in reference {
+            let candidate = r.time - t.time;
+            if candidate.abs() > MAX_ALIGNMENT_OFFSET_SECONDS {
+                continue;
+            }
+            let (matches, offset_sum) = score_offset(target, reference, candidate);
+            let better = match best {
+                None => true,
+                Some((best_matches, best_candidate, _)) => {
+                    matches > best_matches
+                        || (matches == best_matches && candidate.abs() < best_candidate.abs())
+                }
+            };
+            if better {
+                best = Some((matches, candidate, offset_sum));
+            }
+        }
+    }
+
+    match best {
+        Some((matches, _, offset_sum)) if matches >= MIN_TRANSIENT_MATCHES => {
+            Ok(TransientAlignment {
+                offset_ms: offset_sum / matches as f64 * 1000.0,
+                matches,
+                target_transients: target.len(),
+                reference_transients: reference.len(),
+            })
+        }
+        _ => bail!(
+            "Could not match transients between target ({} found) and reference ({} found). At least {} matching sync marks are required.",
+            target.len(),
+            reference.len(),
+            MIN_TRANSIENT_MATCHES
+        ),
+    }
+}
+
+/// Result of cross-correlating the loudness envelopes of a target and a reference track.
+#[derive(Debug)]
+pub struct CorrelationAlignment {
+    /// Delay (in milliseconds) to apply to the target so it lines up with the reference
+    pub offset_ms: f64,
+    /// Correlation of the envelopes at that offset (1.0 is a perfect match)
+    pub correlation: f64,
+}
+
+/// How the offset to a reference track was found.
+#[derive(Debug)]
+pub enum ReferenceAlignment {
+    Transients(TransientAlignment),
+    Correlation(CorrelationAlignment),
+}
+
+impl ReferenceAlignment {
+    /// Delay (in milliseconds) to apply to the target so it lines up with the reference
+    pub fn offset_ms(&self) -> f64 {
+        match self {
+            ReferenceAlignment::Transients(alignment) => alignment.offset_ms,
+            ReferenceAlignment::Correlation(alignment) => alignment.offset_ms,
+        }
+    }
+
+    /// Short description of the evidence, e.g. "12 transient matches".
+    pub fn describe(&self) -> String {
+        match self {
+            ReferenceAlignment::Transients(alignment) => {
+                format!("{} transient matches", alignment.matches)
+            }
+            ReferenceAlignment::Correlation(alignment) => {
+                format!("cross-correlation {:.2}", alignment.correlation)
+            }
+        }
+    }
+
+    /// How sure the alignment is, from 0 to 1: the share of the sync marks of the sparser track
+    /// that found a partner, or the correlation of the loudness envelopes.
+    pub fn confidence(&self) -> f64 {
+        match self {
+            ReferenceAlignment::Transients(alignment) => {
+                let marks = alignment
+                    .target_transients
+                    .min(alignment.reference_transients)
+                    .max(1);
+                (alignment.matches as f64 / marks as f64).clamp(0.0, 1.0)
+            }
+            ReferenceAlignment::Correlation(alignment) => alignment.correlation.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Loudness envelope (in dB, one value per `CORRELATION_WINDOW_MS`) with its mean removed.
+fn loudness_envelope(samples: &[f32], window: usize) -> Vec<f64> {
+    let levels: Vec<f64> = samples
+        .chunks(window)
+        .map(|w| {
+            let energy = w.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>() / w.len() as f64;
+            (10.0 * energy.max(1e-12).log10()).max(CORRELATION_FLOOR_DB)
+        })
+        .collect();
+    let mean = levels.iter().sum::<f64>() / levels.len().max(1) as f64;
+    levels.into_iter().map(|l| l - mean).collect()
+}
+
+/// Pearson correlation of `target[i]` with `reference[i + lag]` over their overlap, or None if
+/// they overlap by fewer than `min_overlap` values or either side is constant.
+fn lagged_correlation(
+    target: &[f64],
+    reference: &[f64],
+    lag: i64,
+    min_overlap: usize,
+) -> Option<f64> {
+    let start = (-lag).max(0) as usize;
+    let end = (reference.len() as i64 - lag).min(target.len() as i64);
+    if end <= start as i64 || ((end as usize) - start) < min_overlap.max(2) {
+        return None;
+    }
+    let end = end as usize;
+    let n = (end - start) as f64;
+    let (mut sx, mut sy, mut sxx, mut syy, mut sxy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for i in start..end {
+        let x = target[i];
+        let y = reference[(i as i64 + lag) as usize];
+        sx += x;
+        sy += y;
+        sxx += x * x;
+        syy += y * y;
+        sxy += x * y;
+    }
+    let variance = (sxx - sx * sx / n) * (syy - sy * sy / n);
+    (variance > 0.0).then(|| (sxy - sx * sy / n) / variance.sqrt())
+}
+
+/// Lag in `lags` with the highest correlation, as `(lag, correlation)`.
+fn best_lag(
+    target: &[f64],
+    reference: &[f64],
+    lags: std::ops::RangeInclusive<i64>,
+    min_overlap: usize,
+) -> Option<(i64, f64)> {
+    lags.filter_map(|lag| lagged_correlation(target, reference, lag, min_overlap).map(|c| (lag, c)))
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.abs().cmp(&a.0.abs())))
+}
+
+/// Find the offset between two tracks of the same content by cross-correlating their loudness
+/// envelopes within `MAX_ALIGNMENT_OFFSET_SECONDS`, then refine it on the samples themselves.
+pub fn correlate_offset(
+    target: &[f32],
+    reference: &[f32],
+    sample_rate: u32,
+) -> Result<CorrelationAlignment> {
+    let window = ((sample_rate as f64 * CORRELATION_WINDOW_MS / 1000.0) as usize).max(1);
+    let target_envelope = loudness_envelope(target, window);
+    let reference_envelope = loudness_envelope(reference, window);
+    let max_lag = (MAX_ALIGNMENT_OFFSET_SECONDS * 1000.0 / CORRELATION_WINDOW_MS) as i64;
+    let min_overlap = (target_envelope.len().min(reference_envelope.len()) as f64
+        * MIN_CORRELATION_OVERLAP) as usize;
+    let Some((coarse, correlation)) = best_lag(
+        &target_envelope,
+        &reference_envelope,
+        -max_lag..=max_lag,
+        min_overlap,
+    ) else {
+        bail!("The target and reference audio are too short to cross-correlate.");
+    };
+    if correlation < MIN_CORRELATION {
+        bail!(
+            "The loudness of the target and reference audio does not line up at any offset within ±{} s (best correlation {:.2}, at least {:.2} is required).",
+            MAX_ALIGNMENT_OFFSET_SECONDS,
+            correlation,
+            MIN_CORRELATION
+        );
+    }
+
+    // Search one window either side of the coarse offset on a stretch of the raw samples
+    let coarse_samples = coarse * window as i64;
+    let refine_len = (CORRELATION_REFINE_SECONDS * sample_rate as f64) as usize;
+    let target_start =
+        ((target.len().saturating_sub(refine_len)) / 2).max((-coarse_samples).max(0) as usize);
+    let target_end = (target_start + refine_len).min(target.len());
+    let excerpt: Vec<f64> = target[target_start.min(target_end)..target_end]
+        .iter()
+        .map(|s| *s as f64)
+        .collect();
+    let full_reference: Vec<f64> = reference.iter().map(|s| *s as f64).collect();
+    let shift = target_start as i64;
+    let offset_samples = best_lag(
+        &excerpt,
+        &full_reference,
+        coarse_samples + shift - window as i64..=coarse_samples + shift + window as i64,
+        excerpt.len() / 2,
+    )
+    .map(|(lag, _)| lag - shift)
+    .unwrap_or(coarse_samples);
+
+    Ok(CorrelationAlignment {
+        offset_ms: offset_samples as f64 / sample_rate as f64 * 1000.0,
+        correlation,
+    })
+}
+
+/// Line a target up with a reference track: by matching sync marks (claps, beeps, slates) when
+/// both have enough of them, otherwise by cross-correlating their loudness envelopes.
+pub fn align_to_reference(
+    target: &str,
+    target_map: &str,
+    reference: &str,
+    reference_map: &str,
+) -> Result<ReferenceAlignment> {
+    let target_samples = decode_pcm_mono(target, target_map, 0.0, ALIGNMENT_WINDOW_SECONDS)?;
+    let reference_samples =
+        decode_pcm_mono(reference, reference_map, 0.0, ALIGNMENT_WINDOW_SECONDS)?;
+    let target_transients = detect_transients(&target_samples, ANALYSIS_SAMPLE_RATE);
+    let reference_transients = detect_transients(&reference_samples, ANALYSIS_SAMPLE_RATE);
+    match match_transients(&target_transients, &reference_transients) {
+        Ok(alignment) => Ok(ReferenceAlignment::Transients(alignment)),
+        Err(transient_error) => {
+            correlate_offset(&target_samples, &reference_samples, ANALYSIS_SAMPLE_RATE)
+                .map(ReferenceAlignment::Correlation)
+                .map_err(|e| anyhow::anyhow!("{} {}", transient_error, e))
+        }
+    }
+}
+
+/// Result of lining up the first onsets of a target and a reference stream.
+#[derive(Debug)]
+pub struct OnsetAlignment {
+    pub target_onset: f64,
+    pub reference_onset: f64,
+    /// Delay (in milliseconds) to apply to the target so its first onset lines up with the reference
+    pub offset_ms: f64,
+}
+
+/// Detect clap/beep-like transients in the target and reference audio and derive the offset between them.
+pub fn align_by_transients(
+    target: &str,
+    target_map: &str,
+    reference: &str,
+    reference_map: &str,
+) -> Result<TransientAlignment> {
+    let target_samples = decode_pcm_mono(target, target_map, 0.0, ALIGNMENT_WINDOW_SECONDS)?;
+    let reference_samples =
+        decode_pcm_mono(reference, reference_map, 0.0, ALIGNMENT_WINDOW_SECONDS)?;
+    let target_transients = detect_transients(&target_samples, ANALYSIS_SAMPLE_RATE);
+    let reference_transients = detect_transients(&reference_samples, ANALYSIS_SAMPLE_RATE);
+    match_transients(&target_transients, &reference_transients)
+}
+
+/// Time (in seconds) of the first window of `samples` louder than `AUDIBLE_FLOOR_DB`.
+fn first_audible(samples: &[f32], sample_rate: u32) -> Option<f64> {
+    let window = ((sample_rate as f64 * TRANSIENT_WINDOW_MS / 1000.0) as usize).max(1);
+    samples
+        .chunks(window)
+        .position(|w| {
+            let energy = w.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>() / w.len() as f64;
+            10.0 * energy.max(1e-12).log10() > AUDIBLE_FLOOR_DB
+        })
+        .map(|i| (i * window) as f64 / sample_rate as f64)
+}
+
+/// Time (in seconds) of the first audio louder than `AUDIBLE_FLOOR_DB` within the alignment window,
+/// or `None` if the stream stays silent. `map` is an ffmpeg stream specifier for input 0.
+pub fn first_audible_time(input: &str, map: &str) -> Result<Option<f64>> {
+    let samples = decode_pcm_mono(input, map, 0.0, ALIGNMENT_WINDOW_SECONDS)?;
+    Ok(first_audible(&samples, ANALYSIS_SAMPLE_RATE))
+}
+
+/// Time (in seconds) of the first onset of a stream within the alignment window: its first
+/// transient, or the first audible content if it has no sharp onsets.
+pub fn first_onset_time(input: &str, map: &str) -> Result<Option<f64>> {
+    let samples = decode_pcm_mono(input, map, 0.0, ALIGNMENT_WINDOW_SECONDS)?;
+    Ok(detect_transients(&samples, ANALYSIS_SAMPLE_RATE)
+        .first()
+        .map(|t| t.time)
+        .or_else(|| first_audible(&samples, ANALYSIS_SAMPLE_RATE)))
+}
+
+/// Time (in seconds) at which the picture of the first video stream starts: the end of the
+/// leading black frames, or 0 if the video does not start black.
+pub fn first_picture_time(input: &str) -> Result<f64> {
+    let output = ffmpeg_output(
+        &[
+            "-t",
+            &format_seconds(ALIGNMENT_WINDOW_SECONDS),
+            "-i",
+            input,
+            "-map",
+            "0:v:0",
+            "-vf",
+            &format!(
+                "blackdetect=d={}:pix_th=0.10",
+                format_seconds(MIN_BLACK_SECONDS)
+            ),
+            "-an",
+            "-f",
+            "null",
+            "-",
+        ],
+        false,
+    )?;
+    if !output.status.success() {
+        bail!(
+            "Could not analyse the video of '{}': {}",
+            input,
+            output.last_error_line()
+        );
+    }
+    let black = Regex::new(r"black_start:\s*([0-9.]+)\s+black_end:\s*([0-9.]+)")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(black
+        .captures(&stderr)
+        .and_then(|c| {
+            let start: f64 = c[1].parse().ok()?;
+            let end: f64 = c[2].parse().ok()?;
+            (start < MIN_BLACK_SECONDS).then_some(end)
+        })
+        .unwrap_or(0.0))
+}
+
+/// Integrated loudness (in LUFS) of an audio stream, measured over the whole stream with ebur128.
+/// `map` is an ffmpeg stream specifier for input 0.
+pub fn integrated_loudness(input: &str, map: &str) -> Result<f64> {
+    let output = ffmpeg_output(
+        &[
+            "-i", input, "-map", map, "-af", "ebur128", "-f", "null", "-",
+        ],
+        false,
+    )?;
+    if !output.status.success() {
+        bail!(
+            "Could not measure the loudness of '{}': {}",
+            input,
+            output.last_error_line()
+        );
+    }
+    let summary = Regex::new(r"I:\s+(-?[0-9.]+) LUFS")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match summary
+        .captures_iter(&stderr)
+        .last()
+        .and_then(|c| c[1].parse::<f64>().ok())
+    {
+        Some(loudness) => Ok(loudness),
+        None => bail!("ffmpeg reported no integrated loudness for '{}'.", input),
+    }
+}
+
+/// Derive the offset between the first onsets of a target and a reference stream.
+pub fn align_by_onset(
+    target: &str,
+    target_map: &str,
+    reference: &str,
+    reference_map: &str,
+) -> Result<OnsetAlignment> {
+    let Some(target_onset) = first_onset_time(target, target_map)? else {
+        bail!("No onset found in the target audio stream.");
+    };
+    let Some(reference_onset) = first_onset_time(reference, reference_map)? else {
+        bail!("No onset found in the reference audio stream.");
+    };
+    Ok(OnsetAlignment {
+        target_onset,
+        reference_onset,
+        offset_ms: (reference_onset - target_onset) * 1000.0,
+    })
+}
@@ -1,116 +1,1518 @@
+use crate::analysis::{
+    DEFAULT_IN_SYNC_THRESHOLD_MS, ReferenceAlignment, align_by_onset, align_to_reference,
+    first_audible_time, first_picture_time, integrated_loudness,
+};
 use crate::audio_metadata::{
+    AudioProperties, AudioStream, StreamFormat, find_audio_stream_by_tags, find_main_audio_stream,
     get_audio_stream_duration, get_file_duration, get_stream_bitrate_for_processing,
-    inspect_audio_streams, probe_audio_stream,
+    get_video_frame_rate, inspect_audio_streams, language_warnings, probe_audio_properties,
+    probe_audio_stream, verify_encoded_track,
 };
 use crate::audio_processing::{
-    concat_audio_segments, convert_audio_codec, extract_audio_stream_to_flac, find_quietest_point,
-    fit_audio_to_length, remux_audio_stream, split_and_delay_audio,
+    DEFAULT_AUTO_THRESHOLD_MARGIN_DB, Delay, MuxerTimestamps, QuietCandidate, QuietestPointResult,
+    RemuxOptions, ReplacedAudio, RunOptions, concat_audio_segments, convert_audio_codec,
+    correct_drift, default_segment_jobs, edit_audio, extract_audio_range_to_flac,
+    extract_audio_stream_to_flac, fit_audio_to_length, intermediate_file, is_lossless_codec,
+    measure_head_peak, measure_noise_floor, pad_audio_head, remux_audio_stream,
+    remux_audio_streams, render_split_waveform, scan_quiet_candidates, shift_video_stream,
+    simulate_segments, split_and_delay_audio, step_samples, str_args,
+};
+use crate::breaks::{Break, detect_black_intervals, find_breaks};
+use crate::channels::{ChannelReport, analyze_channels, remap_channels};
+use crate::chapters::{read_chapters, retime_chapters, write_chapters_metadata};
+use crate::checkpoint::{
+    LOCK_FILE, LiveRun, Manifest, ResolvedSplit, evict_failed_runs, failed_runs, find_resumable,
+    job_fingerprint,
+};
+use crate::container::{
+    audio_extension_for_codec, count_streams, default_export_path, export_audio_track,
+    find_incompatible_streams, find_timestamp_shifts, refresh_container_stats, verify_muxed_output,
+};
+use crate::detector::{Detector, build_detector, find_split_point, parse_detector_command};
+use crate::doctor::{CheckStatus, DoctorOptions, report_text, run_checks};
+use crate::exit_code::RunError;
+use crate::extents::measure_extents;
+use crate::history::{
+    EditRecord, format_utc, history_tag, read_history, sidecar_path, supports_track_tags,
 };
-use crate::util::path_to_str;
+use crate::hooks::{HookContext, TaskHooks, run_hook};
+use crate::i18n::{Lang, Msg, is_yes, set_lang, t, tf};
+use crate::import::parse_corrections;
+use crate::language::normalize_or_warn;
+use crate::logging::{self, LOG};
+use crate::media_server::MediaServer;
+use crate::plan::PlanReport;
+use crate::porcelain;
+use crate::project::{PROJECT_FILE, Project};
+use crate::qc::{ArtifactKind, scan_artifacts};
+use crate::season::{SeasonTemplate, find_episodes, resolve_episode};
+use crate::silence::{Silence, detect_silences};
+use crate::split_map::SplitMap;
+use crate::subtitles::{
+    Timeline, external_subtitle_output, is_supported_subtitle_file, list_subtitle_streams,
+    retime_embedded_subtitle, retime_subtitle_file,
+};
+use crate::temp_usage::{TempUsage, dir_size, format_bytes};
+use crate::util::{
+    StagedFile, create_numbered_temp_dir, create_temp_dir, ensure_not_overwriting, move_dir,
+    path_to_str, render_template, same_file, temp_root, usable_temp_root, write_file_atomic,
+};
+use crate::watch::{FolderWatcher, JobQueue, PRIORITY_EXTENSION, job_priority, move_into};
 use crate::{
-    cli::Args,
-    ffmpeg::{check_dependency, check_ffmpeg_installation, check_ffmpeg_version},
+    cli::{
+        ApplySeasonArgs, Args, CacheCommand, Cli, Command, CompareArgs, DetectOffsetArgs,
+        DetectorKind, DoctorArgs, FrameUnits, ImportArgs, ProbeQuietArgs, ProjectCommand,
+        SplitPoint, StreamArgs, StreamSpecifier, SuggestSplitsArgs, WatchArgs,
+    },
+    ffmpeg::{
+        ChildSettings, FFmpegError, check_dependency, check_ffmpeg_installation,
+        check_ffmpeg_version, ffprobe_executable, run_ffmpeg,
+    },
+    nudger::{SyncOptions, SyncReport, TrackReport},
+    progress::{
+        CancellationToken, PipelineHooks, PromptGuard, Stage, abort_requested, clear_stop_request,
+        install_stop_signals, stop_requested, terminal_progress,
+    },
     task::Task,
 };
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use clap::Parser;
 use comfy_table::{Table, presets::UTF8_FULL};
 use serde_json;
 use std::{
-    env,
     fs::{self},
-    io,
-    io::Write,
+    io::{self, BufRead, BufReader, IsTerminal, Read},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
 };
+use tracing::{debug, error, info, warn};
+
+/// Default of --stall-timeout, matching `ffmpeg::DEFAULT_STALL_TIMEOUT`.
+const DEFAULT_STALL_TIMEOUT_MINUTES: f64 = 10.0;
+/// Sample peaks of an encoded `--sample-encode` sample at or above this (in dBFS) may clip.
+const SAMPLE_CLIP_DB: f64 = -0.1;
+/// Audio trimmed by a negative initial delay counts as content above this peak (in dBFS).
+const START_TRIM_AUDIBLE_DB: f64 = -60.0;
+/// Audio decoded before a range measured on its own (`probe-quiet`, `--dry-run`) so the
+/// momentary loudness (a 400 ms window) at its start matches what a full run measures.
+const PROBE_PRE_ROLL_SECONDS: f64 = 1.0;
+/// Candidates listed by `probe-quiet` are at least this far (in seconds) apart.
+const PROBE_CANDIDATE_SPACING: f64 = 1.0;
+/// Seconds shown on each side of a split point in the `--review-dir` waveforms.
+const REVIEW_WINDOW_SECONDS: f64 = 2.0;
+/// Seconds of audio on each side of a split point in the `--export-previews` clips.
+const PREVIEW_SECONDS: f64 = 3.0;
+/// Placeholders of `--title` and `--corrected-title` templates.
+const TITLE_PLACEHOLDERS: [&str; 7] = [
+    "orig_title",
+    "lang",
+    "codec",
+    "stream",
+    "offset",
+    "splits",
+    "drift_ppm",
+];
+
+/// Progress bars are drawn on stderr when it is a terminal. Ctrl+C stops the run after the
+/// current stage.
+pub fn run(cli: Cli) -> Result<()> {
+    clear_stop_request();
+    install_stop_signals();
+    let hooks = PipelineHooks {
+        progress: (io::stderr().is_terminal() && !cli.quiet).then(terminal_progress),
+        ..PipelineHooks::default()
+    };
+    run_with_hooks(cli, &hooks)
+}
+
+/// Run the tool, reporting pipeline progress to `hooks` and stopping when its token is cancelled.
+/// A cancelled run kills the running ffmpeg child and removes its temp files.
+pub fn run_with_hooks(cli: Cli, hooks: &PipelineHooks) -> Result<()> {
+    set_lang(cli.lang.unwrap_or_else(Lang::from_locale));
+    // Jobs enter their own settings; these serve the commands that only probe
+    let _children = ChildSettings {
+        ffmpeg_path: cli.ffmpeg_path.clone(),
+        ffprobe_path: cli.ffprobe_path.clone(),
+        cancel: hooks.cancel.clone(),
+        ..ChildSettings::default()
+    }
+    .enter();
+    // Jobs resolve their own temp dir; this one serves the commands that are not jobs
+    let temp_root = match usable_temp_root(cli.temp_dir.as_deref()) {
+        Ok(root) => root,
+        // The doctor reports an unusable temp dir instead of failing on it
+        Err(_) if matches!(cli.command, Command::Doctor(_)) => temp_root(cli.temp_dir.as_deref()),
+        Err(e) => return Err(e),
+    };
+    // Dropped when the run returns, after the final status record
+    let _porcelain = cli
+        .porcelain
+        .then(porcelain::enable)
+        .transpose()
+        .context("Could not switch to --porcelain output")?;
+    logging::init(cli.verbose, cli.quiet, cli.log_file.as_deref()).with_context(|| {
+        format!(
+            "Could not open the log file '{}'",
+            cli.log_file.as_deref().unwrap_or_default()
+        )
+    })?;
+    info!(
+        target: LOG,
+        "sync-nudger {} started: {}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::args().collect::<Vec<_>>().join(" ")
+    );
+    let child_args = global_args(&cli);
+    let result = match cli.command {
+        Command::Process(mut args) => {
+            args.ffmpeg_path = cli.ffmpeg_path;
+            args.ffprobe_path = cli.ffprobe_path;
+            args.temp_dir = cli.temp_dir;
+            args.quiet = cli.quiet;
+            run_process(args, hooks)
+        }
+        Command::Resolve(mut args) => {
+            args.resolve_only = true;
+            args.ffmpeg_path = cli.ffmpeg_path;
+            args.ffprobe_path = cli.ffprobe_path;
+            args.temp_dir = cli.temp_dir;
+            args.quiet = cli.quiet;
+            run_process(args, hooks)
+        }
+        Command::Inspect(inspect) => handle_inspect(&inspect.input, inspect.duration_tolerance),
+        Command::Check => handle_ffmpeg_check(),
+        Command::Doctor(doctor) => handle_doctor(&doctor, cli.temp_dir),
+        #[cfg(feature = "selftest")]
+        Command::Selftest(selftest) => handle_selftest(&selftest, &temp_root, hooks),
+        Command::DetectOffset(detect) => handle_detect_offset(&detect),
+        Command::CheckChannels(target) => handle_check_channels(&target),
+        Command::Qc(target) => handle_qc(&target),
+        Command::Gaps(target) => handle_gaps(&target),
+        Command::ProbeQuiet(probe) => handle_probe_quiet(&probe, &temp_root),
+        Command::SuggestSplits(suggest) => handle_suggest_splits(&suggest),
+        Command::ApplySeason(mut season) => {
+            season.quiet = cli.quiet;
+            season.temp_dir = cli.temp_dir;
+            handle_apply_season(&season, hooks)
+        }
+        Command::Watch(watch) => handle_watch(&watch, &child_args, &temp_root, hooks),
+        Command::Compare(compare) => handle_compare(&compare),
+        Command::Import(import) => handle_import(&import),
+        Command::Project(project) => handle_project(project.action, cli.quiet, hooks),
+        Command::Cache(cache) => handle_cache(cache.action, &temp_root),
+    };
+    match &result {
+        Ok(()) => {
+            info!(target: LOG, "Finished");
+            porcelain::emit("status", &[&"ok"]);
+        }
+        Err(e) => {
+            error!(target: LOG, "Failed: {:#}", e);
+            porcelain::emit("status", &[&"error", &format!("{:#}", e)]);
+        }
+    }
+    result
+}
 
-pub fn run(args: Args) -> Result<()> {
-    // Handle --check-ffmpeg command
-    if args.check_ffmpeg {
-        return handle_ffmpeg_check();
+/// The global options of `cli` as arguments, so child processes of the run use the same ones.
+fn global_args(cli: &Cli) -> Vec<String> {
+    let mut args = Vec::new();
+    for (option, value) in [
+        ("--ffmpeg-path", &cli.ffmpeg_path),
+        ("--ffprobe-path", &cli.ffprobe_path),
+        ("--temp-dir", &cli.temp_dir),
+        ("--log-file", &cli.log_file),
+    ] {
+        if let Some(value) = value {
+            args.extend([option.to_string(), value.clone()]);
+        }
+    }
+    if let Some(lang) = cli.lang {
+        args.extend(["--lang".to_string(), lang.to_string()]);
+    }
+    if cli.quiet {
+        args.push("--quiet".to_string());
     }
+    if cli.verbose > 0 {
+        args.push(format!("-{}", "v".repeat(cli.verbose as usize)));
+    }
+    args
+}
 
-    // Handle --inspect command
-    if args.inspect {
-        let input = args
-            .input
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("--input is required for inspection"))?;
-        return handle_inspect(input);
+/// Removes the temp dir of a run if the run is cancelled; finished runs clean up on their own.
+struct CancelledRunCleanup {
+    dir: PathBuf,
+    cancel: CancellationToken,
+}
+
+impl CancelledRunCleanup {
+    fn new(dir: &Path, hooks: &PipelineHooks) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            cancel: hooks.cancel.clone(),
+        }
+    }
+}
+
+impl Drop for CancelledRunCleanup {
+    fn drop(&mut self) {
+        if self.cancel.is_cancelled() {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
     }
+}
 
+fn run_process(args: Args, hooks: &PipelineHooks) -> Result<()> {
+    if args.quiet
+        && !args.yes
+        && !args.dry_run
+        && !args.resolve_only
+        && args.sample_encode.is_none()
+    {
+        bail!("--quiet hides the plan, so it needs --yes to apply it without confirmation.");
+    }
     // Load task file if provided and merge with CLI args
     let task = load_task_from_args(&args)?;
-    let input = args
+
+    // Several inputs or a directory apply the same plan to every file
+    if args.input.len() > 1 || args.input.first().is_some_and(|i| Path::new(i).is_dir()) {
+        return handle_batch(&args, task, hooks);
+    }
+    run_task(args, task, hooks)
+}
+
+/// Apply the same plan to every input file (directories are expanded to the media files in
+/// them), writing each output under the output directory with the input's file name.
+fn handle_batch(args: &Args, task: Option<Task>, hooks: &PipelineHooks) -> Result<()> {
+    if matches!(args.write_task_file, Some(Some(_))) {
+        bail!(
+            "--write-task-file cannot name a single file when processing several inputs; pass it without a value to write one task file per input."
+        );
+    }
+    if matches!(args.export_audio, Some(Some(_))) {
+        bail!(
+            "--export-audio cannot name a single file when processing several inputs; pass it without a value to export next to each output."
+        );
+    }
+    if matches!(args.keep_temp, Some(Some(_))) {
+        bail!(
+            "--keep-temp cannot name a single directory when processing several inputs; pass it without a value to keep them next to each output."
+        );
+    }
+    if args.plan_out.is_some() {
+        bail!("--plan-out is not supported when processing several inputs.");
+    }
+    if args.review_dir.is_some() {
+        bail!("--review-dir is not supported when processing several inputs.");
+    }
+    if args.export_previews.is_some() {
+        bail!("--export-previews is not supported when processing several inputs.");
+    }
+    if !args.profile_outputs.is_empty() {
+        bail!("--profile-output is not supported when processing several inputs.");
+    }
+
+    let first = Path::new(&args.input[0]);
+    let output_dir = match &args.output {
+        Some(output) => PathBuf::from(output),
+        None if first.is_dir() => first.join("synced"),
+        None => first.parent().unwrap_or(Path::new(".")).join("synced"),
+    };
+    let mut inputs = Vec::new();
+    for input in &args.input {
+        if Path::new(input).is_dir() {
+            inputs.extend(find_episodes(input, &output_dir)?);
+        } else {
+            inputs.push(PathBuf::from(input));
+        }
+    }
+
+    let mut jobs: Vec<(String, String)> = Vec::new();
+    for input in &inputs {
+        let file_name = input
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not a file.", input.display()))?;
+        let output = path_to_str(&output_dir.join(file_name))?.to_string();
+        if let Some((other, _)) = jobs
+            .iter()
+            .find(|(_, o)| same_file(Path::new(o), Path::new(&output)))
+        {
+            bail!(
+                "'{}' and '{}' would both be written to '{}'. Process them in separate runs.",
+                other,
+                input.display(),
+                output
+            );
+        }
+        jobs.push((path_to_str(input)?.to_string(), output));
+    }
+
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::BatchApplying,
+            &[
+                ("count", jobs.len().to_string()),
+                ("dir", output_dir.display().to_string())
+            ]
+        )
+    );
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_header(vec![t(Msg::HeaderInput), t(Msg::HeaderOutput)]);
+    for (input, output) in &jobs {
+        table.add_row(vec![input, output]);
+    }
+    info!("{}", table);
+
+    if args.yes {
+        info!("\n{}", t(Msg::YesProvided));
+    } else {
+        println!("\n{}", t(Msg::ProceedAllFiles));
+        let mut answer = String::new();
+        let prompt = PromptGuard::new();
+        io::stdin().read_line(&mut answer)?;
+        drop(prompt);
+        if !is_yes(&answer) {
+            println!("{}", t(Msg::Aborting));
+            return Err(RunError::Aborted.into());
+        }
+    }
+
+    fs::create_dir_all(&output_dir)?;
+    let mut results = Vec::new();
+    for (input, output) in &jobs {
+        hooks.check_cancelled()?;
+        info!(
+            "\n▶️ {}",
+            tf(Msg::Processing, &[("file", input.to_string())])
+        );
+        let mut file_args = args.clone();
+        file_args.input = vec![input.clone()];
+        file_args.output = Some(output.clone());
+        file_args.yes = true;
+        let result = run_task(file_args, task.clone(), hooks);
+        if result.is_err() && hooks.cancel.is_cancelled() {
+            return result;
+        }
+        results.push((input, result));
+    }
+
+    let mut summary = Table::new();
+    summary
+        .load_preset(UTF8_FULL)
+        .set_header(vec![t(Msg::HeaderInput), t(Msg::HeaderStatus)]);
+    for (input, result) in &results {
+        let status = match result {
+            Ok(()) => format!("✅ {}", t(Msg::StatusDone)),
+            Err(e) => format!("❌ {}", e),
+        };
+        summary.add_row(vec![input.to_string(), status]);
+    }
+    info!("\n▶️ {}", t(Msg::BatchSummary));
+    info!("{}", summary);
+
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    if failed == 0 {
+        info!(
+            "\n✅ {}",
+            tf(
+                Msg::ProcessedFiles,
+                &[
+                    ("count", results.len().to_string()),
+                    ("dir", output_dir.display().to_string())
+                ]
+            )
+        );
+        Ok(())
+    } else {
+        bail!("{} of {} files failed.", failed, results.len())
+    }
+}
+
+/// Hook commands of one job and the context they run with, which is `None` when the hooks
+/// do not run (e.g. on a dry run).
+struct JobHooks {
+    commands: TaskHooks,
+    context: Option<HookContext>,
+}
+
+impl JobHooks {
+    /// Run the hook of a finished stage. A failing stage hook only warns.
+    fn stage_finished(&self, stage: Stage) {
+        if let (Some(command), Some(context)) = (self.commands.stage(stage), &self.context) {
+            let context = HookContext {
+                event: stage.as_str().to_string(),
+                ..context.clone()
+            };
+            if let Err(e) = run_hook(command, &context) {
+                warn!("⚠️ {:#}", e);
+            }
+        }
+        debug!(target: LOG, "Finished the {} stage", stage.as_str());
+    }
+}
+
+/// Run one job of the command line: the task file with the options of `args` on top.
+fn run_task(args: Args, task: Option<Task>, hooks: &PipelineHooks) -> Result<()> {
+    let options = sync_options(&args);
+    let task = task_from_args(args, task)?;
+    run_job(task, &options, hooks).map(drop)
+}
+
+/// The options of `args` that say how a job runs rather than what it does.
+fn sync_options(args: &Args) -> SyncOptions {
+    SyncOptions {
+        dry_run: args.dry_run,
+        resolve_only: args.resolve_only,
+        resume: args.resume,
+        debug: args.debug,
+        ignore_ffmpeg_version: args.ignore_ffmpeg_version,
+        write_task_file: args.write_task_file.clone(),
+        plan_out: args.plan_out.clone(),
+        review_dir: args.review_dir.clone(),
+        export_previews: args.export_previews.clone(),
+        export_audio: args.export_audio.clone(),
+        keep_temp: args.keep_temp.clone(),
+        cache_max_age: args.cache_max_age,
+        cache_max_size: args.cache_max_size,
+        stop_file: args.stop_file.clone(),
+        yes: args.yes,
+        quiet: args.quiet,
+        sample_encode: args.sample_encode,
+        track_output: args.track_output.clone(),
+        task_file: args.task.clone().flatten(),
+    }
+}
+
+/// The task of a job: `task` (or an empty one) with the options given in `args` taking
+/// precedence.
+fn task_from_args(mut args: Args, task: Option<Task>) -> Result<Task> {
+    if let Some(path) = args.split_map.take() {
+        let map = SplitMap::load(&path)?;
+        info!(
+            "🗺️ {}",
+            tf(Msg::LoadedSplitMap, &[("path", path.to_string())])
+        );
+        args.initial_delay = map.initial_delay.unwrap_or(0.0);
+        args.splits = map.splits;
+        args.split_ranges = map.split_ranges;
+        args.ramps = map.ramps;
+    }
+    let task = task.unwrap_or_default();
+    let stream_given =
+        !args.stream.is_empty() || args.language.is_some() || args.stream_title.is_some();
+    // The plans carry the stream and its delays; the command line cannot say which plan it means
+    if !task.stream_plans.is_empty()
+        && (stream_given
+            || args.initial_delay != 0.0
+            || !args.splits.is_empty()
+            || !args.split_ranges.is_empty()
+            || !args.ramps.is_empty()
+            || args.drift_ppm.is_some()
+            || args.stretch.is_some()
+            || !args.sync_points.is_empty())
+    {
+        bail!(
+            "The task file has stream_plans; set the stream, delays and splits in each plan instead of on the command line."
+        );
+    }
+    fn list_or<T>(given: Vec<T>, task: Vec<T>) -> Vec<T> {
+        if given.is_empty() { task } else { given }
+    }
+    let flag = |given: bool, task: Option<bool>| if given { Some(true) } else { task };
+    // --stream, --language and --stream-title on the command line all override the stream of
+    // the task file
+    let (stream, streams, language, stream_title) = if stream_given {
+        let stream = args.stream.first().copied();
+        match args.stream.len() {
+            1 => (stream, Vec::new(), args.language, args.stream_title),
+            _ => (None, args.stream, args.language, args.stream_title),
+        }
+    } else {
+        (task.stream, task.streams, task.language, task.stream_title)
+    };
+    let silence_given = args.silence_threshold != -95.0;
+    let mut media_server = match (args.refresh_server, args.server_url) {
+        (Some(kind), Some(url)) => Some(MediaServer {
+            kind,
+            url,
+            token: None,
+            path_map: None,
+        }),
+        _ => task.media_server,
+    };
+    if let Some(server) = media_server.as_mut() {
+        if args.server_token.is_some() {
+            server.token = args.server_token;
+        }
+        if args.server_path_map.is_some() {
+            server.path_map = args.server_path_map;
+        }
+    }
+    let mut hooks = task.hooks.unwrap_or_default();
+    if args.pre_hook.is_some() {
+        hooks.pre = args.pre_hook;
+    }
+    if args.post_hook.is_some() {
+        hooks.post = args.post_hook;
+    }
+    Ok(Task {
+        input: args.input.into_iter().next().or(task.input),
+        output: args.output.or(task.output),
+        stream,
+        streams,
+        language,
+        stream_title,
+        initial_delay: if args.initial_delay != 0.0 {
+            Some(args.initial_delay)
+        } else {
+            task.initial_delay
+        },
+        splits: list_or(args.splits, task.splits),
+        split_ranges: list_or(args.split_ranges, task.split_ranges),
+        ramps: list_or(args.ramps, task.ramps),
+        drift_ppm: args.drift_ppm.or(task.drift_ppm),
+        stretch: args.stretch.or(task.stretch),
+        fps: args.fps.or(task.fps),
+        sync_points: list_or(args.sync_points, task.sync_points),
+        bitrate: args.bitrate.or(task.bitrate),
+        silence_threshold: if silence_given {
+            Some(args.silence_threshold)
+        } else {
+            task.silence_threshold
+        },
+        // An explicit --silence-threshold also overrides the auto threshold of the task file
+        auto_threshold: match args.auto_threshold {
+            Some(margin) => Some(margin.unwrap_or(DEFAULT_AUTO_THRESHOLD_MARGIN_DB)),
+            None if silence_given => None,
+            None => task.auto_threshold,
+        },
+        max_peak: args.max_peak.or(task.max_peak),
+        avoid_speech: flag(args.avoid_speech, task.avoid_speech),
+        zero_crossing: if args.no_zero_crossing {
+            Some(false)
+        } else {
+            task.zero_crossing
+        },
+        detector: args.detector.or(task.detector),
+        detector_command: args.detector_command.or(task.detector_command),
+        hooks: (!hooks.is_empty()).then_some(hooks),
+        media_server,
+        profile_outputs: list_or(args.profile_outputs, task.profile_outputs),
+        fit_length: flag(args.fit_length, task.fit_length),
+        pad_head: flag(args.pad_head, task.pad_head),
+        trim_tail: flag(args.trim_tail, task.trim_tail),
+        ffmpeg_threads: args.ffmpeg_threads.or(task.ffmpeg_threads),
+        jobs: args.jobs.or(task.jobs),
+        deterministic: flag(args.deterministic, task.deterministic),
+        segment_files: flag(args.segment_files, task.segment_files),
+        crossfade: args.crossfade.or(task.crossfade),
+        intermediate: args.intermediate.or(task.intermediate),
+        stall_timeout: if args.stall_timeout != DEFAULT_STALL_TIMEOUT_MINUTES {
+            Some(args.stall_timeout)
+        } else {
+            task.stall_timeout
+        },
+        abort_on_stall: flag(args.abort_on_stall, task.abort_on_stall),
+        ffmpeg_path: args.ffmpeg_path.or(task.ffmpeg_path),
+        ffprobe_path: args.ffprobe_path.or(task.ffprobe_path),
+        temp_dir: args.temp_dir.or(task.temp_dir),
+        auto_mkv: flag(args.auto_mkv, task.auto_mkv),
+        refresh_stats: flag(args.refresh_stats, task.refresh_stats),
+        tag_history: flag(args.tag_history, task.tag_history),
+        keep_original: flag(args.keep_original, task.keep_original),
+        shift_video: flag(args.shift_video, task.shift_video),
+        stream_plans: task.stream_plans,
+        corrected_title: args.corrected_title.or(task.corrected_title),
+        title: args.title.or(task.title),
+        avoid_negative_ts: args.avoid_negative_ts.or(task.avoid_negative_ts),
+        muxdelay: args.muxdelay.or(task.muxdelay),
+        muxpreload: args.muxpreload.or(task.muxpreload),
+        copyts: flag(args.copyts, task.copyts),
+        shift_subtitles: args.shift_subtitles.or(task.shift_subtitles),
+        shift_chapters: flag(args.shift_chapters, task.shift_chapters),
+        subtitles: list_or(args.subtitles, task.subtitles),
+        fix_channels: flag(args.fix_channels, task.fix_channels),
+        reference: args.reference.or(task.reference),
+        reference_stream: args.reference_stream.or(task.reference_stream),
+        match_onset: args.match_onset.or(task.match_onset),
+        in_sync_threshold: if args.in_sync_threshold != DEFAULT_IN_SYNC_THRESHOLD_MS {
+            Some(args.in_sync_threshold)
+        } else {
+            task.in_sync_threshold
+        },
+        min_confidence: args.min_confidence.or(task.min_confidence),
+    })
+}
+
+/// Threads each ffmpeg child of the job may use: one for deterministic output, else
+/// `ffmpeg_threads` (`None` leaves the choice to ffmpeg).
+fn job_threads(task: &Task) -> Option<usize> {
+    if task.deterministic.unwrap_or(false) {
+        Some(1)
+    } else {
+        task.ffmpeg_threads
+    }
+}
+
+/// The settings of the ffmpeg and ffprobe children of the job, taken from the task alone.
+fn child_settings(task: &Task, hooks: &PipelineHooks) -> Result<ChildSettings> {
+    let stall_timeout = task.stall_timeout.unwrap_or(DEFAULT_STALL_TIMEOUT_MINUTES);
+    // The task file is not checked by the argument parser
+    let stall_timeout = if stall_timeout > 0.0 {
+        Some(
+            Duration::try_from_secs_f64(stall_timeout * 60.0).map_err(|_| {
+                anyhow::anyhow!("Invalid stall timeout of {} minutes", stall_timeout)
+            })?,
+        )
+    } else {
+        None
+    };
+    Ok(ChildSettings {
+        threads: job_threads(task).unwrap_or(0),
+        ffmpeg_path: task.ffmpeg_path.clone(),
+        ffprobe_path: task.ffprobe_path.clone(),
+        stall_timeout,
+        abort_on_stall: task.abort_on_stall.unwrap_or(false),
+        cancel: hooks.cancel.clone(),
+    })
+}
+
+/// Run one job with its pre, post and stage hooks (see `hooks`).
+pub(crate) fn run_job(
+    task: Task,
+    options: &SyncOptions,
+    hooks: &PipelineHooks,
+) -> Result<SyncReport> {
+    // Nothing of an earlier job carries over: a stop it was asked for, or its ffmpeg settings
+    hooks
+        .stop
+        .start_job(options.stop_file.as_deref().map(Path::new));
+    let _children = child_settings(&task, hooks)?.enter();
+    let commands = task.hooks.clone().unwrap_or_default();
+    commands.validate()?;
+    info!(
+        target: LOG,
+        "Job: {} -> {}",
+        task.input.as_deref().unwrap_or("(no input)"),
+        task.output.as_deref().unwrap_or("(no output)")
+    );
+    // A dry run changes nothing a hook could react to
+    let (Some(input), Some(output), false) =
+        (task.input.clone(), task.output.clone(), options.dry_run)
+    else {
+        let job_hooks = JobHooks {
+            commands,
+            context: None,
+        };
+        return process_task(task, options, hooks, &job_hooks);
+    };
+    let context = HookContext {
+        input,
+        output,
+        stream: job_streams(&task)
+            .iter()
+            .map(|stream| stream.to_string())
+            .reduce(|all, stream| format!("{},{}", all, stream)),
+        task_file: options.task_file.clone(),
+        ..HookContext::default()
+    };
+    if let Some(pre) = &commands.pre {
+        info!(
+            "🪝 {}",
+            tf(Msg::RunningPreHook, &[("command", pre.to_string())])
+        );
+        run_hook(
+            pre,
+            &HookContext {
+                event: "pre".to_string(),
+                ..context.clone()
+            },
+        )?;
+    }
+    let job_hooks = JobHooks {
+        commands,
+        context: Some(context.clone()),
+    };
+    let result = process_task(task, options, hooks, &job_hooks);
+    match &result {
+        Ok(_) => info!(target: LOG, "Job finished: {}", context.output),
+        Err(e) => error!(target: LOG, "Job failed: {}: {:#}", context.input, e),
+    }
+    if let Some(post) = &job_hooks.commands.post {
+        let status = match result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<FFmpegError>())
+        {
+            _ if result.is_ok() => "succeeded",
+            Some(FFmpegError::Stopped) => "stopped",
+            Some(FFmpegError::Cancelled) => "cancelled",
+            _ => "failed",
+        };
+        info!(
+            "🪝 {}",
+            tf(Msg::RunningPostHook, &[("command", post.to_string())])
+        );
+        let context = HookContext {
+            event: "post".to_string(),
+            status: Some(status.to_string()),
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+            ..context
+        };
+        if let Err(e) = run_hook(post, &context) {
+            warn!("⚠️ {:#}", e);
+        }
+    }
+    result
+}
+
+/// The muxer timestamp options of a job.
+fn muxer_timestamps(task: &Task) -> Result<MuxerTimestamps> {
+    let timestamps = MuxerTimestamps {
+        avoid_negative_ts: task.avoid_negative_ts,
+        muxdelay: task.muxdelay,
+        muxpreload: task.muxpreload,
+        copyts: task.copyts.unwrap_or(false),
+    };
+    for (name, value) in [
+        ("--muxdelay", timestamps.muxdelay),
+        ("--muxpreload", timestamps.muxpreload),
+    ] {
+        if let Some(value) = value
+            && (value < 0.0 || !value.is_finite())
+        {
+            bail!(
+                "{} must be a non-negative number of seconds (got {}).",
+                name,
+                value
+            );
+        }
+    }
+    Ok(timestamps)
+}
+
+/// The task a stream plan runs as: the plan's stream, delays and splits, with the other options
+/// taken from the plan where it sets them and from the task around it otherwise.
+fn stream_plan_task(task: &Task, plan: &Task) -> Task {
+    Task {
+        stream: plan.stream,
+        streams: Vec::new(),
+        language: plan.language.clone(),
+        stream_title: plan.stream_title.clone(),
+        initial_delay: plan.initial_delay,
+        splits: plan.splits.clone(),
+        split_ranges: plan.split_ranges.clone(),
+        ramps: plan.ramps.clone(),
+        drift_ppm: plan.drift_ppm,
+        stretch: plan.stretch,
+        sync_points: plan.sync_points.clone(),
+        bitrate: plan.bitrate.clone().or_else(|| task.bitrate.clone()),
+        title: plan.title.clone().or_else(|| task.title.clone()),
+        stream_plans: Vec::new(),
+        ..task.clone()
+    }
+}
+
+/// The streams of a task: `streams`, else `stream`.
+fn job_streams(task: &Task) -> Vec<StreamSpecifier> {
+    if task.streams.is_empty() {
+        task.stream.into_iter().collect()
+    } else {
+        task.streams.clone()
+    }
+}
+
+/// Turn a job on several streams into one stream plan per stream, each with the delays and
+/// splits of the task.
+fn multi_stream_task(task: Task, streams: &[StreamSpecifier]) -> Result<Task> {
+    if !task.stream_plans.is_empty() {
+        bail!("Give either several streams or stream_plans, not both.");
+    }
+    if task.stream.is_some() && !task.streams.is_empty() {
+        bail!("The task file sets both 'stream' and 'streams'; keep one of them.");
+    }
+    if task.shift_video.unwrap_or(false) {
+        bail!("--shift-video leaves every audio stream as it is; do not select several streams.");
+    }
+    let plan = Task {
+        initial_delay: task.initial_delay,
+        splits: task.splits.clone(),
+        split_ranges: task.split_ranges.clone(),
+        ramps: task.ramps.clone(),
+        drift_ppm: task.drift_ppm,
+        stretch: task.stretch,
+        sync_points: task.sync_points.clone(),
+        ..Task::default()
+    };
+    Ok(Task {
+        stream: None,
+        streams: Vec::new(),
+        stream_plans: streams
+            .iter()
+            .map(|&stream| Task {
+                stream: Some(stream),
+                ..plan.clone()
+            })
+            .collect(),
+        ..task
+    })
+}
+
+/// Run the `stream_plans` of a task file. Every plan runs the pipeline up to its finished track,
+/// then all tracks replace their streams in a single remux. A plan that ends without a track
+/// (declined, or already in sync) leaves its stream unchanged.
+fn process_stream_plans(
+    task: Task,
+    options: &SyncOptions,
+    hooks: &PipelineHooks,
+    job_hooks: &JobHooks,
+) -> Result<SyncReport> {
+    let input = task
+        .input
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("--input is required"))?;
+    let output = task
+        .output
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("--output is required"))?;
+    // These follow the timeline of a single track
+    if task.keep_original.unwrap_or(false)
+        || task.shift_chapters.unwrap_or(false)
+        || task.shift_subtitles.is_some()
+        || !task.subtitles.is_empty()
+        || !task.profile_outputs.is_empty()
+        || task.tag_history.unwrap_or(false)
+        || task.refresh_stats.unwrap_or(false)
+    {
+        bail!(
+            "keep_original, shift_chapters, shift_subtitles, subtitles, profile outputs, tag_history and refresh_stats follow a single remuxed track and cannot be combined with several streams or stream_plans."
+        );
+    }
+    if options.resolve_only || options.write_task_file.is_some() {
+        bail!(
+            "Resolving a task with several streams or stream_plans into a task file is not supported."
+        );
+    }
+    ensure_not_overwriting(&output, "output file", &[("input file", input.as_str())])?;
+    let timestamps = muxer_timestamps(&task)?;
+
+    let tmpdir = create_temp_dir(&usable_temp_root(task.temp_dir.as_deref())?)?;
+    let _cleanup = CancelledRunCleanup::new(&tmpdir, hooks);
+    let mut tracks: Vec<ReplacedAudio> = Vec::new();
+    let mut reports: Vec<TrackReport> = Vec::new();
+    for (i, plan) in task.stream_plans.iter().enumerate() {
+        hooks.check_cancelled()?;
+        let plan_task = stream_plan_task(&task, plan);
+        let stream_spec = match (
+            plan_task.stream,
+            &plan_task.language,
+            &plan_task.stream_title,
+        ) {
+            (Some(stream_spec), _, _) => resolve_auto_stream(&input, stream_spec)?,
+            (None, None, None) => bail!(
+                "Stream plan {} sets neither a stream nor a language or stream title.",
+                i + 1
+            ),
+            (None, language, title) => {
+                let language = language.as_ref().map(|l| normalize_or_warn(l, "language"));
+                stream_for_tags(&input, language.as_deref(), title.as_deref())?
+            }
+        };
+        let audio_meta = probe_audio_stream(&input, stream_spec)?;
+        if tracks
+            .iter()
+            .any(|t| t.audio_stream_idx == audio_meta.stream_index)
+        {
+            bail!(
+                "Two stream plans edit audio stream #{}.",
+                audio_meta.absolute_index
+            );
+        }
+        info!(
+            "\n🎚️ {}",
+            tf(
+                Msg::StreamPlan,
+                &[
+                    ("number", (i + 1).to_string()),
+                    ("count", task.stream_plans.len().to_string()),
+                    ("stream", audio_meta.absolute_index.to_string()),
+                    ("codec", audio_meta.codec.to_string())
+                ]
+            )
+        );
+        let track_dir = tmpdir.join(format!("plan_{}", i + 1));
+        fs::create_dir_all(&track_dir)?;
+        let plan_options = SyncOptions {
+            track_output: Some(path_to_str(&track_dir)?.to_string()),
+            ..options.clone()
+        };
+        let report = process_task(plan_task, &plan_options, hooks, job_hooks)?;
+        let Some(track) = fs::read_dir(&track_dir)?.next().transpose()? else {
+            info!(
+                "ℹ️ {}",
+                tf(
+                    Msg::StreamUnchanged,
+                    &[("stream", audio_meta.absolute_index.to_string())]
+                )
+            );
+            continue;
+        };
+        let mut metadata = Vec::new();
+        if !audio_meta.language.is_empty() {
+            metadata.push(("language".to_string(), audio_meta.language.clone()));
+        }
+        if !audio_meta.title.is_empty() {
+            metadata.push(("title".to_string(), audio_meta.title.clone()));
+        }
+        tracks.push(ReplacedAudio {
+            audio_stream_idx: audio_meta.stream_index,
+            path: track.path(),
+            metadata,
+        });
+        reports.extend(report.tracks);
+    }
+
+    if options.dry_run || tracks.is_empty() {
+        if tracks.is_empty() && !options.dry_run {
+            info!("\n✅ {}", t(Msg::NoPlanChanged));
+        }
+        fs::remove_dir_all(&tmpdir)?;
+        return Ok(SyncReport {
+            output: None,
+            tracks: reports,
+        });
+    }
+
+    info!("\n▶️ {}", t(Msg::Remuxing));
+    let media_duration = get_file_duration(&input)?;
+    hooks.report(Stage::Remuxing, 0.0);
+    let expected_streams = count_streams(&input)?;
+    let staged = StagedFile::new(&output)?;
+    let run = RunOptions {
+        deterministic: task.deterministic.unwrap_or(false),
+        debug: options.debug,
+        ..RunOptions::default()
+    };
+    remux_audio_streams(
+        &input,
+        &tracks,
+        staged.path(),
+        timestamps,
+        &run,
+        &|seconds| {
+            hooks.report(
+                Stage::Remuxing,
+                (seconds / media_duration.max(1.0)).clamp(0.0, 1.0),
+            )
+        },
+    )?;
+    commit_output(staged, &output, expected_streams)?;
+    hooks.report(Stage::Remuxing, 1.0);
+    job_hooks.stage_finished(Stage::Remuxing);
+    fs::remove_dir_all(&tmpdir)?;
+
+    if let Some(server) = &task.media_server {
+        match server.refresh(Path::new(&output)) {
+            Ok(path) => info!(
+                "✅ {}",
+                tf(
+                    Msg::ServerRefreshed,
+                    &[
+                        ("server", server.kind.to_string()),
+                        ("path", path.to_string())
+                    ]
+                )
+            ),
+            Err(e) => warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::ServerRefreshFailed,
+                    &[
+                        ("server", server.kind.to_string()),
+                        ("error", format!("{:#}", e))
+                    ]
+                )
+            ),
+        }
+    }
+
+    porcelain::emit("output", &[&output]);
+    if options.quiet {
+        println!("{}", output);
+    }
+    info!(
+        "✅ {}",
+        tf(Msg::ProcessingComplete, &[("output", output.to_string())])
+    );
+    Ok(SyncReport {
+        output: Some(output),
+        tracks: reports,
+    })
+}
+
+/// Run the sync pipeline for `task`.
+fn process_task(
+    task: Task,
+    options: &SyncOptions,
+    hooks: &PipelineHooks,
+    job_hooks: &JobHooks,
+) -> Result<SyncReport> {
+    let streams = job_streams(&task);
+    if streams.len() > 1 {
+        let task = multi_stream_task(task, &streams)?;
+        return process_stream_plans(task, options, hooks, job_hooks);
+    }
+    if !task.stream_plans.is_empty() {
+        return process_stream_plans(task, options, hooks, job_hooks);
+    }
+    let input = task
         .input
         .as_ref()
-        .or_else(|| task.as_ref().and_then(|t| t.input.as_ref()))
         .ok_or_else(|| anyhow::anyhow!("--input is required"))?;
-    let output = args
+    let output = task
         .output
         .as_ref()
-        .or_else(|| task.as_ref().and_then(|t| t.output.as_ref()))
         .ok_or_else(|| anyhow::anyhow!("--output is required"))?;
-    if input == output {
-        bail!("Input and output file cannot be the same.");
-    }
-    let stream = args
-        .stream
-        .or_else(|| task.as_ref().and_then(|t| t.stream))
-        .ok_or_else(|| anyhow::anyhow!("--stream is required"))?;
-    let initial_delay = if args.initial_delay != 0.0 {
-        args.initial_delay
-    } else {
-        task.as_ref().and_then(|t| t.initial_delay).unwrap_or(0.0)
-    };
-    let bitrate = args
-        .bitrate
-        .clone()
-        .or_else(|| task.as_ref().and_then(|t| t.bitrate.clone()));
-    let silence_threshold = if args.silence_threshold != -95.0 {
-        args.silence_threshold
-    } else {
-        task.as_ref()
-            .and_then(|t| t.silence_threshold)
-            .unwrap_or(-95.0)
+    let stream_arg = streams.first().copied();
+    let (language, stream_title) = (task.language.clone(), task.stream_title.clone());
+    let shift_video = task.shift_video.unwrap_or(false);
+    // Shifting the video copies every audio stream, so none has to be selected
+    if stream_arg.is_none() && language.is_none() && stream_title.is_none() && !shift_video {
+        bail!("--stream, --language or --stream-title is required");
+    }
+    let language = language.map(|l| normalize_or_warn(&l, "--language"));
+    let mut initial_delay_given = task.initial_delay.is_some();
+    let mut initial_delay = task.initial_delay.unwrap_or(0.0);
+    let bitrate = task.bitrate.clone();
+    let mut silence_threshold = task.silence_threshold.unwrap_or(-95.0);
+    let auto_threshold = task.auto_threshold;
+    let mut splits = task.splits.clone();
+    let mut split_ranges = task.split_ranges.clone();
+    // Frame counts from a video editor become seconds and milliseconds at the video frame rate
+    let fps = task.fps;
+    if splits.iter().any(|s| s.frames.any()) || split_ranges.iter().any(|r| r.frames.any()) {
+        let fps = match fps {
+            Some(fps) => fps,
+            None => get_video_frame_rate(input)?,
+        };
+        info!(
+            "ℹ️ {}",
+            tf(Msg::ConvertingFrames, &[("fps", format!("{:.3}", fps))])
+        );
+        for split in &mut splits {
+            split.frames_to_seconds(fps);
+        }
+        for range in &mut split_ranges {
+            range.frames_to_seconds(fps);
+            if range.start >= range.end {
+                bail!(
+                    "Split range {:.3}-{:.3} s: the start must be before the end.",
+                    range.start,
+                    range.end
+                );
+            }
+        }
+    }
+    let ramps = task.ramps.clone();
+    let mut drift_ppm = task.drift_ppm;
+    let stretch = task.stretch;
+    let sync_points = &task.sync_points;
+    if let Some(stretch) = stretch {
+        if drift_ppm.is_some() {
+            bail!("Give either a stretch ratio or a drift in ppm, not both.");
+        }
+        drift_ppm = Some((stretch - 1.0) * 1_000_000.0);
+    }
+    // Two measured delays fix a line: its slope is the drift, its value at 0 the initial delay
+    let mut drift_from_sync_points = false;
+    if !sync_points.is_empty() {
+        let [first, second] = sync_points[..] else {
+            bail!("Give exactly two sync points (got {}).", sync_points.len());
+        };
+        if (second.time - first.time).abs() < f64::EPSILON {
+            bail!("The two sync points must be at different times.");
+        }
+        if drift_ppm.is_some() || initial_delay_given {
+            bail!(
+                "Sync points set the drift and initial delay; drop drift_ppm, stretch and initial_delay."
+            );
+        }
+        let slope = (second.delay - first.delay) / 1000.0 / (second.time - first.time);
+        let stretch = 1.0 + slope;
+        if stretch <= 0.0 {
+            bail!("The sync points imply a delay that grows faster than the audio plays.");
+        }
+        // The drift correction stretches the delayed track, so the initial delay shrinks with it
+        initial_delay = (first.delay - slope * first.time * 1000.0) / stretch;
+        drift_ppm = Some(slope * 1_000_000.0);
+        drift_from_sync_points = true;
+        initial_delay_given = true;
+        info!(
+            "ℹ️ {}",
+            tf(
+                Msg::SyncPointsDrift,
+                &[
+                    ("first_time", format!("{:.3}", first.time)),
+                    ("first_delay", format!("{:+.3}", first.delay)),
+                    ("second_time", format!("{:.3}", second.time)),
+                    ("second_delay", format!("{:+.3}", second.delay)),
+                    ("drift", format!("{:+.3}", slope * 1_000_000.0)),
+                    ("delay", format!("{:.3}", initial_delay))
+                ]
+            )
+        );
+    }
+    let auto_mkv = task.auto_mkv.unwrap_or(false);
+    let tag_history = task.tag_history.unwrap_or(false);
+    let shift_subtitles = task.shift_subtitles.clone();
+    let timestamps = muxer_timestamps(&task)?;
+    let keep_original = task.keep_original.unwrap_or(false);
+    let corrected_title = task.corrected_title.clone();
+    let title_template = task.title.clone();
+    // Catch unknown placeholders before any work is done
+    let placeholder_check: Vec<(&str, String)> = TITLE_PLACEHOLDERS
+        .iter()
+        .map(|name| (*name, String::new()))
+        .collect();
+    for template in [&title_template, &corrected_title].into_iter().flatten() {
+        render_template(template, &placeholder_check)?;
+    }
+    let shift_chapters = task.shift_chapters.unwrap_or(false);
+    let subtitles = task.subtitles.clone();
+    for subtitle in &subtitles {
+        if !is_supported_subtitle_file(Path::new(subtitle)) {
+            bail!(
+                "Cannot re-time '{}': only SubRip (.srt) and ASS/SSA (.ass, .ssa) subtitles are supported.",
+                subtitle
+            );
+        }
+    }
+    let refresh_stats = task.refresh_stats.unwrap_or(false);
+    let segment_files = task.segment_files.unwrap_or(false);
+    let fix_channels = task.fix_channels.unwrap_or(false);
+    let fit_length = task.fit_length.unwrap_or(false);
+    let pad_head = task.pad_head.unwrap_or(false);
+    let trim_tail = task.trim_tail.unwrap_or(false);
+    if fit_length && trim_tail {
+        bail!("fit_length already sets the length of the audio; drop trim_tail.");
+    }
+    let reference = task.reference.clone();
+    let reference_stream = task.reference_stream;
+    let match_onset = task.match_onset;
+    let plan_out = options.plan_out.clone();
+
+    // Nothing the run writes may overwrite a file it reads
+    let mut sources = vec![("input file", input.as_str())];
+    if let Some(reference) = &reference {
+        sources.push(("reference file", reference.as_str()));
+    }
+    ensure_not_overwriting(output, "output file", &sources)?;
+    if let Some(plan_out) = &plan_out {
+        ensure_not_overwriting(plan_out, "plan file", &sources)?;
+    }
+    let profile_outputs = task.profile_outputs.clone();
+    for profile in &profile_outputs {
+        ensure_not_overwriting(&profile.output, "profile output file", &sources)?;
+        if same_file(Path::new(&profile.output), Path::new(output)) {
+            bail!(
+                "The --profile-output file {} is the main output file.",
+                profile.output
+            );
+        }
+    }
+    let deterministic = task.deterministic.unwrap_or(false);
+    if shift_video {
+        // Stream copy can only move the whole video, so anything that varies over time is out
+        if !splits.is_empty()
+            || !split_ranges.is_empty()
+            || !ramps.is_empty()
+            || drift_ppm.is_some()
+            || reference.is_some()
+            || match_onset.is_some()
+            || !profile_outputs.is_empty()
+        {
+            bail!(
+                "--shift-video only applies a constant --initial-delay to the video; drop the splits, split ranges, ramps, drift, reference and profile outputs."
+            );
+        }
+        return shift_video_job(
+            options,
+            (input, output),
+            initial_delay,
+            timestamps,
+            &RunOptions {
+                deterministic,
+                debug: options.debug,
+                ..RunOptions::default()
+            },
+            hooks,
+            job_hooks,
+        );
+    }
+    let max_peak = task.max_peak;
+    let avoid_speech = task.avoid_speech.unwrap_or(false);
+    let zero_crossing = task.zero_crossing.unwrap_or(true);
+    let detector_kind = task.detector;
+    let detector_command = task.detector_command.clone();
+    if detector_kind == Some(DetectorKind::External) {
+        parse_detector_command(detector_command.as_deref().unwrap_or_default())?;
+    }
+    let media_server = task.media_server.clone();
+    let in_sync_threshold = task
+        .in_sync_threshold
+        .unwrap_or(DEFAULT_IN_SYNC_THRESHOLD_MS);
+    let min_confidence = task.min_confidence;
+
+    // Already in the `ChildSettings` of the job; kept for the task file
+    let ffmpeg_threads = task.ffmpeg_threads;
+    let ffmpeg_path = task.ffmpeg_path.clone();
+    let ffprobe_path = task.ffprobe_path.clone();
+    let stall_timeout = task.stall_timeout.unwrap_or(DEFAULT_STALL_TIMEOUT_MINUTES);
+    let abort_on_stall = task.abort_on_stall.unwrap_or(false);
+    let intermediate = task.intermediate;
+    let crossfade_ms = task.crossfade;
+    let threads = job_threads(&task);
+    if deterministic {
+        info!("🔒 {}", t(Msg::DeterministicMode));
+    }
+    let jobs = task.jobs;
+    if jobs == Some(0) {
+        bail!("--jobs must be at least 1.");
+    }
+    let run = RunOptions {
+        intermediate: intermediate.unwrap_or_default(),
+        crossfade: crossfade_ms.unwrap_or(0.0) / 1000.0,
+        deterministic,
+        segment_jobs: jobs.unwrap_or_else(|| default_segment_jobs(threads)),
+        debug: options.debug,
     };
-    let splits = if !args.splits.is_empty() {
-        args.splits.clone()
+    let temp_dir = task.temp_dir.clone();
+    let temp_root = usable_temp_root(temp_dir.as_deref())?;
+    if let Some(stop_file) = &options.stop_file {
+        info!(
+            "⏸️ {}",
+            tf(Msg::StopFileHint, &[("path", stop_file.to_string())])
+        );
+    }
+
+    check_ffmpeg_version(options.ignore_ffmpeg_version)?;
+    check_dependency(&ffprobe_executable())?;
+
+    // Make temp dir for files, or pick up the one a failed run of this job left behind. Dry runs
+    // and sample encodes work in a scratch dir of their own and leave the others alone.
+    let scratch_only = options.dry_run || options.sample_encode.is_some();
+    let job = job_fingerprint(&task, input)?;
+    let resumed = if options.resume && !scratch_only {
+        find_resumable(&temp_root, &job)
     } else {
-        task.as_ref().map(|t| t.splits.clone()).unwrap_or_default()
+        None
     };
-    let split_ranges = if !args.split_ranges.is_empty() {
-        args.split_ranges.clone()
-    } else {
-        task.as_ref()
-            .map(|t| t.split_ranges.clone())
-            .unwrap_or_default()
+    let (live_run, mut manifest) = match resumed {
+        Some((live_run, manifest)) => {
+            let dir = live_run.dir();
+            info!(
+                "♻️ {}",
+                tf(
+                    Msg::ResumingRun,
+                    &[
+                        ("dir", dir.display().to_string()),
+                        ("stages", manifest.describe())
+                    ]
+                )
+            );
+            // Marks the dir as used, so eviction drops it last
+            manifest.save(dir)?;
+            (live_run, manifest)
+        }
+        None => {
+            if options.resume && !scratch_only {
+                info!("ℹ️ {}", t(Msg::NothingToResume));
+            }
+            let dir = if deterministic {
+                create_numbered_temp_dir(&temp_root)?
+            } else {
+                create_temp_dir(&temp_root)?
+            };
+            (LiveRun::claim(&dir)?, Manifest::new(&job))
+        }
     };
-    let fit_length = if args.fit_length {
-        true
+    let tmpdir = live_run.dir().to_path_buf();
+    let _cleanup = CancelledRunCleanup::new(&tmpdir, hooks);
+    let temp_usage = TempUsage::new(&tmpdir);
+
+    // Failed runs keep their temp dirs for --resume, each with a full copy of the track
+    let evicted = if scratch_only {
+        Vec::new()
     } else {
-        task.as_ref().and_then(|t| t.fit_length).unwrap_or(false)
+        evict_failed_runs(
+            &temp_root,
+            Some(&tmpdir),
+            days(options.cache_max_age),
+            options.cache_max_size,
+        )
     };
+    if !evicted.is_empty() {
+        info!(
+            "🧹 {}",
+            tf(
+                Msg::EvictedRuns,
+                &[
+                    ("count", evicted.len().to_string()),
+                    ("days", options.cache_max_age.to_string()),
+                    ("size", format_bytes(options.cache_max_size)),
+                    (
+                        "freed",
+                        format_bytes(evicted.iter().map(|(_, size)| size).sum())
+                    )
+                ]
+            )
+        );
+    }
 
-    check_ffmpeg_version(args.ignore_ffmpeg_version)?;
-    check_dependency("ffprobe")?;
+    // Make sure the output container can hold every stream that will be mapped
+    let incompatible = find_incompatible_streams(input, output, tmpdir.as_path())?;
+    let output = if incompatible.is_empty() {
+        output.clone()
+    } else {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).set_header(vec![
+            t(Msg::HeaderIndex),
+            t(Msg::HeaderType),
+            t(Msg::HeaderCodec),
+            t(Msg::HeaderReason),
+        ]);
+        for stream in &incompatible {
+            table.add_row(vec![
+                stream.index.to_string(),
+                stream.codec_type.clone(),
+                stream.codec.clone(),
+                stream.reason.clone(),
+            ]);
+        }
+        warn!("\n⚠️ {}", t(Msg::IncompatibleStreams));
+        info!("{table}");
 
-    // Make temp dir for files
-    let tmpdir = env::temp_dir().join(format!("split_audio_{}", std::process::id()));
-    fs::create_dir_all(&tmpdir)?;
+        let mkv_output = std::path::Path::new(output)
+            .with_extension("mkv")
+            .to_string_lossy()
+            .to_string();
+        if !auto_mkv {
+            fs::remove_dir_all(&tmpdir)?;
+            bail!(
+                "Output container of '{}' does not support {} of the input streams. Use a Matroska output such as '{}' or pass --auto-mkv.",
+                output,
+                incompatible.len(),
+                mkv_output
+            );
+        }
+        if let Err(e) = ensure_not_overwriting(&mkv_output, "Matroska output", &sources) {
+            fs::remove_dir_all(&tmpdir)?;
+            return Err(e);
+        }
+        info!(
+            "ℹ️ {}",
+            tf(Msg::AutoMkv, &[("path", mkv_output.to_string())])
+        );
+        mkv_output
+    };
+    let output = &output;
 
     // Get audio stream metadata
-    let audio_meta = probe_audio_stream(input, stream)?;
-    println!("ℹ️ Original audio codec: {}", audio_meta.codec);
+    let stream_spec = match (stream_arg, &language, &stream_title) {
+        (Some(stream_spec), _, _) => resolve_auto_stream(input, stream_spec)?,
+        (None, None, None) => bail!("--stream, --language or --stream-title is required"),
+        (None, language, title) => stream_for_tags(input, language.as_deref(), title.as_deref())?,
+    };
+    let audio_meta = probe_audio_stream(input, stream_spec)?;
+    let stream = audio_meta.absolute_index;
+    info!(
+        "ℹ️ {}",
+        tf(
+            Msg::OriginalCodec,
+            &[("codec", audio_meta.codec.to_string())]
+        )
+    );
 
-    // Determine bitrate
-    let bitrate = if let Some(b) = bitrate {
-        println!("ℹ️ Using user-provided bitrate: {}", b);
-        b
+    // Nudging the commentary instead of the main mix is an easy mistake to make
+    let audio_streams = inspect_audio_streams(input)?;
+    for warning in language_warnings(&audio_streams) {
+        warn!("⚠️ {}", warning);
+    }
+    let default_stream = audio_streams.iter().find(|s| s.default);
+    let default_stream_detail = match default_stream {
+        Some(s) if s.index == stream => format!("#{} (this stream)", s.index),
+        Some(s) => format!("#{} ({}, {})", s.index, s.language, s.title),
+        None => "none".to_string(),
+    };
+    let selected = audio_streams.iter().find(|s| s.index == stream);
+    let commentary = selected.is_some_and(|s| s.commentary);
+    if let Some(selected) = selected.filter(|s| s.commentary) {
+        warn!(
+            "⚠️ {}",
+            tf(
+                Msg::LooksLikeCommentary,
+                &[
+                    ("stream", selected.index.to_string()),
+                    ("title", selected.title.to_string())
+                ]
+            )
+        );
+        if let Some(main) = default_stream.filter(|s| s.index != stream) {
+            warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::DefaultStreamHint,
+                    &[
+                        ("stream", main.index.to_string()),
+                        ("language", main.language.to_string()),
+                        ("title", main.title.to_string())
+                    ]
+                )
+            );
+        }
+    }
+
+    // Determine bitrate. Lossless tracks are encoded back to their own codec without one.
+    let bitrate = if is_lossless_codec(&audio_meta.codec) {
+        match &bitrate {
+            Some(b) => info!(
+                "ℹ️ {}",
+                tf(
+                    Msg::IgnoringBitrate,
+                    &[
+                        ("bitrate", b.to_string()),
+                        ("codec", audio_meta.codec.to_string())
+                    ]
+                )
+            ),
+            None => info!(
+                "ℹ️ {}",
+                tf(
+                    Msg::LosslessCodec,
+                    &[("codec", audio_meta.codec.to_string())]
+                )
+            ),
+        }
+        None
+    } else if let Some(b) = bitrate {
+        info!("ℹ️ {}", tf(Msg::UserBitrate, &[("bitrate", b.to_string())]));
+        Some(b)
     } else {
         // Use improved bitrate detection
         match get_stream_bitrate_for_processing(input, stream) {
             Ok(detected_bitrate) => {
-                println!("ℹ️ Automatically detected bitrate: {}", detected_bitrate);
-                detected_bitrate
+                info!(
+                    "ℹ️ {}",
+                    tf(
+                        Msg::DetectedBitrate,
+                        &[("bitrate", detected_bitrate.to_string())]
+                    )
+                );
+                Some(detected_bitrate)
             }
             Err(e) => {
                 bail!("{}", e);
@@ -121,341 +1523,3764 @@ pub fn run(args: Args) -> Result<()> {
     let original_title = audio_meta.title.clone();
     let original_lang = audio_meta.language.clone();
     let audio_stream_idx = audio_meta.stream_index;
+    // Length of the track, to turn ffmpeg's progress (in seconds) into a fraction per stage
+    let media_duration = match get_audio_stream_duration(input, stream)? {
+        Some(duration) => duration,
+        None => get_file_duration(input)?,
+    };
+    let progress_fraction = |seconds: f64| (seconds / media_duration.max(1.0)).clamp(0.0, 1.0);
+    let source_properties = probe_audio_properties(input, Some(stream))?;
+    let sample_rate = source_properties.sample_rate;
 
-    let flac_path = tmpdir.join("target_audio.flac");
-
-    // 1. Extract target audio to temporary file for analysis
-    println!("ℹ️ Extracting target audio track to temporary FLAC file...");
-    extract_audio_stream_to_flac(input, stream, flac_path.as_path(), args.debug)?;
+    // Offsets applied to an already nudged track add up with the earlier ones
+    let previous_edits = match read_history(input, stream) {
+        Ok(history) => history,
+        Err(e) => {
+            warn!(
+                "⚠️ {}",
+                tf(Msg::HistoryUnreadable, &[("error", e.to_string())])
+            );
+            Vec::new()
+        }
+    };
+    if let Some(last) = previous_edits.last() {
+        warn!(
+            "⚠️ {}",
+            tf(
+                Msg::AlreadyNudged,
+                &[
+                    ("stream", stream.to_string()),
+                    ("count", previous_edits.len().to_string()),
+                    ("date", last.applied_at_utc()),
+                    ("edit", last.describe_edit())
+                ]
+            )
+        );
+        warn!("⚠️ {}", t(Msg::DelaysOnTop));
+    }
 
-    // 2. Resolve split points
-    println!("ℹ️ Resolving split points...");
-    let mut all_splits: Vec<(f64, f64, String)> = Vec::new();
-    if !splits.is_empty() {
-        for split in &splits {
-            all_splits.push((split.time, split.delay, format!("{:.3}", split.time)));
+    let export_path = options.export_audio.as_ref().map(|path| {
+        path.clone()
+            .unwrap_or_else(|| default_export_path(output, &original_lang, stream, &original_codec))
+    });
+    if let Some(export_path) = &export_path {
+        let mut export_sources = sources.clone();
+        export_sources.push(("output file", output.as_str()));
+        if let Err(e) = ensure_not_overwriting(export_path, "exported audio file", &export_sources)
+        {
+            fs::remove_dir_all(&tmpdir)?;
+            return Err(e);
         }
     }
-    if !split_ranges.is_empty() {
-        for range in &split_ranges {
-            println!(
-                "ℹ️ Finding quietest point in range {:.3}s - {:.3}s",
-                range.start, range.end
-            );
-            let result = find_quietest_point(
-                &flac_path,
-                range.start,
-                range.end,
-                silence_threshold,
-                args.debug,
-            )?;
+
+    let keep_temp = options.keep_temp.as_ref().map(|dir| {
+        dir.clone()
+            .unwrap_or_else(|| format!("{}.sync-nudger-temp", output))
+    });
+    if let Some(dir) = &keep_temp
+        && Path::new(dir).exists()
+    {
+        fs::remove_dir_all(&tmpdir)?;
+        bail!(
+            "The directory for the intermediate files '{}' already exists. Remove it or pass another one to --keep-temp.",
+            dir
+        );
+    }
+
+    // Re-timed external subtitles go next to the output
+    let subtitle_outputs: Vec<(String, String)> = subtitles
+        .iter()
+        .map(|s| (s.clone(), external_subtitle_output(s, input, output)))
+        .collect();
+    for (subtitle, subtitle_output) in &subtitle_outputs {
+        let mut subtitle_sources = sources.clone();
+        subtitle_sources.push(("output file", output.as_str()));
+        subtitle_sources.push(("subtitle file", subtitle.as_str()));
+        if let Err(e) =
+            ensure_not_overwriting(subtitle_output, "re-timed subtitle file", &subtitle_sources)
+        {
+            fs::remove_dir_all(&tmpdir)?;
+            return Err(e);
+        }
+    }
+
+    let flac_path = intermediate_file(&tmpdir, "target_audio", run.intermediate);
+
+    // 1. Extract target audio to temporary file for analysis. A dry run that only resolves
+    // split ranges measures each range on its own instead.
+    let extract_track = !(options.dry_run || options.sample_encode.is_some())
+        || fix_channels
+        || auto_threshold.is_some()
+        || reference.is_some()
+        || match_onset.is_some();
+    let extracted = manifest.extracted && flac_path.is_file();
+    if extracted {
+        info!("♻️ {}", t(Msg::ReusingExtracted));
+    } else if extract_track {
+        info!(
+            "ℹ️ {}",
+            tf(Msg::Extracting, &[("format", run.intermediate.to_string())])
+        );
+        hooks.report(Stage::Extracting, 0.0);
+        extract_audio_stream_to_flac(input, stream, flac_path.as_path(), &run, &|seconds| {
+            hooks.report(Stage::Extracting, progress_fraction(seconds))
+        })?;
+        hooks.report(Stage::Extracting, 1.0);
+        job_hooks.stage_finished(Stage::Extracting);
+        temp_usage.record(Stage::Extracting);
+    }
+
+    // Fix swapped or inverted channels before anything is measured on the extracted audio
+    if fix_channels && !extracted {
+        info!("ℹ️ {}", t(Msg::CheckingLayout));
+        hooks.check_cancelled()?;
+        let properties = probe_audio_properties(path_to_str(flac_path.as_path())?, None)?;
+        let report = analyze_channels(
+            path_to_str(flac_path.as_path())?,
+            "0:a:0",
+            &properties.channel_layout,
+            properties.channels as usize,
+        )?;
+        print_channel_issues(&report);
+        if let Some(pan) = report.pan_filter() {
+            info!(
+                "ℹ️ {}",
+                tf(Msg::RemappingChannels, &[("pan", pan.to_string())])
+            );
+            let fixed_path = intermediate_file(&tmpdir, "target_audio_fixed", run.intermediate);
+            remap_channels(flac_path.as_path(), fixed_path.as_path(), &pan, &run)?;
+            fs::rename(&fixed_path, &flac_path)?;
+        }
+    }
+
+    if extract_track && !extracted {
+        manifest.extracted = true;
+        manifest.save(&tmpdir)?;
+    }
+
+    // Set the silence threshold relative to the noise floor of this stream
+    let mut noise_floor = None;
+    if let Some(margin) = auto_threshold {
+        info!("ℹ️ {}", t(Msg::MeasuringNoiseFloor));
+        hooks.check_cancelled()?;
+        match measure_noise_floor(flac_path.as_path())? {
+            Some(floor) => {
+                silence_threshold = floor + margin;
+                info!(
+                    "  ✅ {}",
+                    tf(
+                        Msg::NoiseFloor,
+                        &[
+                            ("floor", format!("{:.1}", floor)),
+                            ("threshold", format!("{:.1}", silence_threshold))
+                        ]
+                    )
+                );
+                noise_floor = Some(floor);
+            }
+            None => warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::SampleSilent,
+                    &[("threshold", format!("{:.1}", silence_threshold))]
+                )
+            ),
+        }
+    }
+
+    // Derive the initial delay from sync marks shared with the reference
+    let mut reference_alignment = None;
+    if let Some(reference) = &reference {
+        info!(
+            "ℹ️ {}",
+            tf(
+                Msg::AligningReference,
+                &[("reference", reference.to_string())]
+            )
+        );
+        hooks.check_cancelled()?;
+        hooks.report(Stage::Aligning, 0.0);
+        let reference_map = match reference_stream {
+            Some(s) => resolve_auto_stream(reference, s)?.to_map(),
+            None => "0:a:0".to_string(),
+        };
+        let alignment = align_to_reference(
+            path_to_str(flac_path.as_path())?,
+            "0:a:0",
+            reference,
+            &reference_map,
+        )?;
+        match &alignment {
+            ReferenceAlignment::Transients(transients) => info!(
+                "  ✅ {}",
+                tf(
+                    Msg::MatchedTransients,
+                    &[
+                        ("matches", transients.matches.to_string()),
+                        ("target", transients.target_transients.to_string()),
+                        ("reference", transients.reference_transients.to_string()),
+                        ("offset", format!("{:.3}", transients.offset_ms))
+                    ]
+                )
+            ),
+            ReferenceAlignment::Correlation(correlation) => info!(
+                "  ✅ {}",
+                tf(
+                    Msg::LoudnessCorrelated,
+                    &[
+                        ("correlation", format!("{:.2}", correlation.correlation)),
+                        ("offset", format!("{:.3}", correlation.offset_ms))
+                    ]
+                )
+            ),
+        }
+        if initial_delay_given {
+            info!(
+                "ℹ️ {}",
+                tf(
+                    Msg::KeepingDelayReference,
+                    &[
+                        ("delay", format!("{:.3}", initial_delay)),
+                        ("suggested", format!("{:.3}", alignment.offset_ms()))
+                    ]
+                )
+            );
+        } else {
+            initial_delay = alignment.offset_ms();
+        }
+        reference_alignment = Some(alignment);
+        hooks.report(Stage::Aligning, 1.0);
+        job_hooks.stage_finished(Stage::Aligning);
+    }
+
+    // Derive the initial delay from the first onset of another stream of the input
+    let mut onset_offset = None;
+    if let Some(onset_stream) = match_onset {
+        info!(
+            "ℹ️ {}",
+            tf(Msg::MatchingOnset, &[("stream", onset_stream.to_string())])
+        );
+        hooks.check_cancelled()?;
+        hooks.report(Stage::Aligning, 0.0);
+        let alignment = align_by_onset(
+            path_to_str(flac_path.as_path())?,
+            "0:a:0",
+            input,
+            &resolve_auto_stream(input, onset_stream)?.to_map(),
+        )?;
+        let offset_ms = alignment.offset_ms;
+        info!(
+            "  ✅ {}",
+            tf(
+                Msg::OnsetMatched,
+                &[
+                    ("target", format!("{:.3}", alignment.target_onset)),
+                    ("reference", format!("{:.3}", alignment.reference_onset)),
+                    ("stream", onset_stream.to_string()),
+                    ("offset", format!("{:.3}", offset_ms))
+                ]
+            )
+        );
+        if initial_delay_given {
+            info!(
+                "ℹ️ {}",
+                tf(
+                    Msg::KeepingDelayOnset,
+                    &[
+                        ("delay", format!("{:.3}", initial_delay)),
+                        ("suggested", format!("{:.3}", offset_ms))
+                    ]
+                )
+            );
+        } else {
+            initial_delay = offset_ms;
+        }
+        onset_offset = Some(offset_ms);
+        hooks.report(Stage::Aligning, 1.0);
+        job_hooks.stage_finished(Stage::Aligning);
+    }
+
+    // A measured offset within the threshold needs no re-encode if nothing else is planned
+    let measured = reference_alignment.is_some() || onset_offset.is_some();
+    if measured
+        && !initial_delay_given
+        && initial_delay.abs() < in_sync_threshold
+        && splits.is_empty()
+        && split_ranges.is_empty()
+        && ramps.is_empty()
+        && drift_ppm.is_none()
+        && profile_outputs.is_empty()
+    {
+        info!(
+            "✅ {}",
+            tf(
+                Msg::AlreadyInSync,
+                &[
+                    ("offset", format!("{:.3}", initial_delay)),
+                    ("threshold", in_sync_threshold.to_string())
+                ]
+            )
+        );
+        fs::remove_dir_all(&tmpdir)?;
+        return Ok(SyncReport::default());
+    }
+
+    // 2. Resolve split points
+    info!("ℹ️ {}", t(Msg::ResolvingSplits));
+    hooks.check_cancelled()?;
+    hooks.report(Stage::Resolving, 0.0);
+    // Split point, delay, where it came from and the user's label
+    let mut all_splits: Vec<ResolvedSplit> = Vec::new();
+    // Split points found in ranges and their confidence
+    let mut found_splits: Vec<(f64, Option<f64>)> = Vec::new();
+    if let Some(resolved) = &manifest.resolved_splits {
+        info!("♻️ {}", t(Msg::ReusingSplits));
+        all_splits = resolved.clone();
+        found_splits = manifest.found_splits.clone();
+    }
+    if manifest.resolved_splits.is_none() && !splits.is_empty() {
+        for split in &splits {
+            all_splits.push((
+                split.time,
+                split.delay,
+                format!("{:.3}", split.time),
+                split.label.clone(),
+            ));
+        }
+    }
+    if manifest.resolved_splits.is_none() && !split_ranges.is_empty() {
+        let detector = build_detector(
+            detector_kind.unwrap_or_default(),
+            silence_threshold,
+            max_peak,
+            avoid_speech,
+            detector_command.as_deref(),
+        )?;
+        for (i, range) in split_ranges.iter().enumerate() {
+            info!(
+                "ℹ️ {}",
+                tf(
+                    Msg::FindingQuietest,
+                    &[
+                        ("start", format!("{:.3}", range.start)),
+                        ("end", format!("{:.3}", range.end))
+                    ]
+                )
+            );
+            let result = if extract_track {
+                find_split_point(
+                    detector.as_ref(),
+                    &flac_path,
+                    range.start,
+                    range.end,
+                    zero_crossing,
+                    options.debug,
+                )?
+            } else {
+                find_quietest_point_in_stream(
+                    input,
+                    stream,
+                    (range.start, range.end),
+                    detector.as_ref(),
+                    zero_crossing,
+                    tmpdir.as_path(),
+                    &run,
+                )?
+            };
             if let Some(debug_output) = &result.debug_output {
                 eprintln!("{}", debug_output);
             }
-            println!(
-                "  ✅ Found quietest point at {:.3}s (Loudness: {:.2} LUFS)",
-                result.time, result.loudness
-            );
+            match (result.loudness, result.peak) {
+                (Some(loudness), Some(peak)) => info!(
+                    "  ✅ {}",
+                    tf(
+                        Msg::FoundQuietestPeak,
+                        &[
+                            ("time", format!("{:.3}", result.time)),
+                            ("loudness", format!("{:.2}", loudness)),
+                            ("peak", format!("{:.2}", peak))
+                        ]
+                    )
+                ),
+                (Some(loudness), None) => info!(
+                    "  ✅ {}",
+                    tf(
+                        Msg::FoundQuietest,
+                        &[
+                            ("time", format!("{:.3}", result.time)),
+                            ("loudness", format!("{:.2}", loudness))
+                        ]
+                    )
+                ),
+                _ => info!(
+                    "  ✅ {}",
+                    tf(
+                        Msg::FoundSplitPoint,
+                        &[
+                            ("time", format!("{:.3}", result.time)),
+                            ("detector", detector.name().to_string())
+                        ]
+                    )
+                ),
+            }
+            if let Some(original) = result.snapped_from {
+                info!(
+                    "  〰️ {}",
+                    tf(
+                        Msg::MovedZeroCrossing,
+                        &[
+                            ("from", format!("{:.4}", original)),
+                            ("to", format!("{:.4}", result.time))
+                        ]
+                    )
+                );
+            }
             all_splits.push((
                 result.time,
                 range.delay,
                 format!("{:.3}-{:.3}", range.start, range.end),
+                range.label.clone(),
+            ));
+            found_splits.push((result.time, result.confidence));
+            hooks.report(Stage::Resolving, (i + 1) as f64 / split_ranges.len() as f64);
+        }
+    }
+    hooks.report(Stage::Resolving, 1.0);
+    job_hooks.stage_finished(Stage::Resolving);
+
+    all_splits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let split_confidence = |time: f64| {
+        found_splits
+            .iter()
+            .find(|(found, _)| *found == time)
+            .and_then(|(_, confidence)| *confidence)
+    };
+    porcelain::emit("initial-delay", &[&initial_delay]);
+    for (time, delay, source, label) in &all_splits {
+        porcelain::emit(
+            "split",
+            &[
+                time,
+                delay,
+                source,
+                &label.as_deref().unwrap_or(""),
+                &split_confidence(*time)
+                    .map(|c| format!("{:.2}", c))
+                    .unwrap_or_default(),
+            ],
+        );
+    }
+    if manifest.resolved_splits.is_none() && !options.dry_run {
+        manifest.resolved_splits = Some(all_splits.clone());
+        manifest.found_splits = found_splits.clone();
+        manifest.save(&tmpdir)?;
+    }
+    validate_ramps(&all_splits, &ramps)?;
+
+    // A negative initial delay trims the head of the first segment, which must survive it
+    let mut start_trim: Option<(f64, Option<f64>)> = None;
+    if initial_delay < 0.0 {
+        let trim = -initial_delay / 1000.0;
+        let first_segment = all_splits
+            .iter()
+            .map(|(point, _, _, _)| *point)
+            .chain(ramps.iter().map(|ramp| ramp.start))
+            .fold(media_duration, f64::min);
+        if trim >= first_segment {
+            fs::remove_dir_all(&tmpdir)?;
+            bail!(
+                "An initial delay of {:.3} ms trims more than the first segment ({:.3} s).",
+                initial_delay,
+                first_segment
+            );
+        }
+        let peak = if extract_track {
+            measure_head_peak(path_to_str(&flac_path)?, "0:a:0", trim)?
+        } else {
+            measure_head_peak(input, &format!("0:{}", stream), trim)?
+        };
+        if let Some(peak) = peak
+            && peak > START_TRIM_AUDIBLE_DB
+        {
+            warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::TrimNotSilent,
+                    &[
+                        ("seconds", format!("{:.3}", trim)),
+                        ("peak", format!("{:.1}", peak))
+                    ]
+                )
+            );
+        }
+        start_trim = Some((trim, peak));
+    }
+
+    // Play the plan through on paper first, so an impossible one fails before any ffmpeg work
+    let (split_points, delays, tempos) = segment_boundaries(&all_splits, &ramps, initial_delay);
+    let track = TrackReport {
+        stream: audio_meta.absolute_index,
+        initial_delay,
+        splits: all_splits
+            .iter()
+            .map(|(time, delay, _, label)| SplitPoint {
+                time: *time,
+                delay: *delay,
+                label: label.clone(),
+                frames: Default::default(),
+            })
+            .collect(),
+        drift_ppm,
+    };
+    let simulated =
+        match simulate_segments(&split_points, &delays, &tempos, media_duration, sample_rate) {
+            Ok(simulated) => simulated,
+            Err(e) => {
+                fs::remove_dir_all(&tmpdir)?;
+                return Err(e);
+            }
+        };
+
+    // Title of the corrected track: the template if any, the untouched title otherwise
+    let short_number = |value: f64| {
+        format!("{:.3}", value)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    };
+    let title_values = [
+        ("orig_title", original_title.clone()),
+        ("lang", original_lang.clone()),
+        ("codec", original_codec.clone()),
+        ("stream", stream.to_string()),
+        ("offset", short_number(initial_delay)),
+        ("splits", all_splits.len().to_string()),
+        ("drift_ppm", short_number(drift_ppm.unwrap_or(0.0))),
+    ];
+    let corrected_track_title = match (keep_original, &corrected_title, &title_template) {
+        (true, Some(template), _) | (_, _, Some(template)) => {
+            render_template(template, &title_values)?
+        }
+        (true, None, None) => "Corrected".to_string(),
+        (false, _, None) => original_title.clone(),
+    };
+
+    // Automatic suggestions below --min-confidence are not applied without review
+    let offset_confidence = reference_alignment
+        .as_ref()
+        .map(ReferenceAlignment::confidence);
+    let describe_confidence = |confidence: Option<f64>| {
+        confidence.map_or_else(
+            || "not scored".to_string(),
+            |c| format!("confidence {:.2}", c),
+        )
+    };
+    let mut low_confidence = Vec::new();
+    if let Some(min_confidence) = min_confidence {
+        if !initial_delay_given
+            && (reference_alignment.is_some() || onset_offset.is_some())
+            && offset_confidence.is_none_or(|c| c < min_confidence)
+        {
+            low_confidence.push(format!(
+                "initial delay {:.3} ms ({})",
+                initial_delay,
+                describe_confidence(offset_confidence)
+            ));
+        }
+        for (time, confidence) in &found_splits {
+            if confidence.is_none_or(|c| c < min_confidence) {
+                low_confidence.push(format!(
+                    "split at {:.3}s ({})",
+                    time,
+                    describe_confidence(*confidence)
+                ));
+            }
+        }
+    }
+
+    // --- User Confirmation ---
+    if !all_splits.is_empty()
+        || !low_confidence.is_empty()
+        || !ramps.is_empty()
+        || plan_out.is_some()
+        || options.review_dir.is_some()
+        || options.export_previews.is_some()
+        || options.resolve_only
+        || options.dry_run
+        || options.sample_encode.is_some()
+        || start_trim.is_some()
+        || drift_ppm.is_some()
+    {
+        // Get audio duration for the selected stream
+        let audio_duration = match get_audio_stream_duration(input, stream) {
+            Ok(Some(dur)) => format!("{:.3} s", dur),
+            Ok(None) => "unknown".to_string(),
+            Err(_) => "unknown".to_string(),
+        };
+
+        let mut report = PlanReport::default();
+        // Delays as they are applied: whole samples, rounded once from the start of the track
+        let steps = step_samples(
+            &std::iter::once(initial_delay)
+                .chain(all_splits.iter().map(|(_, delay, _, _)| *delay))
+                .map(Delay::from_millis)
+                .collect::<Vec<_>>(),
+            sample_rate,
+        );
+        let mut plan_rows: Vec<(f64, [String; 4], Option<f64>)> = all_splits
+            .iter()
+            .zip(&steps[1..])
+            .map(|((point, delay, source, label), step)| {
+                (
+                    *point,
+                    [
+                        source.clone(),
+                        format!("{:.3}", point),
+                        format!("{:.3} ({:+} samples)", delay, step),
+                        label.clone().unwrap_or_default(),
+                    ],
+                    split_confidence(*point),
+                )
+            })
+            .collect();
+        for ramp in &ramps {
+            plan_rows.push((
+                ramp.start,
+                [
+                    "ramp".to_string(),
+                    format!("{:.3}-{:.3}", ramp.start, ramp.end),
+                    format!("{:.3} (gradual)", ramp.delay),
+                    String::new(),
+                ],
+                None,
+            ));
+        }
+        if let Some((trim, _)) = start_trim {
+            plan_rows.push((
+                0.0,
+                [
+                    "start trim".to_string(),
+                    format!("0.000-{:.3}", trim),
+                    format!("{:.3} ({:+} samples)", initial_delay, steps[0]),
+                    String::new(),
+                ],
+                None,
             ));
         }
+        plan_rows.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, [source, split, delay, label], confidence) in plan_rows {
+            report.add_split(source, split, delay, label, confidence);
+        }
+        report.offset_confidence = offset_confidence;
+        for (i, segment) in simulated.iter().enumerate() {
+            let adjustment = if segment.trim > 0.0 {
+                format!("-{:.3}", segment.trim)
+            } else {
+                format!("+{:.3}", segment.silence)
+            };
+            report.add_segment(
+                format!("{}", i + 1),
+                format!("{:.3}-{:.3}", segment.source_start, segment.source_end),
+                adjustment,
+                format!("{:.3}-{:.3}", segment.output_start, segment.output_end),
+            );
+        }
+
+        report.add_detail("Input File", input.as_str());
+        report.add_detail("Output File", output.as_str());
+        report.add_detail("Audio Duration", audio_duration);
+        if let Some(last) = simulated.last() {
+            // The drift correction stretches the concatenated track as a whole
+            let simulated_duration =
+                last.output_end * (1.0 + drift_ppm.unwrap_or(0.0) / 1_000_000.0);
+            report.add_detail(
+                "Simulated Duration",
+                format!(
+                    "{:.3} s ({:+.3} s)",
+                    simulated_duration,
+                    simulated_duration - media_duration
+                ),
+            );
+        }
+
+        let stream_name = if !original_title.is_empty() {
+            original_title.clone()
+        } else if !original_lang.is_empty() {
+            original_lang.clone()
+        } else {
+            "Untitled".to_string()
+        };
+
+        report.add_detail(
+            "Initial Delay",
+            format!(
+                "{:.3} ms ({:+} samples at {} Hz)",
+                initial_delay, steps[0], sample_rate
+            ),
+        );
+        if let Some(ppm) = drift_ppm {
+            report.add_detail(
+                "Clock Drift",
+                format!(
+                    "{:+.3} ppm (stretch {:.9}){}",
+                    ppm,
+                    1.0 + ppm / 1_000_000.0,
+                    if drift_from_sync_points {
+                        ", from sync points"
+                    } else {
+                        ""
+                    }
+                ),
+            );
+        }
+
+        if let (Some(reference), Some(alignment)) = (&reference, &reference_alignment) {
+            report.add_detail(
+                "Reference",
+                format!(
+                    "{} ({}, {:.3} ms, confidence {:.2})",
+                    reference,
+                    alignment.describe(),
+                    alignment.offset_ms(),
+                    alignment.confidence()
+                ),
+            );
+        }
+        if let (Some(onset_stream), Some(offset_ms)) = (match_onset, onset_offset) {
+            report.add_detail(
+                "Matched Onset",
+                format!("stream {} ({:.3} ms)", onset_stream, offset_ms),
+            );
+        }
+
+        report.add_detail("Stream ID", format!("#{}", stream));
+        if commentary {
+            report.add_detail(
+                "Stream Name",
+                format!("{} ⚠️ commentary or audio description?", stream_name),
+            );
+        } else {
+            report.add_detail("Stream Name", stream_name);
+        }
+        report.add_detail("Default Stream", default_stream_detail.as_str());
+        if keep_original {
+            report.add_detail(
+                "Original Stream",
+                format!(
+                    "kept as the last stream, corrected stream titled '{}'",
+                    corrected_track_title
+                ),
+            );
+        } else if title_template.is_some() {
+            report.add_detail("Title", corrected_track_title.as_str());
+        }
+        report.add_detail("Codec", original_codec.as_str());
+        report.add_detail("Bitrate", bitrate.as_deref().unwrap_or("lossless"));
+        report.add_detail(
+            "Silence Threshold",
+            match (noise_floor, auto_threshold) {
+                (Some(floor), Some(margin)) => format!(
+                    "{:.1} LUFS (noise floor {:.1} + {:.1} dB)",
+                    silence_threshold, floor, margin
+                ),
+                _ => format!("{:.1} LUFS", silence_threshold),
+            },
+        );
+        if let Some(max_peak) = max_peak {
+            report.add_detail("Max True Peak", format!("{:.1} dBFS", max_peak));
+        }
+        if let Some(crossfade) = crossfade_ms
+            && crossfade > 0.0
+        {
+            report.add_detail("Crossfade", format!("{:.1} ms at each join", crossfade));
+        }
+        if !profile_outputs.is_empty() {
+            report.add_detail(
+                "Profile Outputs",
+                profile_outputs
+                    .iter()
+                    .map(|profile| format!("{} ({:+.1} ms)", profile.output, profile.offset))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+        }
+        if let Some(kind) = detector_kind
+            && !split_ranges.is_empty()
+        {
+            report.add_detail(
+                "Split Detector",
+                match (kind, &detector_command) {
+                    (DetectorKind::External, Some(command)) => format!("{} ({})", kind, command),
+                    _ => kind.to_string(),
+                },
+            );
+        }
+        if let Some((trim, peak)) = start_trim {
+            let content = match peak {
+                Some(peak) if peak > START_TRIM_AUDIBLE_DB => {
+                    format!("⚠️ not silent, peak {:.1} dBFS", peak)
+                }
+                Some(peak) if peak.is_finite() => format!("silent, peak {:.1} dBFS", peak),
+                _ => "silent".to_string(),
+            };
+            report.add_detail("Start Trim", format!("{:.3} s ({})", trim, content));
+        }
+        if let Some(last) = previous_edits.last() {
+            report.add_detail(
+                "Previous Edits",
+                format!(
+                    "{} (last on {} UTC: {})",
+                    previous_edits.len(),
+                    last.applied_at_utc(),
+                    last.describe_edit()
+                ),
+            );
+        }
+
+        info!("\n▶️ {}", t(Msg::ProposedPlan));
+        info!("{}", report.split_table());
+        info!("\n▶️ {}", t(Msg::SimulatedTimeline));
+        info!("{}", report.segment_table());
+        info!("\n▶️ {}", t(Msg::JobDetails));
+        info!("{}", report.details_table());
+
+        if let Some(review_dir) = &options.review_dir
+            && !split_points.is_empty()
+        {
+            fs::create_dir_all(review_dir)?;
+            info!(
+                "\n🖼️ {}",
+                tf(
+                    Msg::RenderingWaveforms,
+                    &[
+                        ("seconds", format!("{:.0}", REVIEW_WINDOW_SECONDS)),
+                        ("count", split_points.len().to_string()),
+                        ("dir", review_dir.to_string())
+                    ]
+                )
+            );
+            for (i, split) in split_points.iter().enumerate() {
+                let window = (
+                    (split - REVIEW_WINDOW_SECONDS).max(0.0),
+                    (split + REVIEW_WINDOW_SECONDS).min(media_duration),
+                );
+                let image =
+                    Path::new(review_dir).join(format!("split_{:02}_{:.3}s.png", i + 1, split));
+                render_split_waveform(input, stream, window, *split, &image, options.debug)?;
+                info!("   {}", image.display());
+            }
+        }
+
+        if let Some(preview_dir) = &options.export_previews
+            && !split_points.is_empty()
+        {
+            info!(
+                "\n🎧 {}",
+                tf(
+                    Msg::ExportingPreviews,
+                    &[
+                        ("seconds", format!("{:.0}", PREVIEW_SECONDS)),
+                        ("count", split_points.len().to_string()),
+                        ("dir", preview_dir.to_string())
+                    ]
+                )
+            );
+            let previews = export_previews(
+                input,
+                stream,
+                media_duration,
+                (&split_points, &delays, &tempos),
+                Path::new(preview_dir),
+                tmpdir.as_path(),
+                &run,
+            )?;
+            for preview in previews {
+                info!("   {}", preview.display());
+            }
+        }
+
+        if let Some(plan_out) = &plan_out {
+            report.write(plan_out)?;
+            info!(
+                "✅ {}",
+                tf(Msg::WrotePlan, &[("path", plan_out.to_string())])
+            );
+        }
+
+        // Resolving only writes the task file, so there is nothing to confirm
+        if options.dry_run {
+            match &plan_out {
+                Some(plan_out) => info!(
+                    "\n{}",
+                    tf(Msg::DryRunStopPlan, &[("path", plan_out.to_string())])
+                ),
+                None => info!("\n{}", t(Msg::DryRunStop)),
+            }
+            fs::remove_dir_all(&tmpdir)?;
+            return Ok(SyncReport {
+                output: None,
+                tracks: vec![track],
+            });
+        } else if let Some(length) = options.sample_encode {
+            // Center the sample on the first boundary, or start at the beginning without one
+            let center = split_points.first().copied().unwrap_or(length / 2.0);
+            let start = (center - length / 2.0).clamp(0.0, (media_duration - length).max(0.0));
+            let window = (start, (start + length).min(media_duration));
+            info!(
+                "\nℹ️ {}",
+                tf(
+                    Msg::EncodingSample,
+                    &[
+                        ("start", format!("{:.3}", window.0)),
+                        ("end", format!("{:.3}", window.1)),
+                        ("codec", original_codec.to_string()),
+                        (
+                            "bitrate",
+                            bitrate
+                                .clone()
+                                .unwrap_or_else(|| t(Msg::Lossless).to_string())
+                        )
+                    ]
+                )
+            );
+            let stats = encode_sample(
+                input,
+                stream,
+                window,
+                (&split_points, &delays, &tempos),
+                (&original_codec, bitrate.as_deref(), &audio_meta.format),
+                tmpdir.as_path(),
+                &run,
+            )?;
+            let projected = stats.size as f64 * media_duration / stats.duration.max(f64::EPSILON);
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .set_header(vec![t(Msg::HeaderMeasurement), t(Msg::HeaderValue)]);
+            table.add_row(vec![
+                "Sample".to_string(),
+                format!(
+                    "{:.3}s - {:.3}s ({:.3} s)",
+                    window.0, window.1, stats.duration
+                ),
+            ]);
+            table.add_row(vec![
+                "Encoded Size".to_string(),
+                format!("{:.1} KiB", stats.size as f64 / 1024.0),
+            ]);
+            table.add_row(vec![
+                "Effective Bitrate".to_string(),
+                format!(
+                    "{:.1} kb/s",
+                    stats.size as f64 * 8.0 / stats.duration.max(f64::EPSILON) / 1000.0
+                ),
+            ]);
+            table.add_row(vec![
+                "Projected Track Size".to_string(),
+                format!("{:.1} MiB", projected / (1024.0 * 1024.0)),
+            ]);
+            table.add_row(vec![
+                "Loudness".to_string(),
+                format!(
+                    "{:.1} LUFS (source {:.1} LUFS, {:+.1} LU)",
+                    stats.encoded_loudness,
+                    stats.source_loudness,
+                    stats.encoded_loudness - stats.source_loudness
+                ),
+            ]);
+            table.add_row(vec![
+                "Sample Peak".to_string(),
+                match stats.peak {
+                    Some(peak) if peak >= SAMPLE_CLIP_DB => {
+                        format!("⚠️ {:.1} dBFS (may clip)", peak)
+                    }
+                    Some(peak) => format!("{:.1} dBFS", peak),
+                    None => "unknown".to_string(),
+                },
+            ]);
+            info!("\n▶️ {}", t(Msg::SampleEncode));
+            info!("{}", table);
+            info!("\n{}", t(Msg::SampleEncodeStop));
+            fs::remove_dir_all(&tmpdir)?;
+            return Ok(SyncReport {
+                output: None,
+                tracks: vec![track],
+            });
+        } else if options.resolve_only {
+            info!("\n{}", t(Msg::ResolvedOnly));
+        } else if options.yes && low_confidence.is_empty() {
+            info!("\n{}", t(Msg::YesProvided));
+        } else {
+            if !low_confidence.is_empty() {
+                warn!(
+                    "\n⚠️ {}",
+                    tf(
+                        Msg::BelowConfidence,
+                        &[
+                            (
+                                "confidence",
+                                format!("{:.2}", min_confidence.unwrap_or_default())
+                            ),
+                            ("splits", low_confidence.join(", "))
+                        ]
+                    )
+                );
+                // Without a terminal to review in, the file is left for a manual run
+                if options.yes && (!io::stdin().is_terminal() || options.quiet) {
+                    fs::remove_dir_all(&tmpdir)?;
+                    return Err(RunError::NeedsReview(low_confidence.join(", ")).into());
+                }
+            }
+            println!("\n{}", t(Msg::ProceedPlan));
+            let mut input = String::new();
+            let prompt = PromptGuard::new();
+            io::stdin().read_line(&mut input)?;
+            drop(prompt);
+            if !is_yes(&input) {
+                println!("{}", t(Msg::Aborting));
+                fs::remove_dir_all(&tmpdir)?;
+                // A declined stream plan leaves its stream unchanged; the other plans go on
+                if options.track_output.is_some() {
+                    return Ok(SyncReport::default());
+                }
+                return Err(RunError::Aborted.into());
+            }
+        }
+    }
+
+    // Optionally write the task to a file (after confirmation); `resolve` always writes one
+    let write_task_file = options
+        .write_task_file
+        .clone()
+        .or(options.resolve_only.then_some(None));
+    if let Some(write_task_file) = &write_task_file {
+        let out_path = if let Some(path) = write_task_file {
+            path.clone().to_string()
+        } else {
+            // Use input file path with extension replaced by .json
+            let input_path = std::path::Path::new(input);
+            let mut out = input_path.to_path_buf();
+            out.set_extension("json");
+            out.to_string_lossy().to_string()
+        };
+        let mut task_sources = sources.clone();
+        task_sources.push(("output file", output.as_str()));
+        ensure_not_overwriting(&out_path, "task file", &task_sources)?;
+        let task = Task {
+            input: Some(input.to_string()),
+            output: Some(output.to_string()),
+            // The task names the stream auto picked, so a rerun processes the same one
+            stream: match stream_arg {
+                Some(StreamSpecifier::Auto) => Some(StreamSpecifier::Index(stream)),
+                other => other,
+            },
+            streams: Vec::new(),
+            language: language.clone(),
+            stream_title: stream_title.clone(),
+            initial_delay: Some(initial_delay),
+            splits: splits.clone(),
+            split_ranges: split_ranges.clone(),
+            ramps: ramps.clone(),
+            drift_ppm,
+            stretch: None,
+            sync_points: Vec::new(),
+            bitrate: bitrate.clone(),
+            silence_threshold: Some(silence_threshold),
+            auto_threshold,
+            max_peak,
+            avoid_speech: Some(avoid_speech),
+            zero_crossing: Some(zero_crossing),
+            detector: detector_kind,
+            hooks: (!job_hooks.commands.is_empty()).then(|| job_hooks.commands.clone()),
+            media_server: media_server.clone(),
+            profile_outputs: profile_outputs.clone(),
+            crossfade: crossfade_ms,
+            detector_command: detector_command.clone(),
+            fit_length: Some(fit_length),
+            pad_head: Some(pad_head),
+            trim_tail: Some(trim_tail),
+            fps,
+            shift_video: None,
+            stream_plans: Vec::new(),
+            auto_mkv: Some(auto_mkv),
+            refresh_stats: Some(refresh_stats),
+            tag_history: Some(tag_history),
+            shift_subtitles: shift_subtitles.clone(),
+            shift_chapters: Some(shift_chapters),
+            keep_original: Some(keep_original),
+            corrected_title: corrected_title.clone(),
+            title: title_template.clone(),
+            avoid_negative_ts: timestamps.avoid_negative_ts,
+            muxdelay: timestamps.muxdelay,
+            muxpreload: timestamps.muxpreload,
+            copyts: Some(timestamps.copyts),
+            subtitles: subtitles.clone(),
+            fix_channels: Some(fix_channels),
+            ffmpeg_threads,
+            jobs,
+            deterministic: Some(deterministic),
+            segment_files: Some(segment_files),
+            intermediate,
+            stall_timeout: Some(stall_timeout),
+            abort_on_stall: Some(abort_on_stall),
+            ffmpeg_path: ffmpeg_path.clone(),
+            ffprobe_path: ffprobe_path.clone(),
+            temp_dir: temp_dir.clone(),
+            reference: reference.clone(),
+            reference_stream,
+            match_onset,
+            in_sync_threshold: Some(in_sync_threshold),
+            min_confidence,
+        };
+        write_file_atomic(&out_path, task.to_file_string(&out_path)?.as_bytes())?;
+        porcelain::emit("task-file", &[&out_path]);
+        info!(
+            "✅ {}",
+            tf(Msg::WroteTask, &[("path", out_path.to_string())])
+        );
+    }
+    if options.resolve_only {
+        fs::remove_dir_all(&tmpdir)?;
+        return Ok(SyncReport {
+            output: None,
+            tracks: vec![track],
+        });
+    }
+
+    let final_flac = match Manifest::file(&tmpdir, manifest.edited.as_ref()) {
+        Some(edited) => {
+            info!("♻️ {}", t(Msg::ReusingConcatenated));
+            edited
+        }
+        None => {
+            // 3. Split and delay
+            info!("ℹ️ {}", t(Msg::Splitting));
+            hooks.check_cancelled()?;
+            let final_flac = if segment_files {
+                let split_files = split_and_delay_audio(
+                    flac_path.as_path(),
+                    &split_points,
+                    &delays,
+                    &tempos,
+                    tmpdir.as_path(),
+                    &run,
+                    &|fraction| {
+                        hooks.report(Stage::Splitting, fraction);
+                        temp_usage.sample();
+                    },
+                )?;
+                temp_usage.record(Stage::Splitting);
+
+                // 4. Concat list
+                hooks.check_cancelled()?;
+                hooks.report(Stage::Concatenating, 0.0);
+                let final_flac =
+                    concat_audio_segments(&split_files, tmpdir.as_path(), &run, &|seconds| {
+                        hooks.report(Stage::Concatenating, progress_fraction(seconds))
+                    })?;
+                hooks.report(Stage::Concatenating, 1.0);
+                job_hooks.stage_finished(Stage::Concatenating);
+                temp_usage.record(Stage::Concatenating);
+                final_flac
+            } else {
+                // Cut, delay and concatenate in one pass, without a file per segment
+                hooks.report(Stage::Splitting, 0.0);
+                let final_flac = edit_audio(
+                    flac_path.as_path(),
+                    &split_points,
+                    &delays,
+                    &tempos,
+                    tmpdir.as_path(),
+                    &run,
+                    &|seconds| {
+                        hooks.report(Stage::Splitting, progress_fraction(seconds));
+                        temp_usage.sample();
+                    },
+                )?;
+                hooks.report(Stage::Splitting, 1.0);
+                job_hooks.stage_finished(Stage::Splitting);
+                temp_usage.record(Stage::Splitting);
+                final_flac
+            };
+
+            // Correct clock drift across the whole track
+            let final_flac = match drift_ppm {
+                Some(ppm) if ppm != 0.0 => {
+                    info!(
+                        "ℹ️ {}",
+                        tf(Msg::CorrectingDrift, &[("ppm", format!("{:+.3}", ppm))])
+                    );
+                    let drift_path =
+                        intermediate_file(&tmpdir, "target_audio_final_drift", run.intermediate);
+                    correct_drift(final_flac.as_path(), drift_path.as_path(), ppm, &run)?;
+                    drift_path
+                }
+                _ => final_flac,
+            };
+            manifest.edited = final_flac
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string());
+            manifest.save(&tmpdir)?;
+            final_flac
+        }
+    };
+
+    let final_extension = audio_extension_for_codec(&original_codec);
+    let final_audio_for_remux = tmpdir.join(format!("final_for_remux.{}", final_extension));
+    if Manifest::file(&tmpdir, manifest.encoded.as_ref()).is_some() {
+        info!("♻️ {}", t(Msg::ReusingEncoded));
+    } else {
+        // --- Fit to original length if requested ---
+        info!("\n▶️ {}", t(Msg::AdjustingLengths));
+        hooks.check_cancelled()?;
+        hooks.report(Stage::Adjusting, 0.0);
+
+        let mut fitted_flac = final_flac.clone();
+        let extents = if pad_head || trim_tail {
+            Some(measure_extents(input, stream)?)
+        } else {
+            None
+        };
+        let head_pad = extents
+            .filter(|_| pad_head)
+            .map(|e| e.head_gap())
+            .filter(|gap| *gap > 0.0005)
+            .unwrap_or(0.0);
+        if head_pad > 0.0 {
+            info!(
+                "⏮️ {}",
+                tf(Msg::PaddingHead, &[("seconds", format!("{:.3}", head_pad))])
+            );
+            let padded_path =
+                intermediate_file(&tmpdir, "target_audio_final_padded", run.intermediate);
+            pad_audio_head(fitted_flac.as_path(), padded_path.as_path(), head_pad, &run)?;
+            fitted_flac = padded_path;
+        }
+        if let Some(extents) = extents.filter(|_| trim_tail) {
+            // The edited audio starts where the original did, or with the video when padded
+            let limit = extents.video.end - (extents.audio.start - head_pad);
+            let duration = get_file_duration(path_to_str(fitted_flac.as_path())?)?;
+            if limit > 0.0 && duration > limit + 0.001 {
+                info!(
+                    "✂️ {}",
+                    tf(
+                        Msg::CuttingTail,
+                        &[("seconds", format!("{:.3}", duration - limit))]
+                    )
+                );
+                let trimmed_path =
+                    intermediate_file(&tmpdir, "target_audio_final_trimmed", run.intermediate);
+                fit_audio_to_length(fitted_flac.as_path(), trimmed_path.as_path(), limit, &run)?;
+                fitted_flac = trimmed_path;
+            }
+        }
+        let mut orig_duration_val = None;
+        let mut processed_duration_val = None;
+        let mut adjusted_duration_val = None;
+        if fit_length && let Ok(Some(orig_duration)) = get_audio_stream_duration(input, stream) {
+            // A padded head is part of the length to keep
+            let orig_duration = orig_duration + head_pad;
+            orig_duration_val = Some(orig_duration);
+            // Get duration of the processed audio
+            let processed_duration = get_file_duration(path_to_str(fitted_flac.as_path())?)?;
+            processed_duration_val = Some(processed_duration);
+            let fitted_path =
+                intermediate_file(&tmpdir, "target_audio_final_fitted", run.intermediate);
+            fit_audio_to_length(
+                fitted_flac.as_path(),
+                fitted_path.as_path(),
+                orig_duration,
+                &run,
+            )?;
+            fitted_flac = fitted_path;
+            // Get duration of the adjusted audio
+            let adjusted_duration = get_file_duration(path_to_str(fitted_flac.as_path())?)?;
+            adjusted_duration_val = Some(adjusted_duration);
+        }
+
+        hooks.report(Stage::Adjusting, 1.0);
+
+        job_hooks.stage_finished(Stage::Adjusting);
+        temp_usage.record(Stage::Adjusting);
+
+        // Show duration table if fit_length was used
+        if fit_length {
+            use comfy_table::Table;
+            let mut dur_table = Table::new();
+            dur_table.set_header(vec![t(Msg::HeaderType), t(Msg::HeaderDurationSeconds)]);
+            let orig_str = orig_duration_val
+                .map(|v| format!("{:.3}", v))
+                .unwrap_or_else(|| "unknown".to_string());
+            let new_str = processed_duration_val
+                .map(|v| format!("{:.3}", v))
+                .unwrap_or_else(|| "unknown".to_string());
+            let adj_str = adjusted_duration_val
+                .map(|v| format!("{:.3}", v))
+                .unwrap_or_else(|| "unknown".to_string());
+            dur_table.add_row(vec!["Original", orig_str.as_str()]);
+            dur_table.add_row(vec!["New (pre-adjustment)", new_str.as_str()]);
+            dur_table.add_row(vec!["Adjusted (post-fit)", adj_str.as_str()]);
+            info!("{}", dur_table);
+        }
+
+        // 5. Convert final audio back to original codec
+        info!("\n▶️ {}", t(Msg::Converting));
+        hooks.check_cancelled()?;
+        hooks.report(Stage::Encoding, 0.0);
+        convert_audio_codec(
+            fitted_flac.as_path(),
+            &original_codec,
+            bitrate.as_deref(),
+            &audio_meta.format,
+            final_audio_for_remux.as_path(),
+            &run,
+            &|seconds| hooks.report(Stage::Encoding, progress_fraction(seconds)),
+        )?;
+        hooks.report(Stage::Encoding, 1.0);
+        job_hooks.stage_finished(Stage::Encoding);
+
+        // Make sure the encoded track is what the remux expects before touching the output
+        let expected = AudioProperties {
+            codec: original_codec.clone(),
+            duration: Some(get_file_duration(path_to_str(fitted_flac.as_path())?)?),
+            ..source_properties
+        };
+        let encoded =
+            verify_encoded_track(path_to_str(final_audio_for_remux.as_path())?, &expected)
+                .map_err(|e| RunError::VerificationFailed(e.to_string()))?;
+        info!(
+            "✅ {}",
+            tf(
+                Msg::VerifiedTrack,
+                &[
+                    ("codec", encoded.codec.to_string()),
+                    ("channels", encoded.channels.to_string()),
+                    ("rate", encoded.sample_rate.to_string()),
+                    (
+                        "duration",
+                        format!("{:.3}", encoded.duration.unwrap_or_default())
+                    )
+                ]
+            )
+        );
+        manifest.encoded = Some(format!("final_for_remux.{}", final_extension));
+        manifest.save(&tmpdir)?;
+    }
+
+    // Optionally export the corrected track as a standalone audio file
+    if let Some(export_path) = &export_path {
+        let written = export_audio_track(
+            final_audio_for_remux.as_path(),
+            export_path,
+            &original_codec,
+            &run,
+        )?;
+        info!(
+            "✅ {}",
+            tf(Msg::ExportedTrack, &[("path", written.to_string())])
+        );
+    }
+    temp_usage.record(Stage::Encoding);
+
+    // Re-time the selected embedded subtitles with the same plan as the audio
+    let mut replaced_subtitles = Vec::new();
+    if let Some(indices) = &shift_subtitles {
+        info!("\n▶️ {}", t(Msg::ShiftSubtitles));
+        let timeline = Timeline::new(simulated.clone(), drift_ppm);
+        let streams = list_subtitle_streams(input)?;
+        for index in indices {
+            if !streams.iter().any(|s| s.index == *index) {
+                bail!("Stream {} is not a subtitle stream of {}.", index, input);
+            }
+        }
+        for subtitle in streams
+            .iter()
+            .filter(|s| indices.is_empty() || indices.contains(&s.index))
+        {
+            if !subtitle.is_text() {
+                warn!(
+                    "⚠️ {}",
+                    tf(
+                        Msg::BitmapSubtitle,
+                        &[
+                            ("stream", subtitle.index.to_string()),
+                            ("codec", subtitle.codec.to_string())
+                        ]
+                    )
+                );
+                continue;
+            }
+            let (replaced, stats) =
+                retime_embedded_subtitle(input, subtitle, &timeline, &tmpdir, options.debug)?;
+            info!(
+                "✅ {}",
+                tf(
+                    Msg::ShiftedSubtitleStream,
+                    &[
+                        ("stream", subtitle.index.to_string()),
+                        ("codec", subtitle.codec.to_string()),
+                        ("cues", (stats.cues - stats.dropped).to_string()),
+                        ("dropped", stats.dropped.to_string())
+                    ]
+                )
+            );
+            replaced_subtitles.push(replaced);
+        }
+    }
+
+    // Move the chapters with the delayed content
+    let mut chapters_path = None;
+    if shift_chapters {
+        info!("\n▶️ {}", t(Msg::ShiftChapters));
+        let chapters = read_chapters(input)?;
+        if chapters.is_empty() {
+            info!("✅ {}", t(Msg::NoChapters));
+        } else {
+            let timeline = Timeline::new(simulated.clone(), drift_ppm);
+            let retimed = retime_chapters(&chapters, &timeline);
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).set_header(vec![
+                t(Msg::HeaderChapter),
+                t(Msg::HeaderTitle),
+                t(Msg::HeaderStart),
+                t(Msg::HeaderNewStart),
+                t(Msg::HeaderShift),
+            ]);
+            for (i, (old, new)) in chapters.iter().zip(&retimed).enumerate() {
+                table.add_row(vec![
+                    (i + 1).to_string(),
+                    old.title.clone().unwrap_or_default(),
+                    format!("{:.3} s", old.start),
+                    format!("{:.3} s", new.start),
+                    format!("{:+.3} ms", (new.start - old.start) * 1000.0),
+                ]);
+            }
+            info!("{table}");
+            let path = tmpdir.join("chapters.ffmeta");
+            write_chapters_metadata(&retimed, &path)?;
+            chapters_path = Some(path);
+        }
+    }
+
+    // A stream plan ends with its track; all plans of the task share one remux
+    if let Some(track_dir) = &options.track_output {
+        let file_name = final_audio_for_remux.file_name().unwrap_or_default();
+        fs::copy(&final_audio_for_remux, Path::new(track_dir).join(file_name))?;
+        fs::remove_dir_all(&tmpdir)?;
+        return Ok(SyncReport {
+            output: None,
+            tracks: vec![track],
+        });
+    }
+
+    // 6. Remux audio back in place of the original
+    info!("\n▶️ {}", t(Msg::Remuxing));
+    hooks.check_cancelled()?;
+    hooks.report(Stage::Remuxing, 0.0);
+    let mut track_metadata = Vec::new();
+    if !original_lang.is_empty() {
+        track_metadata.push(("language".to_string(), original_lang.clone()));
+    }
+    // With --keep-original the title tells the two tracks apart in the player
+    if !corrected_track_title.is_empty() {
+        track_metadata.push(("title".to_string(), corrected_track_title.clone()));
+    }
+    // Record this edit after any earlier ones, in the track tags or in a sidecar file
+    let mut sidecar_history = None;
+    if tag_history {
+        let mut history = previous_edits.clone();
+        let mut record = EditRecord::new(
+            input,
+            stream,
+            initial_delay,
+            all_splits
+                .iter()
+                .map(|(time, delay, _, label)| SplitPoint {
+                    time: *time,
+                    delay: *delay,
+                    label: label.clone(),
+                    frames: FrameUnits::default(),
+                })
+                .collect(),
+        );
+        record.ramps = ramps.clone();
+        record.drift_ppm = drift_ppm;
+        history.push(record);
+        if supports_track_tags(output) {
+            track_metadata.push(history_tag(&history)?);
+        } else {
+            sidecar_history = Some(history);
+        }
+    }
+    // The remux keeps every stream in place and appends the original with --keep-original
+    let expected_streams = count_streams(input)? + usize::from(keep_original);
+    let staged = StagedFile::new(output)?;
+    remux_audio_stream(
+        input,
+        final_audio_for_remux.as_path(),
+        staged.path(),
+        audio_stream_idx,
+        RemuxOptions {
+            metadata: &track_metadata,
+            subtitles: &replaced_subtitles,
+            chapters: chapters_path.as_deref(),
+            timestamps,
+            keep_original,
+            audio_offset: 0.0,
+        },
+        &run,
+        &|seconds| hooks.report(Stage::Remuxing, progress_fraction(seconds)),
+    )?;
+    if refresh_stats {
+        let tool = refresh_container_stats(staged.path(), audio_stream_idx, &run)?;
+        info!(
+            "✅ {}",
+            tf(Msg::RefreshedStats, &[("tool", tool.to_string())])
+        );
+    }
+    commit_output(staged, output, expected_streams)?;
+    for profile in &profile_outputs {
+        info!(
+            "ℹ️ {}",
+            tf(
+                Msg::RemuxingProfile,
+                &[
+                    ("offset", format!("{:+.1}", profile.offset)),
+                    ("path", profile.output.to_string())
+                ]
+            )
+        );
+        let staged = StagedFile::new(&profile.output)?;
+        remux_audio_stream(
+            input,
+            final_audio_for_remux.as_path(),
+            staged.path(),
+            audio_stream_idx,
+            RemuxOptions {
+                metadata: &track_metadata,
+                subtitles: &replaced_subtitles,
+                chapters: chapters_path.as_deref(),
+                timestamps,
+                keep_original,
+                audio_offset: profile.offset / 1000.0,
+            },
+            &run,
+            &|_| {},
+        )?;
+        commit_output(staged, &profile.output, expected_streams)?;
+        porcelain::emit("profile-output", &[&profile.offset, &profile.output]);
+        info!(
+            "✅ {}",
+            tf(
+                Msg::WroteProfile,
+                &[
+                    ("path", profile.output.to_string()),
+                    ("offset", format!("{:+.1}", profile.offset))
+                ]
+            )
+        );
+    }
+    hooks.report(Stage::Remuxing, 1.0);
+    job_hooks.stage_finished(Stage::Remuxing);
+    if tag_history {
+        match sidecar_history {
+            Some(history) => {
+                let sidecar = sidecar_path(output);
+                write_file_atomic(&sidecar, serde_json::to_string_pretty(&history)?.as_bytes())?;
+                info!(
+                    "✅ {}",
+                    tf(Msg::WroteHistorySidecar, &[("path", sidecar.to_string())])
+                );
+            }
+            None => info!("✅ {}", t(Msg::RecordedHistory)),
+        }
+    }
+
+    if !subtitle_outputs.is_empty() {
+        let timeline = Timeline::new(simulated.clone(), drift_ppm);
+        for (subtitle, subtitle_output) in &subtitle_outputs {
+            let stats =
+                retime_subtitle_file(Path::new(subtitle), Path::new(subtitle_output), &timeline)?;
+            info!(
+                "✅ {}",
+                tf(
+                    Msg::WroteSubtitles,
+                    &[
+                        ("path", subtitle_output.to_string()),
+                        ("cues", (stats.cues - stats.dropped).to_string()),
+                        ("dropped", stats.dropped.to_string())
+                    ]
+                )
+            );
+        }
+    }
+
+    // A muxer quirk can move streams against each other even though every packet was copied
+    match find_timestamp_shifts(input, output) {
+        Ok(shifts) if shifts.is_empty() => {
+            info!("✅ {}", t(Msg::StartsMatch))
+        }
+        Ok(shifts) => {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).set_header(vec![
+                t(Msg::HeaderIndex),
+                t(Msg::HeaderType),
+                t(Msg::HeaderInputStart),
+                t(Msg::HeaderOutputStart),
+                t(Msg::HeaderShift),
+            ]);
+            for shift in &shifts {
+                table.add_row(vec![
+                    shift.index.to_string(),
+                    shift.codec_type.clone(),
+                    format!("{:.3} s", shift.input_start),
+                    format!("{:.3} s", shift.output_start),
+                    format!("{:+.1} ms", shift.shift_ms()),
+                ]);
+            }
+            warn!("\n⚠️ {}", t(Msg::StreamsMoved));
+            info!("{table}");
+            warn!("⚠️ {}", t(Msg::MaybeOutOfSync));
+        }
+        Err(e) => warn!(
+            "⚠️ {}",
+            tf(Msg::StartTimesUnchecked, &[("error", e.to_string())])
+        ),
+    }
+
+    info!("\n▶️ {}", t(Msg::TempSpace));
+    info!("{}", temp_usage.table());
+
+    // Cleanup, or keep the intermediate files for inspection
+    match &keep_temp {
+        Some(dir) => {
+            // Outside the temp root the lock of the run means nothing
+            let _ = fs::remove_file(tmpdir.join(LOCK_FILE));
+            move_dir(&tmpdir, Path::new(dir))?;
+            info!("📦 {}", tf(Msg::KeptTemp, &[("dir", dir.to_string())]));
+        }
+        None => fs::remove_dir_all(&tmpdir)?,
+    }
+
+    // A failed refresh leaves a correct output, so it only warns
+    if let Some(server) = &media_server {
+        match server.refresh(Path::new(output)) {
+            Ok(path) => info!(
+                "✅ {}",
+                tf(
+                    Msg::ServerRefreshed,
+                    &[
+                        ("server", server.kind.to_string()),
+                        ("path", path.to_string())
+                    ]
+                )
+            ),
+            Err(e) => warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::ServerRefreshFailed,
+                    &[
+                        ("server", server.kind.to_string()),
+                        ("error", format!("{:#}", e))
+                    ]
+                )
+            ),
+        }
+    }
+
+    porcelain::emit("output", &[&output]);
+    if options.quiet {
+        println!("{}", output);
+    }
+    info!(
+        "✅ {}",
+        tf(Msg::ProcessingComplete, &[("output", output.to_string())])
+    );
+    Ok(SyncReport {
+        output: Some(output.clone()),
+        tracks: vec![track],
+    })
+}
+
+/// Check a staged output and move it to `output`.
+fn commit_output(staged: StagedFile, output: &str, expected_streams: usize) -> Result<()> {
+    verify_muxed_output(staged.path(), expected_streams)
+        .map_err(|e| RunError::OutputVerificationFailed(output.to_string(), format!("{:#}", e)))?;
+    staged.commit()?;
+    Ok(())
+}
+
+/// The `--shift-video` job: copy the input with its video streams delayed by `delay_ms`.
+fn shift_video_job(
+    options: &SyncOptions,
+    (input, output): (&str, &str),
+    delay_ms: f64,
+    timestamps: MuxerTimestamps,
+    run: &RunOptions,
+    hooks: &PipelineHooks,
+    job_hooks: &JobHooks,
+) -> Result<SyncReport> {
+    if delay_ms == 0.0 {
+        info!("✅ {}", t(Msg::VideoInSync));
+        return Ok(SyncReport::default());
+    }
+    let media_duration = get_file_duration(input)?;
+    let mut report = PlanReport::default();
+    report.add_detail("Input File", input);
+    report.add_detail("Output File", output);
+    report.add_detail(
+        "Video Delay",
+        format!(
+            "{:.3} ms (video {} the audio, audio copied)",
+            delay_ms,
+            if delay_ms > 0.0 { "after" } else { "before" }
+        ),
+    );
+    info!("\n▶️ {}", t(Msg::JobDetails));
+    info!("{}", report.details_table());
+
+    if options.dry_run {
+        info!("\n{}", t(Msg::DryRunStop));
+        return Ok(SyncReport::default());
+    } else if options.yes {
+        info!("\n{}", t(Msg::YesProvided));
+    } else {
+        println!("\n{}", t(Msg::ProceedPlan));
+        let mut answer = String::new();
+        let prompt = PromptGuard::new();
+        io::stdin().read_line(&mut answer)?;
+        drop(prompt);
+        if !is_yes(&answer) {
+            println!("{}", t(Msg::Aborting));
+            return Err(RunError::Aborted.into());
+        }
+    }
+
+    hooks.report(Stage::Remuxing, 0.0);
+    let expected_streams = count_streams(input)?;
+    let staged = StagedFile::new(output)?;
+    shift_video_stream(
+        input,
+        staged.path(),
+        delay_ms / 1000.0,
+        timestamps,
+        run,
+        &|seconds| {
+            hooks.report(
+                Stage::Remuxing,
+                (seconds / media_duration.max(1.0)).clamp(0.0, 1.0),
+            )
+        },
+    )?;
+    commit_output(staged, output, expected_streams)?;
+    hooks.report(Stage::Remuxing, 1.0);
+    job_hooks.stage_finished(Stage::Remuxing);
+    porcelain::emit("output", &[&output]);
+    if options.quiet {
+        println!("{}", output);
+    }
+    info!(
+        "✅ {}",
+        tf(Msg::ProcessingComplete, &[("output", output.to_string())])
+    );
+    Ok(SyncReport {
+        output: Some(output.to_string()),
+        tracks: Vec::new(),
+    })
+}
+
+fn handle_ffmpeg_check() -> Result<()> {
+    info!("🔍 {}\n", t(Msg::CheckingFfmpeg));
+
+    let check_result = check_ffmpeg_installation();
+
+    // Display FFmpeg status
+    if check_result.ffmpeg_available {
+        if let Some(version_info) = &check_result.ffmpeg_version {
+            info!("✅ {}", t(Msg::FfmpegFound));
+            info!(
+                "   {}",
+                tf(
+                    Msg::FfmpegExecutable,
+                    &[("path", check_result.ffmpeg_path.to_string())]
+                )
+            );
+            info!(
+                "   {}",
+                tf(
+                    Msg::FfmpegVersion,
+                    &[
+                        ("major", version_info.major.to_string()),
+                        ("minor", version_info.minor.to_string()),
+                        ("patch", version_info.patch.to_string())
+                    ]
+                )
+            );
+
+            if version_info.is_compatible {
+                info!("   {}", t(Msg::FfmpegCompatible));
+            } else {
+                info!("   {}", t(Msg::FfmpegTooOld));
+            }
+
+            if version_info.is_tested_version {
+                info!("   {}", t(Msg::FfmpegTestedVersion));
+            } else {
+                info!("   {}", t(Msg::FfmpegTestedWith));
+            }
+        } else {
+            warn!("⚠️  {}", t(Msg::FfmpegVersionUnparsed));
+        }
+    } else if let Some(error) = &check_result.error {
+        error!(
+            "❌ {}",
+            tf(
+                Msg::FfmpegNotFound,
+                &[("path", check_result.ffmpeg_path.to_string())]
+            )
+        );
+        info!("   {}", t(Msg::InstallFfmpeg));
+        bail!("FFmpeg is required but not installed: {}", error);
+    }
+
+    info!("");
+
+    // Display FFprobe status
+    if check_result.ffprobe_available {
+        info!(
+            "✅ {}",
+            tf(
+                Msg::FfprobeFound,
+                &[("path", check_result.ffprobe_path.to_string())]
+            )
+        );
+    } else {
+        error!(
+            "❌ {}",
+            tf(
+                Msg::FfprobeNotFound,
+                &[("path", check_result.ffprobe_path.to_string())]
+            )
+        );
+        bail!("FFprobe is required but not installed");
+    }
+
+    info!("");
+
+    // Display filter availability
+    if check_result.ebur128_filter_available {
+        info!("✅ {}", t(Msg::Ebur128Available));
+    } else {
+        error!("❌ {}", t(Msg::Ebur128Missing));
+        info!("   {}", t(Msg::Ebur128Needed));
+    }
+
+    info!("\n🎉 {}", t(Msg::FfmpegCheckComplete));
+    Ok(())
+}
+
+/// Run the installation and environment diagnostics, show them as a table and print them again
+/// as plain text for a bug report.
+fn handle_doctor(doctor: &DoctorArgs, temp_dir: Option<String>) -> Result<()> {
+    info!("🔍 {}\n", t(Msg::CheckingEnvironment));
+    let checks = run_checks(&DoctorOptions {
+        temp_dir,
+        task: doctor.task.clone(),
+        dirs: doctor.dirs.clone(),
+    });
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        t(Msg::HeaderSection),
+        t(Msg::HeaderCheck),
+        t(Msg::HeaderValue),
+        t(Msg::HeaderStatus),
+    ]);
+    for check in &checks {
+        table.add_row(vec![
+            check.section,
+            check.name.as_str(),
+            check.value.as_str(),
+            check.status.symbol(),
+        ]);
+    }
+    info!("{}", table);
+
+    info!("\n📋 {}\n", t(Msg::DoctorReport));
+    info!("```\n{}```", report_text(&checks));
+
+    let errors = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Error)
+        .count();
+    let warnings = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Warning)
+        .count();
+    if errors > 0 {
+        bail!("{} checks failed and {} need attention.", errors, warnings);
+    }
+    info!(
+        "\n🎉 {}",
+        tf(Msg::NoProblems, &[("warnings", warnings.to_string())])
+    );
+    Ok(())
+}
+
+/// Test every ffmpeg build on generated media and print the results per build.
+#[cfg(feature = "selftest")]
+fn handle_selftest(
+    selftest: &crate::cli::SelftestArgs,
+    temp_root: &Path,
+    hooks: &PipelineHooks,
+) -> Result<()> {
+    use crate::selftest::{detect_builds, run_build};
+
+    let builds = detect_builds(&selftest.ffmpeg);
+    let mut checks = Vec::new();
+    for build in &builds {
+        let msg = if selftest.thorough {
+            Msg::TestingThorough
+        } else {
+            Msg::Testing
+        };
+        info!("🧪 {}", tf(msg, &[("build", build.ffmpeg.to_string())]));
+        hooks.check_cancelled()?;
+        let dir = create_temp_dir(temp_root)?;
+        let _cleanup = CancelledRunCleanup::new(&dir, hooks);
+        let run_pipeline = |args: &[String]| -> Result<()> {
+            let mut args = args.to_vec();
+            if selftest.debug {
+                args.push("--debug".to_string());
+            }
+            let mut args = Args::try_parse_from(args)?;
+            args.ffmpeg_path = Some(build.ffmpeg.clone());
+            args.ffprobe_path = Some(build.ffprobe.clone());
+            args.temp_dir = Some(path_to_str(temp_root)?.to_string());
+            run_process(args, hooks)
+        };
+        checks.extend(run_build(
+            build,
+            &dir,
+            selftest.thorough,
+            selftest.debug,
+            &run_pipeline,
+        ));
+        hooks.check_cancelled()?;
+        fs::remove_dir_all(&dir)?;
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        "ffmpeg",
+        t(Msg::HeaderCheck),
+        t(Msg::HeaderResult),
+        t(Msg::HeaderStatus),
+    ]);
+    for check in &checks {
+        table.add_row(vec![
+            check.build.as_str(),
+            check.name,
+            check.value.as_str(),
+            check.status.symbol(),
+        ]);
+    }
+    info!("\n{}", table);
+
+    let failed: std::collections::HashSet<&str> = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Error)
+        .map(|c| c.build.as_str())
+        .collect();
+    if !failed.is_empty() {
+        bail!(
+            "{} of {} ffmpeg builds failed the selftest.",
+            failed.len(),
+            builds.len()
+        );
+    }
+    info!(
+        "\n🎉 {}",
+        tf(Msg::BuildsPassed, &[("count", builds.len().to_string())])
+    );
+    Ok(())
+}
+
+/// Resolve a season template for every episode, confirm all of them at once and process them.
+fn handle_apply_season(season: &ApplySeasonArgs, hooks: &PipelineHooks) -> Result<()> {
+    if season.quiet && !season.yes {
+        bail!(
+            "--quiet hides the episode plan, so it needs --yes to apply it without confirmation."
+        );
+    }
+    let template = SeasonTemplate::load(&season.task)?;
+    let output_dir = season
+        .output_dir
+        .as_ref()
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::path::Path::new(&season.dir).join("synced"));
+    let episodes = find_episodes(&season.dir, &output_dir)?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::ApplyingSeason,
+            &[
+                ("task", season.task.to_string()),
+                ("count", episodes.len().to_string()),
+                ("dir", season.dir.to_string())
+            ]
+        )
+    );
+
+    let tmpdir = create_temp_dir(&usable_temp_root(season.temp_dir.as_deref())?)?;
+    let _cleanup = CancelledRunCleanup::new(&tmpdir, hooks);
+    let mut resolved = Vec::new();
+    for episode in &episodes {
+        hooks.check_cancelled()?;
+        info!(
+            "ℹ️ {}",
+            tf(
+                Msg::ResolvingEpisode,
+                &[("file", episode.display().to_string())]
+            )
+        );
+        let output = output_dir.join(episode.file_name().unwrap_or_default());
+        resolved.push(resolve_episode(
+            &template,
+            episode,
+            &output,
+            &tmpdir,
+            season.debug,
+        )?);
+    }
+    fs::remove_dir_all(&tmpdir)?;
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        t(Msg::HeaderEpisode),
+        t(Msg::HeaderDurationSeconds),
+        t(Msg::HeaderInitialDelay),
+        t(Msg::HeaderSplitPoints),
+        t(Msg::HeaderTaskFile),
+        t(Msg::HeaderStatus),
+    ]);
+    for episode in &resolved {
+        let splits = episode
+            .task
+            .splits
+            .iter()
+            .map(|s| format!("{:.3}:{:.3}", s.time, s.delay))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let status = if episode.in_sync {
+            "already in sync"
+        } else {
+            "to process"
+        };
+        table.add_row(vec![
+            episode.input.clone(),
+            format!("{:.3}", episode.duration),
+            format!("{:.3}", episode.task.initial_delay.unwrap_or(0.0)),
+            splits,
+            episode.task_path.clone(),
+            status.to_string(),
+        ]);
+    }
+    info!("{}", table);
+
+    if season.yes {
+        info!("\n{}", t(Msg::YesProvided));
+    } else {
+        println!("\n{}", t(Msg::ProceedAllEpisodes));
+        let mut input = String::new();
+        let prompt = PromptGuard::new();
+        io::stdin().read_line(&mut input)?;
+        drop(prompt);
+        if !is_yes(&input) {
+            println!("{}", t(Msg::Aborting));
+            return Err(RunError::Aborted.into());
+        }
+    }
+
+    for episode in &resolved {
+        let json = serde_json::to_string_pretty(&episode.task)?;
+        write_file_atomic(&episode.task_path, json.as_bytes())?;
+        info!(
+            "✅ {}",
+            tf(Msg::WroteTask, &[("path", episode.task_path.clone())])
+        );
+    }
+    if season.resolve_only {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&output_dir)?;
+    let mut failures = Vec::new();
+    let mut skipped = 0;
+    for episode in &resolved {
+        hooks.check_cancelled()?;
+        if episode.in_sync {
+            info!(
+                "\n✅ {}",
+                tf(Msg::SkippingInSync, &[("file", episode.input.to_string())])
+            );
+            skipped += 1;
+            continue;
+        }
+        info!(
+            "\n▶️ {}",
+            tf(Msg::Processing, &[("file", episode.input.clone())])
+        );
+        let mut episode_args = vec!["sync-nudger", "--task", &episode.task_path, "--yes"];
+        if season.debug {
+            episode_args.push("--debug");
+        }
+        let result = Args::try_parse_from(episode_args)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut args| {
+                args.quiet = season.quiet;
+                args.temp_dir = season.temp_dir.clone();
+                run_process(args, hooks)
+            });
+        if let Err(e) = result {
+            if hooks.cancel.is_cancelled() {
+                return Err(e);
+            }
+            error!(
+                "❌ {}",
+                tf(
+                    Msg::FileFailed,
+                    &[
+                        ("file", episode.input.to_string()),
+                        ("error", e.to_string())
+                    ]
+                )
+            );
+            error!(target: LOG, "{} failed: {:#}", episode.input, e);
+            failures.push(episode.input.clone());
+        }
+    }
+
+    if failures.is_empty() {
+        info!(
+            "\n✅ {}",
+            tf(
+                Msg::ProcessedEpisodes,
+                &[
+                    ("count", (resolved.len() - skipped).to_string()),
+                    ("dir", output_dir.display().to_string()),
+                    ("skipped", skipped.to_string())
+                ]
+            )
+        );
+        Ok(())
+    } else {
+        bail!(
+            "{} of {} episodes failed: {}",
+            failures.len(),
+            resolved.len(),
+            failures.join(", ")
+        )
+    }
+}
+
+/// Process media files as they arrive in a directory with a season template, moving each source
+/// to the done (or failed) directory afterwards. Runs until cancelled unless `--once` is given.
+fn handle_watch(
+    watch: &WatchArgs,
+    child_args: &[String],
+    temp_root: &Path,
+    hooks: &PipelineHooks,
+) -> Result<()> {
+    let template = SeasonTemplate::load(&watch.task)?;
+    if watch.interval <= 0.0 || !watch.interval.is_finite() {
+        bail!(
+            "--interval must be a positive number of seconds (got {}).",
+            watch.interval
+        );
+    }
+    if watch.jobs == 0 {
+        bail!("--jobs must be at least 1.");
+    }
+    let dir = Path::new(&watch.dir);
+    if !dir.is_dir() {
+        bail!("'{}' is not a directory.", watch.dir);
+    }
+    let subdir = |path: &Option<String>, name: &str| {
+        path.as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| dir.join(name))
+    };
+    let output_dir = subdir(&watch.output_dir, "synced");
+    let done_dir = subdir(&watch.done_dir, "done");
+    let failed_dir = subdir(&watch.failed_dir, "failed");
+    for (option, target) in [
+        ("--output-dir", &output_dir),
+        ("--done-dir", &done_dir),
+        ("--failed-dir", &failed_dir),
+    ] {
+        // Files put there would be picked up again
+        if same_file(target, dir) {
+            bail!("{} must not be the watched directory.", option);
+        }
+    }
+    fs::create_dir_all(&output_dir)?;
+
+    if watch.once {
+        info!(
+            "🔍 {}",
+            tf(
+                Msg::ProcessingDir,
+                &[
+                    ("dir", watch.dir.to_string()),
+                    ("task", watch.task.to_string())
+                ]
+            )
+        );
+    } else {
+        info!(
+            "👀 {}",
+            tf(
+                Msg::Watching,
+                &[
+                    ("dir", watch.dir.to_string()),
+                    ("task", watch.task.to_string()),
+                    ("interval", watch.interval.to_string())
+                ]
+            )
+        );
+    }
+    let mut watcher = FolderWatcher::new(dir);
+    let mut queue = JobQueue::new();
+    let (mut processed, mut failed) = (0, 0);
+    // Running jobs are waited for before returning, so a stopped one keeps its source file
+    std::thread::scope(|scope| -> Result<()> {
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let mut running = 0;
+        let mut next_poll = Some(std::time::Instant::now());
+        let mut stopping = false;
+        loop {
+            if !stopping && hooks.check_cancelled().is_err() {
+                stopping = true;
+                if !queue.is_empty() {
+                    info!(
+                        "⏸️ {}",
+                        tf(Msg::LeavingQueued, &[("count", queue.len().to_string())])
+                    );
+                }
+            }
+            if !stopping && next_poll.is_some_and(|at| std::time::Instant::now() >= at) {
+                for path in watcher.poll(!watch.once)? {
+                    let priority = job_priority(&path).unwrap_or_else(|e| {
+                        warn!(
+                            "⚠️ {}",
+                            tf(Msg::PriorityFallback, &[("error", e.to_string())])
+                        );
+                        0
+                    });
+                    queue.push(path, priority);
+                }
+                next_poll = (!watch.once).then(|| {
+                    std::time::Instant::now() + std::time::Duration::from_secs_f64(watch.interval)
+                });
+            }
+            // Start the most urgent queued files while the concurrency budget allows
+            while !stopping && running < watch.jobs {
+                let Some((path, priority)) = queue.pop() else {
+                    break;
+                };
+                let priority_note = if priority != 0 {
+                    format!(" (priority {})", priority)
+                } else {
+                    String::new()
+                };
+                info!(
+                    "\n▶️ {}{}",
+                    tf(Msg::Processing, &[("file", path.display().to_string())]),
+                    priority_note
+                );
+                let (template, output_dir, done_tx) = (&template, &output_dir, done_tx.clone());
+                scope.spawn(move || {
+                    let result = process_watched_file(
+                        template, &path, output_dir, watch, child_args, temp_root, hooks,
+                    );
+                    let _ = done_tx.send((path, result));
+                });
+                running += 1;
+            }
+            if running == 0 && (stopping || (watch.once && queue.is_empty())) {
+                break;
+            }
+            // Wait in short steps so finished jobs, new files and a cancellation are noticed quickly
+            let Ok((path, result)) = done_rx.recv_timeout(std::time::Duration::from_millis(200))
+            else {
+                continue;
+            };
+            running -= 1;
+            if result.is_err()
+                && (hooks.cancel.is_cancelled() || stop_requested() || abort_requested())
+            {
+                // Stopped, not failed: the source stays in place for the next run
+                info!(
+                    "⏸️ {}",
+                    tf(
+                        Msg::StoppedProcessing,
+                        &[("file", path.display().to_string())]
+                    )
+                );
+                stopping = true;
+                continue;
+            }
+            let task_path = path.with_extension("json");
+            let priority_path = path.with_extension(PRIORITY_EXTENSION);
+            let target_dir = match result {
+                Ok(true) => {
+                    processed += 1;
+                    &done_dir
+                }
+                Ok(false) => {
+                    info!(
+                        "✅ {}",
+                        tf(Msg::FileInSync, &[("file", path.display().to_string())])
+                    );
+                    &done_dir
+                }
+                Err(e) => {
+                    error!(
+                        "❌ {}",
+                        tf(
+                            Msg::FileFailed,
+                            &[
+                                ("file", path.display().to_string()),
+                                ("error", e.to_string())
+                            ]
+                        )
+                    );
+                    error!(target: LOG, "{} failed: {:#}", path.display(), e);
+                    failed += 1;
+                    &failed_dir
+                }
+            };
+            for file in [&path, &task_path, &priority_path] {
+                if !file.exists() {
+                    continue;
+                }
+                match move_into(file, target_dir) {
+                    Ok(moved) => info!(
+                        "📦 {}",
+                        tf(
+                            Msg::MovedFile,
+                            &[
+                                ("file", file.display().to_string()),
+                                ("dir", moved.display().to_string())
+                            ]
+                        )
+                    ),
+                    Err(e) => warn!(
+                        "⚠️ {}",
+                        tf(
+                            Msg::MoveFailed,
+                            &[
+                                ("file", file.display().to_string()),
+                                ("error", e.to_string())
+                            ]
+                        )
+                    ),
+                }
+            }
+        }
+        Ok(())
+    })?;
+    if hooks.cancel.is_cancelled() || abort_requested() {
+        return Err(FFmpegError::Cancelled.into());
+    }
+    if stop_requested() {
+        info!(
+            "\n⏸️ {}",
+            tf(
+                Msg::WatchStopped,
+                &[
+                    ("count", processed.to_string()),
+                    ("dir", watch.dir.to_string())
+                ]
+            )
+        );
+        return Ok(());
+    }
+
+    if failed == 0 {
+        info!(
+            "\n✅ {}",
+            tf(
+                Msg::ProcessedFiles,
+                &[
+                    ("count", processed.to_string()),
+                    ("dir", output_dir.display().to_string())
+                ]
+            )
+        );
+        Ok(())
+    } else {
+        bail!(
+            "{} files failed; they were moved to {}",
+            failed,
+            failed_dir.display()
+        )
+    }
+}
+
+/// Resolve the template for one watched file, write its task file next to it and process it in
+/// a child process (see `process_in_child`). Returns false when the file is already in sync and
+/// was left alone.
+fn process_watched_file(
+    template: &SeasonTemplate,
+    path: &Path,
+    output_dir: &Path,
+    watch: &WatchArgs,
+    child_args: &[String],
+    temp_root: &Path,
+    hooks: &PipelineHooks,
+) -> Result<bool> {
+    let tmpdir = create_temp_dir(temp_root)?;
+    let _cleanup = CancelledRunCleanup::new(&tmpdir, hooks);
+    let output = output_dir.join(path.file_name().unwrap_or_default());
+    let resolved = resolve_episode(template, path, &output, &tmpdir, watch.debug);
+    fs::remove_dir_all(&tmpdir)?;
+    let episode = resolved?;
+    let json = serde_json::to_string_pretty(&episode.task)?;
+    write_file_atomic(&episode.task_path, json.as_bytes())?;
+    if episode.in_sync {
+        return Ok(false);
+    }
+    // A file stopped with Ctrl+C in an earlier watch picks up where it left off
+    let mut process_args = child_args.to_vec();
+    process_args
+        .extend(["process", "--task", &episode.task_path, "--yes", "--resume"].map(str::to_string));
+    if watch.debug {
+        process_args.push("--debug".to_string());
+    }
+    // Side by side, every line says which file it belongs to
+    let label = (watch.jobs > 1).then(|| path.file_name().unwrap_or_default().to_string_lossy());
+    process_in_child(&process_args, label.as_deref(), hooks)?;
+    Ok(true)
+}
+
+/// Run this binary with `args` as a child process, so jobs running side by side share no
+/// settings, progress bar or prompt: stdin is closed, so nothing can wait for an answer. With
+/// `label`, the child's output is printed line by line behind it instead of going straight to
+/// the terminal. Cancelling `hooks` kills the child.
+fn process_in_child(args: &[String], label: Option<&str>, hooks: &PipelineHooks) -> Result<()> {
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command.args(args).stdin(Stdio::null());
+    if label.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = command.spawn().context("Could not start the job process")?;
+    let status = std::thread::scope(|scope| {
+        if let Some(label) = label {
+            if let Some(stdout) = child.stdout.take() {
+                scope.spawn(move || forward_lines(stdout, label, false));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                scope.spawn(move || forward_lines(stderr, label, true));
+            }
+        }
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok::<_, io::Error>(status);
+            }
+            if hooks.cancel.is_cancelled() {
+                let _ = child.kill();
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    })?;
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => bail!("The job process exited with code {}.", code),
+        None => bail!("The job process was killed."),
+    }
+}
+
+/// Print every line of `stream` behind `[label]`, on stderr with `to_stderr`.
+fn forward_lines(stream: impl Read, label: &str, to_stderr: bool) {
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        if to_stderr {
+            eprintln!("[{}] {}", label, line);
+        } else {
+            println!("[{}] {}", label, line);
+        }
+    }
+}
+
+/// Compare the selected audio streams of two files as a quick sanity check before choosing
+/// which one to fix or use as reference.
+fn handle_compare(compare: &CompareArgs) -> Result<()> {
+    let first_spec = resolve_auto_stream(
+        &compare.first,
+        compare.stream.unwrap_or(StreamSpecifier::Audio(0)),
+    )?;
+    let second_spec = resolve_auto_stream(
+        &compare.second,
+        compare
+            .second_stream
+            .or(compare.stream)
+            .unwrap_or(StreamSpecifier::Audio(0)),
+    )?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::ComparingStreams,
+            &[
+                ("first_stream", first_spec.to_string()),
+                ("first", compare.first.to_string()),
+                ("second_stream", second_spec.to_string()),
+                ("second", compare.second.to_string())
+            ]
+        )
+    );
+
+    let describe =
+        |input: &str, spec: StreamSpecifier| -> Result<(usize, AudioStream, Option<f64>, f64)> {
+            let index = probe_audio_stream(input, spec)?.absolute_index;
+            let stream = inspect_audio_streams(input)?
+                .into_iter()
+                .find(|s| s.index == index)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Could not inspect audio stream {} of {}", spec, input)
+                })?;
+            let duration = get_audio_stream_duration(input, index)?;
+            info!(
+                "ℹ️ {}",
+                tf(Msg::MeasuringLoudness, &[("file", input.to_string())])
+            );
+            let loudness = integrated_loudness(input, &format!("0:{}", index))?;
+            Ok((index, stream, duration, loudness))
+        };
+    let (first_index, first, first_duration, first_loudness) =
+        describe(&compare.first, first_spec)?;
+    let (second_index, second, second_duration, second_loudness) =
+        describe(&compare.second, second_spec)?;
+
+    info!("ℹ️ {}", t(Msg::EstimatingOffset));
+    let offset = align_to_reference(
+        &compare.first,
+        &format!("0:{}", first_index),
+        &compare.second,
+        &format!("0:{}", second_index),
+    );
+
+    let same = |a: &str, b: &str| if a == b { "same" } else { "differs" }.to_string();
+    let duration_str = |d: Option<f64>| {
+        d.map(|v| format!("{:.3} s", v))
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+    let duration_diff = match (first_duration, second_duration) {
+        (Some(a), Some(b)) => format!("{:+.3} s", b - a),
+        _ => "unknown".to_string(),
+    };
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        t(Msg::HeaderProperty),
+        t(Msg::HeaderFirst),
+        t(Msg::HeaderSecond),
+        t(Msg::HeaderDifference),
+    ]);
+    table.add_row(vec![
+        "Stream".to_string(),
+        format!("#{}", first_index),
+        format!("#{}", second_index),
+        String::new(),
+    ]);
+    table.add_row(vec![
+        "Codec".to_string(),
+        first.codec.clone(),
+        second.codec.clone(),
+        same(&first.codec, &second.codec),
+    ]);
+    table.add_row(vec![
+        "Bitrate".to_string(),
+        first.bitrate.clone(),
+        second.bitrate.clone(),
+        same(&first.bitrate, &second.bitrate),
+    ]);
+    table.add_row(vec![
+        "Channels".to_string(),
+        first.channels.clone(),
+        second.channels.clone(),
+        same(&first.channels, &second.channels),
+    ]);
+    table.add_row(vec![
+        "Sample Rate".to_string(),
+        first.sample_rate.clone(),
+        second.sample_rate.clone(),
+        same(&first.sample_rate, &second.sample_rate),
+    ]);
+    table.add_row(vec![
+        "Duration".to_string(),
+        duration_str(first_duration),
+        duration_str(second_duration),
+        duration_diff,
+    ]);
+    table.add_row(vec![
+        "Integrated Loudness".to_string(),
+        format!("{:.1} LUFS", first_loudness),
+        format!("{:.1} LUFS", second_loudness),
+        format!("{:+.1} LU", second_loudness - first_loudness),
+    ]);
+    let offset_str = match &offset {
+        Ok(alignment) => format!("{:.3} ms ({})", alignment.offset_ms(), alignment.describe()),
+        Err(_) => "not detected".to_string(),
+    };
+    table.add_row(vec![
+        "Constant Offset".to_string(),
+        String::new(),
+        String::new(),
+        offset_str,
+    ]);
+    info!("{}", table);
+
+    match offset {
+        Ok(alignment) => info!(
+            "\n💡 {}",
+            tf(
+                Msg::DelayFirstHint,
+                &[("offset", format!("{:.3}", alignment.offset_ms()))]
+            )
+        ),
+        Err(e) => warn!(
+            "\n⚠️ {}",
+            tf(Msg::NoConstantOffset, &[("error", e.to_string())])
+        ),
+    }
+    Ok(())
+}
+
+/// Convert the delays in a report of another sync tool into task files, one per delay.
+fn handle_import(import: &ImportArgs) -> Result<()> {
+    info!(
+        "🔍 {}\n",
+        tf(Msg::ReadingDelays, &[("path", import.report.to_string())])
+    );
+    let contents = fs::read_to_string(&import.report)?;
+    let (format, corrections) = parse_corrections(&contents)?;
+    if corrections.is_empty() {
+        bail!("No delays found in this {} report.", format);
+    }
+
+    let task_path = import.task.clone().unwrap_or_else(|| {
+        let mut out = std::path::PathBuf::from(&import.report);
+        out.set_extension("json");
+        out.to_string_lossy().to_string()
+    });
+    let numbered = |n: usize| {
+        let path = std::path::Path::new(&task_path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(ext) => format!("{}-{}.{}", stem, n, ext.to_string_lossy()),
+            None => format!("{}-{}", stem, n),
+        };
+        path.with_file_name(name).to_string_lossy().to_string()
+    };
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        "#",
+        t(Msg::HeaderInput),
+        t(Msg::HeaderStream),
+        t(Msg::HeaderDelay),
+        t(Msg::HeaderDrift),
+        t(Msg::HeaderFrom),
+        t(Msg::HeaderTaskFile),
+    ]);
+    let mut missing_stream = false;
+    for (i, correction) in corrections.iter().enumerate() {
+        let mut task = correction.to_task();
+        if import.input.is_some() {
+            task.input = import.input.clone();
+        }
+        missing_stream |= task.stream.is_none();
+        let path = if corrections.len() == 1 {
+            task_path.clone()
+        } else {
+            numbered(i + 1)
+        };
+        ensure_not_overwriting(&path, "task file", &[("report", import.report.as_str())])?;
+        write_file_atomic(&path, task.to_file_string(&path)?.as_bytes())?;
+        table.add_row(vec![
+            format!("{}", i + 1),
+            task.input.clone().unwrap_or_else(|| "-".to_string()),
+            task.stream
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            format!("{:.3}", correction.delay_ms),
+            correction
+                .drift_ppm
+                .map(|ppm| format!("{:.1}", ppm))
+                .unwrap_or_else(|| "-".to_string()),
+            correction.origin.clone(),
+            path,
+        ]);
+    }
+
+    info!(
+        "▶️ {}",
+        tf(Msg::DelaysFromReport, &[("format", format.to_string())])
+    );
+    info!("{}", table);
+    if missing_stream {
+        warn!("\n⚠️ {}", t(Msg::UntiedDelays));
+    }
+    info!(
+        "\n✅ {}",
+        tf(
+            Msg::WroteTaskFiles,
+            &[("count", corrections.len().to_string())]
+        )
+    );
+    Ok(())
+}
+
+fn handle_project(action: ProjectCommand, quiet: bool, hooks: &PipelineHooks) -> Result<()> {
+    match action {
+        ProjectCommand::Open { dir, input } => {
+            let dir = Path::new(&dir);
+            if dir.join(PROJECT_FILE).exists() {
+                if input.is_some() {
+                    info!(
+                        "ℹ️ {}",
+                        tf(Msg::ProjectExists, &[("dir", dir.display().to_string())])
+                    );
+                }
+                return print_project_status(&Project::open(dir)?);
+            }
+            let Some(input) = input else {
+                bail!(
+                    "'{}' is not a project yet; pass --input to create one.",
+                    dir.display()
+                );
+            };
+            let project = Project::create(dir, &input)?;
+            info!(
+                "✅ {}",
+                tf(Msg::CreatedProject, &[("dir", dir.display().to_string())])
+            );
+            info!(
+                "   {}",
+                tf(
+                    Msg::ProjectNextSteps,
+                    &[
+                        ("task", project.task_path().display().to_string()),
+                        ("dir", dir.display().to_string())
+                    ]
+                )
+            );
+            Ok(())
+        }
+        ProjectCommand::Status { dir } => print_project_status(&Project::open(Path::new(&dir))?),
+        ProjectCommand::Run {
+            dir,
+            yes,
+            dry_run,
+            debug,
+        } => run_project(Path::new(&dir), (yes, dry_run), debug, quiet, hooks),
+    }
+}
+
+fn print_project_status(project: &Project) -> Result<()> {
+    info!(
+        "📁 {}",
+        tf(
+            Msg::ProjectDir,
+            &[("dir", project.dir.display().to_string())]
+        )
+    );
+    info!(
+        "   {}",
+        tf(
+            Msg::ProjectInput,
+            &[("input", project.meta.input.to_string())]
+        )
+    );
+    info!(
+        "   {}",
+        tf(
+            Msg::ProjectTask,
+            &[("task", project.task_path().display().to_string())]
+        )
+    );
+    info!(
+        "   {}",
+        tf(
+            Msg::ProjectCreated,
+            &[("date", format_utc(project.meta.created_at))]
+        )
+    );
+    if project.meta.attempts.is_empty() {
+        info!("\nℹ️ {}", t(Msg::NoAttempts));
+        return Ok(());
+    }
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        "#",
+        t(Msg::HeaderStarted),
+        t(Msg::HeaderStatus),
+        t(Msg::HeaderError),
+    ]);
+    for attempt in &project.meta.attempts {
+        let status = if attempt.dry_run {
+            format!("{} (dry run)", attempt.status)
+        } else {
+            attempt.status.to_string()
+        };
+        table.add_row(vec![
+            attempt.number.to_string(),
+            format_utc(attempt.started_at),
+            status,
+            attempt.error.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    info!("\n{}", table);
+    Ok(())
+}
+
+fn handle_cache(action: CacheCommand, temp_root: &Path) -> Result<()> {
+    match action {
+        CacheCommand::Stats { projects } => {
+            for root in cache_roots(temp_root, &projects)? {
+                print_cache(&root);
+            }
+            Ok(())
+        }
+        CacheCommand::Clear {
+            projects,
+            older_than,
+        } => {
+            // Without --older-than everything goes, else only what is older, whatever the size
+            let (max_age, max_bytes) = match older_than {
+                Some(older_than) => (days(older_than), u64::MAX),
+                None => (Duration::ZERO, 0),
+            };
+            let mut freed = 0;
+            let mut count = 0;
+            for root in cache_roots(temp_root, &projects)? {
+                for (dir, size) in evict_failed_runs(&root, None, max_age, max_bytes) {
+                    info!(
+                        "🗑️ {}",
+                        tf(
+                            Msg::RemovedDir,
+                            &[
+                                ("dir", dir.display().to_string()),
+                                ("size", format_bytes(size))
+                            ]
+                        )
+                    );
+                    freed += size;
+                    count += 1;
+                }
+            }
+            info!(
+                "✅ {}",
+                tf(
+                    Msg::RemovedTempDirs,
+                    &[("count", count.to_string()), ("size", format_bytes(freed))]
+                )
+            );
+            Ok(())
+        }
+    }
+}
+
+/// The temp root and the caches of `projects`: where failed runs keep their temp dirs.
+fn cache_roots(temp_root: &Path, projects: &[String]) -> Result<Vec<PathBuf>> {
+    let mut roots = vec![temp_root.to_path_buf()];
+    for dir in projects {
+        roots.push(Project::open(Path::new(dir))?.cache_dir());
+    }
+    Ok(roots)
+}
+
+fn print_cache(root: &Path) {
+    let runs = failed_runs(root);
+    info!("📦 {}", root.display());
+    if runs.is_empty() {
+        info!("   {}\n", t(Msg::NoTempDirs));
+        return;
+    }
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        t(Msg::HeaderDir),
+        t(Msg::HeaderFinishedStages),
+        t(Msg::HeaderSize),
+        t(Msg::HeaderLastUsed),
+    ]);
+    let mut total = 0;
+    for run in &runs {
+        let size = dir_size(&run.dir);
+        total += size;
+        let last_used = run
+            .modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| format_utc(d.as_secs()))
+            .unwrap_or_default();
+        table.add_row(vec![
+            run.dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            run.manifest.describe(),
+            format_bytes(size),
+            last_used,
+        ]);
+    }
+    info!("{}", table);
+    info!(
+        "   {}\n",
+        tf(
+            Msg::TempDirsTotal,
+            &[
+                ("count", runs.len().to_string()),
+                ("size", format_bytes(total))
+            ]
+        )
+    );
+}
+
+/// A number of days as a duration, saturating for absurdly large values.
+fn days(days: f64) -> Duration {
+    Duration::try_from_secs_f64(days * 86_400.0).unwrap_or(Duration::MAX)
+}
+
+/// Process the project task as a new attempt. Runs keep their temp dirs in the project cache,
+/// so a failed attempt is picked up again by the next one.
+fn run_project(
+    dir: &Path,
+    (yes, dry_run): (bool, bool),
+    debug: bool,
+    quiet: bool,
+    hooks: &PipelineHooks,
+) -> Result<()> {
+    let mut project = Project::open(dir)?;
+    let task_path = project.task_path();
+    let task = Task::load(Some(path_to_str(&task_path)?))?.unwrap_or_default();
+    let number = project.begin_attempt(dry_run)?;
+    info!(
+        "▶️ {}",
+        tf(
+            Msg::ProjectAttempt,
+            &[
+                ("number", number.to_string()),
+                ("dir", dir.display().to_string())
+            ]
+        )
+    );
+
+    let attempt_dir = project.attempt_dir(number);
+    let mut run_args = vec![
+        "sync-nudger".to_string(),
+        "--task".to_string(),
+        path_to_str(&task_path)?.to_string(),
+    ];
+    if yes {
+        run_args.push("--yes".to_string());
+    }
+    if debug {
+        run_args.push("--debug".to_string());
+    }
+    if dry_run {
+        run_args.push("--dry-run".to_string());
+    } else {
+        run_args.push("--resume".to_string());
+        run_args.push("--export-previews".to_string());
+        run_args.push(path_to_str(&project.previews_dir(number))?.to_string());
+        run_args.push("--plan-out".to_string());
+        run_args.push(path_to_str(&attempt_dir.join("plan.md"))?.to_string());
+        // Tasks with stream_plans cannot be resolved into a single task file
+        if task.stream_plans.is_empty() {
+            run_args.push("--write-task-file".to_string());
+            run_args.push(path_to_str(&attempt_dir.join("resolved.json"))?.to_string());
+        }
     }
+    let result = Args::try_parse_from(run_args)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut args| {
+            args.temp_dir = Some(path_to_str(&project.cache_dir())?.to_string());
+            args.quiet = quiet;
+            run_process(args, hooks)
+        });
+    project.finish_attempt(number, &result)?;
+    if result.is_ok() {
+        info!(
+            "📁 {}",
+            tf(
+                Msg::AttemptRecorded,
+                &[
+                    ("number", number.to_string()),
+                    ("dir", attempt_dir.display().to_string())
+                ]
+            )
+        );
+    }
+    result
+}
 
-    all_splits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
-
-    // --- User Confirmation ---
-    if !all_splits.is_empty() {
-        // Get audio duration for the selected stream
-        let audio_duration = match get_audio_stream_duration(input, stream) {
-            Ok(Some(dur)) => format!("{:.3} s", dur),
-            Ok(None) => "unknown".to_string(),
-            Err(_) => "unknown".to_string(),
-        };
+/// Select the one audio stream matching `language` and `title`, see [`find_audio_stream_by_tags`].
+fn stream_for_tags(
+    input: &str,
+    language: Option<&str>,
+    title: Option<&str>,
+) -> Result<StreamSpecifier> {
+    Ok(StreamSpecifier::Index(find_audio_stream_by_tags(
+        input, language, title,
+    )?))
+}
 
-        let mut table = Table::new();
-        table
-            .set_header(vec!["Source", "Resolved Split (s)", "Delay (ms)"])
-            .load_preset(UTF8_FULL);
+/// Resolve `--stream auto` against `input`, noting which stream it picked.
+fn resolve_auto_stream(input: &str, stream: StreamSpecifier) -> Result<StreamSpecifier> {
+    if stream != StreamSpecifier::Auto {
+        return Ok(stream);
+    }
+    let (index, reason) = find_main_audio_stream(input)?;
+    info!(
+        "ℹ️ {}",
+        tf(
+            Msg::AutoStream,
+            &[
+                ("stream", index.to_string()),
+                ("file", input.to_string()),
+                ("reason", reason.to_string())
+            ]
+        )
+    );
+    Ok(StreamSpecifier::Index(index))
+}
 
-        for (point, delay, source) in &all_splits {
-            table.add_row(vec![
-                source.clone(),
-                format!("{:.3}", point),
-                format!("{:.3}", delay),
-            ]);
+/// The stream chosen by --stream, --language or --stream-title, or the first audio stream.
+fn select_stream(target: &StreamArgs) -> Result<StreamSpecifier> {
+    match (&target.stream, &target.language, &target.stream_title) {
+        (Some(stream_spec), _, _) => resolve_auto_stream(&target.input, *stream_spec),
+        (None, None, None) => Ok(StreamSpecifier::Audio(0)),
+        (None, language, title) => {
+            let language = language
+                .as_ref()
+                .map(|l| normalize_or_warn(l, "--language"));
+            stream_for_tags(&target.input, language.as_deref(), title.as_deref())
         }
+    }
+}
 
-        println!("\n▶️ Proposed Splitting Plan:");
-        println!("{table}");
+/// Compare the first audible content of the selected stream with the reference audio or the
+/// picture start of the input, and suggest the matching initial delay.
+fn handle_detect_offset(args: &DetectOffsetArgs) -> Result<()> {
+    let target = &args.target;
+    let input = &target.input;
+    let stream_spec = select_stream(target)?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::DetectingOffset,
+            &[
+                ("stream", stream_spec.to_string()),
+                ("file", input.to_string())
+            ]
+        )
+    );
 
-        let mut info_table = Table::new();
-        info_table
-            .load_preset(UTF8_FULL)
-            .set_header(vec!["Parameter", "Value"]);
+    let target_start = first_audible_time(input, &stream_spec.to_map())?
+        .ok_or_else(|| anyhow::anyhow!("Audio stream {} stays silent.", stream_spec))?;
+    let (reference_label, reference_start) = if let Some(reference) = &args.reference {
+        let reference_stream = resolve_auto_stream(
+            reference,
+            args.reference_stream.unwrap_or(StreamSpecifier::Audio(0)),
+        )?;
+        let start =
+            first_audible_time(reference, &reference_stream.to_map())?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Audio stream {} of the reference stays silent.",
+                    reference_stream
+                )
+            })?;
+        (format!("Reference audio ({})", reference_stream), start)
+    } else {
+        ("Video picture".to_string(), first_picture_time(input)?)
+    };
 
-        info_table
-            .add_row(vec!["Input File", input])
-            .add_row(vec!["Output File", output])
-            .add_row(vec!["Audio Duration", &audio_duration]);
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![t(Msg::HeaderTrack), t(Msg::HeaderContentStarts)]);
+    table.add_row(vec![
+        format!("Target audio ({})", stream_spec),
+        format!("{:.3}", target_start),
+    ]);
+    table.add_row(vec![reference_label, format!("{:.3}", reference_start)]);
+    info!("{}", table);
 
-        let stream_name = if !original_title.is_empty() {
-            original_title.clone()
-        } else if !original_lang.is_empty() {
-            original_lang.clone()
-        } else {
-            "Untitled".to_string()
-        };
+    let suggested = (reference_start - target_start) * 1000.0;
+    porcelain::emit("offset", &[&target_start, &reference_start, &suggested]);
+    info!(
+        "\n💡 {}",
+        tf(
+            Msg::SuggestedDelay,
+            &[("delay", format!("{:.3}", suggested))]
+        )
+    );
 
-        info_table
-            .add_row(vec!["Initial Delay", &format!("{:.3} ms", initial_delay)])
-            .add_row(vec!["Stream ID", &format!("#{}", stream)])
-            .add_row(vec!["Stream Name", &stream_name])
-            .add_row(vec!["Codec", &original_codec])
-            .add_row(vec!["Bitrate", &bitrate])
-            .add_row(vec![
-                "Silence Threshold",
-                &format!("{:.1} LUFS", silence_threshold),
-            ]);
+    Ok(())
+}
 
-        println!("\n▶️ Job Details:");
-        println!("{info_table}");
+/// Print the channel analysis of the selected stream: level and zero-crossing rate per channel,
+/// followed by any layout issues and the pan filter that would fix them.
+fn handle_check_channels(target: &StreamArgs) -> Result<()> {
+    let input = &target.input;
+    let stream_spec = select_stream(target)?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::CheckingStreamChannels,
+            &[
+                ("stream", stream_spec.to_string()),
+                ("file", input.to_string())
+            ]
+        )
+    );
 
-        if args.yes {
-            println!("\n--yes flag provided, proceeding without confirmation.");
-        } else {
-            println!("\nProceed with this plan? [y/N]");
-            let mut input = String::new();
-            io::stdin().read_line(&mut input)?;
-            if !input.trim().eq_ignore_ascii_case("y") {
-                println!("Aborting operation.");
-                fs::remove_dir_all(&tmpdir)?;
-                return Ok(());
-            }
-        }
+    let stream = probe_audio_stream(input, stream_spec)?.absolute_index;
+    let properties = probe_audio_properties(input, Some(stream))?;
+    let report = analyze_channels(
+        input,
+        &stream_spec.to_map(),
+        &properties.channel_layout,
+        properties.channels as usize,
+    )?;
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        t(Msg::HeaderChannel),
+        t(Msg::HeaderLevel),
+        t(Msg::HeaderZeroCrossings),
+    ]);
+    for channel in &report.channels {
+        table.add_row(vec![
+            channel.name.clone(),
+            format!("{:.1}", channel.level_db),
+            format!("{:.0}", channel.zero_crossings_per_second),
+        ]);
     }
+    let layout = if report.layout.is_empty() {
+        t(Msg::Unknown)
+    } else {
+        &report.layout
+    };
+    info!("{}", tf(Msg::Layout, &[("layout", layout.to_string())]));
+    info!("{}", table);
 
-    // Optionally write the task to a file (after confirmation)
-    if let Some(write_task_file) = &args.write_task_file {
-        let out_path = if let Some(path) = write_task_file {
-            path.clone().to_string()
-        } else {
-            // Use input file path with extension replaced by .json
-            let input_path = std::path::Path::new(input);
-            let mut out = input_path.to_path_buf();
-            out.set_extension("json");
-            out.to_string_lossy().to_string()
-        };
-        let task = Task {
-            input: Some(input.to_string()),
-            output: Some(output.to_string()),
-            stream: Some(stream),
-            initial_delay: Some(initial_delay),
-            splits: splits.clone(),
-            split_ranges: split_ranges.clone(),
-            bitrate: Some(bitrate.clone()),
-            silence_threshold: Some(silence_threshold),
-            fit_length: Some(fit_length),
-        };
-        let json = serde_json::to_string_pretty(&task)?;
-        let mut file = fs::File::create(&out_path)?;
-        file.write_all(json.as_bytes())?;
-        println!("✅ Wrote task to {}", out_path);
+    print_channel_issues(&report);
+    if let Some(pan) = report.pan_filter() {
+        info!(
+            "\n💡 {}",
+            tf(Msg::FixChannelsHint, &[("pan", pan.to_string())])
+        );
     }
 
-    let mut split_points: Vec<f64> = Vec::new();
-    let mut delays: Vec<f64> = vec![initial_delay];
-    for (point, delay, _) in &all_splits {
-        split_points.push(*point);
-        delays.push(*delay);
+    Ok(())
+}
+
+/// List DC offset, clipping and clicks in the selected stream with their timestamps.
+fn handle_qc(target: &StreamArgs) -> Result<()> {
+    let input = &target.input;
+    let stream_spec = select_stream(target)?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::ScanningArtifacts,
+            &[
+                ("stream", stream_spec.to_string()),
+                ("file", input.to_string())
+            ]
+        )
+    );
+
+    let stream = probe_audio_stream(input, stream_spec)?.absolute_index;
+    let properties = probe_audio_properties(input, Some(stream))?;
+    let duration = match properties.duration {
+        Some(duration) => duration,
+        None => get_file_duration(input)?,
+    };
+    let mut artifacts = scan_artifacts(
+        input,
+        &stream_spec.to_map(),
+        &properties.channel_layout,
+        properties.channels as usize,
+        properties.sample_rate,
+        duration,
+    )?;
+    if artifacts.is_empty() {
+        info!("✅ {}", t(Msg::NoArtifacts));
+        return Ok(());
     }
+    artifacts.sort_by(|a, b| a.time.total_cmp(&b.time));
 
-    let n = split_points.len();
-    if delays.len() != n + 1 {
-        bail!("Delays must have one more element than split points.");
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        t(Msg::HeaderTime),
+        t(Msg::HeaderArtifact),
+        t(Msg::HeaderDetail),
+    ]);
+    for artifact in &artifacts {
+        table.add_row(vec![
+            format!("{:.3}", artifact.time),
+            artifact.kind.to_string(),
+            artifact.detail.clone(),
+        ]);
     }
+    info!("{}", table);
 
-    // 3. Split and delay
-    println!("ℹ️ Splitting audio into parts...");
-    let split_files = split_and_delay_audio(
-        flac_path.as_path(),
-        &split_points,
-        &delays,
-        tmpdir.as_path(),
-        args.debug,
-    )?;
+    let count = |kind: ArtifactKind| artifacts.iter().filter(|a| a.kind == kind).count();
+    warn!(
+        "\n⚠️ {}",
+        tf(
+            Msg::FoundArtifacts,
+            &[
+                ("dc", count(ArtifactKind::DcOffset).to_string()),
+                ("clipping", count(ArtifactKind::Clipping).to_string()),
+                ("clicks", count(ArtifactKind::Click).to_string())
+            ]
+        )
+    );
 
-    // 4. Concat list
-    let final_flac = concat_audio_segments(&split_files, tmpdir.as_path(), args.debug)?;
+    Ok(())
+}
 
-    // --- Fit to original length if requested ---
-    println!("\n▶️ Adjusting Audio Lengths...");
+/// Show where the selected stream starts and ends against the video, and the flags that close
+/// the gaps.
+fn handle_gaps(target: &StreamArgs) -> Result<()> {
+    let input = &target.input;
+    let stream_spec = select_stream(target)?;
+    let stream = probe_audio_stream(input, stream_spec)?.absolute_index;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::ComparingWithVideo,
+            &[("stream", stream.to_string()), ("file", input.to_string())]
+        )
+    );
+    let extents = measure_extents(input, stream)?;
 
-    let mut fitted_flac = final_flac.clone();
-    let mut orig_duration_val = None;
-    let mut processed_duration_val = None;
-    let mut adjusted_duration_val = None;
-    if fit_length {
-        if let Ok(Some(orig_duration)) = get_audio_stream_duration(input, stream) {
-            orig_duration_val = Some(orig_duration);
-            // Get duration of the processed audio
-            let processed_duration = get_file_duration(path_to_str(final_flac.as_path())?)?;
-            processed_duration_val = Some(processed_duration);
-            let fitted_path = tmpdir.join("target_audio_final_fitted.flac");
-            fit_audio_to_length(
-                final_flac.as_path(),
-                fitted_path.as_path(),
-                orig_duration,
-                args.debug,
-            )?;
-            fitted_flac = fitted_path;
-            // Get duration of the adjusted audio
-            let adjusted_duration = get_file_duration(path_to_str(fitted_flac.as_path())?)?;
-            adjusted_duration_val = Some(adjusted_duration);
-        }
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        t(Msg::HeaderStream),
+        t(Msg::HeaderIndex),
+        t(Msg::HeaderStartSeconds),
+        t(Msg::HeaderEnd),
+        t(Msg::HeaderDurationSeconds),
+    ]);
+    for (kind, extent) in [("Video", extents.video), ("Audio", extents.audio)] {
+        table.add_row(vec![
+            kind.to_string(),
+            extent.index.to_string(),
+            format!("{:.3}", extent.start),
+            format!("{:.3}", extent.end),
+            format!("{:.3}", extent.duration()),
+        ]);
     }
+    info!("{}", table);
 
-    // Show duration table if fit_length was used
-    if fit_length {
-        use comfy_table::Table;
-        let mut dur_table = Table::new();
-        dur_table.set_header(vec!["Type", "Duration (s)"]);
-        let orig_str = orig_duration_val
-            .map(|v| format!("{:.3}", v))
-            .unwrap_or_else(|| "unknown".to_string());
-        let new_str = processed_duration_val
-            .map(|v| format!("{:.3}", v))
-            .unwrap_or_else(|| "unknown".to_string());
-        let adj_str = adjusted_duration_val
-            .map(|v| format!("{:.3}", v))
-            .unwrap_or_else(|| "unknown".to_string());
-        dur_table.add_row(vec!["Original", orig_str.as_str()]);
-        dur_table.add_row(vec!["New (pre-adjustment)", new_str.as_str()]);
-        dur_table.add_row(vec!["Adjusted (post-fit)", adj_str.as_str()]);
-        println!("{}", dur_table);
-    }
-
-    // 5. Convert final audio back to original codec
-    println!("\n▶️ Converting Audio Back to Original Codec...");
-    let final_extension = match original_codec.as_str() {
-        "aac" => "aac",
-        "ac3" => "ac3",
-        "dts" => "dts",
-        "mp3" => "mp3",
-        "opus" => "opus",
-        _ => "mka", // Matroska audio as a safe fallback container
-    };
-    let final_audio_for_remux = tmpdir.join(format!("final_for_remux.{}", final_extension));
-    convert_audio_codec(
-        fitted_flac.as_path(),
-        &original_codec,
-        &bitrate,
-        final_audio_for_remux.as_path(),
-        args.debug,
-    )?;
+    let (head, tail) = (extents.head_gap(), extents.tail_gap());
+    porcelain::emit(
+        "gaps",
+        &[&stream, &format!("{:.3}", head), &format!("{:.3}", tail)],
+    );
+    // Differences below a millisecond are rounding in the container
+    let mut fixes = Vec::new();
+    if head > 0.001 {
+        info!(
+            "⏮️ {}",
+            tf(
+                Msg::AudioStartsAfter,
+                &[("seconds", format!("{:.3}", head))]
+            )
+        );
+        fixes.push("--pad-head");
+    } else if head < -0.001 {
+        info!(
+            "⏮️ {}",
+            tf(
+                Msg::AudioStartsBefore,
+                &[("seconds", format!("{:.3}", -head))]
+            )
+        );
+    }
+    if tail > 0.001 {
+        info!(
+            "⏭️ {}",
+            tf(Msg::AudioEndsBefore, &[("seconds", format!("{:.3}", tail))])
+        );
+    } else if tail < -0.001 {
+        info!(
+            "⏭️ {}",
+            tf(Msg::AudioRunsPast, &[("seconds", format!("{:.3}", -tail))])
+        );
+        fixes.push("--trim-tail");
+    }
+    if head.abs() <= 0.001 && tail.abs() <= 0.001 {
+        info!("✅ {}", t(Msg::AudioMatchesVideo));
+    }
+    if !fixes.is_empty() {
+        info!(
+            "\n💡 {}",
+            tf(Msg::CloseGapHint, &[("options", fixes.join(" "))])
+        );
+    }
+    Ok(())
+}
 
-    // 6. Remux audio back in place of the original
-    println!("\n▶️ Remux Audio Back in Place of the Original..");
-    remux_audio_stream(
+/// List the silences of a stream as split range candidates. Silences at the very start or end
+/// of the stream are listed but not suggested, as there is nothing to split there.
+fn handle_suggest_splits(suggest: &SuggestSplitsArgs) -> Result<()> {
+    if suggest.min_silence <= 0.0 || !suggest.min_silence.is_finite() {
+        bail!(
+            "--min-silence must be a positive number of seconds (got {}).",
+            suggest.min_silence
+        );
+    }
+    let target = &suggest.target;
+    let input = &target.input;
+    let stream_spec = select_stream(target)?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::LookingForSilences,
+            &[
+                ("seconds", suggest.min_silence.to_string()),
+                ("noise", suggest.noise.to_string()),
+                ("stream", stream_spec.to_string()),
+                ("file", input.to_string())
+            ]
+        )
+    );
+
+    let stream = probe_audio_stream(input, stream_spec)?.absolute_index;
+    let duration = match get_audio_stream_duration(input, stream)? {
+        Some(duration) => duration,
+        None => get_file_duration(input)?,
+    };
+    let silences = detect_silences(
         input,
-        final_audio_for_remux.as_path(),
-        output,
-        audio_stream_idx,
-        &original_title,
-        &original_lang,
-        args.debug,
+        &stream_spec.to_map(),
+        suggest.noise,
+        suggest.min_silence,
+        Some(duration),
     )?;
+    if silences.is_empty() {
+        info!("ℹ️ {}", t(Msg::NoSilences));
+        return Ok(());
+    }
+    if suggest.breaks {
+        return suggest_breaks(suggest, &silences, duration);
+    }
 
-    // Cleanup
-    fs::remove_dir_all(&tmpdir)?;
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        "#",
+        t(Msg::HeaderStartSeconds),
+        t(Msg::HeaderEnd),
+        t(Msg::HeaderLength),
+        t(Msg::HeaderSuggestedOption),
+    ]);
+    let mut suggested = Vec::new();
+    for (i, silence) in silences.iter().enumerate() {
+        let edge = silence.start <= 0.0 || silence.end >= duration - 0.001;
+        let option = if edge {
+            "(start or end of the stream)".to_string()
+        } else {
+            let range = format!("{:.3}:{:.3}:{}", silence.start, silence.end, suggest.delay);
+            porcelain::emit(
+                "range",
+                &[&silence.start, &silence.end, &suggest.delay, &"silence"],
+            );
+            suggested.push(range.clone());
+            format!("--split-range {}", range)
+        };
+        table.add_row(vec![
+            (i + 1).to_string(),
+            format!("{:.3}", silence.start),
+            format!("{:.3}", silence.end),
+            format!("{:.3}", silence.duration()),
+            option,
+        ]);
+    }
+    info!("{}", table);
 
-    println!("✅ Processing complete! Output: {}", output);
+    if suggested.is_empty() {
+        info!("\nℹ️ {}", t(Msg::SilencesAtEdges));
+    } else {
+        info!(
+            "\n✅ {}",
+            tf(
+                Msg::FoundRangeCandidates,
+                &[("count", suggested.len().to_string())]
+            )
+        );
+        info!("--split-range {}", suggested.join(" "));
+    }
     Ok(())
 }
 
-fn handle_ffmpeg_check() -> Result<()> {
-    println!("🔍 Checking FFmpeg installation...\n");
+/// The `--breaks` part of `suggest-splits`: list the silences that overlap black video.
+fn suggest_breaks(suggest: &SuggestSplitsArgs, silences: &[Silence], duration: f64) -> Result<()> {
+    if suggest.min_black <= 0.0 || !suggest.min_black.is_finite() {
+        bail!(
+            "--min-black must be a positive number of seconds (got {}).",
+            suggest.min_black
+        );
+    }
+    info!(
+        "🎬 {}\n",
+        tf(
+            Msg::LookingForBlack,
+            &[
+                ("seconds", suggest.min_black.to_string()),
+                ("file", suggest.target.input.to_string())
+            ]
+        )
+    );
+    let black = detect_black_intervals(&suggest.target.input, suggest.min_black)?;
+    let breaks: Vec<Break> = find_breaks(&black, silences)
+        .into_iter()
+        .filter(|b| b.start > 0.0 && b.end < duration - 0.001)
+        .collect();
+    if breaks.is_empty() {
+        info!(
+            "ℹ️ {}",
+            tf(
+                Msg::NoBreaks,
+                &[
+                    ("black", black.len().to_string()),
+                    ("silences", silences.len().to_string())
+                ]
+            )
+        );
+        return Ok(());
+    }
 
-    let check_result = check_ffmpeg_installation();
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        "#",
+        t(Msg::HeaderStartSeconds),
+        t(Msg::HeaderEnd),
+        t(Msg::HeaderLength),
+        t(Msg::HeaderBlack),
+        t(Msg::HeaderSuggestedOption),
+    ]);
+    let mut suggested = Vec::new();
+    for (i, b) in breaks.iter().enumerate() {
+        let range = format!("{:.3}:{:.3}:{}", b.start, b.end, suggest.delay);
+        porcelain::emit("range", &[&b.start, &b.end, &suggest.delay, &"break"]);
+        table.add_row(vec![
+            (i + 1).to_string(),
+            format!("{:.3}", b.start),
+            format!("{:.3}", b.end),
+            format!("{:.3}", b.duration()),
+            format!("{:.3}", b.black),
+            format!("--split-range {}", range),
+        ]);
+        suggested.push(range);
+    }
+    info!("{}", table);
+    info!(
+        "\n✅ {}",
+        tf(Msg::FoundBreaks, &[("count", suggested.len().to_string())])
+    );
+    info!("--split-range {}", suggested.join(" "));
+    Ok(())
+}
 
-    // Display FFmpeg status
-    if check_result.ffmpeg_available {
-        if let Some(version_info) = &check_result.ffmpeg_version {
-            println!("✅ FFmpeg found:");
-            println!(
-                "   Version: {}.{}.{}",
-                version_info.major, version_info.minor, version_info.patch
-            );
+/// Extract one range of a stream, with a short pre-roll, for measuring that range alone.
+/// Times in the extracted file are offset by `(start - PROBE_PRE_ROLL_SECONDS).max(0.0)`.
+fn extract_range_proxy(
+    input: &str,
+    stream: usize,
+    (start, end): (f64, f64),
+    proxy: &Path,
+    run: &RunOptions,
+) -> Result<()> {
+    let proxy_start = (start - PROBE_PRE_ROLL_SECONDS).max(0.0);
+    extract_audio_range_to_flac(input, stream, proxy_start, end - proxy_start, proxy, run)
+}
 
-            if version_info.is_compatible {
-                println!("   Status: ✅ Compatible (minimum required: 4.0.0)");
-            } else {
-                println!("   Status: ❌ Too old (minimum required: 4.0.0)");
-            }
+/// Like `find_split_point` on the extracted track, measuring only the range itself.
+fn find_quietest_point_in_stream(
+    input: &str,
+    stream: usize,
+    (start, end): (f64, f64),
+    detector: &dyn Detector,
+    zero_crossing: bool,
+    tmpdir: &Path,
+    run: &RunOptions,
+) -> Result<QuietestPointResult> {
+    let proxy = intermediate_file(tmpdir, "range_proxy", run.intermediate);
+    let proxy_start = (start - PROBE_PRE_ROLL_SECONDS).max(0.0);
+    extract_range_proxy(input, stream, (start, end), &proxy, run)?;
+    let mut result = find_split_point(
+        detector,
+        &proxy,
+        start - proxy_start,
+        end - proxy_start,
+        zero_crossing,
+        run.debug,
+    )?;
+    result.snapped_from = result.snapped_from.map(|time| time + proxy_start);
+    result.time += proxy_start;
+    fs::remove_file(&proxy)?;
+    Ok(result)
+}
 
-            if version_info.is_tested_version {
-                println!("   Note: This is the tested version");
-            } else {
-                println!("   Note: Tested with version 7.1.x");
+fn handle_probe_quiet(probe: &ProbeQuietArgs, temp_root: &Path) -> Result<()> {
+    let input = &probe.target.input;
+    let (start, end) = probe.range;
+    let stream_spec = select_stream(&probe.target)?;
+    info!(
+        "🔍 {}\n",
+        tf(
+            Msg::SearchingQuietPoints,
+            &[
+                ("stream", stream_spec.to_string()),
+                ("start", format!("{:.3}", start)),
+                ("end", format!("{:.3}", end)),
+                ("file", input.to_string())
+            ]
+        )
+    );
+    let stream = probe_audio_stream(input, stream_spec)?.absolute_index;
+
+    // Only the range (and a short pre-roll) is extracted instead of the whole track
+    let run = RunOptions {
+        debug: probe.debug,
+        ..RunOptions::default()
+    };
+    let tmpdir = create_temp_dir(temp_root)?;
+    let proxy = intermediate_file(&tmpdir, "probe_range", run.intermediate);
+    let proxy_start = (start - PROBE_PRE_ROLL_SECONDS).max(0.0);
+    let result = (|| {
+        extract_range_proxy(input, stream, (start, end), &proxy, &run)?;
+        let mut silence_threshold = probe.silence_threshold;
+        if let Some(margin) = probe.auto_threshold {
+            let margin = margin.unwrap_or(DEFAULT_AUTO_THRESHOLD_MARGIN_DB);
+            match measure_noise_floor(&proxy)? {
+                Some(floor) => {
+                    silence_threshold = floor + margin;
+                    info!(
+                        "ℹ️ {}",
+                        tf(
+                            Msg::RangeNoiseFloor,
+                            &[
+                                ("floor", format!("{:.1}", floor)),
+                                ("threshold", format!("{:.1}", silence_threshold))
+                            ]
+                        )
+                    );
+                }
+                None => warn!(
+                    "⚠️ {}",
+                    tf(
+                        Msg::RangeSilent,
+                        &[("threshold", format!("{:.1}", silence_threshold))]
+                    )
+                ),
             }
-        } else {
-            println!("⚠️  Could not parse FFmpeg version from output");
         }
-    } else if let Some(error) = &check_result.error {
-        println!("❌ FFmpeg not found in PATH");
-        println!("   Please install FFmpeg and ensure it's accessible from the command line");
-        bail!("FFmpeg is required but not installed: {}", error);
-    }
-
-    println!();
+        let scan = scan_quiet_candidates(
+            &proxy,
+            start - proxy_start,
+            end - proxy_start,
+            silence_threshold,
+            probe.max_peak,
+            probe.avoid_speech,
+        )?;
+        if probe.debug {
+            println!("{}", scan.stderr);
+        }
+        Ok::<_, anyhow::Error>((scan, silence_threshold))
+    })();
+    fs::remove_dir_all(&tmpdir)?;
+    let (scan, silence_threshold) = result?;
 
-    // Display FFprobe status
-    if check_result.ffprobe_available {
-        println!("✅ FFprobe found and working");
-    } else {
-        println!("❌ FFprobe not found in PATH");
-        bail!("FFprobe is required but not installed");
+    // Neighbouring measurements of one quiet passage count as a single candidate
+    let mut quietest = scan.candidates.clone();
+    quietest.sort_by(|a, b| a.loudness.total_cmp(&b.loudness));
+    let mut picked: Vec<QuietCandidate> = Vec::new();
+    for candidate in quietest {
+        if picked.len() == probe.candidates {
+            break;
+        }
+        if picked
+            .iter()
+            .all(|p| (p.time - candidate.time).abs() >= PROBE_CANDIDATE_SPACING)
+        {
+            picked.push(candidate);
+        }
     }
 
-    println!();
+    if picked.is_empty() {
+        let threshold = ("threshold", format!("{:.1}", silence_threshold));
+        let message = if scan.peak_rejected > 0 {
+            tf(
+                Msg::NoPointAbovePeak,
+                &[
+                    threshold,
+                    ("peak", format!("{:.1}", probe.max_peak.unwrap_or_default())),
+                    ("rejected", scan.peak_rejected.to_string()),
+                ],
+            )
+        } else {
+            tf(Msg::NoPointAbove, &[threshold])
+        };
+        error!("❌ {}", message);
+        if scan.speech_rejected > 0 {
+            info!(
+                "🗣️ {}",
+                tf(
+                    Msg::SpeechSkipped,
+                    &[("count", scan.speech_rejected.to_string())]
+                )
+            );
+        }
+        return Ok(());
+    }
 
-    // Display filter availability
-    if check_result.ebur128_filter_available {
-        println!("✅ Required filter 'ebur128' is available");
-    } else {
-        println!("❌ Required filter 'ebur128' not found");
-        println!("   This filter is needed for loudness analysis");
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_header(vec![
+        t(Msg::HeaderRank),
+        t(Msg::HeaderTime),
+        t(Msg::HeaderLoudness),
+        t(Msg::HeaderTruePeak),
+    ]);
+    for (i, candidate) in picked.iter().enumerate() {
+        porcelain::emit(
+            "candidate",
+            &[
+                &(i + 1),
+                &(candidate.time + proxy_start),
+                &candidate.loudness,
+                &candidate
+                    .peak
+                    .map(|peak| peak.to_string())
+                    .unwrap_or_default(),
+            ],
+        );
+        table.add_row(vec![
+            format!("{:.3}", candidate.time + proxy_start),
+            format!("{:.1}", candidate.loudness),
+            candidate
+                .peak
+                .map(|peak| format!("{:.1}", peak))
+                .unwrap_or_else(|| "-".to_string()),
+        ]);
     }
+    info!("{}", table);
+    let values = [
+        ("count", scan.candidates.len().to_string()),
+        ("threshold", format!("{:.1}", silence_threshold)),
+        ("rejected", scan.peak_rejected.to_string()),
+    ];
+    let msg = if scan.peak_rejected > 0 {
+        Msg::MeasuredPointsRejected
+    } else {
+        Msg::MeasuredPoints
+    };
+    info!("\nℹ️ {}", tf(msg, &values));
+    info!(
+        "💡 {}",
+        tf(
+            Msg::SplitRangeHint,
+            &[
+                ("start", start.to_string()),
+                ("end", end.to_string()),
+                ("time", format!("{:.3}", picked[0].time + proxy_start))
+            ]
+        )
+    );
 
-    println!("\n🎉 FFmpeg check complete!");
     Ok(())
 }
 
-fn handle_inspect(input: &str) -> Result<()> {
-    println!("🔍 Inspecting audio streams in: {}\n", input);
+fn print_channel_issues(report: &ChannelReport) {
+    if report.issues.is_empty() {
+        info!("✅ {}", t(Msg::NoChannelIssues));
+    }
+    for issue in &report.issues {
+        warn!("⚠️ {}", report.describe(issue));
+    }
+}
+
+fn handle_inspect(input: &str, duration_tolerance_ms: f64) -> Result<()> {
+    info!(
+        "🔍 {}\n",
+        tf(Msg::InspectingStreams, &[("file", input.to_string())])
+    );
 
     let streams = inspect_audio_streams(input)?;
 
     if streams.is_empty() {
-        println!("❌ No audio streams found in the input file.");
+        error!("❌ {}", t(Msg::NoAudioStreams));
         return Ok(());
     }
+    for warning in language_warnings(&streams) {
+        warn!("⚠️ {}", warning);
+    }
+    let container_duration = Some(get_file_duration(input)?).filter(|d| *d > 0.0);
 
     let mut table = Table::new();
     table.load_preset(UTF8_FULL);
     table.set_header(vec![
-        "Index",
-        "Codec",
-        "Channels",
-        "Sample Rate",
-        "Bitrate",
-        "Language",
-        "Title",
+        t(Msg::HeaderIndex),
+        t(Msg::HeaderCodec),
+        t(Msg::HeaderChannels),
+        t(Msg::HeaderSampleRate),
+        t(Msg::HeaderBitrate),
+        t(Msg::HeaderLanguage),
+        t(Msg::HeaderTitle),
+        t(Msg::HeaderDuration),
+        t(Msg::HeaderContainerDelta),
     ]);
 
+    let indices: Vec<usize> = streams.iter().map(|stream| stream.index).collect();
+    let mut off_length = Vec::new();
     for stream in streams {
+        // Positive when the stream runs longer than the container says
+        let delta = stream
+            .duration
+            .zip(container_duration)
+            .map(|(stream, container)| stream - container);
+        let flagged = delta.is_some_and(|d| d.abs() * 1000.0 > duration_tolerance_ms);
+        if flagged {
+            off_length.push((stream.index, delta.unwrap_or_default()));
+        }
+        porcelain::emit(
+            "stream",
+            &[
+                &stream.index,
+                &stream.codec,
+                &stream.channels,
+                &stream.sample_rate,
+                &stream.bitrate,
+                &stream.language,
+                &stream.title,
+                &u8::from(stream.default),
+                &u8::from(stream.commentary),
+                &stream
+                    .duration
+                    .map(|d| format!("{:.3}", d))
+                    .unwrap_or_default(),
+                &delta.map(|d| format!("{:.3}", d)).unwrap_or_default(),
+            ],
+        );
         table.add_row(vec![
             stream.index.to_string(),
             stream.codec,
@@ -464,12 +5289,302 @@ fn handle_inspect(input: &str) -> Result<()> {
             stream.bitrate,
             stream.language,
             stream.title,
+            stream
+                .duration
+                .map(|d| format!("{:.3}s", d))
+                .unwrap_or_else(|| "-".to_string()),
+            match delta {
+                Some(d) if flagged => format!("⚠️ {:+.3}s", d),
+                Some(d) => format!("{:+.3}s", d),
+                None => "-".to_string(),
+            },
         ]);
     }
 
-    println!("{}", table);
-    println!("\n💡 Use the 'Index' value with --stream to select an audio stream for processing.");
+    info!("{}", table);
+    if let Some(container) = container_duration {
+        info!(
+            "ℹ️ {}",
+            tf(
+                Msg::ContainerDuration,
+                &[("duration", format!("{:.3}", container))]
+            )
+        );
+    }
+    for (index, delta) in &off_length {
+        let msg = if *delta < 0.0 {
+            Msg::AudioShorter
+        } else {
+            Msg::AudioLonger
+        };
+        warn!(
+            "⚠️ {}",
+            tf(
+                msg,
+                &[
+                    ("stream", index.to_string()),
+                    ("seconds", format!("{:.3}", delta.abs()))
+                ]
+            )
+        );
+    }
+
+    // Tracks written with --tag-history carry the edits applied to them
+    let mut history_table = Table::new();
+    history_table.load_preset(UTF8_FULL);
+    history_table.set_header(vec![
+        t(Msg::HeaderIndex),
+        t(Msg::HeaderEdit),
+        t(Msg::HeaderApplied),
+        t(Msg::HeaderVersion),
+        t(Msg::HeaderSource),
+        t(Msg::HeaderSplitsAndDelays),
+    ]);
+    let mut has_history = false;
+    for stream in &indices {
+        let history = match read_history(input, *stream) {
+            Ok(history) => history,
+            Err(e) => {
+                warn!(
+                    "⚠️ {}",
+                    tf(
+                        Msg::StreamHistoryUnreadable,
+                        &[("stream", stream.to_string()), ("error", e.to_string())]
+                    )
+                );
+                continue;
+            }
+        };
+        for (i, record) in history.iter().enumerate() {
+            has_history = true;
+            history_table.add_row(vec![
+                stream.to_string(),
+                (i + 1).to_string(),
+                record.applied_at_utc(),
+                record.version.clone(),
+                record.source.clone(),
+                record.describe_edit(),
+            ]);
+        }
+    }
+    if has_history {
+        info!("\n📜 {}", t(Msg::AppliedEdits));
+        info!("{}", history_table);
+        if Path::new(&sidecar_path(input)).is_file() {
+            info!(
+                "ℹ️ {}",
+                tf(Msg::HistoryReadFrom, &[("path", sidecar_path(input))])
+            );
+        }
+        warn!("⚠️ {}", t(Msg::TracksAlreadyNudged));
+    }
+
+    info!("\n💡 {}", t(Msg::IndexHint));
+
+    Ok(())
+}
+
+/// Size and quality of a `--sample-encode` sample.
+struct SampleStats {
+    /// Encoded size in bytes
+    size: u64,
+    /// Length of the encoded sample in seconds
+    duration: f64,
+    source_loudness: f64,
+    encoded_loudness: f64,
+    /// Highest sample peak of the encoded sample in dBFS
+    peak: Option<f64>,
+}
+
+/// Apply the segment plan to `window` of a stream and encode the result with `codec`,
+/// `bitrate` and `format`, as the full run would. Delays before the window only move it and are left out.
+fn encode_sample(
+    input: &str,
+    stream: usize,
+    window: (f64, f64),
+    (split_points, delays, tempos): (&[f64], &[Delay], &[f64]),
+    (codec, bitrate, format): (&str, Option<&str>, &StreamFormat),
+    tmpdir: &Path,
+    run: &RunOptions,
+) -> Result<SampleStats> {
+    let sample_dir = tmpdir.join("sample");
+    fs::create_dir_all(&sample_dir)?;
+    let source = intermediate_file(&sample_dir, "sample_source", run.intermediate);
+    extract_audio_range_to_flac(input, stream, window.0, window.1 - window.0, &source, run)?;
+
+    let (sample_points, sample_delays, sample_tempos) =
+        window_plan(window, (split_points, delays, tempos));
+    let edited = edit_audio(
+        &source,
+        &sample_points,
+        &sample_delays,
+        &sample_tempos,
+        &sample_dir,
+        run,
+        &|_| {},
+    )?;
+    let encoded = sample_dir.join(format!("sample.{}", audio_extension_for_codec(codec)));
+    convert_audio_codec(&edited, codec, bitrate, format, &encoded, run, &|_| {})?;
+
+    let encoded_str = path_to_str(&encoded)?;
+    let duration = get_file_duration(encoded_str)?;
+    Ok(SampleStats {
+        size: fs::metadata(&encoded)?.len(),
+        duration,
+        source_loudness: integrated_loudness(path_to_str(&source)?, "0:a:0")?,
+        encoded_loudness: integrated_loudness(encoded_str, "0:a:0")?,
+        peak: measure_head_peak(encoded_str, "0:a:0", duration)?,
+    })
+}
+
+/// The edit of `window` alone, with times relative to its start. The segment the window starts
+/// in keeps its speed; boundaries inside it keep their delays.
+fn window_plan(
+    window: (f64, f64),
+    (split_points, delays, tempos): (&[f64], &[Delay], &[f64]),
+) -> (Vec<f64>, Vec<Delay>, Vec<f64>) {
+    let first = split_points.iter().filter(|p| **p <= window.0).count();
+    let mut points = Vec::new();
+    let mut window_delays = vec![Delay::default()];
+    let mut window_tempos = vec![tempos[first]];
+    for (i, point) in split_points.iter().enumerate().skip(first) {
+        if *point >= window.1 {
+            break;
+        }
+        points.push(point - window.0);
+        window_delays.push(delays[i + 1]);
+        window_tempos.push(tempos[i + 1]);
+    }
+    (points, window_delays, window_tempos)
+}
+
+/// Write `PREVIEW_SECONDS` of audio before and after every split point to `dir` as FLAC, once
+/// as in the source and once with the edit applied, and return the written files.
+fn export_previews(
+    input: &str,
+    stream: usize,
+    media_duration: f64,
+    plan: (&[f64], &[Delay], &[f64]),
+    dir: &Path,
+    tmpdir: &Path,
+    run: &RunOptions,
+) -> Result<Vec<PathBuf>> {
+    let preview_dir = tmpdir.join("previews");
+    fs::create_dir_all(&preview_dir)?;
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for (i, split) in plan.0.iter().enumerate() {
+        let window = (
+            (split - PREVIEW_SECONDS).max(0.0),
+            (split + PREVIEW_SECONDS).min(media_duration),
+        );
+        let source = intermediate_file(&preview_dir, "preview_source", run.intermediate);
+        extract_audio_range_to_flac(input, stream, window.0, window.1 - window.0, &source, run)?;
+        let (points, delays, tempos) = window_plan(window, plan);
+        let edited = edit_audio(
+            &source,
+            &points,
+            &delays,
+            &tempos,
+            &preview_dir,
+            run,
+            &|_| {},
+        )?;
+        for (clip, kind) in [(&source, "original"), (&edited, "delayed")] {
+            let preview = dir.join(format!("split_{:02}_{:.3}s_{}.flac", i + 1, split, kind));
+            run_ffmpeg(
+                &str_args(&run.output_args(&[
+                    "-y",
+                    "-i",
+                    path_to_str(clip)?,
+                    "-c:a",
+                    "flac",
+                    path_to_str(&preview)?,
+                ])),
+                run.debug,
+            )?;
+            written.push(preview);
+        }
+    }
+    fs::remove_dir_all(&preview_dir)?;
+    Ok(written)
+}
+
+/// Split points, step delays and playback speeds of every segment. Each boundary starts a new
+/// segment; a ramp is a segment that is stretched by its delay, followed by an unchanged one.
+fn segment_boundaries(
+    all_splits: &[(f64, f64, String, Option<String>)],
+    ramps: &[crate::cli::DelayRamp],
+    initial_delay: f64,
+) -> (Vec<f64>, Vec<Delay>, Vec<f64>) {
+    let mut boundaries: Vec<(f64, f64, f64)> = all_splits
+        .iter()
+        .map(|(point, delay, _, _)| (*point, *delay, 1.0))
+        .collect();
+    for ramp in ramps {
+        let span = ramp.end - ramp.start;
+        boundaries.push((ramp.start, 0.0, span / (span + ramp.delay / 1000.0)));
+        boundaries.push((ramp.end, 0.0, 1.0));
+    }
+    boundaries.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut split_points: Vec<f64> = Vec::new();
+    let mut delays: Vec<Delay> = vec![Delay::from_millis(initial_delay)];
+    let mut tempos: Vec<f64> = vec![1.0];
+    for (point, delay, tempo) in &boundaries {
+        split_points.push(*point);
+        delays.push(Delay::from_millis(*delay));
+        tempos.push(*tempo);
+    }
+    (split_points, delays, tempos)
+}
 
+/// Ensure ramps, from the command line or a task file, have a length, neither overlap each other
+/// nor contain a step split.
+fn validate_ramps(
+    all_splits: &[(f64, f64, String, Option<String>)],
+    ramps: &[crate::cli::DelayRamp],
+) -> Result<()> {
+    for (i, ramp) in ramps.iter().enumerate() {
+        if ramp.start >= ramp.end {
+            bail!(
+                "The ramp {:.3}s - {:.3}s must start before it ends.",
+                ramp.start,
+                ramp.end
+            );
+        }
+        if !ramp.delay.is_finite() || (ramp.end - ramp.start) * 1000.0 + ramp.delay <= 0.0 {
+            bail!(
+                "A delay of {} ms would shrink the ramp {:.3}s - {:.3}s to zero length.",
+                ramp.delay,
+                ramp.start,
+                ramp.end
+            );
+        }
+        if let Some((point, _, _, _)) = all_splits
+            .iter()
+            .find(|(point, _, _, _)| *point > ramp.start && *point < ramp.end)
+        {
+            bail!(
+                "Split at {:.3}s falls inside the ramp {:.3}s - {:.3}s.",
+                point,
+                ramp.start,
+                ramp.end
+            );
+        }
+        if let Some(other) = ramps[i + 1..]
+            .iter()
+            .find(|other| other.start < ramp.end && ramp.start < other.end)
+        {
+            bail!(
+                "Ramps {:.3}s - {:.3}s and {:.3}s - {:.3}s overlap.",
+                ramp.start,
+                ramp.end,
+                other.start,
+                other.end
+            );
+        }
+    }
     Ok(())
 }
 
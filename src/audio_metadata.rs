@@ -1,14 +1,32 @@
 use anyhow::{Result, bail};
 use std::process::Command;
 
-use crate::ffmpeg::FFmpegError;
+use crate::cli::StreamSpecifier;
+use crate::ffmpeg::{FFmpegError, ffprobe_executable};
+use crate::language::{normalize_language, normalize_or_keep, unknown_language_warning};
 
 /// Struct to hold audio stream metadata
 pub struct AudioStreamMetadata {
+    /// Absolute stream index in the input file
+    pub absolute_index: usize,
+    /// Position among the audio streams of the input file
     pub stream_index: usize,
     pub codec: String,
     pub title: String,
     pub language: String,
+    pub format: StreamFormat,
+}
+
+/// Channel layout, sample rate and sample format of an audio stream, kept by the re-encode.
+/// Values the probe did not report are 0 or empty.
+#[derive(Debug, Clone, Default)]
+pub struct StreamFormat {
+    pub channels: u32,
+    /// ffmpeg channel layout name (e.g. `5.1(side)`)
+    pub channel_layout: String,
+    pub sample_rate: u32,
+    /// ffmpeg sample format of the decoded audio (e.g. `s32`, `fltp`)
+    pub sample_fmt: String,
 }
 
 #[derive(Debug)]
@@ -20,10 +38,36 @@ pub struct AudioStream {
     pub bitrate: String,
     pub language: String,
     pub title: String,
+    /// Whether the stream has the default disposition
+    pub default: bool,
+    /// Whether the stream is commentary or audio description rather than the main mix,
+    /// judged by its dispositions and title
+    pub commentary: bool,
+    /// Length of the stream in seconds, from the stream or its Matroska `DURATION` tag
+    pub duration: Option<f64>,
+}
+
+/// Words in a track title that mark commentary or audio description.
+const COMMENTARY_TITLE_WORDS: &[&str] = &[
+    "commentary",
+    "comments",
+    "audio description",
+    "descriptive",
+    "described",
+    "isolated score",
+    "karaoke",
+];
+
+/// Whether a track title suggests commentary or audio description rather than the main mix.
+pub fn is_commentary_title(title: &str) -> bool {
+    let title = title.to_lowercase();
+    COMMENTARY_TITLE_WORDS
+        .iter()
+        .any(|word| title.contains(word))
 }
 
 pub fn inspect_audio_streams(input_file: &str) -> Result<Vec<AudioStream>, FFmpegError> {
-    let output = Command::new("ffprobe")
+    let output = Command::new(ffprobe_executable())
         .args([
             "-v",
             "quiet",
@@ -78,14 +122,11 @@ pub fn inspect_audio_streams(input_file: &str) -> Result<Vec<AudioStream>, FFmpe
 
             let bitrate = get_stream_bitrate(&stream, file_duration);
 
-            let language = if let Some(tags) = stream["tags"].as_object() {
-                tags.get("language")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("unknown")
-                    .to_string()
-            } else {
-                "unknown".to_string()
-            };
+            let language = stream["tags"]["language"]
+                .as_str()
+                .map(normalize_or_keep)
+                .filter(|l| !l.is_empty())
+                .unwrap_or_else(|| "unknown".to_string());
 
             let title = if let Some(tags) = stream["tags"].as_object() {
                 tags.get("title")
@@ -96,6 +137,11 @@ pub fn inspect_audio_streams(input_file: &str) -> Result<Vec<AudioStream>, FFmpe
                 "-".to_string()
             };
 
+            let disposition = |flag: &str| stream["disposition"][flag].as_i64() == Some(1);
+            let commentary = disposition("comment")
+                || disposition("visual_impaired")
+                || is_commentary_title(&title);
+
             streams.push(AudioStream {
                 index,
                 codec,
@@ -104,6 +150,9 @@ pub fn inspect_audio_streams(input_file: &str) -> Result<Vec<AudioStream>, FFmpe
                 bitrate,
                 language,
                 title,
+                default: disposition("default"),
+                commentary,
+                duration: stream_duration(stream),
             });
         }
     }
@@ -111,6 +160,22 @@ pub fn inspect_audio_streams(input_file: &str) -> Result<Vec<AudioStream>, FFmpe
     Ok(streams)
 }
 
+/// Duration of a stream as reported by ffprobe, or from the `DURATION` tag Matroska muxers write
+/// instead (`HH:MM:SS.nnnnnnnnn`).
+pub fn stream_duration(stream: &serde_json::Value) -> Option<f64> {
+    if let Some(duration) = stream["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+    {
+        return Some(duration);
+    }
+    let tags = stream["tags"].as_object()?;
+    ["DURATION", "DURATION-eng"]
+        .iter()
+        .filter_map(|tag| tags.get(*tag).and_then(|v| v.as_str()))
+        .find_map(|d| crate::cli::parse_timecode(d).ok())
+}
+
 fn get_stream_bitrate(stream: &serde_json::Value, file_duration: Option<f64>) -> String {
     // Try direct bit_rate field first
     if let Some(br) = stream["bit_rate"].as_str() {
@@ -164,6 +229,16 @@ fn get_stream_bitrate(stream: &serde_json::Value, file_duration: Option<f64>) ->
     }
 }
 
+/// Warnings for the streams whose language tag is unknown; [`inspect_audio_streams`] keeps such
+/// tags as they are without saying so, as it runs several times per file.
+pub fn language_warnings(streams: &[AudioStream]) -> Vec<String> {
+    streams
+        .iter()
+        .filter(|s| s.language != "unknown")
+        .filter_map(|s| unknown_language_warning(&s.language, &format!("audio stream {}", s.index)))
+        .collect()
+}
+
 pub fn get_stream_bitrate_for_processing(
     input_file: &str,
     stream_index: usize,
@@ -194,15 +269,43 @@ pub fn get_stream_bitrate_for_processing(
     Err(FFmpegError::BitrateUndetermined { stream_index })
 }
 
+/// Pick the main audio stream of a file: the default stream unless it is commentary, else the
+/// first stream that is not commentary, else the first stream. Returns its absolute index and
+/// why it was picked.
+pub fn find_main_audio_stream(input: &str) -> Result<(usize, &'static str)> {
+    let streams = inspect_audio_streams(input)?;
+    if let Some(stream) = streams.iter().find(|s| s.default && !s.commentary) {
+        Ok((stream.index, "default disposition"))
+    } else if let Some(stream) = streams.iter().find(|s| !s.commentary) {
+        Ok((stream.index, "first audio stream that is not commentary"))
+    } else if let Some(stream) = streams.first() {
+        Ok((stream.index, "first audio stream"))
+    } else {
+        bail!("'{}' has no audio streams.", input)
+    }
+}
+
+/// Resolve `auto` to the absolute index of the main audio stream; other specifiers are
+/// returned as they are.
+pub fn resolve_stream(input: &str, stream: StreamSpecifier) -> Result<StreamSpecifier> {
+    match stream {
+        StreamSpecifier::Auto => Ok(StreamSpecifier::Index(find_main_audio_stream(input)?.0)),
+        _ => Ok(stream),
+    }
+}
+
 /// Probe the input file for the audio stream index, codec, title, and language.
-pub fn probe_audio_stream(input: &str, stream: usize) -> Result<AudioStreamMetadata> {
+/// The stream may be given by absolute index or by position among the audio streams;
+/// both are resolved against the same ffprobe listing so they always agree.
+pub fn probe_audio_stream(input: &str, stream: StreamSpecifier) -> Result<AudioStreamMetadata> {
+    let stream = resolve_stream(input, stream)?;
     // Get stream index and codec
-    let ffprobe_streams = Command::new("ffprobe")
+    let ffprobe_streams = Command::new(ffprobe_executable())
         .args(&[
             "-v",
             "error",
             "-show_entries",
-            "stream=index,codec_type,codec_name",
+            "stream=index,codec_type,codec_name,sample_fmt,sample_rate,channels,channel_layout",
             "-of",
             "csv=p=0",
             input,
@@ -211,14 +314,33 @@ pub fn probe_audio_stream(input: &str, stream: usize) -> Result<AudioStreamMetad
     let streams_info = String::from_utf8_lossy(&ffprobe_streams.stdout);
     let mut audio_count = 0;
     let mut audio_stream_idx = -1isize;
+    let mut absolute_index = 0;
     let mut original_codec = String::new();
+    let mut format = StreamFormat::default();
     for line in streams_info.lines() {
         let parts: Vec<_> = line.split(',').collect();
         if parts.len() >= 3 && parts[2] == "audio" {
             if let Ok(id) = parts[0].parse::<usize>() {
-                if id == stream {
+                let selected = match stream {
+                    StreamSpecifier::Index(index) => id == index,
+                    StreamSpecifier::Audio(n) => audio_count as usize == n,
+                    StreamSpecifier::Auto => unreachable!("resolved above"),
+                };
+                if selected {
                     audio_stream_idx = audio_count;
+                    absolute_index = id;
                     original_codec = parts[1].to_string();
+                    // Audio fields follow in ffprobe's order: sample_fmt, sample_rate, channels, layout
+                    let field = |i: usize| parts.get(i).map(|s| s.trim()).unwrap_or("");
+                    format = StreamFormat {
+                        sample_fmt: field(3).to_string(),
+                        sample_rate: field(4).parse().unwrap_or(0),
+                        channels: field(5).parse().unwrap_or(0),
+                        channel_layout: match field(6) {
+                            "unknown" => String::new(),
+                            layout => layout.to_string(),
+                        },
+                    };
                     break;
                 }
             }
@@ -232,7 +354,7 @@ pub fn probe_audio_stream(input: &str, stream: usize) -> Result<AudioStreamMetad
         bail!("Could not determine codec for audio stream {}", stream);
     }
     // Get title
-    let ffprobe_title = Command::new("ffprobe")
+    let ffprobe_title = Command::new(ffprobe_executable())
         .args(&[
             "-v",
             "error",
@@ -249,7 +371,7 @@ pub fn probe_audio_stream(input: &str, stream: usize) -> Result<AudioStreamMetad
         .trim()
         .to_owned();
     // Get language
-    let ffprobe_lang = Command::new("ffprobe")
+    let ffprobe_lang = Command::new(ffprobe_executable())
         .args(&[
             "-v",
             "error",
@@ -262,20 +384,97 @@ pub fn probe_audio_stream(input: &str, stream: usize) -> Result<AudioStreamMetad
             input,
         ])
         .output()?;
-    let original_lang = String::from_utf8_lossy(&ffprobe_lang.stdout)
-        .trim()
-        .to_owned();
+    let original_lang = normalize_or_keep(&String::from_utf8_lossy(&ffprobe_lang.stdout));
     Ok(AudioStreamMetadata {
+        absolute_index,
         stream_index: audio_stream_idx as usize,
         codec: original_codec,
         title: original_title,
         language: original_lang,
+        format,
     })
 }
 
+/// Absolute indices of the audio streams whose language matches `language`.
+/// Both the requested and the tagged languages are normalized, so `ja`, `jp` and `jpn` match each other.
+pub fn find_audio_streams_by_language(input: &str, language: &str) -> Result<Vec<usize>> {
+    let wanted = language_key(language);
+    Ok(inspect_audio_streams(input)?
+        .into_iter()
+        .filter(|s| s.language.eq_ignore_ascii_case(&wanted))
+        .map(|s| s.index)
+        .collect())
+}
+
+/// Absolute index of the one audio stream tagged with `language` whose title contains `title`
+/// (ignoring case); either may be left out. A title that matches one stream exactly wins over
+/// streams that only contain it. Fails if no stream or several streams match.
+pub fn find_audio_stream_by_tags(
+    input: &str,
+    language: Option<&str>,
+    title: Option<&str>,
+) -> Result<usize> {
+    let wanted_language = language.map(language_key);
+    let wanted_title = title.map(|t| t.trim().to_lowercase());
+    let matches: Vec<AudioStream> = inspect_audio_streams(input)?
+        .into_iter()
+        .filter(|s| {
+            wanted_language
+                .as_ref()
+                .is_none_or(|l| s.language.eq_ignore_ascii_case(l))
+                && wanted_title
+                    .as_ref()
+                    .is_none_or(|t| s.title.to_lowercase().contains(t.as_str()))
+        })
+        .collect();
+    let exact: Vec<&AudioStream> = match &wanted_title {
+        Some(t) => matches
+            .iter()
+            .filter(|s| s.title.trim().to_lowercase() == *t)
+            .collect(),
+        None => Vec::new(),
+    };
+    if let [stream] = exact.as_slice() {
+        return Ok(stream.index);
+    }
+
+    let selector = match (language, title) {
+        (Some(l), Some(t)) => format!("tagged with language '{}' and titled '{}'", l, t),
+        (Some(l), None) => format!("tagged with language '{}'", l),
+        (None, Some(t)) => format!("titled '{}'", t),
+        (None, None) => bail!("Select the audio stream by language or title."),
+    };
+    match matches.as_slice() {
+        [stream] => Ok(stream.index),
+        [] => bail!(
+            "No audio stream is {}. Use `sync-nudger inspect` to list the audio streams.",
+            selector
+        ),
+        _ => bail!(
+            "Several audio streams are {}: {}. Narrow the selection by language and title, or give the stream index.",
+            selector,
+            matches
+                .iter()
+                .map(|s| format!(
+                    "#{} ({}, {} ch, \"{}\")",
+                    s.index, s.codec, s.channels, s.title
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// A language as it is compared with the stream tags: normalized where it is known.
+fn language_key(language: &str) -> String {
+    normalize_language(language)
+        .map(str::to_string)
+        .unwrap_or_else(|| language.trim().to_ascii_lowercase())
+}
+
 /// Get the duration of the audio stream (in seconds)
 pub fn get_audio_stream_duration(input_file: &str, stream_index: usize) -> Result<Option<f64>> {
-    let output = Command::new("ffprobe")
+    let output = Command::new(ffprobe_executable())
         .args(&[
             "-v",
             "error",
@@ -321,8 +520,14 @@ pub fn get_audio_stream_duration(input_file: &str, stream_index: usize) -> Resul
 /// Build FFmpeg -map arguments to replace a specific audio stream with a new one from input 1.
 /// Returns a Vec<String> of -map arguments.
 pub fn build_stream_map_args(input: &str, replaced_audio_stream_idx: usize) -> Result<Vec<String>> {
+    build_streams_map_args(input, &[replaced_audio_stream_idx])
+}
+
+/// Build FFmpeg -map arguments that replace several audio streams: the audio stream at position
+/// `replaced[j]` (among the audio streams) is taken from input `j + 1`.
+pub fn build_streams_map_args(input: &str, replaced: &[usize]) -> Result<Vec<String>> {
     // Use ffprobe to get all streams and their types
-    let ffprobe_streams = std::process::Command::new("ffprobe")
+    let ffprobe_streams = std::process::Command::new(ffprobe_executable())
         .args(&[
             "-v",
             "error",
@@ -342,10 +547,10 @@ pub fn build_stream_map_args(input: &str, replaced_audio_stream_idx: usize) -> R
             let idx = parts[0];
             let typ = parts[1];
             if typ == "audio" {
-                if audio_count == replaced_audio_stream_idx {
-                    // Insert the new audio stream from input 1 in place of this one
+                if let Some(j) = replaced.iter().position(|idx| *idx == audio_count) {
+                    // Insert the new audio stream from its input in place of this one
                     map_args.push("-map".to_string());
-                    map_args.push("1:0".to_string());
+                    map_args.push(format!("{}:0", j + 1));
                 } else {
                     map_args.push("-map".to_string());
                     map_args.push(format!("0:{}", idx));
@@ -361,9 +566,79 @@ pub fn build_stream_map_args(input: &str, replaced_audio_stream_idx: usize) -> R
     Ok(map_args)
 }
 
+/// Build ffmpeg -map arguments that take every video stream from input 1 (the input again,
+/// shifted) and every other stream from input 0, in their original order.
+pub fn build_video_shift_map_args(input: &str) -> Result<Vec<String>> {
+    let ffprobe_streams = std::process::Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "stream=index,codec_type",
+            "-of",
+            "csv=p=0",
+            input,
+        ])
+        .output()?;
+    let streams_info = String::from_utf8_lossy(&ffprobe_streams.stdout);
+    let mut map_args = Vec::new();
+    let mut video_count = 0;
+    for line in streams_info.lines() {
+        if let [idx, typ] = line.split(',').collect::<Vec<_>>()[..] {
+            let from = if typ == "video" {
+                video_count += 1;
+                1
+            } else {
+                0
+            };
+            map_args.push("-map".to_string());
+            map_args.push(format!("{}:{}", from, idx));
+        }
+    }
+    if video_count == 0 {
+        bail!("'{}' has no video stream to shift.", input);
+    }
+    Ok(map_args)
+}
+
+/// Frame rate of the first video stream, from its average frame rate (or its base rate when
+/// the container does not give one).
+pub fn get_video_frame_rate(input: &str) -> Result<f64> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=avg_frame_rate,r_frame_rate",
+            "-of",
+            "default=noprint_wrappers=1",
+            input,
+        ])
+        .output()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let rate = |key: &str| {
+        text.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .and_then(|value| {
+                let (num, den) = value.trim().split_once('/').unwrap_or((value.trim(), "1"));
+                let rate = num.parse::<f64>().ok()? / den.parse::<f64>().ok()?;
+                (rate.is_finite() && rate > 0.0).then_some(rate)
+            })
+    };
+    match rate("avg_frame_rate").or_else(|| rate("r_frame_rate")) {
+        Some(rate) => Ok(rate),
+        None => bail!(
+            "Could not determine the frame rate of '{}'; pass --fps to use frame counts.",
+            input
+        ),
+    }
+}
+
 /// Get the duration (in seconds) of any media file (container duration).
 pub fn get_file_duration(path: &str) -> anyhow::Result<f64> {
-    let output = std::process::Command::new("ffprobe")
+    let output = std::process::Command::new(ffprobe_executable())
         .args([
             "-v",
             "error",
@@ -380,3 +655,135 @@ pub fn get_file_duration(path: &str) -> anyhow::Result<f64> {
         .unwrap_or(0.0);
     Ok(duration)
 }
+
+/// Get the sample rate (in Hz) of the first audio stream of a media file.
+pub fn get_sample_rate(path: &str) -> Result<u32> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "a:0",
+            "-show_entries",
+            "stream=sample_rate",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path,
+        ])
+        .output()?;
+    let sample_rate = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    match sample_rate.parse() {
+        Ok(rate) => Ok(rate),
+        Err(_) => bail!("Could not determine sample rate of {}", path),
+    }
+}
+
+/// Codec, layout and length of one audio stream.
+#[derive(Debug, Clone)]
+pub struct AudioProperties {
+    pub codec: String,
+    pub channels: u32,
+    /// ffmpeg channel layout name (e.g. `stereo`, `5.1(side)`), empty if not reported
+    pub channel_layout: String,
+    pub sample_rate: u32,
+    /// ffmpeg sample format name (e.g. `s16`, `s32`), empty if not reported
+    pub sample_fmt: String,
+    /// Duration in seconds, if the container reports one
+    pub duration: Option<f64>,
+}
+
+/// Probe an audio stream by absolute index, or the first audio stream if `stream_index` is None.
+pub fn probe_audio_properties(path: &str, stream_index: Option<usize>) -> Result<AudioProperties> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-show_streams",
+            "-show_format",
+            "-of",
+            "json",
+            path,
+        ])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "ffprobe could not read {}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let stream = json["streams"]
+        .as_array()
+        .and_then(|streams| {
+            streams.iter().find(|s| match stream_index {
+                Some(index) => s["index"].as_u64() == Some(index as u64),
+                None => s["codec_type"].as_str() == Some("audio"),
+            })
+        })
+        .ok_or_else(|| anyhow::anyhow!("No audio stream found in {}", path))?;
+    let parse_f64 = |v: &serde_json::Value| v.as_str().and_then(|d| d.parse::<f64>().ok());
+    Ok(AudioProperties {
+        codec: stream["codec_name"]
+            .as_str()
+            .unwrap_or("unknown")
+            .to_string(),
+        channels: stream["channels"].as_u64().unwrap_or(0) as u32,
+        channel_layout: stream["channel_layout"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        sample_rate: stream["sample_rate"]
+            .as_str()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or(0),
+        sample_fmt: stream["sample_fmt"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        duration: parse_f64(&stream["duration"]).or_else(|| parse_f64(&json["format"]["duration"])),
+    })
+}
+
+/// Check an encoded track against the properties it should have before it is remuxed.
+/// `expected.duration` is compared with a tolerance that allows for encoder padding.
+pub fn verify_encoded_track(path: &str, expected: &AudioProperties) -> Result<AudioProperties> {
+    let actual = probe_audio_properties(path, None)?;
+    if actual.codec != expected.codec {
+        bail!(
+            "Encoded track has codec '{}' instead of '{}'.",
+            actual.codec,
+            expected.codec
+        );
+    }
+    if actual.channels != expected.channels {
+        bail!(
+            "Encoded track has {} channels instead of {}.",
+            actual.channels,
+            expected.channels
+        );
+    }
+    if actual.sample_rate != expected.sample_rate {
+        bail!(
+            "Encoded track has a sample rate of {} Hz instead of {} Hz.",
+            actual.sample_rate,
+            expected.sample_rate
+        );
+    }
+    match (actual.duration, expected.duration) {
+        (None, _) => bail!("Encoded track has no duration."),
+        (Some(actual_duration), _) if actual_duration <= 0.0 => bail!("Encoded track is empty."),
+        (Some(actual_duration), Some(expected_duration)) => {
+            let tolerance = 0.25 + expected_duration * 0.001;
+            if (actual_duration - expected_duration).abs() > tolerance {
+                bail!(
+                    "Encoded track is {:.3}s long instead of {:.3}s.",
+                    actual_duration,
+                    expected_duration
+                );
+            }
+        }
+        (Some(_), None) => {}
+    }
+    Ok(actual)
+}
@@ -1,277 +1,1296 @@
-use crate::audio_metadata::build_stream_map_args;
-use crate::ffmpeg::FFmpegError;
-use crate::ffmpeg::run_ffmpeg;
-use anyhow::Result;
+use crate::audio_metadata::{
+    StreamFormat, build_stream_map_args, build_streams_map_args, build_video_shift_map_args,
+    get_sample_rate, probe_audio_properties,
+};
+use crate::cli::{AvoidNegativeTs, IntermediateFormat};
+use crate::ffmpeg::{ChildSettings, FFmpegError, ffprobe_executable};
+use crate::ffmpeg::{
+    ffmpeg_output, format_samples, format_seconds, run_ffmpeg, run_ffmpeg_with_progress,
+    seconds_to_samples,
+};
+use crate::subtitles::ReplacedSubtitle;
+use crate::vad::{in_speech, speech_spans};
+use anyhow::{Result, bail};
 use regex::Regex;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Settings of one run that shape the files it writes, decided once in `run_process` and passed
+/// to every step.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunOptions {
+    /// Format of the intermediate files
+    pub intermediate: IntermediateFormat,
+    /// Length of the crossfade at each join in seconds (0 joins without crossfade). Each
+    /// segment but the last runs on by the crossfade, so the overlap keeps every later segment
+    /// at its planned time.
+    pub crossfade: f64,
+    /// Write bitexact output: no version strings, timestamps or random IDs
+    pub deterministic: bool,
+    /// Number of segments split and delayed at the same time by `split_and_delay_audio` (0 runs
+    /// one at a time)
+    pub segment_jobs: usize,
+    /// Show ffmpeg's own output
+    pub debug: bool,
+}
+
+impl RunOptions {
+    /// `args` with the output options of the run before the output (the last argument).
+    pub fn output_args<S: AsRef<str>>(&self, args: &[S]) -> Vec<String> {
+        let mut args: Vec<String> = args.iter().map(|a| a.as_ref().to_string()).collect();
+        if self.deterministic && !args.is_empty() {
+            let output = args.len() - 1;
+            args.splice(
+                output..output,
+                ["-fflags", "+bitexact", "-flags:a", "+bitexact"].map(str::to_string),
+            );
+        }
+        args
+    }
+
+    /// Codec of the intermediate files.
+    pub fn codec(&self) -> &'static str {
+        self.intermediate.codec()
+    }
+}
+
+/// The filter joining the labelled audio `inputs` into `[a]`: the concat filter, or a chain of
+/// `acrossfade` filters of `crossfade` seconds.
+fn join_filter(inputs: &[String], crossfade: f64) -> String {
+    if crossfade <= 0.0 || inputs.len() < 2 {
+        return inputs.concat() + &format!("concat=n={}:v=0:a=1[a]", inputs.len());
+    }
+    let mut graph = String::new();
+    let mut joined = inputs[0].clone();
+    for (i, input) in inputs.iter().enumerate().skip(1) {
+        let output = if i + 1 == inputs.len() {
+            "[a]".to_string()
+        } else {
+            format!("[xf{}]", i)
+        };
+        if !graph.is_empty() {
+            graph.push(';');
+        }
+        graph.push_str(&format!(
+            "{}{}acrossfade=d={}:c1=tri:c2=tri{}",
+            joined,
+            input,
+            format_seconds(crossfade),
+            output
+        ));
+        joined = output;
+    }
+    graph
+}
+
+/// Number of segments to split and delay at the same time when each ffmpeg process may use
+/// `threads` threads: one per CPU, or as many as fit the CPUs without oversubscribing them.
+pub fn default_segment_jobs(threads: Option<usize>) -> usize {
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    match threads {
+        Some(threads) if threads > 0 => (cpus / threads).max(1),
+        _ => cpus,
+    }
+}
+
+/// Path of an intermediate file named `stem` in `dir`, with the extension of `format`.
+pub fn intermediate_file(dir: &Path, stem: &str, format: IntermediateFormat) -> PathBuf {
+    dir.join(format!("{}.{}", stem, format.extension()))
+}
 
 #[derive(Debug)]
 pub struct QuietestPointResult {
     pub time: f64,
-    pub loudness: f64,
+    /// Momentary loudness (LUFS) at the chosen point, if the detector measures it
+    pub loudness: Option<f64>,
+    /// Highest true peak (dBFS) across channels at the chosen point, if ffmpeg reported one
+    pub peak: Option<f64>,
+    /// Time the detector picked, if it was moved to the nearest zero crossing
+    pub snapped_from: Option<f64>,
+    /// How clearly the point stands out from the rest of the range, from 0 to 1, if the detector
+    /// can tell
+    pub confidence: Option<f64>,
     pub debug_output: Option<String>,
 }
 
+/// A dip this far (in dB or LU) below the median level of a range counts as a certain split point.
+const CONFIDENT_DIP_DB: f64 = 15.0;
+
+/// Confidence of picking `level` as the quietest of `levels`: how far it dips below their median,
+/// relative to [`CONFIDENT_DIP_DB`]. A range that is equally quiet throughout scores 0.
+pub fn dip_confidence(level: f64, levels: &[f64]) -> f64 {
+    if levels.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = levels.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    ((median - level) / CONFIDENT_DIP_DB).clamp(0.0, 1.0)
+}
+
 /// Helper to convert a Path to &str, returning an error if not valid UTF-8.
 fn path_to_str(path: &Path) -> anyhow::Result<&str> {
     path.to_str()
         .ok_or_else(|| anyhow::anyhow!("Invalid path (not UTF-8)"))
 }
 
-/// Extract a specific audio stream from a media file to a FLAC file using ffmpeg.
+/// Borrow owned ffmpeg arguments as the `&str` slice `run_ffmpeg` expects.
+pub fn str_args(args: &[String]) -> Vec<&str> {
+    args.iter().map(|s| s.as_str()).collect()
+}
+
+/// ffmpeg arguments that extract a specific audio stream from a media file to an intermediate
+/// file in the format of `run`.
+pub fn extract_args(
+    input: &str,
+    stream: usize,
+    output_path: &Path,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    Ok(run.output_args(&[
+        "-y".to_string(),
+        "-i".to_string(),
+        input.to_string(),
+        "-map".to_string(),
+        format!("0:{}", stream),
+        "-c:a".to_string(),
+        run.codec().to_string(),
+        path_to_str(output_path)?.to_string(),
+    ]))
+}
+
+/// Extract a specific audio stream from a media file to an intermediate file using ffmpeg.
+/// `progress` receives the seconds of audio extracted so far.
 pub fn extract_audio_stream_to_flac(
     input: &str,
     stream: usize,
     output_path: &std::path::Path,
-    debug: bool,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
 ) -> anyhow::Result<()> {
-    let output_path_str = path_to_str(output_path)?;
-    crate::ffmpeg::run_ffmpeg(
-        &[
+    run_ffmpeg_with_progress(
+        &str_args(&extract_args(input, stream, output_path, run)?),
+        run.debug,
+        progress,
+    )?;
+    Ok(())
+}
+
+/// Extract `duration` seconds of an audio stream, starting at `start`, to an intermediate file. A cheap
+/// stand-in for the full extraction when only one range of the track is analyzed.
+pub fn extract_audio_range_to_flac(
+    input: &str,
+    stream: usize,
+    start: f64,
+    duration: f64,
+    output_path: &Path,
+    run: &RunOptions,
+) -> Result<()> {
+    run_ffmpeg(
+        &str_args(&run.output_args(&[
             "-y",
+            "-ss",
+            &format_seconds(start),
+            "-t",
+            &format_seconds(duration),
             "-i",
             input,
             "-map",
             &format!("0:{}", stream),
             "-c:a",
-            "flac",
-            output_path_str,
-        ],
-        debug,
+            run.codec(),
+            path_to_str(output_path)?,
+        ])),
+        run.debug,
     )?;
     Ok(())
 }
 
-pub fn find_quietest_point(
+/// Margin (in dB) above the noise floor used by `--auto-threshold` when none is given.
+pub const DEFAULT_AUTO_THRESHOLD_MARGIN_DB: f64 = 6.0;
+/// How much of the track (in seconds, from the start) is measured for the noise floor.
+const NOISE_FLOOR_SAMPLE_SECONDS: f64 = 900.0;
+/// Momentary loudness at or below this is digital silence and says nothing about the floor.
+const DIGITAL_SILENCE_LUFS: f64 = -120.0;
+
+/// Estimate the noise floor (in LUFS) of an audio file as the 5th percentile of its momentary
+/// loudness, ignoring digital silence. Returns None if the sample holds nothing but silence.
+pub fn measure_noise_floor(audio_path: &Path) -> Result<Option<f64>, FFmpegError> {
+    let audio_path_str = audio_path.to_str().ok_or_else(|| {
+        FFmpegError::CommandFailed(
+            "measure_noise_floor".to_string(),
+            "Invalid audio path".to_string(),
+        )
+    })?;
+    let output = ffmpeg_output(
+        &[
+            "-t",
+            &format_seconds(NOISE_FLOOR_SAMPLE_SECONDS),
+            "-i",
+            audio_path_str,
+            "-af",
+            "ebur128",
+            "-f",
+            "null",
+            "-",
+        ],
+        false,
+    )?;
+    if !output.status.success() {
+        return Err(FFmpegError::CommandFailed(
+            "measure_noise_floor".to_string(),
+            output.last_error_line(),
+        ));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let re =
+        Regex::new(r"\[Parsed_ebur128_0 @ [^\]]+\] t:\s*[\d.]+\s*TARGET:.*M:\s*([-\d.]+)").unwrap();
+    let mut loudness: Vec<f64> = re
+        .captures_iter(&stderr)
+        .filter_map(|cap| cap.get(1)?.as_str().parse::<f64>().ok())
+        .filter(|m| *m > DIGITAL_SILENCE_LUFS)
+        .collect();
+    if loudness.is_empty() {
+        return Ok(None);
+    }
+    loudness.sort_by(f64::total_cmp);
+    Ok(Some(loudness[loudness.len() / 20]))
+}
+
+/// A point above the silence threshold that qualifies as a split point.
+#[derive(Debug, Clone, Copy)]
+pub struct QuietCandidate {
+    pub time: f64,
+    /// Momentary loudness in LUFS
+    pub loudness: f64,
+    /// Highest true peak (dBFS) across channels, if ffmpeg reported one
+    pub peak: Option<f64>,
+}
+
+/// Every qualifying point between two times, in time order.
+pub struct QuietScan {
+    pub candidates: Vec<QuietCandidate>,
+    /// Points skipped because their true peak exceeds `max_peak`
+    pub peak_rejected: usize,
+    /// Points skipped because they fall inside speech (see `scan_quiet_candidates`)
+    pub speech_rejected: usize,
+    /// ffmpeg's ebur128 log
+    pub stderr: String,
+}
+
+/// Measure the momentary loudness between `start` and `end` and keep the points above
+/// `silence_threshold`. With `max_peak`, points whose true peak exceeds it (in dBFS) are skipped,
+/// and with `avoid_speech`, points inside speech (see `vad::speech_spans`), so a split does not
+/// land in a breath between words.
+pub fn scan_quiet_candidates(
     audio_path: &Path,
     start: f64,
     end: f64,
     silence_threshold: f64,
-    debug: bool,
-) -> Result<QuietestPointResult, FFmpegError> {
+    max_peak: Option<f64>,
+    avoid_speech: bool,
+) -> Result<QuietScan, FFmpegError> {
     let duration = end - start;
     let audio_path_str = audio_path.to_str().ok_or_else(|| {
         FFmpegError::CommandFailed(
-            "find_quietest_point".to_string(),
+            "scan_quiet_candidates".to_string(),
             "Invalid audio path".to_string(),
         )
     })?;
-    let output = Command::new("ffmpeg")
-        .args(&[
+    let output = ffmpeg_output(
+        &[
             "-i",
             audio_path_str,
             "-ss",
-            &start.to_string(),
+            &format_seconds(start),
             "-t",
-            &duration.to_string(),
+            &format_seconds(duration),
             "-af",
             "ebur128=peak=true",
             "-f",
             "null",
             "-",
-        ])
-        .output()?;
+        ],
+        false,
+    )?;
 
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let debug_output = if debug {
-        Some(format!(
-            "\n--- FFMPEG STDERR for quietest point ---\n{}\n--- END FFMPEG STDERR ---",
-            stderr
-        ))
-    } else {
-        None
-    };
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
 
-    let re =
-        Regex::new(r"\[Parsed_ebur128_0 @ [^\]]+\] t:\s*([\d.]+)\s*TARGET:.*M:\s*([-\d.]+)\s*S:")
-            .unwrap();
+    let re = Regex::new(
+        r"\[Parsed_ebur128_0 @ [^\]]+\] t:\s*([\d.]+)\s*TARGET:.*M:\s*([-\d.]+)\s*S:(?:.*FTPK:((?:\s*-?(?:[\d.]+|inf))+)\s*dBFS)?",
+    )
+    .unwrap();
 
-    let mut loudness_points: Vec<(f64, f64)> = Vec::new();
+    let mut candidates = Vec::new();
+    let mut peak_rejected = 0;
     for cap in re.captures_iter(&stderr) {
         if let (Some(time_str), Some(loudness_str)) = (cap.get(1), cap.get(2)) {
             if let (Ok(time), Ok(loudness)) = (
                 time_str.as_str().parse::<f64>(),
                 loudness_str.as_str().parse::<f64>(),
             ) {
+                // Frame true peak per channel; the loudest channel counts
+                let peak = cap.get(3).and_then(|peaks| {
+                    peaks
+                        .as_str()
+                        .split_whitespace()
+                        .filter_map(|p| p.parse::<f64>().ok())
+                        .reduce(f64::max)
+                });
                 // The ebur128 `t:` timestamp is relative to the start of the segment.
                 // We only care about points above the silence threshold.
                 if time >= start && time <= end && loudness > silence_threshold {
-                    loudness_points.push((time, loudness));
+                    if let (Some(max_peak), Some(peak)) = (max_peak, peak)
+                        && peak > max_peak
+                    {
+                        peak_rejected += 1;
+                        continue;
+                    }
+                    candidates.push(QuietCandidate {
+                        time,
+                        loudness,
+                        peak,
+                    });
                 }
             }
         }
     }
 
-    if loudness_points.is_empty() {
+    let mut speech_rejected = 0;
+    if avoid_speech && !candidates.is_empty() {
+        let spans = speech_spans(audio_path_str, start, end).map_err(|e| {
+            FFmpegError::CommandFailed("scan_quiet_candidates".to_string(), e.to_string())
+        })?;
+        let before = candidates.len();
+        candidates.retain(|candidate| !in_speech(&spans, candidate.time));
+        speech_rejected = before - candidates.len();
+    }
+
+    Ok(QuietScan {
+        candidates,
+        peak_rejected,
+        speech_rejected,
+        stderr,
+    })
+}
+
+/// Highest sample peak (in dBFS) in the first `duration` seconds of an audio stream, or None
+/// if ffmpeg reports none. `map` is an ffmpeg stream specifier for input 0.
+pub fn measure_head_peak(
+    input: &str,
+    map: &str,
+    duration: f64,
+) -> Result<Option<f64>, FFmpegError> {
+    let output = ffmpeg_output(
+        &[
+            "-t",
+            &format_seconds(duration),
+            "-i",
+            input,
+            "-map",
+            map,
+            "-af",
+            "volumedetect",
+            "-f",
+            "null",
+            "-",
+        ],
+        false,
+    )?;
+    if !output.status.success() {
         return Err(FFmpegError::CommandFailed(
-            "find_quietest_point".to_string(),
-            format!(
-                "Could not find any audible point in range {:.3}s - {:.3}s above the threshold of {:.2} LUFS. Try adjusting --silence-threshold.",
-                start, end, silence_threshold
-            ),
+            "measure_head_peak".to_string(),
+            output.last_error_line(),
         ));
     }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let re = Regex::new(r"max_volume:\s*(-?[\d.]+|-inf) dB").unwrap();
+    Ok(re.captures(&stderr).and_then(|cap| match &cap[1] {
+        "-inf" => Some(f64::NEG_INFINITY),
+        value => value.parse().ok(),
+    }))
+}
+
+/// Size of the waveform images `render_split_waveform` writes.
+const REVIEW_IMAGE_SIZE: (u32, u32) = (800, 200);
+
+/// Render the waveform of audio stream `stream` of `input` between `window.0` and `window.1`
+/// as a PNG with a red line at `split`, so a cut location can be checked by eye.
+pub fn render_split_waveform(
+    input: &str,
+    stream: usize,
+    window: (f64, f64),
+    split: f64,
+    output: &Path,
+    debug: bool,
+) -> Result<(), FFmpegError> {
+    let (width, height) = REVIEW_IMAGE_SIZE;
+    let length = (window.1 - window.0).max(f64::EPSILON);
+    let marker = (((split - window.0) / length * width as f64).round() as u32).min(width - 2);
+    let filter = format!(
+        "[0:{}]showwavespic=s={}x{}:split_channels=1:colors=white,drawbox=x={}:y=0:w=2:h=ih:color=red:t=fill[wave]",
+        stream, width, height, marker
+    );
+    let output_str = output.to_str().ok_or_else(|| {
+        FFmpegError::CommandFailed(
+            "render_split_waveform".to_string(),
+            format!("Invalid path: {}", output.display()),
+        )
+    })?;
+    run_ffmpeg(
+        &[
+            "-y",
+            "-ss",
+            &format_seconds(window.0),
+            "-t",
+            &format_seconds(length),
+            "-i",
+            input,
+            "-filter_complex",
+            &filter,
+            "-map",
+            "[wave]",
+            "-frames:v",
+            "1",
+            output_str,
+        ],
+        debug,
+    )
+}
+
+/// Find the point with the lowest momentary loudness between `start` and `end`.
+/// With `max_peak`, points whose true peak exceeds it (in dBFS) are skipped, so short
+/// transients hidden inside a quiet loudness window are not cut through, and with
+/// `avoid_speech`, points inside speech.
+pub fn find_quietest_point(
+    audio_path: &Path,
+    start: f64,
+    end: f64,
+    silence_threshold: f64,
+    max_peak: Option<f64>,
+    avoid_speech: bool,
+    debug: bool,
+) -> Result<QuietestPointResult, FFmpegError> {
+    let scan = scan_quiet_candidates(
+        audio_path,
+        start,
+        end,
+        silence_threshold,
+        max_peak,
+        avoid_speech,
+    )?;
+    let debug_output = if debug {
+        Some(format!(
+            "\n--- FFMPEG STDERR for quietest point ---\n{}\n--- END FFMPEG STDERR ---",
+            scan.stderr
+        ))
+    } else {
+        None
+    };
+
+    if scan.candidates.is_empty() && scan.speech_rejected > 0 {
+        return Err(FFmpegError::NoAudiblePoint(format!(
+            "Every audible point in range {:.3}s - {:.3}s is inside speech. Widen the range or drop --avoid-speech.",
+            start, end
+        )));
+    }
+    if scan.candidates.is_empty() && scan.peak_rejected > 0 {
+        return Err(FFmpegError::NoAudiblePoint(format!(
+            "Every point in range {:.3}s - {:.3}s has a true peak above {:.2} dBFS. Try raising --max-peak.",
+            start,
+            end,
+            max_peak.unwrap_or_default()
+        )));
+    }
 
     // From the candidates, find the one with the lowest loudness.
-    let (quietest_time, min_loudness) = loudness_points
+    let quietest = scan
+        .candidates
         .iter()
-        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-        .map(|(t, l)| (*t, *l))
-        .unwrap(); // Safe to unwrap because loudness_points is not empty
+        .min_by(|a, b| a.loudness.partial_cmp(&b.loudness).unwrap())
+        .ok_or_else(|| {
+            FFmpegError::NoAudiblePoint(format!(
+                "Could not find any audible point in range {:.3}s - {:.3}s above the threshold of {:.2} LUFS. Try adjusting --silence-threshold.",
+                start, end, silence_threshold
+            ))
+        })?;
 
+    let levels: Vec<f64> = scan.candidates.iter().map(|c| c.loudness).collect();
     Ok(QuietestPointResult {
-        time: quietest_time,
-        loudness: min_loudness,
+        time: quietest.time,
+        loudness: Some(quietest.loudness),
+        peak: quietest.peak,
+        snapped_from: None,
+        confidence: Some(dip_confidence(quietest.loudness, &levels)),
         debug_output,
     })
 }
 
-/// Split and delay audio segments according to split points and delays.
-/// Returns a Vec<PathBuf> of the resulting split files.
-pub fn split_and_delay_audio(
-    flac_path: &Path,
+/// Build an `atempo` filter chain that changes playback speed by `factor` (> 1.0 is faster).
+/// A single `atempo` instance only accepts factors between 0.5 and 2.0, so larger changes are chained.
+pub fn tempo_filter(factor: f64) -> String {
+    let mut remaining = factor;
+    let mut stages = Vec::new();
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    stages.push(format!("atempo={:.9}", remaining));
+    stages.join(",")
+}
+
+/// Build a resampling filter chain that lengthens audio by `ppm` parts per million (shortens it if negative).
+/// `asetrate` only accepts whole sample rates, so the remaining rounding error is corrected with `atempo`.
+pub fn drift_filter(sample_rate: u32, ppm: f64) -> String {
+    let stretch = 1.0 + ppm / 1_000_000.0;
+    let declared = (sample_rate as f64 / stretch).round() as u32;
+    let mut filter = format!("asetrate={},aresample={}", declared, sample_rate);
+    let residual = sample_rate as f64 / (stretch * declared as f64);
+    if (residual - 1.0).abs() > 1e-9 {
+        filter.push(',');
+        filter.push_str(&tempo_filter(residual));
+    }
+    filter
+}
+
+/// ffmpeg arguments that apply a uniform clock drift correction of `ppm` parts per million to
+/// an intermediate file at `sample_rate`.
+pub fn drift_args(
+    input_path: &Path,
+    output_path: &Path,
+    sample_rate: u32,
+    ppm: f64,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    Ok(run.output_args(&[
+        "-y",
+        "-i",
+        path_to_str(input_path)?,
+        "-af",
+        &drift_filter(sample_rate, ppm),
+        "-c:a",
+        run.codec(),
+        path_to_str(output_path)?,
+    ]))
+}
+
+/// Apply a uniform clock drift correction of `ppm` parts per million to an intermediate file.
+pub fn correct_drift(
+    input_path: &Path,
+    output_path: &Path,
+    ppm: f64,
+    run: &RunOptions,
+) -> Result<()> {
+    let sample_rate = get_sample_rate(path_to_str(input_path)?)?;
+    let args = drift_args(input_path, output_path, sample_rate, ppm, run)?;
+    run_ffmpeg(&str_args(&args), run.debug)?;
+    Ok(())
+}
+
+/// A delay in whole microseconds. Delays add up exactly and are only rounded to samples as an
+/// offset from the start of the track, so a fractional delay such as one NTSC frame (33.367 ms)
+/// is not rounded again at every split.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Delay {
+    micros: i64,
+}
+
+impl Delay {
+    pub fn from_millis(millis: f64) -> Self {
+        Self {
+            micros: (millis * 1000.0).round() as i64,
+        }
+    }
+
+    pub fn millis(self) -> f64 {
+        self.micros as f64 / 1000.0
+    }
+
+    /// Nearest whole number of samples at `sample_rate` (halves round away from zero).
+    pub fn samples(self, sample_rate: u32) -> i64 {
+        let scaled = self.micros as i128 * sample_rate as i128;
+        let rounded = (scaled.abs() + 500_000) / 1_000_000;
+        (rounded * scaled.signum()) as i64
+    }
+}
+
+impl std::ops::Add for Delay {
+    type Output = Delay;
+
+    fn add(self, other: Delay) -> Delay {
+        Delay {
+            micros: self.micros + other.micros,
+        }
+    }
+}
+
+/// Samples inserted (positive) or removed (negative) at the start of each segment. Every
+/// segment ends up within half a sample of the exact sum of the delays before it.
+pub fn step_samples(delays: &[Delay], sample_rate: u32) -> Vec<i64> {
+    let mut offset = Delay::default();
+    let mut applied = 0;
+    delays
+        .iter()
+        .map(|delay| {
+            offset = offset + *delay;
+            let target = offset.samples(sample_rate);
+            let step = target - applied;
+            applied = target;
+            step
+        })
+        .collect()
+}
+
+/// Where one segment of the split plan comes from and where it lands in the output, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatedSegment {
+    pub source_start: f64,
+    pub source_end: f64,
+    /// Silence inserted before the segment
+    pub silence: f64,
+    /// Audio removed from the head of the segment
+    pub trim: f64,
+    /// Playback speed factor of the segment
+    pub tempo: f64,
+    pub output_start: f64,
+    pub output_end: f64,
+}
+
+/// Work out the timeline `plan_segments` produces for a track of `duration` seconds without
+/// running ffmpeg: every segment's source range, inserted silence or trim, and output position.
+/// The output ends at the `output_end` of the last segment. Fails if a segment would end up
+/// with no audio, e.g. when a negative delay removes more than the segment holds.
+pub fn simulate_segments(
     split_points: &[f64],
-    delays: &[f64],
-    tmpdir: &Path,
-    debug: bool,
-) -> Result<Vec<PathBuf>> {
+    delays: &[Delay],
+    tempos: &[f64],
+    duration: f64,
+    sample_rate: u32,
+) -> Result<Vec<SimulatedSegment>> {
     let n = split_points.len();
-    let mut split_files = Vec::new();
-    let mut prev = 0.0f64;
+    if delays.len() != n + 1 || tempos.len() != n + 1 {
+        bail!("Delays must have one more element than split points.");
+    }
+    let steps = step_samples(delays, sample_rate);
+    let total = seconds_to_samples(duration, sample_rate);
+    let seconds = |samples: u64| samples as f64 / sample_rate as f64;
+    let mut segments = Vec::with_capacity(n + 1);
+    let mut prev = 0u64;
+    let mut position = 0u64;
     for i in 0..=n {
-        let part = tmpdir.join(format!("part_{}.flac", i + 1));
-        let (start, duration) = (prev, if i < n { split_points[i] - prev } else { 0.0 });
-        let start_str = start.to_string();
-        let mut ffmpeg_args = vec!["-y", "-i", path_to_str(flac_path)?, "-ss", &start_str];
-        let duration_str;
-        if i < n {
-            duration_str = duration.to_string();
-            ffmpeg_args.push("-t");
-            ffmpeg_args.push(&duration_str);
-            prev = split_points[i];
+        let end = if i < n {
+            seconds_to_samples(split_points[i], sample_rate)
+        } else {
+            total
+        };
+        if end <= prev {
+            bail!(
+                "Segment {} ({:.3}s - {:.3}s) has no audio; split points must be in order and within the track.",
+                i + 1,
+                seconds(prev),
+                seconds(end)
+            );
         }
-        ffmpeg_args.extend_from_slice(&[
-            "-af",
-            "asetpts=PTS-STARTPTS",
-            "-c:a",
-            "flac",
-            path_to_str(&part)?,
-        ]);
-        run_ffmpeg(&ffmpeg_args, debug)?;
-        let delay = delays[i];
-        let target = if delay > 0.0 {
-            let delayed = tmpdir.join(format!("part_{}_delayed.flac", i + 1));
-            let delay_str = delay.to_string();
-            run_ffmpeg(
-                &[
-                    "-y",
-                    "-i",
-                    path_to_str(&part)?,
-                    "-filter_complex",
-                    &format!("adelay={}|{},asetpts=PTS-STARTPTS", delay_str, delay_str),
-                    "-c:a",
-                    "flac",
-                    path_to_str(&delayed)?,
-                ],
-                debug,
-            )?;
-            std::fs::remove_file(&part)?;
-            delayed
-        } else if delay < 0.0 {
-            let trimmed = tmpdir.join(format!("part_{}_trimmed.flac", i + 1));
-            let trim_s = (-delay as f64) / 1000.0;
-            let trim_s_str = trim_s.to_string();
-            run_ffmpeg(
-                &[
-                    "-y",
-                    "-i",
-                    path_to_str(&part)?,
-                    "-ss",
-                    &trim_s_str,
-                    "-af",
-                    "asetpts=PTS-STARTPTS",
-                    "-c:a",
-                    "flac",
-                    path_to_str(&trimmed)?,
-                ],
-                debug,
-            )?;
-            std::fs::remove_file(&part)?;
+        let tempo = tempos[i];
+        if !tempo.is_finite() || tempo <= 0.0 {
+            bail!(
+                "Segment {} ({:.3}s - {:.3}s) would be stretched to a negative length.",
+                i + 1,
+                seconds(prev),
+                seconds(end)
+            );
+        }
+        // Same order as the ffmpeg jobs: cut, change speed, then delay or trim the head
+        let length = ((end - prev) as f64 / tempo).round() as u64;
+        let step = steps[i];
+        let (silence, trim) = if step >= 0 {
+            (step as u64, 0)
+        } else {
+            (0, step.unsigned_abs())
+        };
+        if trim >= length {
+            bail!(
+                "A delay of {:.3} ms removes all of segment {} ({:.3}s - {:.3}s).",
+                delays[i].millis(),
+                i + 1,
+                seconds(prev),
+                seconds(end)
+            );
+        }
+        let output_end = position + silence + length - trim;
+        segments.push(SimulatedSegment {
+            source_start: seconds(prev),
+            source_end: seconds(end),
+            silence: seconds(silence),
+            trim: seconds(trim),
+            tempo,
+            output_start: seconds(position),
+            output_end: seconds(output_end),
+        });
+        prev = end;
+        position = output_end;
+    }
+    Ok(segments)
+}
+
+/// The ffmpeg invocations that produce one segment of the split plan.
+pub struct SegmentJob {
+    /// Cuts the segment out of the source, applying its tempo
+    pub cut_args: Vec<String>,
+    /// File written by the cut
+    pub part: PathBuf,
+    /// Delays or trims the cut file; the cut file is removed afterwards
+    pub delay_args: Option<Vec<String>>,
+    /// File holding the finished segment
+    pub output: PathBuf,
+}
+
+/// Where one segment is cut from the source and how it is moved, in samples.
+struct SegmentCut {
+    start: u64,
+    /// None for the last segment, which runs to the end of the source
+    end: Option<u64>,
+    tempo: f64,
+    /// Samples of silence inserted (positive) or trimmed (negative) after the tempo change
+    step: i64,
+}
+
+/// Sample rate and length of the audio a plan cuts into segments.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceAudio {
+    pub sample_rate: u32,
+    /// Length in seconds, 0 if unknown
+    pub duration: f64,
+}
+
+impl SourceAudio {
+    /// Probe the sample rate and length of an intermediate file.
+    pub fn probe(path: &Path) -> Result<Self> {
+        Ok(Self {
+            sample_rate: get_sample_rate(path_to_str(path)?)?,
+            duration: intermediate_duration(path)?,
+        })
+    }
+}
+
+/// Cut the source into segments according to split points and delays. Split points are rounded
+/// to whole samples at the sample rate of `source`, so segments neither overlap nor leave gaps, and delays as
+/// in `step_samples`. A negative delay of a segment at normal speed trims its head in the cut.
+/// With a `crossfade` (in seconds), every segment but the last runs on by the crossfade (at its
+/// speed), and every segment, including the last one up to the end of `source`, must be at
+/// least as long as the crossfade.
+fn segment_cuts(
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
+    source: SourceAudio,
+    crossfade: f64,
+) -> Result<Vec<SegmentCut>> {
+    let SourceAudio {
+        sample_rate,
+        duration,
+    } = source;
+    let n = split_points.len();
+    let steps = step_samples(delays, sample_rate);
+    let overlap = seconds_to_samples(crossfade, sample_rate);
+    let total = seconds_to_samples(duration, sample_rate);
+    let mut cuts = Vec::new();
+    let mut prev = 0u64;
+    for i in 0..=n {
+        let mut step = steps[i];
+        let start = if step < 0 && tempos[i] == 1.0 {
+            let trimmed = prev + step.unsigned_abs();
+            step = 0;
             trimmed
         } else {
-            part
+            prev
         };
-        split_files.push(target);
+        let end = if i < n {
+            let end = seconds_to_samples(split_points[i], sample_rate).max(prev);
+            if start >= end {
+                bail!(
+                    "A delay of {:.3} ms removes all of segment {} ({:.3}s - {:.3}s).",
+                    delays[i].millis(),
+                    i + 1,
+                    prev as f64 / sample_rate as f64,
+                    split_points[i]
+                );
+            }
+            if end - start < overlap {
+                bail!(
+                    "Segment {} ({:.3}s - {:.3}s) is shorter than the crossfade of {:.3} s.",
+                    i + 1,
+                    start as f64 / sample_rate as f64,
+                    split_points[i],
+                    crossfade
+                );
+            }
+            prev = end;
+            Some(end + (overlap as f64 * tempos[i]).round() as u64)
+        } else {
+            // The last segment runs to the end of the stream, into which it is crossfaded
+            if n > 0 && total > 0 && total.saturating_sub(start) < overlap {
+                bail!(
+                    "Segment {} ({:.3}s - {:.3}s) is shorter than the crossfade of {:.3} s.",
+                    i + 1,
+                    start as f64 / sample_rate as f64,
+                    duration,
+                    crossfade
+                );
+            }
+            None
+        };
+        cuts.push(SegmentCut {
+            start,
+            end,
+            tempo: tempos[i],
+            step,
+        });
     }
-    Ok(split_files)
+    Ok(cuts)
 }
 
-/// Concatenate audio segments using ffmpeg concat filter. Returns the path to the final FLAC file.
-pub fn concat_audio_segments(
-    split_files: &[PathBuf],
+/// Plan the ffmpeg invocations for every segment according to split points and delays (see
+/// `segment_cuts`). `tempos` holds one playback speed factor per segment (1.0 leaves the segment
+/// untouched), which is how gradual delay ramps are applied.
+pub fn plan_segments(
+    flac_path: &Path,
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
     tmpdir: &Path,
-    debug: bool,
-) -> Result<PathBuf> {
+    audio: SourceAudio,
+    run: &RunOptions,
+) -> Result<Vec<SegmentJob>> {
+    let sample_rate = audio.sample_rate;
+    let source = path_to_str(flac_path)?.to_string();
+    let mut jobs = Vec::new();
+    for (i, cut) in segment_cuts(split_points, delays, tempos, audio, run.crossfade)?
+        .into_iter()
+        .enumerate()
+    {
+        let part = intermediate_file(tmpdir, &format!("part_{}", i + 1), run.intermediate);
+        let mut cut_args = vec![
+            "-y".to_string(),
+            "-i".to_string(),
+            source.clone(),
+            "-ss".to_string(),
+            format_samples(cut.start, sample_rate),
+        ];
+        if let Some(end) = cut.end {
+            cut_args.push("-t".to_string());
+            cut_args.push(format_samples(end - cut.start, sample_rate));
+        }
+        let filter = if cut.tempo != 1.0 {
+            format!("asetpts=PTS-STARTPTS,{}", tempo_filter(cut.tempo))
+        } else {
+            "asetpts=PTS-STARTPTS".to_string()
+        };
+        cut_args.extend([
+            "-af".to_string(),
+            filter,
+            "-c:a".to_string(),
+            run.codec().to_string(),
+            path_to_str(&part)?.to_string(),
+        ]);
+        let cut_args = run.output_args(&cut_args);
+
+        let step = cut.step;
+        let (delay_args, output) = if step > 0 {
+            let delayed =
+                intermediate_file(tmpdir, &format!("part_{}_delayed", i + 1), run.intermediate);
+            // Delay every channel by a whole number of samples
+            let args = run.output_args(&[
+                "-y".to_string(),
+                "-i".to_string(),
+                path_to_str(&part)?.to_string(),
+                "-filter_complex".to_string(),
+                format!("adelay=delays={}S:all=1,asetpts=PTS-STARTPTS", step),
+                "-c:a".to_string(),
+                run.codec().to_string(),
+                path_to_str(&delayed)?.to_string(),
+            ]);
+            (Some(args), delayed)
+        } else if step < 0 {
+            let trimmed =
+                intermediate_file(tmpdir, &format!("part_{}_trimmed", i + 1), run.intermediate);
+            let args = run.output_args(&[
+                "-y".to_string(),
+                "-i".to_string(),
+                path_to_str(&part)?.to_string(),
+                "-ss".to_string(),
+                format_samples(step.unsigned_abs(), sample_rate),
+                "-af".to_string(),
+                "asetpts=PTS-STARTPTS".to_string(),
+                "-c:a".to_string(),
+                run.codec().to_string(),
+                path_to_str(&trimmed)?.to_string(),
+            ]);
+            (Some(args), trimmed)
+        } else {
+            (None, part.clone())
+        };
+        jobs.push(SegmentJob {
+            cut_args,
+            part,
+            delay_args,
+            output,
+        });
+    }
+    Ok(jobs)
+}
+
+/// Split and delay audio segments according to split points and delays (see `plan_segments`),
+/// running up to `run.segment_jobs` segments at the same time. After a failure no new segment is
+/// started. `progress` receives the fraction of segments done.
+/// Returns a Vec<PathBuf> of the resulting split files.
+pub fn split_and_delay_audio(
+    flac_path: &Path,
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
+    tmpdir: &Path,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
+) -> Result<Vec<PathBuf>> {
+    let jobs = plan_segments(
+        flac_path,
+        split_points,
+        delays,
+        tempos,
+        tmpdir,
+        SourceAudio::probe(flac_path)?,
+        run,
+    )?;
+    let total = jobs.len();
+    let run_job = |job: &SegmentJob| -> Result<()> {
+        run_ffmpeg(&str_args(&job.cut_args), run.debug)?;
+        if let Some(delay_args) = &job.delay_args {
+            run_ffmpeg(&str_args(delay_args), run.debug)?;
+            std::fs::remove_file(&job.part)?;
+        }
+        Ok(())
+    };
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    // Workers start their children like this thread does
+    let children = ChildSettings::current();
+    std::thread::scope(|scope| -> Result<()> {
+        for _ in 0..run.segment_jobs.max(1).min(total) {
+            let done_tx = done_tx.clone();
+            let children = children.clone();
+            let (jobs, next, failed, run_job) = (&jobs, &next, &failed, &run_job);
+            scope.spawn(move || {
+                let _children = children.enter();
+                while !failed.load(Ordering::SeqCst) {
+                    let Some(job) = jobs.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                        break;
+                    };
+                    let result = run_job(job);
+                    if result.is_err() {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    let _ = done_tx.send(result);
+                }
+            });
+        }
+        drop(done_tx);
+        // Progress is reported from this thread, as the callback need not be thread-safe
+        let mut done = 0;
+        let mut first_error = None;
+        for result in done_rx {
+            match result {
+                Ok(()) => {
+                    done += 1;
+                    progress(done as f64 / total as f64);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    })?;
+    Ok(jobs.into_iter().map(|job| job.output).collect())
+}
+
+/// ffmpeg arguments that concatenate audio segments into `final_flac` using the concat filter
+/// (or crossfades, see `RunOptions::crossfade`).
+pub fn concat_args(
+    split_files: &[PathBuf],
+    final_flac: &Path,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
     let mut concat_args: Vec<String> = vec!["-y".to_string()];
     for s in split_files {
         concat_args.push("-i".to_string());
         concat_args.push(path_to_str(s)?.to_string());
     }
-    let filter_complex_str = (0..split_files.len())
+    let inputs: Vec<String> = (0..split_files.len())
         .map(|i| format!("[{}:a]", i))
-        .collect::<String>()
-        + &format!("concat=n={}:v=0:a=1[a]", split_files.len());
+        .collect();
+    let filter_complex_str = join_filter(&inputs, run.crossfade);
     concat_args.push("-filter_complex".to_string());
     concat_args.push(filter_complex_str);
     concat_args.push("-map".to_string());
     concat_args.push("[a]".to_string());
-    let final_flac = tmpdir.join("target_audio_final.flac");
     concat_args.push("-c:a".to_string());
-    concat_args.push("flac".to_string());
-    concat_args.push(path_to_str(&final_flac)?.to_string());
-    let concat_args_slice: Vec<&str> = concat_args.iter().map(|s| s.as_str()).collect();
-    run_ffmpeg(&concat_args_slice, debug)?;
+    concat_args.push(run.codec().to_string());
+    concat_args.push(path_to_str(final_flac)?.to_string());
+    Ok(run.output_args(&concat_args))
+}
+
+/// Check that every segment has the codec, sample rate, sample format and channel layout of the
+/// first, as the concat filter would otherwise join them into subtly corrupted audio. Fails
+/// naming the first segment that differs and every property it differs in.
+pub fn check_segment_consistency(split_files: &[PathBuf]) -> Result<()> {
+    let Some(first) = split_files.first() else {
+        return Ok(());
+    };
+    let expected = probe_audio_properties(path_to_str(first)?, None)?;
+    for (i, file) in split_files.iter().enumerate().skip(1) {
+        let actual = probe_audio_properties(path_to_str(file)?, None)?;
+        let mut differences = Vec::new();
+        let mut compare = |name: &str, actual: String, expected: String| {
+            if actual != expected {
+                differences.push(format!("{} {} instead of {}", name, actual, expected));
+            }
+        };
+        compare("codec", actual.codec, expected.codec.clone());
+        compare(
+            "sample rate",
+            format!("{} Hz", actual.sample_rate),
+            format!("{} Hz", expected.sample_rate),
+        );
+        compare(
+            "sample format",
+            actual.sample_fmt,
+            expected.sample_fmt.clone(),
+        );
+        compare(
+            "channels",
+            actual.channels.to_string(),
+            expected.channels.to_string(),
+        );
+        compare(
+            "channel layout",
+            format!("'{}'", actual.channel_layout),
+            format!("'{}'", expected.channel_layout),
+        );
+        if !differences.is_empty() {
+            bail!(
+                "Segment {} ({}) does not match segment 1 ({}): {}. Joining them would corrupt the audio.",
+                i + 1,
+                file.display(),
+                first.display(),
+                differences.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Concatenate audio segments using ffmpeg concat filter, after checking that they match (see
+/// `check_segment_consistency`). Returns the path to the final intermediate file.
+/// `progress` receives the seconds of audio written so far.
+pub fn concat_audio_segments(
+    split_files: &[PathBuf],
+    tmpdir: &Path,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
+) -> Result<PathBuf> {
+    check_segment_consistency(split_files)?;
+    let final_flac = intermediate_file(tmpdir, "target_audio_final", run.intermediate);
+    run_ffmpeg_with_progress(
+        &str_args(&concat_args(split_files, &final_flac, run)?),
+        run.debug,
+        progress,
+    )?;
+    Ok(final_flac)
+}
+
+/// ffmpeg arguments that cut, delay and concatenate every segment (see `segment_cuts`) in a
+/// single pass over `flac_path`, writing the edited audio to `output`. Cuts are made with
+/// `atrim` at exact samples.
+pub fn edit_args(
+    flac_path: &Path,
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
+    source: SourceAudio,
+    output: &Path,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    let cuts = segment_cuts(split_points, delays, tempos, source, run.crossfade)?;
+    let mut graph = format!("[0:a]asplit={}", cuts.len());
+    for i in 0..cuts.len() {
+        graph.push_str(&format!("[in{}]", i));
+    }
+    for (i, cut) in cuts.iter().enumerate() {
+        graph.push_str(&format!(";[in{}]atrim=start_sample={}", i, cut.start));
+        if let Some(end) = cut.end {
+            graph.push_str(&format!(":end_sample={}", end));
+        }
+        graph.push_str(",asetpts=PTS-STARTPTS");
+        if cut.tempo != 1.0 {
+            graph.push(',');
+            graph.push_str(&tempo_filter(cut.tempo));
+        }
+        if cut.step > 0 {
+            // Delay every channel by a whole number of samples
+            graph.push_str(&format!(
+                ",adelay=delays={}S:all=1,asetpts=PTS-STARTPTS",
+                cut.step
+            ));
+        } else if cut.step < 0 {
+            graph.push_str(&format!(
+                ",atrim=start_sample={},asetpts=PTS-STARTPTS",
+                cut.step.unsigned_abs()
+            ));
+        }
+        graph.push_str(&format!("[seg{}]", i));
+    }
+    graph.push(';');
+    let segments: Vec<String> = (0..cuts.len()).map(|i| format!("[seg{}]", i)).collect();
+    graph.push_str(&join_filter(&segments, run.crossfade));
+    Ok(run.output_args(&[
+        "-y".to_string(),
+        "-i".to_string(),
+        path_to_str(flac_path)?.to_string(),
+        "-filter_complex".to_string(),
+        graph,
+        "-map".to_string(),
+        "[a]".to_string(),
+        "-c:a".to_string(),
+        run.codec().to_string(),
+        path_to_str(output)?.to_string(),
+    ]))
+}
+
+/// Cut, delay and concatenate every segment with one ffmpeg run (see `edit_args`), without
+/// writing a file per segment. Returns the path to the edited intermediate file.
+/// `progress` receives the seconds of audio written so far.
+pub fn edit_audio(
+    flac_path: &Path,
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
+    tmpdir: &Path,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
+) -> Result<PathBuf> {
+    let final_flac = intermediate_file(tmpdir, "target_audio_final", run.intermediate);
+    let args = edit_args(
+        flac_path,
+        split_points,
+        delays,
+        tempos,
+        SourceAudio::probe(flac_path)?,
+        &final_flac,
+        run,
+    )?;
+    run_ffmpeg_with_progress(&str_args(&args), run.debug, progress)?;
     Ok(final_flac)
 }
 
-/// Convert FLAC audio to the target codec and bitrate. Returns the output path.
+/// ffmpeg arguments that convert intermediate audio to the target codec, at `bitrate` when given.
+/// The channel layout and sample rate of `format` are set explicitly so the encoder cannot fall
+/// back to its defaults; its sample format is kept for lossless codecs, where it is the bit depth.
+pub fn convert_args(
+    input_flac: &Path,
+    codec: &str,
+    bitrate: Option<&str>,
+    format: &StreamFormat,
+    output_path: &Path,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        path_to_str(input_flac)?.to_string(),
+    ];
+    if codec == run.codec() && codec.starts_with("pcm_") {
+        // The intermediate file already holds the samples in the target codec
+        args.extend(["-c:a".to_string(), "copy".to_string()]);
+    } else {
+        // Relabel the layout by channel index, so a layout the intermediate cannot store
+        // (e.g. 5.1(side) read back as 5.1) is not remixed
+        let mut filter = "asetpts=PTS-STARTPTS".to_string();
+        if !format.channel_layout.is_empty() {
+            filter.push_str(&format!(
+                ",channelmap=channel_layout={}",
+                format.channel_layout
+            ));
+        }
+        args.extend([
+            "-af".to_string(),
+            filter,
+            "-c:a".to_string(),
+            codec.to_string(),
+        ]);
+        if format.channels > 0 {
+            args.extend(["-ac".to_string(), format.channels.to_string()]);
+        }
+        if !format.channel_layout.is_empty() {
+            args.extend(["-channel_layout".to_string(), format.channel_layout.clone()]);
+        }
+        if format.sample_rate > 0 {
+            args.extend(["-ar".to_string(), format.sample_rate.to_string()]);
+        }
+        if is_lossless_codec(codec) && !format.sample_fmt.is_empty() {
+            args.extend(["-sample_fmt".to_string(), format.sample_fmt.clone()]);
+        }
+    }
+    if let Some(bitrate) = bitrate {
+        args.push("-b:a".to_string());
+        args.push(bitrate.to_string());
+    }
+    args.push(path_to_str(output_path)?.to_string());
+    Ok(run.output_args(&args))
+}
+
+/// Whether a codec stores audio losslessly. Edited lossless tracks are encoded back to the
+/// same codec, which needs no bitrate.
+pub fn is_lossless_codec(codec: &str) -> bool {
+    matches!(codec, "flac" | "alac") || codec.starts_with("pcm_")
+}
+
+/// Convert intermediate audio to the target codec and bitrate (None for lossless codecs).
+/// `progress` receives the seconds of audio encoded so far.
 pub fn convert_audio_codec(
     input_flac: &Path,
     codec: &str,
-    bitrate: &str,
+    bitrate: Option<&str>,
+    format: &StreamFormat,
     output_path: &Path,
-    debug: bool,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
 ) -> Result<()> {
-    run_ffmpeg(
-        &[
-            "-y",
-            "-i",
-            path_to_str(input_flac)?,
-            "-af",
-            "asetpts=PTS-STARTPTS",
-            "-c:a",
+    run_ffmpeg_with_progress(
+        &str_args(&convert_args(
+            input_flac,
             codec,
-            "-b:a",
             bitrate,
-            path_to_str(output_path)?,
-        ],
-        debug,
+            format,
+            output_path,
+            run,
+        )?),
+        run.debug,
+        progress,
     )?;
     Ok(())
 }
 
-/// Trim or pad the audio at input_path to match target_duration (seconds), writing to output_path.
-/// If the input is longer, it is trimmed. If shorter, it is padded with silence.
-pub fn fit_audio_to_length(
-    input_path: &Path,
-    output_path: &Path,
-    target_duration: f64,
-    debug: bool,
-) -> Result<()> {
-    // Get duration of the input audio
-    let output = std::process::Command::new("ffprobe")
+/// Duration in seconds of an intermediate file, or 0 if ffprobe does not report one.
+pub fn intermediate_duration(path: &Path) -> Result<f64> {
+    let output = std::process::Command::new(ffprobe_executable())
         .args([
             "-v",
             "error",
@@ -279,80 +1298,387 @@ pub fn fit_audio_to_length(
             "format=duration",
             "-of",
             "default=noprint_wrappers=1:nokey=1",
-            path_to_str(input_path)?,
+            path_to_str(path)?,
         ])
         .output()?;
-    let input_duration: f64 = String::from_utf8_lossy(&output.stdout)
+    Ok(String::from_utf8_lossy(&output.stdout)
         .trim()
         .parse()
-        .unwrap_or(0.0);
+        .unwrap_or(0.0))
+}
+
+/// ffmpeg arguments that trim or pad audio of `input_duration` seconds to `target_duration`:
+/// a longer input is trimmed, a shorter one padded with silence. None if the lengths already
+/// match, in which case the input is copied as it is.
+pub fn fit_length_args(
+    input_path: &Path,
+    output_path: &Path,
+    input_duration: f64,
+    target_duration: f64,
+    run: &RunOptions,
+) -> Result<Option<Vec<String>>> {
+    let input = path_to_str(input_path)?;
+    let output = path_to_str(output_path)?;
     if input_duration > target_duration + 0.001 {
         // Trim to target duration
-        run_ffmpeg(
-            &[
-                "-y",
-                "-i",
-                path_to_str(input_path)?,
-                "-af",
-                &format!("atrim=0:{:.6}", target_duration),
-                "-c:a",
-                "flac",
-                path_to_str(output_path)?,
-            ],
-            debug,
-        )?;
+        Ok(Some(run.output_args(&[
+            "-y",
+            "-i",
+            input,
+            "-af",
+            &format!("atrim=0:{}", format_seconds(target_duration)),
+            "-c:a",
+            run.codec(),
+            output,
+        ])))
     } else if input_duration < target_duration - 0.001 {
         // Pad with silence to target duration
         let pad_len = target_duration - input_duration;
-        run_ffmpeg(
-            &[
-                "-y",
-                "-i",
-                path_to_str(input_path)?,
-                "-af",
-                &format!("apad=pad_dur={:.6}", pad_len),
-                "-t",
-                &format!("{:.6}", target_duration),
-                "-c:a",
-                "flac",
-                path_to_str(output_path)?,
-            ],
-            debug,
-        )?;
+        Ok(Some(run.output_args(&[
+            "-y",
+            "-i",
+            input,
+            "-af",
+            &format!("apad=pad_dur={}", format_seconds(pad_len)),
+            "-t",
+            &format_seconds(target_duration),
+            "-c:a",
+            run.codec(),
+            output,
+        ])))
     } else {
+        Ok(None)
+    }
+}
+
+/// Trim or pad the audio at input_path to match target_duration (seconds), writing to output_path.
+/// If the input is longer, it is trimmed. If shorter, it is padded with silence.
+pub fn fit_audio_to_length(
+    input_path: &Path,
+    output_path: &Path,
+    target_duration: f64,
+    run: &RunOptions,
+) -> Result<()> {
+    let input_duration = intermediate_duration(input_path)?;
+    match fit_length_args(
+        input_path,
+        output_path,
+        input_duration,
+        target_duration,
+        run,
+    )? {
+        Some(args) => run_ffmpeg(&str_args(&args), run.debug)?,
         // Already matches duration, just copy
-        std::fs::copy(input_path, output_path)?;
+        None => {
+            std::fs::copy(input_path, output_path)?;
+        }
     }
     Ok(())
 }
 
+/// ffmpeg arguments that put `seconds` of silence (rounded to whole samples at `sample_rate`)
+/// before the audio at input_path, writing to output_path.
+pub fn pad_head_args(
+    input_path: &Path,
+    output_path: &Path,
+    sample_rate: u32,
+    seconds: f64,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    let samples = seconds_to_samples(seconds, sample_rate);
+    Ok(run.output_args(&[
+        "-y",
+        "-i",
+        path_to_str(input_path)?,
+        "-af",
+        &format!("adelay=delays={}S:all=1,asetpts=PTS-STARTPTS", samples),
+        "-c:a",
+        run.codec(),
+        path_to_str(output_path)?,
+    ]))
+}
+
+/// Put `seconds` of silence (rounded to whole samples) before the audio at input_path, writing
+/// to output_path.
+pub fn pad_audio_head(
+    input_path: &Path,
+    output_path: &Path,
+    seconds: f64,
+    run: &RunOptions,
+) -> Result<()> {
+    let sample_rate = get_sample_rate(path_to_str(input_path)?)?;
+    let args = pad_head_args(input_path, output_path, sample_rate, seconds, run)?;
+    run_ffmpeg(&str_args(&args), run.debug)?;
+    Ok(())
+}
+
+/// Timestamp options of the muxer in the remux; unset options keep ffmpeg's defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MuxerTimestamps {
+    pub avoid_negative_ts: Option<AvoidNegativeTs>,
+    /// Maximum demux-decode delay in seconds
+    pub muxdelay: Option<f64>,
+    /// Initial demux-decode delay in seconds
+    pub muxpreload: Option<f64>,
+    /// Keep the input timestamps instead of starting the output at zero
+    pub copyts: bool,
+}
+
+/// What the remux writes besides the new audio stream.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RemuxOptions<'a> {
+    /// (key, value) tags written on the new track, such as its language and title
+    pub metadata: &'a [(String, String)],
+    /// Re-timed subtitles, each replacing the subtitle stream at its index while keeping
+    /// that stream's tags and dispositions
+    pub subtitles: &'a [ReplacedSubtitle],
+    /// FFMETADATA file whose chapters replace the chapters of the input
+    pub chapters: Option<&'a Path>,
+    pub timestamps: MuxerTimestamps,
+    /// Also copy the original audio stream, as the last stream and without dispositions
+    pub keep_original: bool,
+    /// Seconds the new audio stream is shifted by in the container (positive delays it)
+    pub audio_offset: f64,
+}
+
+/// ffmpeg arguments that remux the new audio stream in place of the original audio stream.
+/// `map_args` come from `build_stream_map_args`.
+pub fn remux_args(
+    input: &str,
+    new_audio: &Path,
+    output: &str,
+    map_args: &[String],
+    audio_stream_idx: usize,
+    options: RemuxOptions,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    let RemuxOptions {
+        metadata,
+        subtitles,
+        chapters,
+        timestamps,
+        keep_original,
+        audio_offset,
+    } = options;
+    let metadata_spec = format!("-metadata:s:a:{}", audio_stream_idx);
+    let mut ffmpeg_remux = vec!["-y".to_string()];
+    if timestamps.copyts {
+        ffmpeg_remux.push("-copyts".to_string());
+    }
+    ffmpeg_remux.extend(["-i".to_string(), input.to_string()]);
+    if audio_offset != 0.0 {
+        ffmpeg_remux.push("-itsoffset".to_string());
+        ffmpeg_remux.push(format_seconds(audio_offset));
+    }
+    ffmpeg_remux.extend(["-i".to_string(), path_to_str(new_audio)?.to_string()]);
+    for subtitle in subtitles {
+        ffmpeg_remux.push("-i".to_string());
+        ffmpeg_remux.push(path_to_str(&subtitle.path)?.to_string());
+    }
+    if let Some(chapters) = chapters {
+        ffmpeg_remux.push("-f".to_string());
+        ffmpeg_remux.push("ffmetadata".to_string());
+        ffmpeg_remux.push("-i".to_string());
+        ffmpeg_remux.push(path_to_str(chapters)?.to_string());
+        ffmpeg_remux.push("-map_chapters".to_string());
+        ffmpeg_remux.push((subtitles.len() + 2).to_string());
+    }
+    // Every stream keeps its position, so input and output indices are the same
+    let mut map_args = map_args.to_vec();
+    for (i, subtitle) in subtitles.iter().enumerate() {
+        let original = format!("0:{}", subtitle.index);
+        match map_args.iter_mut().find(|arg| **arg == original) {
+            Some(arg) => *arg = format!("{}:0", i + 2),
+            None => bail!("Stream {} is not part of the remux.", subtitle.index),
+        }
+    }
+    // Appended last, the kept original leaves the index of every other stream unchanged
+    let original_output_index = map_args.len() / 2;
+    if keep_original {
+        map_args.push("-map".to_string());
+        map_args.push(format!("0:a:{}", audio_stream_idx));
+    }
+    ffmpeg_remux.extend(map_args);
+    ffmpeg_remux.push("-c".to_string());
+    ffmpeg_remux.push("copy".to_string());
+    if keep_original {
+        ffmpeg_remux.push(format!("-disposition:{}", original_output_index));
+        ffmpeg_remux.push("0".to_string());
+    }
+    for subtitle in subtitles {
+        if subtitle.codec != "copy" {
+            ffmpeg_remux.push(format!("-c:{}", subtitle.index));
+            ffmpeg_remux.push(subtitle.codec.clone());
+        }
+        ffmpeg_remux.push(format!("-map_metadata:s:{}", subtitle.index));
+        ffmpeg_remux.push(format!("0:s:{}", subtitle.index));
+        ffmpeg_remux.push(format!("-disposition:{}", subtitle.index));
+        ffmpeg_remux.push(subtitle.disposition.clone());
+    }
+    for (key, value) in metadata {
+        ffmpeg_remux.push(metadata_spec.clone());
+        ffmpeg_remux.push(format!("{}={}", key, value));
+    }
+    if let Some(mode) = timestamps.avoid_negative_ts {
+        ffmpeg_remux.push("-avoid_negative_ts".to_string());
+        ffmpeg_remux.push(mode.to_string());
+    }
+    if let Some(muxdelay) = timestamps.muxdelay {
+        ffmpeg_remux.push("-muxdelay".to_string());
+        ffmpeg_remux.push(format_seconds(muxdelay));
+    }
+    if let Some(muxpreload) = timestamps.muxpreload {
+        ffmpeg_remux.push("-muxpreload".to_string());
+        ffmpeg_remux.push(muxpreload.to_string());
+    }
+    ffmpeg_remux.push(output.to_string());
+    Ok(run.output_args(&ffmpeg_remux))
+}
+
+/// ffmpeg arguments that copy every stream of `input` with the video streams shifted by
+/// `video_offset` seconds (positive delays the video). `map_args` come from
+/// `build_video_shift_map_args`.
+pub fn video_shift_args(
+    input: &str,
+    output: &str,
+    map_args: &[String],
+    video_offset: f64,
+    timestamps: MuxerTimestamps,
+    run: &RunOptions,
+) -> Vec<String> {
+    let mut args = vec!["-y".to_string()];
+    if timestamps.copyts {
+        args.push("-copyts".to_string());
+    }
+    args.extend([
+        "-i".to_string(),
+        input.to_string(),
+        "-itsoffset".to_string(),
+        format_seconds(video_offset),
+        "-i".to_string(),
+        input.to_string(),
+    ]);
+    args.extend(map_args.iter().cloned());
+    args.extend(["-c".to_string(), "copy".to_string()]);
+    args.extend(muxer_timestamp_args(timestamps));
+    args.push(output.to_string());
+    run.output_args(&args)
+}
+
+/// Output options for the muxer timestamp settings that are set.
+fn muxer_timestamp_args(timestamps: MuxerTimestamps) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(mode) = timestamps.avoid_negative_ts {
+        args.push("-avoid_negative_ts".to_string());
+        args.push(mode.to_string());
+    }
+    if let Some(muxdelay) = timestamps.muxdelay {
+        args.push("-muxdelay".to_string());
+        args.push(format_seconds(muxdelay));
+    }
+    if let Some(muxpreload) = timestamps.muxpreload {
+        args.push("-muxpreload".to_string());
+        args.push(muxpreload.to_string());
+    }
+    args
+}
+
+/// A finished track that replaces one audio stream in `remux_audio_streams`.
+#[derive(Debug, Clone)]
+pub struct ReplacedAudio {
+    /// Position of the replaced stream among the audio streams of the input
+    pub audio_stream_idx: usize,
+    pub path: PathBuf,
+    /// (key, value) tags written on the new track
+    pub metadata: Vec<(String, String)>,
+}
+
+/// ffmpeg arguments that remux several new audio streams, each in place of the audio stream
+/// it replaces. `map_args` come from `build_streams_map_args` with the tracks in this order.
+pub fn remux_streams_args(
+    input: &str,
+    tracks: &[ReplacedAudio],
+    output: &str,
+    map_args: &[String],
+    timestamps: MuxerTimestamps,
+    run: &RunOptions,
+) -> Result<Vec<String>> {
+    let mut args = vec!["-y".to_string()];
+    if timestamps.copyts {
+        args.push("-copyts".to_string());
+    }
+    args.extend(["-i".to_string(), input.to_string()]);
+    for track in tracks {
+        args.push("-i".to_string());
+        args.push(path_to_str(&track.path)?.to_string());
+    }
+    args.extend(map_args.iter().cloned());
+    args.extend(["-c".to_string(), "copy".to_string()]);
+    for track in tracks {
+        for (key, value) in &track.metadata {
+            args.push(format!("-metadata:s:a:{}", track.audio_stream_idx));
+            args.push(format!("{}={}", key, value));
+        }
+    }
+    args.extend(muxer_timestamp_args(timestamps));
+    args.push(output.to_string());
+    Ok(run.output_args(&args))
+}
+
+/// Remux several new audio streams in place of the streams they replace, in one pass.
+/// `progress` receives the seconds of media written so far.
+pub fn remux_audio_streams(
+    input: &str,
+    tracks: &[ReplacedAudio],
+    output: &str,
+    timestamps: MuxerTimestamps,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
+) -> anyhow::Result<()> {
+    let replaced: Vec<usize> = tracks.iter().map(|t| t.audio_stream_idx).collect();
+    let map_args = build_streams_map_args(input, &replaced)?;
+    let args = remux_streams_args(input, tracks, output, &map_args, timestamps, run)?;
+    run_ffmpeg_with_progress(&str_args(&args), run.debug, progress)?;
+    Ok(())
+}
+
+/// Copy the input file with its video streams shifted by `video_offset` seconds and every
+/// other stream unchanged. `progress` receives the seconds of media written so far.
+pub fn shift_video_stream(
+    input: &str,
+    output: &str,
+    video_offset: f64,
+    timestamps: MuxerTimestamps,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
+) -> anyhow::Result<()> {
+    let map_args = build_video_shift_map_args(input)?;
+    let args = video_shift_args(input, output, &map_args, video_offset, timestamps, run);
+    run_ffmpeg_with_progress(&str_args(&args), run.debug, progress)?;
+    Ok(())
+}
+
 /// Remux the new audio stream in place of the original audio stream in the input file.
+/// `progress` receives the seconds of media written so far.
 pub fn remux_audio_stream(
     input: &str,
     new_audio: &std::path::Path,
     output: &str,
     audio_stream_idx: usize,
-    original_title: &str,
-    original_lang: &str,
-    debug: bool,
+    options: RemuxOptions,
+    run: &RunOptions,
+    progress: &dyn Fn(f64),
 ) -> anyhow::Result<()> {
     let map_args = build_stream_map_args(input, audio_stream_idx)?;
-    let metadata_spec = format!("-metadata:s:a:{}", audio_stream_idx);
-    let title_value = format!("title={}", original_title);
-    let lang_value = format!("language={}", original_lang);
-    let mut ffmpeg_remux = vec!["-y", "-i", input, "-i", path_to_str(new_audio)?];
-    ffmpeg_remux.extend(map_args.iter().map(|s| s.as_str()));
-    ffmpeg_remux.push("-c");
-    ffmpeg_remux.push("copy");
-    if !original_lang.is_empty() {
-        ffmpeg_remux.push(&metadata_spec);
-        ffmpeg_remux.push(&lang_value);
-    }
-    if !original_title.is_empty() {
-        ffmpeg_remux.push(&metadata_spec);
-        ffmpeg_remux.push(&title_value);
-    }
-    ffmpeg_remux.push(output);
-    crate::ffmpeg::run_ffmpeg(&ffmpeg_remux, debug)?;
+    let args = remux_args(
+        input,
+        new_audio,
+        output,
+        &map_args,
+        audio_stream_idx,
+        options,
+        run,
+    )?;
+    run_ffmpeg_with_progress(&str_args(&args), run.debug, progress)?;
     Ok(())
 }
@@ -0,0 +1,94 @@
+use anyhow::{Result, bail};
+use regex::Regex;
+
+use crate::ffmpeg::{ffmpeg_output, format_seconds};
+use crate::silence::Silence;
+
+/// Share of dark pixels a frame needs to count as black (`blackdetect` `pix_th`).
+const BLACK_PIXEL_THRESHOLD: f64 = 0.10;
+
+/// An interval of black video, in seconds from the start of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlackInterval {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// A break in a broadcast capture (an ad or recap boundary): where black video and silence
+/// overlap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Break {
+    /// Start of the overlap of black video and silence
+    pub start: f64,
+    /// End of the overlap of black video and silence
+    pub end: f64,
+    /// Length of the black video around the break
+    pub black: f64,
+}
+
+impl Break {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Find the intervals where the first video stream stays black for at least `min_duration`
+/// seconds, with ffmpeg's `blackdetect` filter.
+pub fn detect_black_intervals(input: &str, min_duration: f64) -> Result<Vec<BlackInterval>> {
+    let filter = format!(
+        "blackdetect=d={}:pix_th={}",
+        format_seconds(min_duration),
+        BLACK_PIXEL_THRESHOLD
+    );
+    let output = ffmpeg_output(
+        &[
+            "-i", input, "-map", "0:v:0", "-vf", &filter, "-an", "-f", "null", "-",
+        ],
+        false,
+    )?;
+    if !output.status.success() {
+        bail!(
+            "Could not look for black video in '{}': {}",
+            input,
+            output.last_error_line()
+        );
+    }
+    parse_blackdetect(&String::from_utf8_lossy(&output.stderr))
+}
+
+/// Collect the `black_start` / `black_end` pairs `blackdetect` logs to stderr.
+pub fn parse_blackdetect(stderr: &str) -> Result<Vec<BlackInterval>> {
+    let event = Regex::new(r"black_start:\s*(-?[0-9.]+)\s+black_end:\s*(-?[0-9.]+)")?;
+    Ok(event
+        .captures_iter(stderr)
+        .filter_map(|caps| {
+            let start: f64 = caps[1].parse().ok()?;
+            let end: f64 = caps[2].parse().ok()?;
+            (end > start).then_some(BlackInterval {
+                start: start.max(0.0),
+                end,
+            })
+        })
+        .collect())
+}
+
+/// The overlaps of black video and silence, in order. Each overlap is a range where the cut
+/// between programme and break (and so a sync step) is likely.
+pub fn find_breaks(black: &[BlackInterval], silences: &[Silence]) -> Vec<Break> {
+    let mut breaks = Vec::new();
+    for interval in black {
+        for silence in silences {
+            let start = interval.start.max(silence.start);
+            let end = interval.end.min(silence.end);
+            if end > start {
+                breaks.push(Break {
+                    start,
+                    end,
+                    black: interval.end - interval.start,
+                });
+            }
+        }
+    }
+    breaks.sort_by(|a, b| a.start.total_cmp(&b.start));
+    breaks
+}
@@ -0,0 +1,292 @@
+use anyhow::{Result, bail};
+
+use crate::analysis::ANALYSIS_SAMPLE_RATE;
+use crate::audio_processing::{RunOptions, str_args};
+use crate::ffmpeg::{FFmpegError, ffmpeg_output, format_seconds, run_ffmpeg};
+
+/// How much of the stream (in seconds, from the start) is decoded for channel analysis.
+const CHANNEL_ANALYSIS_SECONDS: f64 = 180.0;
+/// Channels quieter than this (in dBFS) count as silent.
+const SILENT_CHANNEL_DB: f64 = -80.0;
+/// Two channels correlating above this (with matching levels) carry the same signal.
+const DUAL_MONO_CORRELATION: f64 = 0.99;
+/// Two channels correlating below this carry the same signal with inverted polarity.
+const INVERTED_CORRELATION: f64 = -0.9;
+/// LFE content stays below ~120 Hz, so it crosses zero far less often than this per second.
+const LFE_MAX_ZERO_CROSSINGS: f64 = 400.0;
+
+/// Level and zero-crossing rate of one channel.
+#[derive(Debug, Clone)]
+pub struct ChannelStats {
+    pub name: String,
+    pub level_db: f64,
+    pub zero_crossings_per_second: f64,
+}
+
+/// A defect found in the channel layout, with the pan mapping that fixes it if there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelIssue {
+    DualMono { first: usize, second: usize },
+    Silent { channel: usize },
+    InvertedPolarity { first: usize, second: usize },
+    SwappedLfe { lfe: usize, with: usize },
+}
+
+#[derive(Debug)]
+pub struct ChannelReport {
+    pub layout: String,
+    pub channels: Vec<ChannelStats>,
+    pub issues: Vec<ChannelIssue>,
+}
+
+/// Channel names of the common layouts, in ffmpeg's channel order.
+pub fn channel_names(layout: &str, count: usize) -> Vec<String> {
+    let names: &[&str] = match layout {
+        "mono" => &["FC"],
+        "stereo" => &["FL", "FR"],
+        "2.1" => &["FL", "FR", "LFE"],
+        "quad" => &["FL", "FR", "BL", "BR"],
+        "5.0" | "5.0(side)" => &["FL", "FR", "FC", "SL", "SR"],
+        "5.1" => &["FL", "FR", "FC", "LFE", "BL", "BR"],
+        "5.1(side)" => &["FL", "FR", "FC", "LFE", "SL", "SR"],
+        "7.1" => &["FL", "FR", "FC", "LFE", "BL", "BR", "SL", "SR"],
+        _ => &[],
+    };
+    if names.len() == count {
+        names.iter().map(|n| n.to_string()).collect()
+    } else {
+        (1..=count).map(|i| format!("ch{}", i)).collect()
+    }
+}
+
+/// Decode `duration` seconds of an audio stream from `start`, resampled to `sample_rate`, to
+/// one PCM sample vector per channel.
+pub fn decode_pcm_channels(
+    input: &str,
+    map: &str,
+    channels: usize,
+    start: f64,
+    duration: f64,
+    sample_rate: u32,
+) -> Result<Vec<Vec<f32>>, FFmpegError> {
+    let output = ffmpeg_output(
+        &[
+            "-v",
+            "error",
+            "-ss",
+            &format_seconds(start),
+            "-t",
+            &format_seconds(duration),
+            "-i",
+            input,
+            "-map",
+            map,
+            "-ac",
+            &channels.to_string(),
+            "-ar",
+            &sample_rate.to_string(),
+            "-f",
+            "f32le",
+            "-",
+        ],
+        false,
+    )?;
+    if !output.status.success() {
+        return Err(FFmpegError::CommandFailed(
+            "decode_pcm_channels".to_string(),
+            output.last_error_line(),
+        ));
+    }
+    let mut planes = vec![Vec::new(); channels];
+    for (i, b) in output.stdout.chunks_exact(4).enumerate() {
+        planes[i % channels].push(f32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    }
+    Ok(planes)
+}
+
+fn level_db(samples: &[f32]) -> f64 {
+    let energy = samples
+        .iter()
+        .map(|s| (*s as f64) * (*s as f64))
+        .sum::<f64>()
+        / samples.len().max(1) as f64;
+    10.0 * energy.max(1e-12).log10()
+}
+
+fn zero_crossings_per_second(samples: &[f32]) -> f64 {
+    let crossings = samples
+        .windows(2)
+        .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+        .count();
+    crossings as f64 * ANALYSIS_SAMPLE_RATE as f64 / samples.len().max(1) as f64
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f64 {
+    let (mut ab, mut aa, mut bb) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in a.iter().zip(b) {
+        ab += (*x as f64) * (*y as f64);
+        aa += (*x as f64) * (*x as f64);
+        bb += (*y as f64) * (*y as f64);
+    }
+    if aa == 0.0 || bb == 0.0 {
+        0.0
+    } else {
+        ab / (aa.sqrt() * bb.sqrt())
+    }
+}
+
+/// Look for dual-mono content, silent channels, inverted polarity and a swapped LFE channel
+/// in an audio stream. `map` is an ffmpeg stream specifier for input 0.
+pub fn analyze_channels(
+    input: &str,
+    map: &str,
+    layout: &str,
+    channel_count: usize,
+) -> Result<ChannelReport> {
+    if channel_count == 0 {
+        bail!("Could not determine the channel count of the stream.");
+    }
+    let planes = decode_pcm_channels(
+        input,
+        map,
+        channel_count,
+        0.0,
+        CHANNEL_ANALYSIS_SECONDS,
+        ANALYSIS_SAMPLE_RATE,
+    )?;
+    let names = channel_names(layout, channel_count);
+    let channels: Vec<ChannelStats> = planes
+        .iter()
+        .zip(&names)
+        .map(|(plane, name)| ChannelStats {
+            name: name.clone(),
+            level_db: level_db(plane),
+            zero_crossings_per_second: zero_crossings_per_second(plane),
+        })
+        .collect();
+
+    let mut issues = Vec::new();
+    let silent = |i: usize| channels[i].level_db < SILENT_CHANNEL_DB;
+    for (i, _) in channels.iter().enumerate() {
+        if silent(i) {
+            issues.push(ChannelIssue::Silent { channel: i });
+        }
+    }
+    // The front pair carries the main mix; identical or inverted content there is a defect
+    if channel_count >= 2 && !silent(0) && !silent(1) {
+        let front = correlation(&planes[0], &planes[1]);
+        if front > DUAL_MONO_CORRELATION
+            && (channels[0].level_db - channels[1].level_db).abs() < 1.0
+        {
+            issues.push(ChannelIssue::DualMono {
+                first: 0,
+                second: 1,
+            });
+        } else if front < INVERTED_CORRELATION {
+            issues.push(ChannelIssue::InvertedPolarity {
+                first: 0,
+                second: 1,
+            });
+        }
+    }
+    // A full-band LFE next to a bass-only channel means the two were mapped the wrong way round
+    if let Some(lfe) = names.iter().position(|n| n == "LFE")
+        && !silent(lfe)
+        && channels[lfe].zero_crossings_per_second > LFE_MAX_ZERO_CROSSINGS
+        && let Some(with) = (0..channel_count).find(|&i| {
+            i != lfe && !silent(i) && channels[i].zero_crossings_per_second < LFE_MAX_ZERO_CROSSINGS
+        })
+    {
+        issues.push(ChannelIssue::SwappedLfe { lfe, with });
+    }
+
+    Ok(ChannelReport {
+        layout: layout.to_string(),
+        channels,
+        issues,
+    })
+}
+
+impl ChannelReport {
+    /// Human-readable description of an issue.
+    pub fn describe(&self, issue: &ChannelIssue) -> String {
+        let name = |i: usize| self.channels[i].name.as_str();
+        match issue {
+            ChannelIssue::DualMono { first, second } => format!(
+                "{} and {} carry the same signal (dual mono)",
+                name(*first),
+                name(*second)
+            ),
+            ChannelIssue::Silent { channel } => format!("{} is silent", name(*channel)),
+            ChannelIssue::InvertedPolarity { first, second } => format!(
+                "{} is polarity-inverted relative to {}",
+                name(*second),
+                name(*first)
+            ),
+            ChannelIssue::SwappedLfe { lfe, with } => format!(
+                "{} carries full-band audio while {} only carries bass; they appear swapped",
+                name(*lfe),
+                name(*with)
+            ),
+        }
+    }
+
+    /// `pan` filter that fixes the swapped and inverted channels, or None if nothing can be fixed.
+    /// Dual-mono and silent channels are only reported, as the missing content cannot be restored.
+    pub fn pan_filter(&self) -> Option<String> {
+        let count = self.channels.len();
+        let mut sources: Vec<String> = (0..count).map(|i| format!("c{}", i)).collect();
+        let mut changed = false;
+        for issue in &self.issues {
+            match issue {
+                ChannelIssue::InvertedPolarity { second, .. } => {
+                    sources[*second] = format!("-1*c{}", second);
+                    changed = true;
+                }
+                ChannelIssue::SwappedLfe { lfe, with } => {
+                    sources.swap(*lfe, *with);
+                    changed = true;
+                }
+                ChannelIssue::DualMono { .. } | ChannelIssue::Silent { .. } => {}
+            }
+        }
+        if !changed {
+            return None;
+        }
+        let layout = if self.layout.is_empty() || self.layout == "unknown" {
+            format!("{}c", count)
+        } else {
+            self.layout.clone()
+        };
+        let mapping = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| format!("c{}={}", i, source))
+            .collect::<Vec<_>>()
+            .join("|");
+        Some(format!("pan={}|{}", layout, mapping))
+    }
+}
+
+/// Rewrite an intermediate file through a `pan` filter that fixes its channel mapping.
+pub fn remap_channels(
+    input_flac: &std::path::Path,
+    output_flac: &std::path::Path,
+    pan: &str,
+    run: &RunOptions,
+) -> Result<()> {
+    run_ffmpeg(
+        &str_args(&run.output_args(&[
+            "-y",
+            "-i",
+            crate::util::path_to_str(input_flac)?,
+            "-af",
+            pan,
+            "-c:a",
+            run.codec(),
+            crate::util::path_to_str(output_flac)?,
+        ])),
+        run.debug,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anyhow::{Result, bail};
+use std::path::Path;
+use std::process::Command;
+
+use crate::ffmpeg::ffprobe_executable;
+use crate::subtitles::Timeline;
+use crate::util::{path_to_str, write_file_atomic};
+
+/// A chapter of a media file, with its times in seconds.
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+/// Read the chapters of a media file, in order.
+pub fn read_chapters(input: &str) -> Result<Vec<Chapter>> {
+    let output = Command::new(ffprobe_executable())
+        .args(["-v", "error", "-show_chapters", "-of", "json", input])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "ffprobe failed to read chapters: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    Ok(json["chapters"]
+        .as_array()
+        .map(|chapters| {
+            chapters
+                .iter()
+                .filter_map(|c| {
+                    let start = c["start_time"].as_str()?.parse::<f64>().ok()?;
+                    let end = c["end_time"].as_str()?.parse::<f64>().unwrap_or(start);
+                    Some(Chapter {
+                        start,
+                        end,
+                        title: c["tags"]["title"].as_str().map(|t| t.to_string()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Move chapters through the delay plan. A chapter starting in trimmed audio starts where the
+/// audio resumes; chapters keep their order and never end before they start.
+pub fn retime_chapters(chapters: &[Chapter], timeline: &Timeline) -> Vec<Chapter> {
+    let mut retimed: Vec<Chapter> = Vec::with_capacity(chapters.len());
+    for chapter in chapters {
+        let previous_start = retimed.last().map(|c| c.start).unwrap_or(0.0);
+        let start = timeline.map_clamped(chapter.start).max(previous_start);
+        let end = timeline.map_clamped(chapter.end).max(start);
+        retimed.push(Chapter {
+            start,
+            end,
+            title: chapter.title.clone(),
+        });
+    }
+    retimed
+}
+
+/// Escape the characters that are special in an FFMETADATA file.
+fn escape_metadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Write chapters as an FFMETADATA file that ffmpeg can map chapters from.
+pub fn write_chapters_metadata(chapters: &[Chapter], path: &Path) -> Result<()> {
+    let mut contents = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        contents.push_str("[CHAPTER]\nTIMEBASE=1/1000\n");
+        contents.push_str(&format!(
+            "START={}\nEND={}\n",
+            (chapter.start * 1000.0).round() as i64,
+            (chapter.end * 1000.0).round() as i64
+        ));
+        if let Some(title) = &chapter.title {
+            contents.push_str(&format!("title={}\n", escape_metadata(title)));
+        }
+    }
+    write_file_atomic(path_to_str(path)?, contents.as_bytes())?;
+    Ok(())
+}
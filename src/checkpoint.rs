@@ -0,0 +1,313 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::temp_usage::dir_size;
+use crate::util::{path_to_str, write_file_atomic};
+
+/// File in a run's temp dir that records the stages the run has finished.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// File in a run's temp dir holding the PID of the process running in it.
+pub const LOCK_FILE: &str = "run.lock";
+
+/// Temp dirs of the runs in progress in this process.
+static LIVE_RUNS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// A split point, its delay, where it came from and its label, as resolved by a run.
+pub type ResolvedSplit = (f64, f64, String, Option<String>);
+
+/// The stages a run has finished, kept in its temp dir so a failed run can be resumed with
+/// `--resume`. Files are named relative to the temp dir.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Fingerprint of the job, see [`job_fingerprint`]
+    pub job: String,
+    /// The target audio is extracted (and its channels fixed)
+    #[serde(default)]
+    pub extracted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_splits: Option<Vec<ResolvedSplit>>,
+    /// Split points found in ranges, with their confidence if the detector can tell
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub found_splits: Vec<(f64, Option<f64>)>,
+    /// Split, delayed, concatenated and drift-corrected audio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited: Option<String>,
+    /// Audio encoded back to the original codec, ready to remux
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoded: Option<String>,
+}
+
+impl Manifest {
+    pub fn new(job: &str) -> Self {
+        Self {
+            job: job.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn load(dir: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        write_file_atomic(path_to_str(&dir.join(MANIFEST_FILE))?, json.as_bytes())?;
+        Ok(())
+    }
+
+    /// A file recorded by a stage, if it is still in the temp dir.
+    pub fn file(dir: &Path, name: Option<&String>) -> Option<PathBuf> {
+        name.map(|name| dir.join(name))
+            .filter(|path| path.is_file())
+    }
+
+    /// The finished stages, for the resume message.
+    pub fn describe(&self) -> String {
+        let stages: Vec<&str> = [
+            (self.extracted, "extraction"),
+            (self.resolved_splits.is_some(), "split points"),
+            (self.edited.is_some(), "split and concat"),
+            (self.encoded.is_some(), "encoding"),
+        ]
+        .into_iter()
+        .filter_map(|(done, stage)| done.then_some(stage))
+        .collect();
+        if stages.is_empty() {
+            "no finished stages".to_string()
+        } else {
+            stages.join(", ")
+        }
+    }
+}
+
+/// What a job's intermediate files depend on. Serialized as JSON and hashed for the fingerprint.
+#[derive(Serialize)]
+struct JobKey<'a, O: Serialize> {
+    /// Stages may change between versions
+    version: &'static str,
+    options: &'a O,
+    input: PathBuf,
+    size: u64,
+    /// Modification time in nanoseconds since the epoch
+    modified: Option<u128>,
+}
+
+/// Fingerprint a job from its options and the identity of its input (path, size and
+/// modification time), so a run is only resumed for the same options and source. The hash is
+/// SHA-256 over the JSON of both, so it stays the same between builds and platforms.
+pub fn job_fingerprint(options: &impl Serialize, input: &str) -> Result<String> {
+    let metadata = fs::metadata(input)?;
+    let key = JobKey {
+        version: env!("CARGO_PKG_VERSION"),
+        options,
+        input: fs::canonicalize(input)?,
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos()),
+    };
+    let digest = Sha256::digest(serde_json::to_vec(&key)?);
+    Ok(digest[..16].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// The temp dir of a run that was left behind by a failure, with its manifest.
+pub struct FailedRun {
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+    /// When the run last finished a stage
+    pub modified: SystemTime,
+}
+
+/// All temp dirs of failed runs in `root`, least recently used first.
+pub fn failed_runs(root: &Path) -> Vec<FailedRun> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut runs: Vec<FailedRun> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("sync_nudger_"))
+        })
+        .filter_map(|dir| {
+            let manifest = Manifest::load(&dir)?;
+            let modified = fs::metadata(dir.join(MANIFEST_FILE))
+                .and_then(|m| m.modified())
+                .ok()?;
+            Some(FailedRun {
+                dir,
+                manifest,
+                modified,
+            })
+        })
+        .collect();
+    runs.sort_by_key(|run| run.modified);
+    runs
+}
+
+/// Marks a temp dir as in use by a run of this process until dropped, so neither other jobs of
+/// this process nor other instances evict or resume it.
+pub struct LiveRun {
+    dir: PathBuf,
+}
+
+impl LiveRun {
+    /// Claim `dir` for a run of this process.
+    pub fn claim(dir: &Path) -> Result<Self> {
+        let mut live = LIVE_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+        Self::claim_locked(dir, &mut live)
+    }
+
+    /// Create the lock file of `dir`, failing if it exists. A lock left behind by a process that
+    /// is gone is taken over; one of a running process (or of a run of this one) is not.
+    fn claim_locked(dir: &Path, live: &mut Vec<PathBuf>) -> Result<Self> {
+        if in_progress(dir, live) {
+            bail!("'{}' is in use by another run.", dir.display());
+        }
+        let lock = dir.join(LOCK_FILE);
+        let mut took_over = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock) {
+                Ok(mut file) => {
+                    file.write_all(std::process::id().to_string().as_bytes())?;
+                    break;
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && !took_over => {
+                    if in_progress(dir, live) {
+                        bail!("'{}' is in use by another run.", dir.display());
+                    }
+                    // Only one more attempt, so a claimant that got there first keeps the dir
+                    let _ = fs::remove_file(&lock);
+                    took_over = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    bail!("'{}' is in use by another run.", dir.display());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        live.push(dir.to_path_buf());
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for LiveRun {
+    fn drop(&mut self) {
+        let mut live = LIVE_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+        live.retain(|dir| *dir != self.dir);
+        // The dir may be gone already, removed or moved for --keep-temp
+        let _ = fs::remove_file(self.dir.join(LOCK_FILE));
+    }
+}
+
+/// Find the temp dir in `root` of the most recent run of a job that was left behind by a
+/// failure and claim it. Dirs of runs still in progress, or claimed by another instance first, are skipped.
+pub fn find_resumable(root: &Path, job: &str) -> Option<(LiveRun, Manifest)> {
+    let mut live = LIVE_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    failed_runs(root)
+        .into_iter()
+        .rev()
+        .filter(|run| run.manifest.job == job)
+        .find_map(|run| {
+            let claimed = LiveRun::claim_locked(&run.dir, &mut live).ok()?;
+            Some((claimed, run.manifest))
+        })
+}
+
+/// Whether a run is in progress in a temp dir: one of this process claimed it, or the process
+/// named by its lock file (or else by its name, `sync_nudger_<pid>_...`) is still running.
+fn in_progress(dir: &Path, live: &[PathBuf]) -> bool {
+    let lock = fs::read_to_string(dir.join(LOCK_FILE)).ok();
+    // Created but not written yet by the instance claiming it
+    if lock.as_deref() == Some("") {
+        return true;
+    }
+    let pid = lock
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .or_else(|| {
+            dir.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("sync_nudger_"))
+                .and_then(|n| n.split('_').next())
+                .and_then(|pid| pid.parse::<u32>().ok())
+        });
+    match pid {
+        Some(pid) if pid == std::process::id() => live.iter().any(|d| d == dir),
+        Some(pid) => process_running(pid),
+        None => false,
+    }
+}
+
+#[cfg(unix)]
+fn process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks whether the process exists
+    unsafe {
+        libc::kill(pid, 0) == 0
+            || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+}
+
+#[cfg(not(unix))]
+fn process_running(_pid: u32) -> bool {
+    false
+}
+
+/// Default of `--cache-max-age`, in days.
+pub const DEFAULT_CACHE_MAX_AGE_DAYS: f64 = 7.0;
+
+/// Default of `--cache-max-size`: 20G.
+pub const DEFAULT_CACHE_MAX_SIZE: u64 = 20 << 30;
+
+/// Remove the temp dirs of failed runs in `root` (other than `keep` and those of runs in
+/// progress) that were not used for `max_age`, then the least recently used ones until the rest
+/// fit in `max_bytes`. Returns the removed dirs and the bytes they held.
+pub fn evict_failed_runs(
+    root: &Path,
+    keep: Option<&Path>,
+    max_age: Duration,
+    max_bytes: u64,
+) -> Vec<(PathBuf, u64)> {
+    let now = SystemTime::now();
+    // Held throughout, so no job of this process claims a dir while it is removed
+    let live = LIVE_RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    let runs: Vec<(FailedRun, u64)> = failed_runs(root)
+        .into_iter()
+        .filter(|run| Some(run.dir.as_path()) != keep && !in_progress(&run.dir, &live))
+        .map(|run| {
+            let size = dir_size(&run.dir);
+            (run, size)
+        })
+        .collect();
+    let mut remaining: u64 = runs.iter().map(|(_, size)| size).sum();
+    let mut removed = Vec::new();
+    for (run, size) in runs {
+        let expired = now
+            .duration_since(run.modified)
+            .is_ok_and(|age| age > max_age);
+        if (expired || remaining > max_bytes) && fs::remove_dir_all(&run.dir).is_ok() {
+            remaining -= size;
+            removed.push((run.dir, size));
+        }
+    }
+    removed
+}
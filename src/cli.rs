@@ -1,21 +1,78 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use serde;
+use std::fmt;
+
+use crate::checkpoint::DEFAULT_CACHE_MAX_AGE_DAYS;
+use crate::i18n::{Lang, parse_lang};
 
 /// Rust version of the multi-split/delay audio tool
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(author, version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// ffmpeg executable to use (default: $SYNC_NUDGER_FFMPEG, or ffmpeg from PATH)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub ffmpeg_path: Option<String>,
+
+    /// ffprobe executable to use (default: $SYNC_NUDGER_FFPROBE, or ffprobe from PATH)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub ffprobe_path: Option<String>,
+
+    /// Directory for temp files, e.g. on a disk with room for the extracted audio of a long movie (default: $SYNC_NUDGER_TEMP_DIR, or the system temp dir)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub temp_dir: Option<String>,
+
+    /// Language of the messages: en, de or ja (default: from LC_ALL, LC_MESSAGES or LANG, else English)
+    #[arg(long, global = true, value_parser = parse_lang, value_name = "LANG")]
+    pub lang: Option<Lang>,
+
+    /// Write a stable, versioned tab-separated format for scripts to stdout and move the human-readable output to stderr (Unix only)
+    #[arg(long, global = true)]
+    pub porcelain: bool,
+
+    /// Print only warnings and errors (to stderr) and the path of each finished output (to stdout), without tables or progress. Needs --yes where the plan would be confirmed
+    #[arg(short, long, global = true, conflicts_with = "porcelain")]
+    pub quiet: bool,
+
+    /// Log to stderr as well: -v for each job, ffmpeg failure and hook, -vv also for every ffmpeg command
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Append a timestamped log of the run (every ffmpeg command, how it ended, and why a job failed) to this file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file: Option<String>,
+}
+
+/// Options of the `process` and `resolve` commands. Serialized, they identify a job for
+/// `--resume`; options that do not change the result are skipped.
+#[derive(Parser, Debug, Clone, serde::Serialize)]
 pub struct Args {
-    /// Input media file (video or audio, any FFmpeg-supported format)
-    #[arg(short = 'i', long)]
-    pub input: Option<String>,
+    /// Input media file (video or audio, any FFmpeg-supported format). Pass several files or a directory to apply the same plan to each of them; --output then names the output directory.
+    #[arg(short = 'i', long, num_args = 1.., value_name = "FILE")]
+    pub input: Vec<String>,
 
-    /// Output media file (any FFmpeg-supported format)
+    /// Output media file (any FFmpeg-supported format), or the output directory when processing several inputs (default: a "synced" directory next to the inputs)
     #[arg(short = 'o', long)]
     pub output: Option<String>,
 
-    /// Audio stream index (e.g. 6)
-    #[arg(short = 's', long)]
-    pub stream: Option<usize>,
+    /// Audio stream to process: absolute index (e.g. 6 or 0:6), ffmpeg-style audio specifier (e.g. a:1 for the second audio stream), or auto for the main audio stream (the default one, else the first that is not commentary). Repeat to apply the same delays and splits to several streams (e.g. the 5.1 and stereo mix of a dub), remuxed into one output.
+    #[arg(short = 's', long, value_parser = parse_stream_specifier)]
+    pub stream: Vec<StreamSpecifier>,
+
+    /// Select the audio stream tagged with this language instead of giving --stream (e.g. jpn, ja or jp). Fails if several streams match; narrow it down with --stream-title.
+    #[arg(
+        short = 'l',
+        long,
+        visible_alias = "stream-lang",
+        conflicts_with = "stream"
+    )]
+    pub language: Option<String>,
+
+    /// Select the audio stream whose title contains this text (ignoring case) instead of giving --stream, e.g. "Commentary". Combines with --language; fails if several streams match.
+    #[arg(long, value_name = "TEXT", conflicts_with = "stream")]
+    pub stream_title: Option<String>,
 
     /// Path to a JSON file describing the full task (input, output, stream, splits, delays, etc). CLI arguments override values in the task file.
     #[arg(short = 't', long = "task")]
@@ -25,15 +82,39 @@ pub struct Args {
     #[arg(short = 'd', long, default_value_t = 0.0, conflicts_with = "split_map")]
     pub initial_delay: f64,
 
-    /// Split points and subsequent delays, in format <seconds>:<delay_ms>. (conflicts with --split-map)
+    /// Split points and subsequent delays, in format <time>:<delay_ms>[:label=<text>]; the time is in seconds or [HH:]MM:SS.mmm. (conflicts with --split-map)
     #[arg(short = 'p', long = "split", value_parser = parse_split, num_args = 1.., conflicts_with = "split_map")]
     pub splits: Vec<SplitPoint>,
 
-    /// Split ranges and subsequent delays, in format <start_time>:<end_time>:<delay_ms>. (conflicts with --split-map)
+    /// Split ranges and subsequent delays, in format <start_time>:<end_time>:<delay_ms>[:label=<text>]; times are in seconds or [HH:]MM:SS.mmm. (conflicts with --split-map)
     #[arg(short = 'r', long = "split-range", value_parser = parse_split_range, num_args = 1.., conflicts_with = "split_map")]
     pub split_ranges: Vec<SplitRange>,
 
-    /// Output bitrate (e.g. 80k). If not provided, it will be detected automatically.
+    /// Gradual delay ramps, in format <start_time>:<end_time>:<delay_ms>. The delay grows linearly over the span instead of as a step. (conflicts with --split-map)
+    #[arg(long = "ramp", value_parser = parse_ramp, num_args = 1.., conflicts_with = "split_map")]
+    pub ramps: Vec<DelayRamp>,
+
+    /// Load the initial delay, splits, split ranges and ramps from this file: JSON, YAML or TOML with the fields of a task file, or a .txt/.map file with one `delay`, `split`, `range` or `ramp` entry per line. Overrides the same fields of the task file.
+    #[arg(long, value_name = "FILE")]
+    pub split_map: Option<String>,
+
+    /// Clock drift to correct across the whole track, in parts per million (e.g. 42 or -17.5). Positive values lengthen the audio, negative values shorten it.
+    #[arg(long, allow_hyphen_values = true)]
+    pub drift_ppm: Option<f64>,
+
+    /// Stretch the whole track by this length ratio, as a number or a fraction of frame rates (e.g. 25/23.976 for PAL-speedup audio over film-rate video). Converted to --drift-ppm.
+    #[arg(long, value_parser = parse_ratio, value_name = "RATIO", conflicts_with = "drift_ppm")]
+    pub stretch: Option<f64>,
+
+    /// Frame rate for split times and delays given in frames (e.g. 2024f:+2f), as a number or a fraction (e.g. 24000/1001). Default: the frame rate of the first video stream
+    #[arg(long, value_parser = parse_ratio, value_name = "RATE")]
+    pub fps: Option<f64>,
+
+    /// Two measured offsets, in format <seconds>:<delay_ms>, that the track should have at two points in time; the drift and initial delay are derived from them (give the option twice)
+    #[arg(long = "sync-point", value_parser = parse_sync_point, num_args = 1.., value_name = "TIME:DELAY", conflicts_with_all = ["drift_ppm", "stretch", "initial_delay", "reference", "match_onset"])]
+    pub sync_points: Vec<SyncPoint>,
+
+    /// Output bitrate (e.g. 80k). If not provided, it will be detected automatically. Lossless tracks (FLAC, ALAC, PCM) are encoded back to their own codec and need none.
     #[arg(short = 'b', long)]
     pub bitrate: Option<String>,
 
@@ -43,84 +124,1342 @@ pub struct Args {
     #[arg(short = 'T', long, default_value_t = -95.0)]
     pub silence_threshold: f64,
 
+    /// Measure the noise floor of the stream and set the silence threshold this many dB above it (default: 6), for noisy sources such as tape or VHS transfers
+    #[arg(long, num_args = 0..=1, value_name = "MARGIN_DB", conflicts_with = "silence_threshold")]
+    pub auto_threshold: Option<Option<f64>>,
+
+    /// Highest true peak (in dBFS) allowed at a split point found in a --split-range. Keeps cuts out of short transients (e.g. door slams) that hide inside quiet loudness windows.
+    #[arg(long, allow_hyphen_values = true, value_name = "DBFS")]
+    pub max_peak: Option<f64>,
+
+    /// Skip split points inside speech found by a voice-activity pass, so cuts found in a --split-range do not land in a breath between words
+    #[arg(long)]
+    pub avoid_speech: bool,
+
+    /// Keep split points found in a --split-range where the detector put them instead of moving them to the nearest zero crossing of the waveform (within 5 ms)
+    #[arg(long)]
+    pub no_zero_crossing: bool,
+
+    /// How the split point inside a --split-range is picked: ebur128 (lowest momentary loudness, default), silencedetect (middle of the longest silence), rms (lowest RMS level), vad (middle of the longest pause in speech) or external (--detector-command)
+    #[arg(long, value_parser = parse_detector, value_name = "DETECTOR")]
+    pub detector: Option<DetectorKind>,
+
+    /// Program run by --detector external as `<program> <audio file> <start> <end>`, with arguments quoted as in a shell. It prints the split point in seconds on its first line of output.
+    #[arg(long, value_name = "COMMAND")]
+    pub detector_command: Option<String>,
+
     /// Show ffmpeg logs.
     #[arg(short = 'g', long)]
+    #[serde(skip)]
     pub debug: bool,
 
-    /// Ignore ffmpeg version check.
+    /// Maximum number of threads each ffmpeg process may use for decoding, filtering, and encoding (defaults to ffmpeg's own choice)
+    #[arg(long = "ffmpeg-threads", value_name = "N")]
+    pub ffmpeg_threads: Option<usize>,
+
+    /// Number of segments split and delayed at the same time. Only used with --segment-files; the single-pass edit ignores it. Defaults to the number of CPUs, divided by --ffmpeg-threads when that is set.
+    #[arg(short = 'j', long, value_name = "N")]
+    #[serde(skip)]
+    pub jobs: Option<usize>,
+
+    /// Produce bit-identical audio for the same task and input on every run: ffmpeg runs with one thread, encoders and muxers write bitexact output (no version strings or random IDs) and temp dirs get predictable names. Overrides --ffmpeg-threads.
     #[arg(long)]
-    pub ignore_ffmpeg_version: bool,
+    pub deterministic: bool,
 
-    /// Check FFmpeg installation and version compatibility.
-    #[arg(short = 'c', long)]
-    pub check_ffmpeg: bool,
+    /// Write every segment to its own temporary file and concatenate them afterwards instead of editing the track in a single ffmpeg pass. Slower and needs more temp space, but the segments can be inspected with --keep-temp.
+    #[arg(long = "segment-files")]
+    pub segment_files: bool,
 
-    /// Inspect input file and show all audio streams in a table
-    #[arg(short = 'I', long)]
-    pub inspect: bool,
+    /// Blend the segments into each other over this long at every join instead of cutting hard, to avoid clicks (e.g. 15ms, 0.02s; a plain number is milliseconds). Segments run on by the crossfade, so the timing of the plan is kept.
+    #[arg(long, value_parser = parse_crossfade, value_name = "DURATION")]
+    pub crossfade: Option<f64>,
+
+    /// Codec of the temporary files the track is analyzed and edited in: flac (default), pcm_s24le or pcm_f32le. PCM skips the FLAC encoding, which is slow for 7.1 masters, at several times the temp space.
+    #[arg(long, value_parser = parse_intermediate_format, value_name = "FORMAT")]
+    pub intermediate: Option<IntermediateFormat>,
+
+    /// Warn when an ffmpeg process produces no output for this many minutes, e.g. when a network-mounted input hangs (0 disables the check)
+    #[arg(long, default_value_t = 10.0, value_parser = parse_stall_timeout, value_name = "MINUTES")]
+    pub stall_timeout: f64,
+
+    /// Stop the run with diagnostics instead of only warning when an ffmpeg process stalls
+    #[arg(long)]
+    pub abort_on_stall: bool,
+
+    /// Ignore ffmpeg version check.
+    #[arg(long)]
+    pub ignore_ffmpeg_version: bool,
 
     /// Write the resolved task (after all split points and delays are determined) to this file as JSON. If no file is provided, the input file name (without extension) will be used with .json.
     #[arg(short = 'w', long = "write-task-file", num_args = 0..=1, value_name = "FILE")]
     pub write_task_file: Option<Option<String>>,
 
+    /// Write the proposed splitting plan and job details to this file for review, as Markdown (.md) or JSON (.json)
+    #[arg(long, value_name = "FILE")]
+    pub plan_out: Option<String>,
+
+    /// Before confirmation, render a waveform image of the 2 seconds around each split point into this directory (split_01_<time>s.png, ...) to check the cut locations by eye
+    #[arg(long, value_name = "DIR")]
+    #[serde(skip)]
+    pub review_dir: Option<String>,
+
+    /// Before confirmation, write 3 seconds of audio before and after each split point into this directory as FLAC, once as in the source (split_01_<time>s_original.flac) and once with the delays applied (split_01_<time>s_delayed.flac), to listen to the cuts before the full re-encode
+    #[arg(long, value_name = "DIR")]
+    #[serde(skip)]
+    pub export_previews: Option<String>,
+
+    /// Also write the output with the corrected track shifted by a constant offset, in format <offset_ms>:<file> (e.g. 80:movie.soundbar.mkv for a playback chain that needs 80 ms more delay). The track is encoded once and only remuxed again for each profile; give the option once per profile.
+    #[arg(long = "profile-output", value_parser = parse_profile_output, value_name = "OFFSET:FILE", allow_hyphen_values = true)]
+    pub profile_outputs: Vec<ProfileOutput>,
+
+    /// Also export the corrected audio track as a standalone file. The container is chosen from the codec (e.g. .m4a for AAC, .mka for DTS/TrueHD). If no file is provided, it is written next to the output file.
+    #[arg(long = "export-audio", num_args = 0..=1, value_name = "FILE")]
+    pub export_audio: Option<Option<String>>,
+
+    /// Keep the intermediate files (extracted audio, per-segment files, the encoded track before the remux) in this directory after a successful run instead of deleting them. If no directory is provided, they are kept in <output>.sync-nudger-temp next to the output.
+    #[arg(long = "keep-temp", num_args = 0..=1, value_name = "DIR")]
+    #[serde(skip)]
+    pub keep_temp: Option<Option<String>>,
+
     /// Automatically confirm the splitting plan and proceed without prompting
     #[arg(short = 'y', long = "yes")]
+    #[serde(skip)]
     pub yes: bool,
 
+    /// Probe the streams and resolve the split points, print the splitting plan and job details, then exit without extracting the whole track, re-encoding or writing any file other than the --plan-out
+    #[arg(long = "dry-run", conflicts_with_all = ["write_task_file", "export_audio", "export_previews"])]
+    pub dry_run: bool,
+
+    /// Continue the last failed run of the same job (same input, options and task file) from its last finished stage: extraction, split points, split and concat, or encoding
+    #[arg(long, conflicts_with = "dry_run")]
+    #[serde(skip)]
+    pub resume: bool,
+
+    /// Remove the temp dirs that failed runs keep for --resume once they were not used for this many days (--dry-run and --sample-encode remove none)
+    #[arg(long, default_value_t = DEFAULT_CACHE_MAX_AGE_DAYS, value_parser = parse_days, value_name = "DAYS")]
+    #[serde(skip)]
+    pub cache_max_age: f64,
+
+    /// Remove the least recently used temp dirs of failed runs once together they take more than this, e.g. 20G or 500M
+    #[arg(long, default_value = "20G", value_parser = parse_size, value_name = "SIZE")]
+    #[serde(skip)]
+    pub cache_max_size: u64,
+
+    /// Stop cleanly at the next stage boundary once this file exists (e.g. `touch FILE` from another shell), keeping the finished stages for --resume. The file is removed when it is noticed.
+    #[arg(long, value_name = "FILE")]
+    #[serde(skip)]
+    pub stop_file: Option<String>,
+
+    /// Run this shell command before the job starts; the job is cancelled if it fails. The job context is passed as SYNC_NUDGER_* environment variables and as JSON on stdin.
+    #[arg(long, value_name = "COMMAND")]
+    #[serde(skip)]
+    pub pre_hook: Option<String>,
+
+    /// Run this shell command after the job, whether it succeeded or not (see SYNC_NUDGER_STATUS), e.g. to have a media server rescan the output
+    #[arg(long, value_name = "COMMAND")]
+    #[serde(skip)]
+    pub post_hook: Option<String>,
+
+    /// After a successful run, ask this media server (jellyfin or plex) to refresh the output file.
+    #[arg(long, value_parser = parse_media_server, value_name = "SERVER", requires = "server_url")]
+    pub refresh_server: Option<MediaServerKind>,
+
+    /// Base URL of the --refresh-server, e.g. http://localhost:8096
+    #[arg(long, value_name = "URL")]
+    pub server_url: Option<String>,
+
+    /// API token of the --refresh-server (Jellyfin API key or Plex token). Defaults to the SYNC_NUDGER_SERVER_TOKEN environment variable; never written to task files.
+    #[arg(long, value_name = "TOKEN")]
+    #[serde(skip)]
+    pub server_token: Option<String>,
+
+    /// Replace the LOCAL path prefix of the output with SERVER before telling the media server about it, for servers that see the media under another path (e.g. in a container)
+    #[arg(long, value_name = "LOCAL=SERVER")]
+    pub server_path_map: Option<String>,
+
+    /// Encode only this much audio (e.g. 30s or 2m) around the first split with the planned codec and bitrate, report its size and quality, then exit without processing the whole track
+    #[arg(long, value_parser = parse_sample_length, value_name = "DURATION", conflicts_with_all = ["dry_run", "write_task_file", "export_audio"])]
+    pub sample_encode: Option<f64>,
+
     /// Fit the edited audio stream to the original length (trim or pad with silence at the end of the stream as needed)
     #[arg(short = 'F', long = "fit-length")]
     pub fit_length: bool,
+
+    /// Fill the gap between the start of the video and the later start of the audio stream with silence, so the edited audio starts with the picture (see `gaps`)
+    #[arg(long)]
+    pub pad_head: bool,
+
+    /// Cut the edited audio where the video ends if it runs past the last frame (see `gaps`)
+    #[arg(long, conflicts_with = "fit_length")]
+    pub trim_tail: bool,
+
+    /// If the output container cannot hold every input stream, write a .mkv file instead of failing
+    #[arg(long = "auto-mkv")]
+    pub auto_mkv: bool,
+
+    /// After remuxing, refresh the container duration and track statistics (uses mkvpropedit for Matroska output if installed, otherwise an extra ffmpeg copy pass)
+    #[arg(long = "refresh-stats")]
+    pub refresh_stats: bool,
+
+    /// Record the applied splits and delays on the processed track as Matroska tags (or in a <output>.sync-nudger.json sidecar for other containers), after any history the input already carries
+    #[arg(long = "tag-history")]
+    pub tag_history: bool,
+
+    /// Keep the original audio stream in the output, after all other streams and without the default flag, so it can be compared with the corrected stream in a player
+    #[arg(long = "keep-original")]
+    pub keep_original: bool,
+
+    /// Treat the audio as the reference and apply the initial delay to the video streams instead (stream copy, positive delays the video). Only a constant delay can be applied this way
+    #[arg(long)]
+    pub shift_video: bool,
+
+    /// Title of the corrected stream when the original is kept (default: Corrected). Takes the same placeholders as --title.
+    #[arg(long, requires = "keep_original", value_name = "TITLE")]
+    pub corrected_title: Option<String>,
+
+    /// Title template of the corrected stream, e.g. "{orig_title} [fixed {offset}ms]". Placeholders: {orig_title}, {lang}, {codec}, {stream}, {offset} (initial delay in ms), {splits} (number of split points) and {drift_ppm}; {{ and }} are literal braces.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub title: Option<String>,
+
+    /// How the remux handles negative timestamps: auto, make_zero, make_non_negative or disabled (ffmpeg -avoid_negative_ts). Some source containers make the default shift the corrected track by a frame.
+    #[arg(long, value_parser = parse_avoid_negative_ts, value_name = "MODE")]
+    pub avoid_negative_ts: Option<AvoidNegativeTs>,
+
+    /// Maximum demux-decode delay of the remux in seconds (ffmpeg -muxdelay)
+    #[arg(long, value_name = "SECONDS")]
+    pub muxdelay: Option<f64>,
+
+    /// Initial demux-decode delay of the remux in seconds (ffmpeg -muxpreload)
+    #[arg(long, value_name = "SECONDS")]
+    pub muxpreload: Option<f64>,
+
+    /// Keep the timestamps of the input in the remux instead of starting the output at zero (ffmpeg -copyts)
+    #[arg(long)]
+    pub copyts: bool,
+
+    /// Re-time embedded text subtitle streams (SRT, ASS/SSA, mov_text, WebVTT) with the same split points and delays when remuxing. Without indices, every text subtitle stream is shifted; bitmap subtitles are kept as they are.
+    #[arg(long = "shift-subtitles", num_args = 0.., value_name = "INDEX")]
+    pub shift_subtitles: Option<Vec<usize>>,
+
+    /// Move the chapters of the output with the same split points and delays, so chapters after a split follow the delayed content
+    #[arg(long = "shift-chapters")]
+    pub shift_chapters: bool,
+
+    /// External SRT or ASS/SSA subtitle file to re-time with the same split points and delays (can be repeated). The result is written next to the output, e.g. movie.en.srt becomes <output name>.en.srt.
+    #[arg(long = "subtitle", num_args = 1.., value_name = "FILE")]
+    pub subtitles: Vec<String>,
+
+    /// Check the channel layout of the selected stream before editing and fix swapped or polarity-inverted channels with a pan filter
+    #[arg(long = "fix-channels")]
+    pub fix_channels: bool,
+
+    /// Reference media file with the same content in sync. Claps/beeps found in both tracks are matched to derive the initial delay; without enough of them, both tracks are cross-correlated.
+    #[arg(long, value_name = "FILE")]
+    pub reference: Option<String>,
+
+    /// Audio stream in the reference file, same format as --stream (defaults to the first audio stream)
+    #[arg(long, requires = "reference", value_parser = parse_stream_specifier)]
+    pub reference_stream: Option<StreamSpecifier>,
+
+    /// Audio stream of the input (same format as --stream) whose first onset the target should line up with; derives the initial delay, e.g. for dubbed tracks from a different source
+    #[arg(long, value_name = "STREAM", conflicts_with = "reference", value_parser = parse_stream_specifier)]
+    pub match_onset: Option<StreamSpecifier>,
+
+    /// When the initial delay is measured (--reference, --match-onset) and nothing else is planned, offsets below this many milliseconds count as already in sync and the file is not re-encoded
+    #[arg(long, default_value_t = 20.0, value_name = "MS")]
+    pub in_sync_threshold: f64,
+
+    /// Lowest confidence (0 to 1) of a measured offset or a split point found in a range that is applied without review; below it, --yes and batch runs ask for confirmation when run in a terminal and otherwise skip the file
+    #[arg(long, value_name = "SCORE", value_parser = parse_confidence)]
+    pub min_confidence: Option<f64>,
+
+    /// Stop after the split points and delays are resolved and the task file is written
+    #[arg(skip)]
+    pub resolve_only: bool,
+
+    /// Stop before the remux and copy the finished track into this directory (one stream plan
+    /// of a task file)
+    #[arg(skip)]
+    pub track_output: Option<String>,
+
+    /// --ffmpeg-path, --ffprobe-path and --temp-dir given on the command line, which win over a task file
+    #[arg(skip)]
+    pub ffmpeg_path: Option<String>,
+    #[arg(skip)]
+    pub ffprobe_path: Option<String>,
+    #[arg(skip)]
+    pub temp_dir: Option<String>,
+
+    /// Global --quiet: the plan is not shown and the output path is printed on its own
+    #[arg(skip)]
+    pub quiet: bool,
 }
 
-#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Delay, split and re-time an audio stream and remux it with every other stream unchanged
+    Process(Args),
+    /// Resolve split points and delays and write them to a task file, without processing
+    Resolve(Args),
+    /// Show all audio streams of a file in a table
+    Inspect(InspectArgs),
+    /// Check the FFmpeg installation and version compatibility
+    Check,
+    /// Check FFmpeg, the temp dir, the environment and write permissions, and print a report to paste into a bug report
+    Doctor(DoctorArgs),
+    /// Generate test media and check that every ffmpeg build found (or given) probes, measures and splits it correctly, e.g. after upgrading ffmpeg
+    #[cfg(feature = "selftest")]
+    Selftest(SelftestArgs),
+    /// Detect where the content of an audio stream starts compared to a reference or to the start of the picture, and suggest an --initial-delay value
+    DetectOffset(DetectOffsetArgs),
+    /// Check an audio stream for dual-mono content, silent channels, inverted polarity and a swapped LFE channel
+    CheckChannels(StreamArgs),
+    /// Run only the quiet-point search on one range of a stream and list the candidates, to try out --silence-threshold, --auto-threshold and --max-peak without processing
+    ProbeQuiet(ProbeQuietArgs),
+    /// List the silences of an audio stream with ffmpeg's silencedetect and offer them as --split-range candidates
+    SuggestSplits(SuggestSplitsArgs),
+    /// Scan an audio stream for DC offset, clipping and clicks and list their timestamps; these often mark where a bad edit (and a sync step) was introduced in the source
+    Qc(StreamArgs),
+    /// Report where an audio stream starts and ends compared to the video (leading and trailing gaps, audio running past the picture) and suggest --pad-head / --trim-tail
+    Gaps(StreamArgs),
+    /// Apply a template task to every episode in a directory, resolving times and quiet points per episode
+    ApplySeason(ApplySeasonArgs),
+    /// Watch a directory for new media files, apply a template task to each one as it arrives and move it to a done directory
+    Watch(WatchArgs),
+    /// Compare the audio of two media files: duration, loudness, constant offset, codec and bitrate
+    Compare(CompareArgs),
+    /// Turn the delays of an mkvmerge command line or option file, an eac3to command line or log, or an ffmpeg -itsoffset command line into task files
+    Import(ImportArgs),
+    /// Keep the task, analysis cache, preview clips and attempts of one tricky file in a project directory
+    Project(ProjectArgs),
+    /// List or clear the temp dirs that failed runs keep for --resume, in the temp dir and in projects
+    Cache(CacheArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct InspectArgs {
+    /// Input media file
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    /// Flag streams whose duration differs from the container duration by more than this many milliseconds
+    #[arg(long, default_value_t = 100.0, value_name = "MS")]
+    pub duration_tolerance: f64,
+}
+
+/// Selects one audio stream of a file for the analysis commands.
+#[derive(clap::Args, Debug, Clone)]
+pub struct StreamArgs {
+    /// Input media file
+    #[arg(short = 'i', long)]
+    pub input: String,
+
+    /// Audio stream to analyze, same format as in `process` (defaults to the first audio stream)
+    #[arg(short = 's', long, value_parser = parse_stream_specifier)]
+    pub stream: Option<StreamSpecifier>,
+
+    /// Select the audio stream tagged with this language instead of giving --stream, as in `process`
+    #[arg(
+        short = 'l',
+        long,
+        visible_alias = "stream-lang",
+        conflicts_with = "stream"
+    )]
+    pub language: Option<String>,
+
+    /// Select the audio stream whose title contains this text, as in `process`
+    #[arg(long, value_name = "TEXT", conflicts_with = "stream")]
+    pub stream_title: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ProbeQuietArgs {
+    #[command(flatten)]
+    pub target: StreamArgs,
+
+    /// Range to search, in format <start_time>:<end_time> (seconds or [HH:]MM:SS.mmm)
+    #[arg(short = 'r', long, value_parser = parse_time_range, value_name = "START:END")]
+    pub range: (f64, f64),
+
+    /// Loudness threshold (in LUFS) to consider a point as audible, as in `process`
+    #[arg(short = 'T', long, default_value_t = -95.0)]
+    pub silence_threshold: f64,
+
+    /// Set the silence threshold this many dB above the noise floor of the range (default: 6)
+    #[arg(long, num_args = 0..=1, value_name = "MARGIN_DB", conflicts_with = "silence_threshold")]
+    pub auto_threshold: Option<Option<f64>>,
+
+    /// Highest true peak (in dBFS) allowed at a candidate, as in `process`
+    #[arg(long, allow_hyphen_values = true, value_name = "DBFS")]
+    pub max_peak: Option<f64>,
+
+    /// Skip candidates inside speech, as in `process`
+    #[arg(long)]
+    pub avoid_speech: bool,
+
+    /// Number of candidates to list, quietest first
+    #[arg(short = 'n', long, default_value_t = 10)]
+    pub candidates: usize,
+
+    /// Show ffmpeg logs.
+    #[arg(short = 'g', long)]
+    pub debug: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SuggestSplitsArgs {
+    #[command(flatten)]
+    pub target: StreamArgs,
+
+    /// Shortest silence to list, in seconds
+    #[arg(short = 'm', long, default_value_t = 1.0, value_name = "SECONDS")]
+    pub min_silence: f64,
+
+    /// Level (in dBFS) below which audio counts as silence
+    #[arg(short = 'n', long, default_value_t = -50.0, allow_hyphen_values = true, value_name = "DB")]
+    pub noise: f64,
+
+    /// Delay in milliseconds to put into the suggested --split-range options
+    #[arg(short = 'd', long, default_value_t = 0.0, allow_hyphen_values = true)]
+    pub delay: f64,
+
+    /// Only list breaks: silences that overlap black video (ad and recap boundaries in broadcast captures, where the sync usually steps)
+    #[arg(long)]
+    pub breaks: bool,
+
+    /// Shortest black video that counts as a break, in seconds (with --breaks)
+    #[arg(
+        long,
+        default_value_t = 0.5,
+        value_name = "SECONDS",
+        requires = "breaks"
+    )]
+    pub min_black: f64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DetectOffsetArgs {
+    #[command(flatten)]
+    pub target: StreamArgs,
+
+    /// Reference media file with the same content in sync (defaults to comparing with the picture start of the input)
+    #[arg(long, value_name = "FILE")]
+    pub reference: Option<String>,
+
+    /// Audio stream in the reference file (defaults to the first audio stream)
+    #[arg(long, requires = "reference", value_parser = parse_stream_specifier)]
+    pub reference_stream: Option<StreamSpecifier>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CompareArgs {
+    /// First media file
+    pub first: String,
+
+    /// Second media file
+    pub second: String,
+
+    /// Audio stream to compare, same format as --stream (defaults to the first audio stream)
+    #[arg(short = 's', long, value_parser = parse_stream_specifier)]
+    pub stream: Option<StreamSpecifier>,
+
+    /// Audio stream of the second file if it differs from --stream
+    #[arg(long, value_parser = parse_stream_specifier)]
+    pub second_stream: Option<StreamSpecifier>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ImportArgs {
+    /// Command line, option file or log written by the other tool
+    pub report: String,
+
+    /// Task file to write (defaults to the report name with .json); several delays are written to numbered files (task-1.json, task-2.json, ...)
+    #[arg(short = 't', long)]
+    pub task: Option<String>,
+
+    /// Media file the tasks apply to, when the report does not name it or names a different copy
+    #[arg(short = 'i', long)]
+    pub input: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ApplySeasonArgs {
+    /// Template task file. Split times may be seconds, a percentage of the episode (e.g. "50%") or relative to a chapter start (e.g. "chapter:3+12.5")
+    #[arg(short = 't', long)]
+    pub task: String,
+
+    /// Directory containing the episodes
+    #[arg(long)]
+    pub dir: String,
+
+    /// Directory for the processed episodes (defaults to a "synced" directory inside --dir)
+    #[arg(short = 'o', long)]
+    pub output_dir: Option<String>,
+
+    /// Only resolve and write the per-episode task files, without processing the episodes
+    #[arg(long)]
+    pub resolve_only: bool,
+
+    /// Skip confirmation prompt and proceed automatically
+    #[arg(short = 'y', long = "yes")]
+    pub yes: bool,
+
+    /// Enable debug output (prints all ffmpeg commands and outputs)
+    #[arg(short = 'g', long)]
+    pub debug: bool,
+
+    /// Global --quiet, passed on to every episode
+    #[arg(skip)]
+    pub quiet: bool,
+
+    /// Global --temp-dir, passed on to every episode
+    #[arg(skip)]
+    pub temp_dir: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DoctorArgs {
+    /// Task file to check for parse errors
+    #[arg(short = 't', long)]
+    pub task: Option<String>,
+
+    /// Also check that this directory is writable, e.g. the output directory (can be repeated)
+    #[arg(long = "dir", value_name = "DIR")]
+    pub dirs: Vec<String>,
+}
+
+#[cfg(feature = "selftest")]
+#[derive(clap::Args, Debug, Clone)]
+pub struct SelftestArgs {
+    /// Also run the full pipeline on the test media, in a single pass and with --segment-files, and check the resolved split and the output duration
+    #[arg(long)]
+    pub thorough: bool,
+
+    /// ffmpeg executable to test, with the ffprobe of the same name next to it (can be repeated). Defaults to the configured ffmpeg and every versioned ffmpeg on PATH, such as ffmpeg-6.
+    #[arg(long = "ffmpeg", value_name = "PATH")]
+    pub ffmpeg: Vec<String>,
+
+    /// Show ffmpeg logs.
+    #[arg(short = 'g', long)]
+    pub debug: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ProjectArgs {
+    #[command(subcommand)]
+    pub action: ProjectCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ProjectCommand {
+    /// Create a project for an input file, or show the project if the directory already holds one
+    Open {
+        /// Project directory
+        dir: String,
+
+        /// Input media file (required to create the project)
+        #[arg(short = 'i', long)]
+        input: Option<String>,
+    },
+    /// Show the input, task and attempts of a project
+    Status {
+        /// Project directory
+        dir: String,
+    },
+    /// Process the input with the project task as a new attempt, resuming from the cache of a failed attempt
+    Run {
+        /// Project directory
+        dir: String,
+
+        /// Automatically confirm the splitting plan and proceed without prompting
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+
+        /// Only print the splitting plan, as `process --dry-run`
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+
+        /// Show ffmpeg logs.
+        #[arg(short = 'g', long)]
+        debug: bool,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub action: CacheCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// List the kept temp dirs with their finished stages, size and when they were last used
+    Stats {
+        /// Also list the cache of this project directory (can be repeated)
+        #[arg(long = "project", value_name = "DIR")]
+        projects: Vec<String>,
+    },
+    /// Remove the kept temp dirs
+    Clear {
+        /// Also clear the cache of this project directory (can be repeated)
+        #[arg(long = "project", value_name = "DIR")]
+        projects: Vec<String>,
+
+        /// Only remove temp dirs not used for this many days
+        #[arg(long, value_parser = parse_days, value_name = "DAYS")]
+        older_than: Option<f64>,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct WatchArgs {
+    /// Template task file, as in `apply-season`
+    #[arg(short = 't', long)]
+    pub task: String,
+
+    /// Directory to watch for new media files
+    #[arg(long)]
+    pub dir: String,
+
+    /// Directory for the processed files (defaults to a "synced" directory inside --dir)
+    #[arg(short = 'o', long)]
+    pub output_dir: Option<String>,
+
+    /// Directory the source files and their task files are moved to once processed (defaults to a "done" directory inside --dir)
+    #[arg(long)]
+    pub done_dir: Option<String>,
+
+    /// Directory the source files are moved to when processing fails (defaults to a "failed" directory inside --dir)
+    #[arg(long)]
+    pub failed_dir: Option<String>,
+
+    /// Seconds between two scans of the directory. A file is picked up once its size is the same in two scans in a row.
+    #[arg(long, default_value_t = 10.0, value_name = "SECONDS")]
+    pub interval: f64,
+
+    /// Process the files already in the directory and exit instead of watching
+    #[arg(long)]
+    pub once: bool,
+
+    /// Number of files processed at the same time, each in its own process; above 1, every output line starts with the name of its file. Queued files start in order of priority, set per file with a <name>.priority file holding an integer (higher first, 0 by default), then in order of arrival.
+    #[arg(short = 'j', long, default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Enable debug output (prints all ffmpeg commands and outputs)
+    #[arg(short = 'g', long)]
+    pub debug: bool,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct SplitPoint {
+    #[serde(deserialize_with = "deserialize_time")]
     pub time: f64,
     /// Delay in milliseconds (can be fractional, e.g., 200.5)
     pub delay: f64,
+    /// Note shown in the plan, e.g. "reel change"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Values given in video frames on the command line (`start` is the time)
+    #[serde(skip)]
+    pub frames: FrameUnits,
 }
 
-#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+impl SplitPoint {
+    /// Turn the values given in frames into seconds and milliseconds.
+    pub fn frames_to_seconds(&mut self, fps: f64) {
+        if self.frames.start {
+            self.time /= fps;
+        }
+        if self.frames.delay {
+            self.delay *= 1000.0 / fps;
+        }
+        self.frames = FrameUnits::default();
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct SplitRange {
-    #[serde(rename = "startTime")]
+    #[serde(rename = "startTime", deserialize_with = "deserialize_time")]
     pub start: f64,
-    #[serde(rename = "endTime")]
+    #[serde(rename = "endTime", deserialize_with = "deserialize_time")]
     pub end: f64,
     /// Delay in milliseconds (can be fractional, e.g., 200.5)
     pub delay: f64,
+    /// Note shown in the plan, e.g. "reel change"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Values given in video frames on the command line
+    #[serde(skip)]
+    pub frames: FrameUnits,
+}
+
+impl SplitRange {
+    /// Turn the values given in frames into seconds and milliseconds.
+    pub fn frames_to_seconds(&mut self, fps: f64) {
+        if self.frames.start {
+            self.start /= fps;
+        }
+        if self.frames.end {
+            self.end /= fps;
+        }
+        if self.frames.delay {
+            self.delay *= 1000.0 / fps;
+        }
+        self.frames = FrameUnits::default();
+    }
+}
+
+/// Which values of a split point or range were given in video frames (`2024f`) instead of
+/// seconds and milliseconds. They stay frame counts until the frame rate is known.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameUnits {
+    pub start: bool,
+    pub end: bool,
+    pub delay: bool,
+}
+
+impl FrameUnits {
+    pub fn any(&self) -> bool {
+        self.start || self.end || self.delay
+    }
+}
+
+/// Codec of the intermediate files the track is analyzed and edited in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntermediateFormat {
+    /// Lossless and compact, but slow to encode for many channels
+    #[default]
+    Flac,
+    /// 24-bit integer PCM: no encoding cost, several times the size of FLAC
+    PcmS24le,
+    /// 32-bit float PCM, for sources decoded to float such as AAC or Opus
+    PcmF32le,
+}
+
+impl IntermediateFormat {
+    /// The ffmpeg encoder writing the format.
+    pub fn codec(self) -> &'static str {
+        match self {
+            IntermediateFormat::Flac => "flac",
+            IntermediateFormat::PcmS24le => "pcm_s24le",
+            IntermediateFormat::PcmF32le => "pcm_f32le",
+        }
+    }
+
+    /// Extension of intermediate files. PCM goes into Wave64, which has no 4 GB limit.
+    pub fn extension(self) -> &'static str {
+        match self {
+            IntermediateFormat::Flac => "flac",
+            _ => "w64",
+        }
+    }
+}
+
+impl fmt::Display for IntermediateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.codec())
+    }
+}
+
+/// Strategy that picks the split point inside a `--split-range` (see `detector`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorKind {
+    /// Lowest momentary loudness (ffmpeg's ebur128)
+    #[default]
+    Ebur128,
+    /// Middle of the longest silence found by ffmpeg's silencedetect
+    Silencedetect,
+    /// Lowest RMS level over 100 ms windows
+    Rms,
+    /// Middle of the longest stretch without speech
+    Vad,
+    /// A user-provided program given in `--detector-command`
+    External,
+}
+
+impl DetectorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DetectorKind::Ebur128 => "ebur128",
+            DetectorKind::Silencedetect => "silencedetect",
+            DetectorKind::Rms => "rms",
+            DetectorKind::Vad => "vad",
+            DetectorKind::External => "external",
+        }
+    }
+}
+
+impl fmt::Display for DetectorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Media server asked to refresh the fixed file (see `media_server`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaServerKind {
+    Jellyfin,
+    Plex,
+}
+
+impl fmt::Display for MediaServerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaServerKind::Jellyfin => write!(f, "Jellyfin"),
+            MediaServerKind::Plex => write!(f, "Plex"),
+        }
+    }
 }
 
-fn parse_split(s: &str) -> Result<SplitPoint, String> {
+/// How the muxer treats negative timestamps, the modes of ffmpeg's `-avoid_negative_ts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AvoidNegativeTs {
+    Auto,
+    MakeZero,
+    MakeNonNegative,
+    Disabled,
+}
+
+impl AvoidNegativeTs {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AvoidNegativeTs::Auto => "auto",
+            AvoidNegativeTs::MakeZero => "make_zero",
+            AvoidNegativeTs::MakeNonNegative => "make_non_negative",
+            AvoidNegativeTs::Disabled => "disabled",
+        }
+    }
+}
+
+impl fmt::Display for AvoidNegativeTs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Selects the audio stream of input 0, either by absolute stream index or by position among the audio streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "RawStreamSpecifier", into = "RawStreamSpecifier")]
+pub enum StreamSpecifier {
+    /// Absolute stream index as reported by ffprobe (`6` or `0:6`)
+    Index(usize),
+    /// N-th audio stream, counting from zero (`a:1` or `0:a:1`)
+    Audio(usize),
+    /// The main audio stream, chosen by `audio_metadata::find_main_audio_stream` (`auto`)
+    Auto,
+}
+
+impl StreamSpecifier {
+    /// The ffmpeg `-map` argument selecting this stream from input 0. `Auto` has to be
+    /// resolved with `audio_metadata::resolve_stream` first and maps to the first audio
+    /// stream otherwise.
+    pub fn to_map(self) -> String {
+        match self {
+            StreamSpecifier::Index(index) => format!("0:{}", index),
+            StreamSpecifier::Audio(n) => format!("0:a:{}", n),
+            StreamSpecifier::Auto => "0:a:0".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for StreamSpecifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamSpecifier::Index(index) => write!(f, "{}", index),
+            StreamSpecifier::Audio(n) => write!(f, "a:{}", n),
+            StreamSpecifier::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Task files may give the stream as a bare number or as a specifier string.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum RawStreamSpecifier {
+    Index(usize),
+    Specifier(String),
+}
+
+impl TryFrom<RawStreamSpecifier> for StreamSpecifier {
+    type Error = String;
+
+    fn try_from(raw: RawStreamSpecifier) -> Result<Self, Self::Error> {
+        match raw {
+            RawStreamSpecifier::Index(index) => Ok(StreamSpecifier::Index(index)),
+            RawStreamSpecifier::Specifier(s) => parse_stream_specifier(&s),
+        }
+    }
+}
+
+impl From<StreamSpecifier> for RawStreamSpecifier {
+    fn from(spec: StreamSpecifier) -> Self {
+        match spec {
+            StreamSpecifier::Index(index) => RawStreamSpecifier::Index(index),
+            StreamSpecifier::Audio(_) | StreamSpecifier::Auto => {
+                RawStreamSpecifier::Specifier(spec.to_string())
+            }
+        }
+    }
+}
+
+/// The delay (in milliseconds) measured at one point in time, for deriving a linear drift.
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub struct SyncPoint {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub time: f64,
+    pub delay: f64,
+}
+
+/// An extra output with the whole corrected track shifted by a constant offset, for playback
+/// chains that need their own lipsync compensation.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct ProfileOutput {
+    /// Offset in milliseconds added on top of the plan (positive delays the audio)
+    pub offset: f64,
+    pub output: String,
+}
+
+/// A span over which the delay changes linearly (by time-stretching the audio) instead of in a single step.
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize)]
+pub struct DelayRamp {
+    #[serde(rename = "startTime", deserialize_with = "deserialize_time")]
+    pub start: f64,
+    #[serde(rename = "endTime", deserialize_with = "deserialize_time")]
+    pub end: f64,
+    /// Delay in milliseconds accumulated over the span (can be fractional or negative)
+    pub delay: f64,
+}
+
+/// Task files may give a time as a number of seconds or as a timecode string.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum RawTime {
+    Seconds(f64),
+    Text(String),
+}
+
+/// Deserialize a time given as seconds (`5025.2`) or as a timecode (`"1:23:45.2"`).
+pub fn deserialize_time<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f64, D::Error> {
+    match <RawTime as serde::Deserialize>::deserialize(deserializer)? {
+        RawTime::Seconds(seconds) => Ok(seconds),
+        RawTime::Text(text) => parse_timecode(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Seconds from plain seconds (`5025.2`), `MM:SS.mmm` (`83:45.2`) or `HH:MM:SS.mmm`
+/// (`1:23:45.2`).
+pub fn parse_timecode(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() > 3 {
+        return Err(format!(
+            "invalid time '{}', expected [HH:]MM:SS.mmm or seconds",
+            s
+        ));
+    }
+    let (last, leading) = parts.split_last().unwrap_or((&s, &[]));
+    let mut seconds = last
+        .parse::<f64>()
+        .map_err(|e| format!("invalid time '{}': {}", s, e))?;
+    if !leading.is_empty() && !(0.0..60.0).contains(&seconds) {
+        return Err(format!("seconds must be between 0 and 60 in '{}'", s));
+    }
+    for (i, part) in leading.iter().rev().enumerate() {
+        let value = part
+            .parse::<u64>()
+            .map_err(|_| format!("invalid time '{}', expected [HH:]MM:SS.mmm or seconds", s))?;
+        // Minutes below hours stay below 60; the leading part may be as large as it likes
+        if i == 0 && leading.len() == 2 && value >= 60 {
+            return Err(format!("minutes must be below 60 in '{}'", s));
+        }
+        seconds += value as f64 * 60f64.powi(i as i32 + 1);
+    }
+    Ok(seconds)
+}
+
+/// A parsed value and whether it was given in frames.
+type FrameValue = (f64, bool);
+
+/// A time in seconds or a timecode, or a number of frames (`2024f`). The flag is true for frames.
+fn parse_time_or_frames(s: &str) -> Result<FrameValue, String> {
+    match s.trim().strip_suffix('f') {
+        Some(frames) => parse_frames(frames, s).map(|frames| (frames, true)),
+        None => parse_timecode(s).map(|seconds| (seconds, false)),
+    }
+}
+
+/// A delay in milliseconds, or a number of frames (`+2f`). The flag is true for frames.
+fn parse_delay_or_frames(s: &str) -> Result<FrameValue, String> {
+    let (number, frames) = match s.trim().strip_suffix('f') {
+        Some(frames) => (frames, true),
+        None => (s, false),
+    };
+    let value = number
+        .parse::<f64>()
+        .map_err(|e| format!("invalid delay '{}': {}", s, e))?;
+    Ok((value, frames))
+}
+
+fn parse_frames(frames: &str, s: &str) -> Result<f64, String> {
+    let frames = frames
+        .parse::<f64>()
+        .map_err(|e| format!("invalid frame count '{}': {}", s, e))?;
+    if !frames.is_finite() || frames < 0.0 {
+        return Err(format!("frame count must not be negative in '{}'", s));
+    }
+    Ok(frames)
+}
+
+/// Split the `:`-separated parts of `<start>:<end>` into two times, either of which may be a
+/// timecode or a frame count. When both times use the same notation, the parts are split in
+/// half.
+fn parse_time_pair(parts: &[&str], s: &str) -> Result<(FrameValue, FrameValue), String> {
+    let candidates: Vec<(usize, FrameValue, FrameValue)> = (1..parts.len())
+        .filter_map(|at| {
+            let start = parse_time_or_frames(&parts[..at].join(":")).ok()?;
+            let end = parse_time_or_frames(&parts[at..].join(":")).ok()?;
+            Some((at, start, end))
+        })
+        .collect();
+    let chosen = match candidates[..] {
+        [] => None,
+        [only] => Some(only),
+        _ => candidates
+            .iter()
+            .copied()
+            .find(|(at, _, _)| *at * 2 == parts.len()),
+    };
+    match chosen {
+        Some((_, start, end)) => Ok((start, end)),
+        None if candidates.is_empty() => Err(format!("invalid start or end time in '{}'", s)),
+        None => Err(format!(
+            "ambiguous times in '{}'; write the start and end time in the same notation",
+            s
+        )),
+    }
+}
+
+/// Split an optional trailing `:label=<text>` off a split or range argument. The text may be
+/// wrapped in double quotes.
+fn split_label(s: &str) -> Result<(&str, Option<String>), String> {
+    let Some((value, label)) = s.split_once(":label=") else {
+        return Ok((s, None));
+    };
+    let label = label
+        .strip_prefix('"')
+        .and_then(|l| l.strip_suffix('"'))
+        .unwrap_or(label);
+    if label.is_empty() {
+        return Err(format!("empty label in '{}'", s));
+    }
+    Ok((value, Some(label.to_string())))
+}
+
+pub fn parse_split(s: &str) -> Result<SplitPoint, String> {
+    let (s, label) = split_label(s)?;
     let pos = s
         .rfind(':')
         .ok_or_else(|| format!("invalid format: '{}', expected <time>:<delay>", s))?;
-    let time = s[..pos]
-        .parse()
-        .map_err(|e| format!("invalid time in '{}': {}", s, e))?;
-    let delay = s[pos + 1..]
-        .parse()
-        .map_err(|e| format!("invalid delay in '{}': {}", s, e))?;
-    Ok(SplitPoint { time, delay })
+    let (time, time_frames) =
+        parse_time_or_frames(&s[..pos]).map_err(|e| format!("{} in '{}'", e, s))?;
+    let (delay, delay_frames) =
+        parse_delay_or_frames(&s[pos + 1..]).map_err(|e| format!("{} in '{}'", e, s))?;
+    Ok(SplitPoint {
+        time,
+        delay,
+        label,
+        frames: FrameUnits {
+            start: time_frames,
+            end: false,
+            delay: delay_frames,
+        },
+    })
 }
 
-fn parse_split_range(s: &str) -> Result<SplitRange, String> {
+pub fn parse_split_range(s: &str) -> Result<SplitRange, String> {
+    let (s, label) = split_label(s)?;
     let parts: Vec<&str> = s.split(':').collect();
-    if parts.len() != 3 {
+    let Some((delay, times)) = parts.split_last().filter(|(_, times)| times.len() >= 2) else {
         return Err(format!(
             "invalid format: '{}', expected <start_time>:<end_time>:<delay>",
             s
         ));
+    };
+    let ((start, start_frames), (end, end_frames)) = parse_time_pair(times, s)?;
+    let (delay, delay_frames) =
+        parse_delay_or_frames(delay).map_err(|e| format!("{} in '{}'", e, s))?;
+    // Times in different units are only ordered once the frame rate is known
+    if start_frames == end_frames && start >= end {
+        return Err(format!("start time must be less than end time in '{}'", s));
     }
-    let start = parts[0]
-        .parse()
-        .map_err(|e| format!("invalid start time in '{}': {}", s, e))?;
-    let end = parts[1]
+    Ok(SplitRange {
+        start,
+        end,
+        delay,
+        label,
+        frames: FrameUnits {
+            start: start_frames,
+            end: end_frames,
+            delay: delay_frames,
+        },
+    })
+}
+
+fn parse_time_range(s: &str) -> Result<(f64, f64), String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() < 2 {
+        return Err(format!(
+            "invalid format: '{}', expected <start_time>:<end_time>",
+            s
+        ));
+    }
+    let ((start, start_frames), (end, end_frames)) = parse_time_pair(&parts, s)?;
+    if start_frames || end_frames {
+        return Err(format!("frame counts are not supported here: '{}'", s));
+    }
+    if start >= end {
+        return Err(format!("start time must be less than end time in '{}'", s));
+    }
+    Ok((start, end))
+}
+
+pub fn parse_ramp(s: &str) -> Result<DelayRamp, String> {
+    let range = parse_split_range(s)?;
+    if range.label.is_some() {
+        return Err(format!("labels are not supported on ramps: '{}'", s));
+    }
+    if range.frames.any() {
+        return Err(format!("frame counts are not supported on ramps: '{}'", s));
+    }
+    Ok(DelayRamp {
+        start: range.start,
+        end: range.end,
+        delay: range.delay,
+    })
+}
+
+/// Seconds, optionally with an `s` or `m` unit (e.g. `30`, `30s`, `2m`).
+fn parse_sample_length(s: &str) -> Result<f64, String> {
+    let (number, scale) = if let Some(minutes) = s.strip_suffix('m') {
+        (minutes, 60.0)
+    } else {
+        (s.strip_suffix('s').unwrap_or(s), 1.0)
+    };
+    let seconds = number
+        .parse::<f64>()
+        .map_err(|e| format!("invalid duration '{}': {}", s, e))?
+        * scale;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        return Err(format!("duration must be positive in '{}'", s));
+    }
+    Ok(seconds)
+}
+
+/// A duration in milliseconds, from `15ms`, `0.015s` or a plain number of milliseconds.
+fn parse_crossfade(s: &str) -> Result<f64, String> {
+    let (number, scale) = if let Some(millis) = s.strip_suffix("ms") {
+        (millis, 1.0)
+    } else if let Some(seconds) = s.strip_suffix('s') {
+        (seconds, 1000.0)
+    } else {
+        (s, 1.0)
+    };
+    let millis = number
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid duration '{}': {}", s, e))?
+        * scale;
+    if !millis.is_finite() || millis < 0.0 {
+        return Err(format!("duration must not be negative in '{}'", s));
+    }
+    Ok(millis)
+}
+
+/// A number of minutes that is finite and not negative.
+fn parse_stall_timeout(s: &str) -> Result<f64, String> {
+    let minutes = s
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid timeout '{}': {}", s, e))?;
+    if !minutes.is_finite() || minutes < 0.0 {
+        return Err(format!(
+            "timeout must be a non-negative number of minutes, got '{}'",
+            s
+        ));
+    }
+    Ok(minutes)
+}
+
+/// A number of days that is finite and not negative.
+fn parse_days(s: &str) -> Result<f64, String> {
+    let days = s
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid number of days '{}': {}", s, e))?;
+    if !days.is_finite() || days < 0.0 {
+        return Err(format!("days must not be negative, got '{}'", s));
+    }
+    Ok(days)
+}
+
+/// A size in bytes, optionally with a binary unit: `K`, `M`, `G` or `T` (e.g. `500M`, `20G`).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let trimmed = s.trim().trim_end_matches(['B', 'b']);
+    let (number, scale) = match trimmed.char_indices().last() {
+        Some((i, unit)) if unit.is_ascii_alphabetic() => {
+            let power = match unit.to_ascii_uppercase() {
+                'K' => 1,
+                'M' => 2,
+                'G' => 3,
+                'T' => 4,
+                _ => return Err(format!("unknown unit in size '{}' (use K, M, G or T)", s)),
+            };
+            (&trimmed[..i], 1024f64.powi(power))
+        }
+        _ => (trimmed, 1.0),
+    };
+    let bytes = number
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid size '{}': {}", s, e))?
+        * scale;
+    if !bytes.is_finite() || bytes < 0.0 {
+        return Err(format!("size must not be negative, got '{}'", s));
+    }
+    Ok(bytes as u64)
+}
+
+/// A confidence score between 0 and 1.
+fn parse_confidence(s: &str) -> Result<f64, String> {
+    let score = s
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid confidence '{}': {}", s, e))?;
+    if !(0.0..=1.0).contains(&score) {
+        return Err(format!("confidence must be between 0 and 1, got '{}'", s));
+    }
+    Ok(score)
+}
+
+fn parse_profile_output(s: &str) -> Result<ProfileOutput, String> {
+    let (offset, output) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid format: '{}', expected <offset_ms>:<file>", s))?;
+    let offset = offset
         .parse()
-        .map_err(|e| format!("invalid end time in '{}': {}", s, e))?;
-    let delay = parts[2]
+        .map_err(|e| format!("invalid offset in '{}': {}", s, e))?;
+    if output.is_empty() {
+        return Err(format!("missing output file in '{}'", s));
+    }
+    Ok(ProfileOutput {
+        offset,
+        output: output.to_string(),
+    })
+}
+
+fn parse_sync_point(s: &str) -> Result<SyncPoint, String> {
+    let (time, delay) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("invalid format: '{}', expected <time>:<delay>", s))?;
+    let time = parse_timecode(time).map_err(|e| format!("{} in '{}'", e, s))?;
+    let delay = delay
         .parse()
         .map_err(|e| format!("invalid delay in '{}': {}", s, e))?;
-    if start >= end {
-        return Err(format!("start time must be less than end time in '{}'", s));
+    Ok(SyncPoint { time, delay })
+}
+
+/// A positive number, or a fraction of two (e.g. `25/23.976`).
+fn parse_ratio(s: &str) -> Result<f64, String> {
+    let ratio = match s.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: f64 = numerator
+                .parse()
+                .map_err(|e| format!("invalid numerator in '{}': {}", s, e))?;
+            let denominator: f64 = denominator
+                .parse()
+                .map_err(|e| format!("invalid denominator in '{}': {}", s, e))?;
+            numerator / denominator
+        }
+        None => s
+            .parse()
+            .map_err(|e| format!("invalid ratio '{}': {}", s, e))?,
+    };
+    if !ratio.is_finite() || ratio <= 0.0 {
+        return Err(format!("ratio must be positive in '{}'", s));
+    }
+    Ok(ratio)
+}
+
+fn parse_intermediate_format(s: &str) -> Result<IntermediateFormat, String> {
+    match s {
+        "flac" => Ok(IntermediateFormat::Flac),
+        "pcm_s24le" | "s24" => Ok(IntermediateFormat::PcmS24le),
+        "pcm_f32le" | "f32" => Ok(IntermediateFormat::PcmF32le),
+        _ => Err(format!(
+            "invalid intermediate format '{}' (expected flac, pcm_s24le or pcm_f32le)",
+            s
+        )),
+    }
+}
+
+fn parse_detector(s: &str) -> Result<DetectorKind, String> {
+    match s.replace('-', "_").as_str() {
+        "ebur128" => Ok(DetectorKind::Ebur128),
+        "silencedetect" => Ok(DetectorKind::Silencedetect),
+        "rms" => Ok(DetectorKind::Rms),
+        "vad" => Ok(DetectorKind::Vad),
+        "external" | "external_command" => Ok(DetectorKind::External),
+        _ => Err(format!(
+            "invalid detector '{}' (expected ebur128, silencedetect, rms, vad or external)",
+            s
+        )),
+    }
+}
+
+fn parse_media_server(s: &str) -> Result<MediaServerKind, String> {
+    match s.to_lowercase().as_str() {
+        "jellyfin" => Ok(MediaServerKind::Jellyfin),
+        "plex" => Ok(MediaServerKind::Plex),
+        _ => Err(format!(
+            "invalid media server '{}' (expected jellyfin or plex)",
+            s
+        )),
+    }
+}
+
+fn parse_avoid_negative_ts(s: &str) -> Result<AvoidNegativeTs, String> {
+    match s.replace('-', "_").as_str() {
+        "auto" => Ok(AvoidNegativeTs::Auto),
+        "make_zero" => Ok(AvoidNegativeTs::MakeZero),
+        "make_non_negative" => Ok(AvoidNegativeTs::MakeNonNegative),
+        "disabled" => Ok(AvoidNegativeTs::Disabled),
+        _ => Err(format!(
+            "invalid mode '{}' (expected auto, make_zero, make_non_negative or disabled)",
+            s
+        )),
+    }
+}
+
+fn parse_stream_specifier(s: &str) -> Result<StreamSpecifier, String> {
+    let spec = s.strip_prefix("0:").unwrap_or(s);
+    if spec.eq_ignore_ascii_case("auto") {
+        Ok(StreamSpecifier::Auto)
+    } else if let Some(n) = spec.strip_prefix("a:") {
+        n.parse()
+            .map(StreamSpecifier::Audio)
+            .map_err(|e| format!("invalid audio stream number in '{}': {}", s, e))
+    } else {
+        spec.parse().map(StreamSpecifier::Index).map_err(|e| {
+            format!(
+                "invalid stream specifier '{}': {} (expected <index>, 0:<index>, a:<n>, 0:a:<n> or auto)",
+                s, e
+            )
+        })
     }
-    Ok(SplitRange { start, end, delay })
 }
@@ -0,0 +1,404 @@
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+use tracing::warn;
+
+use crate::audio_metadata::get_file_duration;
+use crate::audio_processing::{RunOptions, str_args};
+use crate::ffmpeg::{check_dependency, ffmpeg_output, ffprobe_executable, run_child, run_ffmpeg};
+use crate::i18n::{Msg, tf};
+use crate::util::path_to_str;
+
+/// Extensions whose container (Matroska) can hold any stream ffmpeg can copy.
+const UNIVERSAL_EXTENSIONS: &[&str] = &["mkv", "mka", "mks"];
+
+/// A stream of the input that the output container cannot hold.
+#[derive(Debug)]
+pub struct IncompatibleStream {
+    pub index: usize,
+    pub codec_type: String,
+    pub codec: String,
+    pub reason: String,
+}
+
+/// List (index, codec_type, codec_name) for every stream of the input file.
+fn list_streams(input: &str) -> Result<Vec<(usize, String, String)>> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "stream=index,codec_type,codec_name",
+            "-of",
+            "csv=p=0",
+            input,
+        ])
+        .output()?;
+    let streams_info = String::from_utf8_lossy(&output.stdout);
+    let mut streams = Vec::new();
+    for line in streams_info.lines() {
+        let parts: Vec<_> = line.split(',').collect();
+        if parts.len() >= 3
+            && let Ok(index) = parts[0].parse::<usize>()
+        {
+            streams.push((index, parts[2].to_string(), parts[1].to_string()));
+        }
+    }
+    Ok(streams)
+}
+
+/// Check a freshly muxed `output` before it replaces anything: ffprobe has to read it, find
+/// `expected_streams` streams and a duration.
+pub fn verify_muxed_output(output: &str, expected_streams: usize) -> Result<()> {
+    let streams = list_streams(output)?.len();
+    if streams != expected_streams {
+        anyhow::bail!(
+            "it holds {} streams instead of {}",
+            streams,
+            expected_streams
+        );
+    }
+    match get_file_duration(output) {
+        Ok(duration) if duration > 0.0 => Ok(()),
+        Ok(_) => anyhow::bail!("it has no duration"),
+        Err(e) => anyhow::bail!("ffprobe cannot read it: {:#}", e),
+    }
+}
+
+/// Number of streams of a media file.
+pub fn count_streams(path: &str) -> Result<usize> {
+    Ok(list_streams(path)?.len())
+}
+
+/// Check every stream of `input` against the muxer chosen by the extension of `output`.
+/// Each stream is stream-copied into a short trial file next to the other temp files,
+/// so the check reflects exactly what the installed ffmpeg supports.
+pub fn find_incompatible_streams(
+    input: &str,
+    output: &str,
+    tmpdir: &Path,
+) -> Result<Vec<IncompatibleStream>> {
+    let extension = match Path::new(output).extension().and_then(|e| e.to_str()) {
+        Some(ext) => ext.to_ascii_lowercase(),
+        None => anyhow::bail!(
+            "Output file '{}' has no extension, so no container can be chosen.",
+            output
+        ),
+    };
+    if UNIVERSAL_EXTENSIONS.contains(&extension.as_str()) {
+        return Ok(Vec::new());
+    }
+
+    let trial_path = tmpdir.join(format!("container_check.{}", extension));
+    let trial = path_to_str(trial_path.as_path())?;
+    let mut incompatible = Vec::new();
+    for (index, codec_type, codec) in list_streams(input)? {
+        let result = ffmpeg_output(
+            &[
+                "-v",
+                "error",
+                "-y",
+                "-i",
+                input,
+                "-map",
+                &format!("0:{}", index),
+                "-c",
+                "copy",
+                "-t",
+                "0.1",
+                trial,
+            ],
+            false,
+        )?;
+        if !result.status.success() {
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            incompatible.push(IncompatibleStream {
+                index,
+                codec_type,
+                codec,
+                reason: stderr
+                    .lines()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("muxer rejected the stream")
+                    .trim()
+                    .to_string(),
+            });
+        }
+    }
+    let _ = std::fs::remove_file(&trial_path);
+    Ok(incompatible)
+}
+
+/// Pick a standalone audio container (file extension) suited to `codec`.
+/// Codecs without a natural elementary or audio-only container go into Matroska audio.
+pub fn audio_extension_for_codec(codec: &str) -> &'static str {
+    match codec {
+        "aac" | "alac" => "m4a",
+        "ac3" => "ac3",
+        "eac3" => "eac3",
+        "mp3" => "mp3",
+        "mp2" => "mp2",
+        "opus" => "opus",
+        "vorbis" => "ogg",
+        "flac" => "flac",
+        "pcm_s16le" | "pcm_s24le" | "pcm_s32le" | "pcm_f32le" | "pcm_u8" => "wav",
+        _ => "mka", // dts, truehd and anything else
+    }
+}
+
+/// Default path for a standalone export of the corrected track, next to the output file.
+pub fn default_export_path(output: &str, language: &str, stream: usize, codec: &str) -> String {
+    let label = if language.is_empty() {
+        format!("stream{}", stream)
+    } else {
+        language.to_string()
+    };
+    Path::new(output)
+        .with_extension(format!("{}.{}", label, audio_extension_for_codec(codec)))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Stream-copy the encoded track into a standalone audio file.
+/// If `path` has no extension, one matching the codec is appended. If the container
+/// rejects the codec, an automatically chosen container falls back to Matroska audio,
+/// while an explicitly requested one is reported as an error.
+pub fn export_audio_track(
+    encoded: &Path,
+    path: &str,
+    codec: &str,
+    run: &RunOptions,
+) -> Result<String> {
+    let explicit = Path::new(path).extension().is_some();
+    let target = if explicit {
+        path.to_string()
+    } else {
+        format!("{}.{}", path, audio_extension_for_codec(codec))
+    };
+    let copy = |target: &str| {
+        run_ffmpeg(
+            &str_args(&run.output_args(&["-y", "-i", path_to_str(encoded)?, "-c", "copy", target])),
+            run.debug,
+        )
+        .map_err(anyhow::Error::from)
+    };
+    match copy(&target) {
+        Ok(()) => Ok(target),
+        Err(e) if explicit => {
+            let _ = std::fs::remove_file(&target);
+            anyhow::bail!(
+                "Could not export {} audio to '{}' ({}). Try the '.{}' extension instead.",
+                codec,
+                target,
+                e,
+                audio_extension_for_codec(codec)
+            )
+        }
+        Err(_) => {
+            let _ = std::fs::remove_file(&target);
+            let fallback = Path::new(&target)
+                .with_extension("mka")
+                .to_string_lossy()
+                .to_string();
+            warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::ContainerRejected,
+                    &[
+                        ("target", target.to_string()),
+                        ("codec", codec.to_string()),
+                        ("fallback", fallback.to_string())
+                    ]
+                )
+            );
+            copy(&fallback)?;
+            Ok(fallback)
+        }
+    }
+}
+
+/// Per-track statistics tags written by mkvmerge. After a stream copy they still describe the original track.
+const MATROSKA_STATISTICS_TAGS: &[&str] =
+    &["BPS", "DURATION", "NUMBER_OF_FRAMES", "NUMBER_OF_BYTES"];
+
+/// Refresh the container duration and track statistics of `output` after the edited audio stream
+/// (`audio_stream_idx`, counted among the audio streams) was remuxed.
+/// Matroska files are updated in place with mkvpropedit when it is installed. Otherwise the file is
+/// stream-copied once more with the stale statistics tags of the edited track cleared, so the muxer
+/// recomputes the duration. Returns the tool that was used.
+pub fn refresh_container_stats(
+    output: &str,
+    audio_stream_idx: usize,
+    run: &RunOptions,
+) -> Result<&'static str> {
+    let extension = Path::new(output)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let matroska = UNIVERSAL_EXTENSIONS.contains(&extension.as_str()) || extension == "webm";
+
+    if matroska && check_dependency("mkvpropedit").is_ok() {
+        let result = run_child(
+            "mkvpropedit",
+            &[
+                output.to_string(),
+                "--add-track-statistics-tags".to_string(),
+            ],
+            run.debug,
+        )?;
+        if !result.status.success() {
+            // mkvpropedit reports errors on stdout
+            let message = String::from_utf8_lossy(&result.stdout);
+            anyhow::bail!(
+                "mkvpropedit failed: {}",
+                message
+                    .lines()
+                    .rev()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("")
+                    .trim()
+            );
+        }
+        return Ok("mkvpropedit");
+    }
+
+    let refreshed = Path::new(output).with_extension(format!("refresh.{}", extension));
+    let refreshed_str = path_to_str(refreshed.as_path())?;
+    let metadata_spec = format!("-metadata:s:a:{}", audio_stream_idx);
+    let mut args = vec![
+        "-y".to_string(),
+        "-i".to_string(),
+        output.to_string(),
+        "-map".to_string(),
+        "0".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+    ];
+    if matroska {
+        for tag in MATROSKA_STATISTICS_TAGS {
+            args.push(metadata_spec.clone());
+            args.push(format!("{}=", tag));
+        }
+    }
+    args.push(refreshed_str.to_string());
+    if let Err(e) = run_ffmpeg(&str_args(&run.output_args(&args)), run.debug) {
+        let _ = std::fs::remove_file(&refreshed);
+        return Err(e.into());
+    }
+    std::fs::rename(&refreshed, output)?;
+    Ok("ffmpeg")
+}
+
+/// Streams whose start moved by more than this many milliseconds against the reference stream
+/// count as shifted by the muxer.
+const TIMESTAMP_SHIFT_TOLERANCE_MS: f64 = 5.0;
+
+/// A stream whose start moved against the other streams while remuxing.
+#[derive(Debug, Clone)]
+pub struct TimestampShift {
+    pub index: usize,
+    pub codec_type: String,
+    /// Start of the stream relative to the reference stream in the input, in seconds
+    pub input_start: f64,
+    /// Start of the stream relative to the reference stream in the output, in seconds
+    pub output_start: f64,
+}
+
+impl TimestampShift {
+    pub fn shift_ms(&self) -> f64 {
+        (self.output_start - self.input_start) * 1000.0
+    }
+}
+
+/// Presentation time of the first packet of a stream.
+fn first_packet_pts(path: &str, index: usize) -> Result<Option<f64>> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            &index.to_string(),
+            "-show_entries",
+            "packet=pts_time",
+            "-read_intervals",
+            "%+#1",
+            "-of",
+            "csv=p=0",
+            path,
+        ])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.trim().trim_end_matches(',').parse::<f64>().ok()))
+}
+
+/// (index, codec_type, start) of every stream, where the start is the PTS of its first packet,
+/// or the stream start time when the packets carry none.
+fn probe_stream_starts(path: &str) -> Result<Vec<(usize, String, Option<f64>)>> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "stream=index,codec_type,start_time",
+            "-of",
+            "csv=p=0",
+            path,
+        ])
+        .output()?;
+    let mut streams = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts: Vec<_> = line.split(',').collect();
+        if parts.len() >= 3
+            && let Ok(index) = parts[0].parse::<usize>()
+        {
+            let start = match first_packet_pts(path, index)? {
+                Some(pts) => Some(pts),
+                None => parts[2].parse::<f64>().ok(),
+            };
+            streams.push((index, parts[1].to_string(), start));
+        }
+    }
+    Ok(streams)
+}
+
+/// Compare the stream starts of the remuxed `output` with those of `input`, which has the same
+/// streams in the same order (followed by any streams the remux added). Starts are measured from the first video stream (or the first
+/// stream), so moving the whole file does not count; a stream moved against the others does,
+/// as ffmpeg's stream copy can do when it shifts negative timestamps.
+pub fn find_timestamp_shifts(input: &str, output: &str) -> Result<Vec<TimestampShift>> {
+    let before = probe_stream_starts(input)?;
+    let after = probe_stream_starts(output)?;
+    if after.len() < before.len() {
+        anyhow::bail!(
+            "The output has {} streams, the input {}.",
+            after.len(),
+            before.len()
+        );
+    }
+    let reference = |streams: &[(usize, String, Option<f64>)]| {
+        streams
+            .iter()
+            .find(|(_, codec_type, start)| codec_type == "video" && start.is_some())
+            .or_else(|| streams.iter().find(|(_, _, start)| start.is_some()))
+            .and_then(|(_, _, start)| *start)
+            .unwrap_or(0.0)
+    };
+    let (input_reference, output_reference) = (reference(&before), reference(&after));
+    let mut shifts = Vec::new();
+    for ((index, codec_type, input_start), (_, _, output_start)) in before.iter().zip(&after) {
+        if let (Some(input_start), Some(output_start)) = (input_start, output_start) {
+            let shift = TimestampShift {
+                index: *index,
+                codec_type: codec_type.clone(),
+                input_start: input_start - input_reference,
+                output_start: output_start - output_reference,
+            };
+            if shift.shift_ms().abs() > TIMESTAMP_SHIFT_TOLERANCE_MS {
+                shifts.push(shift);
+            }
+        }
+    }
+    Ok(shifts)
+}
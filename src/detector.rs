@@ -0,0 +1,441 @@
+use std::path::Path;
+use std::process::Command;
+
+use crate::analysis::{ANALYSIS_SAMPLE_RATE, decode_pcm_mono, decode_pcm_mono_at};
+use crate::audio_metadata::get_sample_rate;
+use crate::audio_processing::{QuietestPointResult, dip_confidence, find_quietest_point};
+use crate::cli::DetectorKind;
+use crate::ffmpeg::{FFmpegError, ffmpeg_output, format_seconds};
+use crate::silence::parse_silencedetect;
+use crate::vad::{in_speech, speech_spans};
+
+/// Noise level (dBFS) below which `silencedetect` counts audio as silent.
+const SILENCE_NOISE_DB: f64 = -50.0;
+/// Shortest silence (in seconds) the `silencedetect` detector considers.
+const SILENCE_MIN_SECONDS: f64 = 0.1;
+/// Length of one RMS window of the `rms` detector.
+const RMS_WINDOW_SECONDS: f64 = 0.1;
+/// Step between two RMS windows.
+const RMS_HOP_SECONDS: f64 = 0.01;
+/// How far (in seconds) a split point may move to reach a zero crossing.
+const ZERO_CROSSING_RADIUS_SECONDS: f64 = 0.005;
+
+/// A strategy for picking the split point inside a `--split-range`.
+pub trait Detector {
+    /// Name of the strategy, as given to `--detector`.
+    fn name(&self) -> &str;
+
+    /// Pick the split point between `start` and `end` (in seconds) of `audio_path`.
+    fn find_split(
+        &self,
+        audio_path: &Path,
+        start: f64,
+        end: f64,
+        debug: bool,
+    ) -> Result<QuietestPointResult, FFmpegError>;
+}
+
+/// Pick the split point between `start` and `end` with `detector`, then with `zero_crossing`
+/// move it to the nearest zero crossing within a few milliseconds, so the cut does not click.
+pub fn find_split_point(
+    detector: &dyn Detector,
+    audio_path: &Path,
+    start: f64,
+    end: f64,
+    zero_crossing: bool,
+    debug: bool,
+) -> Result<QuietestPointResult, FFmpegError> {
+    let mut result = detector.find_split(audio_path, start, end, debug)?;
+    if zero_crossing
+        && let Some(time) = nearest_zero_crossing(audio_path, result.time)?
+        && (start..=end).contains(&time)
+        && time != result.time
+    {
+        result.snapped_from = Some(result.time);
+        result.time = time;
+    }
+    Ok(result)
+}
+
+/// Time of the zero crossing of the (mono-mixed) waveform closest to `time`, within
+/// `ZERO_CROSSING_RADIUS_SECONDS`. The sample of the crossing nearer to zero is taken.
+pub fn nearest_zero_crossing(audio_path: &Path, time: f64) -> Result<Option<f64>, FFmpegError> {
+    let audio = path_str(audio_path, "nearest_zero_crossing")?;
+    let sample_rate = get_sample_rate(audio).map_err(analysis_error("nearest_zero_crossing"))?;
+    let window_start = (time - ZERO_CROSSING_RADIUS_SECONDS).max(0.0);
+    let samples = decode_pcm_mono_at(
+        audio,
+        "0:a:0",
+        window_start,
+        time + ZERO_CROSSING_RADIUS_SECONDS - window_start,
+        sample_rate,
+    )?;
+    let center = (time - window_start) * sample_rate as f64;
+    let crossing = samples
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] == 0.0 || (pair[0] > 0.0) != (pair[1] > 0.0))
+        .map(|(i, pair)| {
+            if pair[1].abs() < pair[0].abs() {
+                i + 1
+            } else {
+                i
+            }
+        })
+        .min_by(|a, b| {
+            (*a as f64 - center)
+                .abs()
+                .total_cmp(&(*b as f64 - center).abs())
+        });
+    Ok(crossing.map(|i| window_start + i as f64 / sample_rate as f64))
+}
+
+/// Build the detector selected with `--detector`. `silence_threshold` and `max_peak` apply to
+/// `ebur128` (and the threshold as a dBFS floor to `rms`), `avoid_speech` to both; `command` is
+/// the program run by `external`.
+pub fn build_detector(
+    kind: DetectorKind,
+    silence_threshold: f64,
+    max_peak: Option<f64>,
+    avoid_speech: bool,
+    command: Option<&str>,
+) -> Result<Box<dyn Detector>, FFmpegError> {
+    Ok(match kind {
+        DetectorKind::Ebur128 => Box::new(Ebur128Detector {
+            silence_threshold,
+            max_peak,
+            avoid_speech,
+        }),
+        DetectorKind::Silencedetect => Box::new(SilenceDetector),
+        DetectorKind::Rms => Box::new(RmsDetector {
+            floor_db: silence_threshold,
+            avoid_speech,
+        }),
+        DetectorKind::Vad => Box::new(VadDetector),
+        DetectorKind::External => Box::new(ExternalDetector {
+            argv: parse_detector_command(command.unwrap_or_default())?,
+        }),
+    })
+}
+
+/// Split `--detector-command` into the program and its arguments, with shell-style quoting.
+pub fn parse_detector_command(command: &str) -> Result<Vec<String>, FFmpegError> {
+    let argv = shell_words::split(command).map_err(|e| {
+        FFmpegError::InvalidDetector(format!("Invalid --detector-command '{}': {}", command, e))
+    })?;
+    if argv.is_empty() {
+        return Err(FFmpegError::InvalidDetector(
+            "--detector external needs a program in --detector-command.".to_string(),
+        ));
+    }
+    Ok(argv)
+}
+
+fn failed(detector: &str, message: String) -> FFmpegError {
+    FFmpegError::DetectorFailed(detector.to_string(), message)
+}
+
+/// Pass on the ffmpeg error behind a failed analysis step; anything else fails the detector.
+fn analysis_error(detector: &str) -> impl Fn(anyhow::Error) -> FFmpegError + '_ {
+    move |e| {
+        e.downcast::<FFmpegError>()
+            .unwrap_or_else(|e| failed(detector, format!("{:#}", e)))
+    }
+}
+
+fn path_str<'a>(audio_path: &'a Path, context: &str) -> Result<&'a str, FFmpegError> {
+    audio_path
+        .to_str()
+        .ok_or_else(|| failed(context, "Invalid audio path".to_string()))
+}
+
+/// The point with the lowest momentary loudness (ffmpeg's `ebur128`), see `find_quietest_point`.
+pub struct Ebur128Detector {
+    pub silence_threshold: f64,
+    pub max_peak: Option<f64>,
+    /// Skip points inside speech
+    pub avoid_speech: bool,
+}
+
+impl Detector for Ebur128Detector {
+    fn name(&self) -> &str {
+        "ebur128"
+    }
+
+    fn find_split(
+        &self,
+        audio_path: &Path,
+        start: f64,
+        end: f64,
+        debug: bool,
+    ) -> Result<QuietestPointResult, FFmpegError> {
+        find_quietest_point(
+            audio_path,
+            start,
+            end,
+            self.silence_threshold,
+            self.max_peak,
+            self.avoid_speech,
+            debug,
+        )
+    }
+}
+
+/// The middle of the longest silence `silencedetect` finds in the range.
+pub struct SilenceDetector;
+
+impl Detector for SilenceDetector {
+    fn name(&self) -> &str {
+        "silencedetect"
+    }
+
+    fn find_split(
+        &self,
+        audio_path: &Path,
+        start: f64,
+        end: f64,
+        debug: bool,
+    ) -> Result<QuietestPointResult, FFmpegError> {
+        let filter = format!(
+            "silencedetect=noise={}dB:d={}",
+            SILENCE_NOISE_DB, SILENCE_MIN_SECONDS
+        );
+        let output = ffmpeg_output(
+            &[
+                "-ss",
+                &format_seconds(start),
+                "-t",
+                &format_seconds(end - start),
+                "-i",
+                path_str(audio_path, "silencedetect")?,
+                "-af",
+                &filter,
+                "-f",
+                "null",
+                "-",
+            ],
+            false,
+        )?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if !output.status.success() {
+            return Err(failed("silencedetect", output.last_error_line()));
+        }
+        // silencedetect reports times from the start of the range
+        let silences = parse_silencedetect(&stderr, Some(end - start))
+            .map_err(|e| failed("silencedetect", e.to_string()))?;
+        let longest = silences
+            .iter()
+            .max_by(|a, b| a.duration().total_cmp(&b.duration()))
+            .ok_or_else(|| {
+                FFmpegError::NoAudiblePoint(format!(
+                    "No silence of at least {:.1}s below {:.0} dB in range {:.3}s - {:.3}s.",
+                    SILENCE_MIN_SECONDS, SILENCE_NOISE_DB, start, end
+                ))
+            })?;
+        Ok(QuietestPointResult {
+            time: start + (longest.start + longest.end) / 2.0,
+            loudness: None,
+            peak: None,
+            snapped_from: None,
+            // A silence several times the minimum length is an unmistakable pause
+            confidence: Some((longest.duration() / (4.0 * SILENCE_MIN_SECONDS)).clamp(0.0, 1.0)),
+            debug_output: debug.then(|| {
+                format!(
+                    "\n--- FFMPEG STDERR for silencedetect ---\n{}\n--- END FFMPEG STDERR ---",
+                    stderr
+                )
+            }),
+        })
+    }
+}
+
+/// The centre of the window with the lowest RMS level above `floor_db` (so digital silence,
+/// which may be an edit, is not picked). With `avoid_speech`, windows inside speech are skipped.
+pub struct RmsDetector {
+    pub floor_db: f64,
+    pub avoid_speech: bool,
+}
+
+impl Detector for RmsDetector {
+    fn name(&self) -> &str {
+        "rms"
+    }
+
+    fn find_split(
+        &self,
+        audio_path: &Path,
+        start: f64,
+        end: f64,
+        _debug: bool,
+    ) -> Result<QuietestPointResult, FFmpegError> {
+        let audio = path_str(audio_path, "rms")?;
+        let samples = decode_pcm_mono(audio, "0:a:0", start, end - start)?;
+        let spans = if self.avoid_speech {
+            speech_spans(audio, start, end).map_err(analysis_error("rms"))?
+        } else {
+            Vec::new()
+        };
+        let window = (RMS_WINDOW_SECONDS * ANALYSIS_SAMPLE_RATE as f64) as usize;
+        let hop = (RMS_HOP_SECONDS * ANALYSIS_SAMPLE_RATE as f64) as usize;
+        let mut quietest: Option<(f64, f64)> = None;
+        let mut levels = Vec::new();
+        let mut offset = 0;
+        while offset + window <= samples.len() {
+            let frame = &samples[offset..offset + window];
+            let energy = frame.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / window as f64;
+            let level = 10.0 * energy.max(1e-12).log10();
+            let time = start + (offset + window / 2) as f64 / ANALYSIS_SAMPLE_RATE as f64;
+            if level > self.floor_db && !in_speech(&spans, time) {
+                levels.push(level);
+                if quietest.is_none_or(|(_, lowest)| level < lowest) {
+                    quietest = Some((time, level));
+                }
+            }
+            offset += hop;
+        }
+        let (time, level) = quietest.ok_or_else(|| {
+            FFmpegError::NoAudiblePoint(format!(
+                "No window in range {:.3}s - {:.3}s is above {:.2} dBFS{}.",
+                start,
+                end,
+                self.floor_db,
+                if spans.is_empty() {
+                    ""
+                } else {
+                    " outside speech"
+                }
+            ))
+        })?;
+        Ok(QuietestPointResult {
+            time,
+            loudness: None,
+            peak: None,
+            snapped_from: None,
+            confidence: Some(dip_confidence(level, &levels)),
+            debug_output: None,
+        })
+    }
+}
+
+/// The middle of the longest stretch without speech (see `vad::speech_spans`).
+pub struct VadDetector;
+
+impl Detector for VadDetector {
+    fn name(&self) -> &str {
+        "vad"
+    }
+
+    fn find_split(
+        &self,
+        audio_path: &Path,
+        start: f64,
+        end: f64,
+        _debug: bool,
+    ) -> Result<QuietestPointResult, FFmpegError> {
+        let spans = speech_spans(path_str(audio_path, "vad")?, start, end)
+            .map_err(analysis_error("vad"))?;
+        // Gaps between the speech spans, clipped to the range
+        let mut gaps = Vec::new();
+        let mut gap_start = start;
+        for (span_start, span_end) in &spans {
+            if *span_start > gap_start {
+                gaps.push((gap_start, span_start.min(end)));
+            }
+            gap_start = gap_start.max(*span_end);
+        }
+        if gap_start < end {
+            gaps.push((gap_start, end));
+        }
+        let (gap_start, gap_end) = gaps
+            .into_iter()
+            .filter(|(a, b)| b > a)
+            .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)))
+            .ok_or_else(|| {
+                FFmpegError::NoAudiblePoint(format!(
+                    "The whole range {:.3}s - {:.3}s is speech. Widen the range.",
+                    start, end
+                ))
+            })?;
+        Ok(QuietestPointResult {
+            time: (gap_start + gap_end) / 2.0,
+            loudness: None,
+            peak: None,
+            snapped_from: None,
+            // A pause of two seconds or more between lines is a safe cut
+            confidence: Some(((gap_end - gap_start) / 2.0).clamp(0.0, 1.0)),
+            debug_output: None,
+        })
+    }
+}
+
+/// A user-provided program, run as `<argv...> <audio file> <start> <end>`, that prints the
+/// split point in seconds from the start of the audio file on its first line of output.
+pub struct ExternalDetector {
+    /// The program and its leading arguments
+    pub argv: Vec<String>,
+}
+
+impl Detector for ExternalDetector {
+    fn name(&self) -> &str {
+        "external"
+    }
+
+    fn find_split(
+        &self,
+        audio_path: &Path,
+        start: f64,
+        end: f64,
+        debug: bool,
+    ) -> Result<QuietestPointResult, FFmpegError> {
+        let command = shell_words::join(&self.argv);
+        let detector = format!("`{}`", command);
+        let (program, leading_args) = self
+            .argv
+            .split_first()
+            .ok_or_else(|| failed("external", "No program to run".to_string()))?;
+        let output = Command::new(program)
+            .args(leading_args)
+            .arg(audio_path)
+            .arg(format_seconds(start))
+            .arg(format_seconds(end))
+            .output()
+            .map_err(|e| failed(&detector, format!("Could not run: {}", e)))?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if !output.status.success() {
+            return Err(failed(
+                &detector,
+                format!("Exited with {}: {}", output.status, stderr.trim()),
+            ));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let line = stdout.lines().next().unwrap_or_default().trim();
+        let time: f64 = line.parse().map_err(|_| {
+            failed(
+                &detector,
+                format!("Expected a timestamp in seconds, got '{}'", line),
+            )
+        })?;
+        if !(start..=end).contains(&time) {
+            return Err(failed(
+                &detector,
+                format!(
+                    "Returned {:.3}s, outside the range {:.3}s - {:.3}s",
+                    time, start, end
+                ),
+            ));
+        }
+        Ok(QuietestPointResult {
+            time,
+            loudness: None,
+            peak: None,
+            snapped_from: None,
+            confidence: None,
+            debug_output: debug.then(|| {
+                format!(
+                    "\n--- STDERR of {} ---\n{}\n--- END STDERR ---",
+                    command, stderr
+                )
+            }),
+        })
+    }
+}
@@ -0,0 +1,344 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::ffmpeg::{FFMPEG_PATH_ENV, FFPROBE_PATH_ENV, check_ffmpeg_installation};
+use crate::i18n::lang;
+use crate::task::{Task, read_task_file};
+use crate::temp_usage::format_bytes;
+use crate::util::{TEMP_DIR_ENV, check_writable_dir, temp_root};
+
+/// Temp dirs with less free space than this get a warning; the extracted audio of a long
+/// movie in 7.1 alone takes several GiB.
+const LOW_TEMP_SPACE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Outcome of one diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Info,
+    Warning,
+    Error,
+}
+
+impl CheckStatus {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "✅",
+            CheckStatus::Info => "ℹ️",
+            CheckStatus::Warning => "⚠️",
+            CheckStatus::Error => "❌",
+        }
+    }
+}
+
+/// One line of the `doctor` report.
+#[derive(Debug, Clone)]
+pub struct DoctorCheck {
+    pub section: &'static str,
+    pub name: String,
+    pub value: String,
+    pub status: CheckStatus,
+}
+
+/// Collects the checks of one section.
+struct Section<'a> {
+    name: &'static str,
+    checks: &'a mut Vec<DoctorCheck>,
+}
+
+impl Section<'_> {
+    fn add(&mut self, name: &str, value: impl Into<String>, status: CheckStatus) {
+        self.checks.push(DoctorCheck {
+            section: self.name,
+            name: name.to_string(),
+            value: value.into(),
+            status,
+        });
+    }
+}
+
+/// What to check besides the installation and environment.
+#[derive(Debug, Default)]
+pub struct DoctorOptions {
+    /// The temp dir given with --temp-dir
+    pub temp_dir: Option<String>,
+    /// Task file to parse
+    pub task: Option<String>,
+    /// Extra directories to check for write permission
+    pub dirs: Vec<String>,
+}
+
+/// Run every diagnostic. Nothing here fails; problems become checks with an error status.
+pub fn run_checks(options: &DoctorOptions) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    check_build(&mut Section {
+        name: "sync-nudger",
+        checks: &mut checks,
+    });
+    check_ffmpeg(&mut Section {
+        name: "FFmpeg",
+        checks: &mut checks,
+    });
+    check_temp_dir(
+        &mut Section {
+            name: "Temp Dir",
+            checks: &mut checks,
+        },
+        options.temp_dir.as_deref(),
+    );
+    check_environment(&mut Section {
+        name: "Environment",
+        checks: &mut checks,
+    });
+    if let Some(task) = &options.task {
+        check_task_file(
+            &mut Section {
+                name: "Task File",
+                checks: &mut checks,
+            },
+            task,
+        );
+    }
+    check_output_dirs(
+        &mut Section {
+            name: "Output Dirs",
+            checks: &mut checks,
+        },
+        &options.dirs,
+    );
+    checks
+}
+
+fn check_build(section: &mut Section) {
+    section.add("Version", env!("CARGO_PKG_VERSION"), CheckStatus::Info);
+    section.add(
+        "Platform",
+        format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        CheckStatus::Info,
+    );
+    section.add("Message language", lang().as_str(), CheckStatus::Info);
+}
+
+fn check_ffmpeg(section: &mut Section) {
+    let result = check_ffmpeg_installation();
+    if !result.ffmpeg_available {
+        section.add(
+            "ffmpeg",
+            format!(
+                "{} ({})",
+                result.ffmpeg_path,
+                result.error.as_deref().unwrap_or("not found")
+            ),
+            CheckStatus::Error,
+        );
+    } else {
+        section.add("ffmpeg", result.ffmpeg_path.as_str(), CheckStatus::Ok);
+        match &result.ffmpeg_version {
+            Some(version) => {
+                let status = match (version.is_compatible, version.is_tested_version) {
+                    (false, _) => CheckStatus::Error,
+                    (true, false) => CheckStatus::Warning,
+                    (true, true) => CheckStatus::Ok,
+                };
+                let note = match status {
+                    CheckStatus::Error => " (too old, minimum required: 4.0.0)",
+                    CheckStatus::Warning => " (untested, tested with 7.1.x)",
+                    _ => "",
+                };
+                section.add(
+                    "ffmpeg version",
+                    format!(
+                        "{}.{}.{}{}",
+                        version.major, version.minor, version.patch, note
+                    ),
+                    status,
+                );
+            }
+            None => section.add(
+                "ffmpeg version",
+                "could not be parsed",
+                CheckStatus::Warning,
+            ),
+        }
+    }
+    if result.ffprobe_available {
+        section.add("ffprobe", result.ffprobe_path.as_str(), CheckStatus::Ok);
+    } else {
+        section.add(
+            "ffprobe",
+            format!("{} (not found)", result.ffprobe_path),
+            CheckStatus::Error,
+        );
+    }
+    if result.ffmpeg_available {
+        if result.ebur128_filter_available {
+            section.add("ebur128 filter", "available", CheckStatus::Ok);
+        } else {
+            section.add(
+                "ebur128 filter",
+                "missing (needed for loudness analysis)",
+                CheckStatus::Error,
+            );
+        }
+    }
+}
+
+fn check_temp_dir(section: &mut Section, option: Option<&str>) {
+    let dir = temp_root(option);
+    let source = if option.is_some() {
+        "--temp-dir".to_string()
+    } else if std::env::var(TEMP_DIR_ENV).is_ok_and(|v| !v.is_empty()) {
+        format!("${}", TEMP_DIR_ENV)
+    } else {
+        "system temp dir".to_string()
+    };
+    section.add(
+        "Path",
+        format!("{} (from {})", dir.display(), source),
+        CheckStatus::Info,
+    );
+    match check_writable_dir(&dir) {
+        Ok(()) => section.add("Writable", "yes", CheckStatus::Ok),
+        Err(e) => {
+            section.add("Writable", e.to_string(), CheckStatus::Error);
+            return;
+        }
+    }
+    match free_space(&dir) {
+        Some(free) if free < LOW_TEMP_SPACE_BYTES => section.add(
+            "Free space",
+            format!(
+                "{} (long movies need more; see --temp-dir)",
+                format_bytes(free)
+            ),
+            CheckStatus::Warning,
+        ),
+        Some(free) => section.add("Free space", format_bytes(free), CheckStatus::Ok),
+        None => section.add("Free space", "unknown", CheckStatus::Info),
+    }
+    match filesystem_type(&dir) {
+        Some(fs_type) if fs_type == "tmpfs" || fs_type == "ramfs" => section.add(
+            "Filesystem",
+            format!("{} (held in memory)", fs_type),
+            CheckStatus::Warning,
+        ),
+        Some(fs_type) => section.add("Filesystem", fs_type, CheckStatus::Info),
+        None => section.add("Filesystem", "unknown", CheckStatus::Info),
+    }
+}
+
+/// Free space of the filesystem holding `dir`, from `df`.
+fn free_space(dir: &Path) -> Option<u64> {
+    let output = Command::new("df").arg("-Pk").arg(dir).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kib: u64 = stdout
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(available_kib * 1024)
+}
+
+/// Type of the filesystem holding `dir`, from the longest matching mount point in
+/// /proc/mounts (Linux only).
+fn filesystem_type(dir: &Path) -> Option<String> {
+    let dir = std::fs::canonicalize(dir).ok()?;
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = PathBuf::from(fields.nth(1)?.replace("\\040", " "));
+            let fs_type = fields.next()?;
+            dir.starts_with(&mount_point)
+                .then(|| (mount_point.components().count(), fs_type.to_string()))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, fs_type)| fs_type)
+}
+
+fn check_environment(section: &mut Section) {
+    match std::env::var_os("PATH") {
+        Some(path) => {
+            for entry in std::env::split_paths(&path) {
+                let status = if entry.is_dir() {
+                    CheckStatus::Info
+                } else {
+                    CheckStatus::Warning
+                };
+                let value = if entry.is_dir() {
+                    entry.display().to_string()
+                } else {
+                    format!("{} (missing)", entry.display())
+                };
+                section.add("PATH", value, status);
+            }
+        }
+        None => section.add("PATH", "not set", CheckStatus::Warning),
+    }
+    for name in [
+        FFMPEG_PATH_ENV,
+        FFPROBE_PATH_ENV,
+        TEMP_DIR_ENV,
+        "LC_ALL",
+        "LC_MESSAGES",
+        "LANG",
+    ] {
+        if let Ok(value) = std::env::var(name) {
+            section.add(&format!("${}", name), value, CheckStatus::Info);
+        }
+    }
+}
+
+fn check_task_file(section: &mut Section, path: &str) {
+    match read_task_file::<Task>(path) {
+        Ok(_) => section.add(path, "parses", CheckStatus::Ok),
+        Err(e) => section.add(path, e.to_string(), CheckStatus::Error),
+    }
+}
+
+fn check_output_dirs(section: &mut Section, extra: &[String]) {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Ok(current) = std::env::current_dir() {
+        dirs.push(current);
+    }
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        dirs.push(PathBuf::from(home));
+    }
+    dirs.extend(extra.iter().map(PathBuf::from));
+    for dir in dirs {
+        let name = dir.display().to_string();
+        match check_writable_dir(&dir) {
+            Ok(()) => section.add(&name, "writable", CheckStatus::Ok),
+            Err(e) => section.add(&name, e.to_string(), CheckStatus::Error),
+        }
+    }
+}
+
+/// The checks as plain text, for pasting into a bug report.
+pub fn report_text(checks: &[DoctorCheck]) -> String {
+    let mut report = String::new();
+    let mut section = "";
+    for check in checks {
+        if check.section != section {
+            if !section.is_empty() {
+                report.push('\n');
+            }
+            section = check.section;
+            report.push_str(&format!("[{}]\n", section));
+        }
+        let marker = match check.status {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Info => "--",
+            CheckStatus::Warning => "WARN",
+            CheckStatus::Error => "ERROR",
+        };
+        report.push_str(&format!("{:<5} {}: {}\n", marker, check.name, check.value));
+    }
+    report
+}
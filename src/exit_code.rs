@@ -0,0 +1,90 @@
+use thiserror::Error;
+
+use crate::ffmpeg::FFmpegError;
+
+/// Failures with an exit code of their own that are not ffmpeg errors.
+#[derive(Debug, Error)]
+pub enum RunError {
+    #[error("Aborted at the confirmation prompt.")]
+    Aborted,
+    #[error("The encoded audio track failed verification: {0}")]
+    VerificationFailed(String),
+    #[error("The muxed output failed verification, so '{0}' was not written: {1}")]
+    OutputVerificationFailed(String, String),
+    #[error(
+        "Automatic suggestions need review: {0}. Run the file without --yes, or lower --min-confidence."
+    )]
+    NeedsReview(String),
+}
+
+/// Why the tool exited, for wrappers that branch on the kind of failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Success,
+    /// Any failure without a code of its own
+    Failed,
+    /// Invalid command line options (clap's own code)
+    BadArguments,
+    /// ffmpeg or ffprobe is missing or unusable
+    FfmpegMissing,
+    /// No audible point was found in a split range
+    NoAudiblePoint,
+    /// Declined at the prompt, cancelled with Ctrl+C or stopped with the stop file
+    Aborted,
+    /// An ffmpeg stage failed or stalled
+    FfmpegFailed,
+    /// The encoded track did not match the source, or the muxed output is unreadable
+    VerificationFailed,
+    /// An automatic suggestion scored below `--min-confidence` and nobody was there to review it
+    NeedsReview,
+}
+
+impl ExitReason {
+    pub fn code(self) -> u8 {
+        match self {
+            ExitReason::Success => 0,
+            ExitReason::Failed => 1,
+            ExitReason::BadArguments => 2,
+            ExitReason::FfmpegMissing => 3,
+            ExitReason::NoAudiblePoint => 4,
+            ExitReason::Aborted => 5,
+            ExitReason::FfmpegFailed => 6,
+            ExitReason::VerificationFailed => 7,
+            ExitReason::NeedsReview => 8,
+        }
+    }
+
+    /// The reason for a failed run, from the first error in the chain that has one.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<RunError>() {
+                    return Some(match e {
+                        RunError::Aborted => ExitReason::Aborted,
+                        RunError::VerificationFailed(_)
+                        | RunError::OutputVerificationFailed(..) => ExitReason::VerificationFailed,
+                        RunError::NeedsReview(_) => ExitReason::NeedsReview,
+                    });
+                }
+                if cause.downcast_ref::<clap::Error>().is_some() {
+                    return Some(ExitReason::BadArguments);
+                }
+                cause.downcast_ref::<FFmpegError>().and_then(|e| match e {
+                    FFmpegError::CommandNotFound(_)
+                    | FFmpegError::VersionMismatch { .. }
+                    | FFmpegError::VersionParseError
+                    | FFmpegError::FFmpegVersionCheckFailed => Some(ExitReason::FfmpegMissing),
+                    FFmpegError::NoAudiblePoint(_) => Some(ExitReason::NoAudiblePoint),
+                    FFmpegError::InvalidDetector(_) => Some(ExitReason::BadArguments),
+                    FFmpegError::DetectorFailed(..) => Some(ExitReason::Failed),
+                    FFmpegError::Cancelled | FFmpegError::Stopped => Some(ExitReason::Aborted),
+                    FFmpegError::CommandFailed(..) | FFmpegError::Stalled { .. } => {
+                        Some(ExitReason::FfmpegFailed)
+                    }
+                    _ => None,
+                })
+            })
+            .unwrap_or(ExitReason::Failed)
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Result, bail};
+use std::process::Command;
+
+use crate::audio_metadata::stream_duration;
+use crate::ffmpeg::ffprobe_executable;
+
+/// Where a stream starts and ends, in seconds on the timeline of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamExtent {
+    pub index: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl StreamExtent {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// The extents of an audio stream and of the video stream it plays against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AvExtents {
+    pub audio: StreamExtent,
+    pub video: StreamExtent,
+}
+
+impl AvExtents {
+    /// How long the picture runs before the audio starts (negative: the audio starts first).
+    pub fn head_gap(&self) -> f64 {
+        self.audio.start - self.video.start
+    }
+
+    /// How long the picture runs after the audio ends (negative: the audio runs past the last
+    /// frame).
+    pub fn tail_gap(&self) -> f64 {
+        self.video.end - self.audio.end
+    }
+}
+
+/// Measure where the audio stream `audio_index` (absolute) and the first video stream of `input`
+/// start and end. Streams without a duration of their own end with the container.
+pub fn measure_extents(input: &str, audio_index: usize) -> Result<AvExtents> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-show_streams",
+            "-show_format",
+            "-of",
+            "json",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "ffprobe failed to read the streams of '{}': {}",
+            input,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let format_start = seconds(&json["format"]["start_time"]).unwrap_or(0.0);
+    let format_end = seconds(&json["format"]["duration"]).map(|d| format_start + d);
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+
+    let extent = |stream: &serde_json::Value| -> Option<StreamExtent> {
+        let start = seconds(&stream["start_time"]).unwrap_or(format_start);
+        let end = match stream_duration(stream) {
+            Some(duration) => start + duration,
+            None => format_end?,
+        };
+        Some(StreamExtent {
+            index: stream["index"].as_u64()? as usize,
+            start,
+            end,
+        })
+    };
+    let Some(audio) = streams
+        .iter()
+        .find(|s| s["index"].as_u64() == Some(audio_index as u64))
+    else {
+        bail!("'{}' has no stream {}.", input, audio_index);
+    };
+    // Cover art is a video stream too, but has no timeline
+    let Some(video) = streams.iter().find(|s| {
+        s["codec_type"].as_str() == Some("video") && s["disposition"]["attached_pic"] != 1
+    }) else {
+        bail!("'{}' has no video stream to compare the audio with.", input);
+    };
+    match (extent(audio), extent(video)) {
+        (Some(audio), Some(video)) => Ok(AvExtents { audio, video }),
+        _ => bail!("Could not determine the stream durations of '{}'.", input),
+    }
+}
+
+/// A number ffprobe reports as a string.
+fn seconds(value: &serde_json::Value) -> Option<f64> {
+    value.as_str().and_then(|s| s.parse().ok())
+}
@@ -4,15 +4,133 @@
 
 use regex::Regex;
 use std::{
-    io,
-    process::{Command, Stdio},
+    cell::RefCell,
+    io::{self, Read, Write},
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
 };
+
+use crate::i18n::{Msg, tf};
+use crate::logging::LOG;
+use crate::progress::{CancellationToken, abort_requested};
 use thiserror::Error;
+use tracing::{debug, error, info, warn};
 
 const EXPECTED_FFMPEG_MAJOR_VERSION: u32 = 7;
 const EXPECTED_FFMPEG_MINOR_VERSION: u32 = 1;
 const MINIMUM_FFMPEG_MAJOR_VERSION: u32 = 4;
 
+/// How often a running child is checked for completion or cancellation.
+const CHILD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A child that writes nothing for this long is reported as stalled, unless changed.
+pub const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Environment variables naming the ffmpeg and ffprobe executables when none is configured.
+pub const FFMPEG_PATH_ENV: &str = "SYNC_NUDGER_FFMPEG";
+pub const FFPROBE_PATH_ENV: &str = "SYNC_NUDGER_FFPROBE";
+
+/// How the ffmpeg and ffprobe children of a job are started and watched. A job enters its own
+/// settings with `enter`, so jobs running one after another or side by side on different
+/// threads never see each other's.
+#[derive(Debug, Clone)]
+pub struct ChildSettings {
+    /// Maximum number of threads each ffmpeg child may use (0 leaves the choice to ffmpeg)
+    pub threads: usize,
+    /// ffmpeg executable; `None` uses `$SYNC_NUDGER_FFMPEG`, else `ffmpeg` from PATH
+    pub ffmpeg_path: Option<String>,
+    /// ffprobe executable; `None` uses `$SYNC_NUDGER_FFPROBE`, else `ffprobe` from PATH
+    pub ffprobe_path: Option<String>,
+    /// A child that writes nothing for this long is reported as stalled (`None` never is)
+    pub stall_timeout: Option<Duration>,
+    /// Kill a stalled child and fail instead of only warning
+    pub abort_on_stall: bool,
+    /// Checked while waiting for children; a cancelled token kills the running child
+    pub cancel: CancellationToken,
+}
+
+impl Default for ChildSettings {
+    fn default() -> Self {
+        Self {
+            threads: 0,
+            ffmpeg_path: None,
+            ffprobe_path: None,
+            stall_timeout: Some(DEFAULT_STALL_TIMEOUT),
+            abort_on_stall: false,
+            cancel: CancellationToken::default(),
+        }
+    }
+}
+
+thread_local! {
+    /// Settings entered on this thread; the defaults outside of a job.
+    static CHILD_SETTINGS: RefCell<ChildSettings> = RefCell::new(ChildSettings::default());
+}
+
+impl ChildSettings {
+    /// Use these settings for every child started on this thread until the guard is dropped,
+    /// which restores the previous ones. Threads a job spawns enter `current()` themselves.
+    pub fn enter(self) -> ChildSettingsGuard {
+        let previous = CHILD_SETTINGS.with(|current| current.replace(self));
+        ChildSettingsGuard {
+            previous: Some(previous),
+        }
+    }
+
+    /// The settings in effect on this thread.
+    pub fn current() -> ChildSettings {
+        CHILD_SETTINGS.with(|current| current.borrow().clone())
+    }
+
+    /// The ffmpeg executable: `ffmpeg_path`, else `$SYNC_NUDGER_FFMPEG`, else `ffmpeg` from PATH.
+    pub fn ffmpeg(&self) -> String {
+        configured_executable(self.ffmpeg_path.clone(), FFMPEG_PATH_ENV, "ffmpeg")
+    }
+
+    /// The ffprobe executable: `ffprobe_path`, else `$SYNC_NUDGER_FFPROBE`, else `ffprobe` from
+    /// PATH.
+    pub fn ffprobe(&self) -> String {
+        configured_executable(self.ffprobe_path.clone(), FFPROBE_PATH_ENV, "ffprobe")
+    }
+
+    /// Insert the thread limit into an ffmpeg argument list: decoder threads before each input,
+    /// encoder threads before the output (the last argument) and a global filter limit.
+    pub fn limit_threads(&self, args: &[&str]) -> Vec<String> {
+        if self.threads == 0 {
+            return args.iter().map(|a| a.to_string()).collect();
+        }
+        let threads = self.threads.to_string();
+        let mut limited = vec!["-filter_threads".to_string(), threads.clone()];
+        for (i, arg) in args.iter().enumerate() {
+            if *arg == "-i" || i == args.len() - 1 {
+                limited.push("-threads".to_string());
+                limited.push(threads.clone());
+            }
+            limited.push(arg.to_string());
+        }
+        limited
+    }
+}
+
+/// Restores the previous `ChildSettings` of the thread when dropped.
+#[must_use = "the settings only apply while the guard lives"]
+pub struct ChildSettingsGuard {
+    previous: Option<ChildSettings>,
+}
+
+impl Drop for ChildSettingsGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            CHILD_SETTINGS.with(|current| current.replace(previous));
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FFmpegVersionInfo {
     pub major: u32,
@@ -24,8 +142,12 @@ pub struct FFmpegVersionInfo {
 
 #[derive(Debug)]
 pub struct FFmpegCheckResult {
+    /// ffmpeg executable that was checked
+    pub ffmpeg_path: String,
     pub ffmpeg_available: bool,
     pub ffmpeg_version: Option<FFmpegVersionInfo>,
+    /// ffprobe executable that was checked
+    pub ffprobe_path: String,
     pub ffprobe_available: bool,
     pub ebur128_filter_available: bool,
     pub error: Option<String>,
@@ -50,6 +172,12 @@ pub enum FFmpegError {
     CommandNotFound(String),
     #[error("Failed to run `{0}`: {1}")]
     CommandFailed(String, String),
+    #[error("{0}")]
+    NoAudiblePoint(String),
+    #[error("The split point detector {0} failed: {1}")]
+    DetectorFailed(String, String),
+    #[error("{0}")]
+    InvalidDetector(String),
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
@@ -60,21 +188,344 @@ pub enum FFmpegError {
     SerdeJson(#[from] serde_json::Error),
     #[error("")]
     BitrateUndetermined { stream_index: usize },
+    #[error("Operation cancelled.")]
+    Cancelled,
+    #[error(
+        "Stopped as requested after the last finished stage. Run the same command with --resume to continue."
+    )]
+    Stopped,
+    #[error(
+        "`{command}` produced no output for {seconds} s and was stopped. Last output: {last_output}. Inputs on network mounts can hang while demuxing; copy the file locally or raise --stall-timeout."
+    )]
+    Stalled {
+        command: String,
+        seconds: u64,
+        last_output: String,
+    },
 }
 
-pub fn run_ffmpeg(args: &[&str], debug: bool) -> Result<(), FFmpegError> {
-    let mut command = Command::new("ffmpeg");
-    command.args(args);
+fn configured_executable(path: Option<String>, env: &str, default: &str) -> String {
+    path.or_else(|| std::env::var(env).ok().filter(|path| !path.is_empty()))
+        .unwrap_or_else(|| default.to_string())
+}
 
-    if !debug {
-        command.stdout(Stdio::null()).stderr(Stdio::null());
+/// The ffmpeg executable: the one of the current `ChildSettings`, else `$SYNC_NUDGER_FFMPEG`,
+/// else `ffmpeg` from PATH.
+pub fn ffmpeg_executable() -> String {
+    CHILD_SETTINGS.with(|current| current.borrow().ffmpeg())
+}
+
+/// The ffprobe executable: the one of the current `ChildSettings`, else `$SYNC_NUDGER_FFPROBE`,
+/// else `ffprobe` from PATH.
+pub fn ffprobe_executable() -> String {
+    CHILD_SETTINGS.with(|current| current.borrow().ffprobe())
+}
+
+/// Seconds as an ffmpeg time argument: fixed microsecond precision (ffmpeg's own time base),
+/// never in exponent form and always with `.` as the decimal separator.
+pub fn format_seconds(seconds: f64) -> String {
+    let micros = (seconds * 1_000_000.0).round() as i64;
+    let sign = if micros < 0 { "-" } else { "" };
+    let micros = micros.unsigned_abs();
+    format!("{}{}.{:06}", sign, micros / 1_000_000, micros % 1_000_000)
+}
+
+/// Nearest whole sample to a time in seconds.
+pub fn seconds_to_samples(seconds: f64, sample_rate: u32) -> u64 {
+    (seconds.max(0.0) * sample_rate as f64).round() as u64
+}
+
+/// A sample position as an ffmpeg time argument (see `format_seconds`).
+pub fn format_samples(samples: u64, sample_rate: u32) -> String {
+    format_seconds(samples as f64 / sample_rate as f64)
+}
+
+/// Insert the thread limit of the current `ChildSettings` into an ffmpeg argument list (see
+/// `ChildSettings::limit_threads`).
+pub fn with_thread_limit(args: &[&str]) -> Vec<String> {
+    CHILD_SETTINGS.with(|current| current.borrow().limit_threads(args))
+}
+
+/// Output of a finished child process.
+pub struct ChildOutput {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl ChildOutput {
+    /// Last non-empty line written to stderr, which is where ffmpeg reports the failure reason.
+    pub fn last_error_line(&self) -> String {
+        String::from_utf8_lossy(&self.stderr)
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    }
+}
+
+/// Time of the last output of a child, in milliseconds since it was spawned, and the media
+/// time (in microseconds) it last reported through `-progress`.
+struct Activity {
+    started: Instant,
+    last_output_ms: AtomicU64,
+    media_time_us: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_output_ms: AtomicU64::new(0),
+            media_time_us: AtomicU64::new(0),
+        }
     }
 
-    let status = command.status()?;
-    if !status.success() {
+    fn touch(&self) {
+        self.last_output_ms
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn idle_ms(&self) -> u64 {
+        (self.started.elapsed().as_millis() as u64)
+            .saturating_sub(self.last_output_ms.load(Ordering::Relaxed))
+    }
+}
+
+/// Read a pipe to the end, optionally forwarding everything read to `echo` as it arrives.
+/// Every read is recorded in `activity` for stall detection. With `parse_progress`, the
+/// `out_time_us=` lines of ffmpeg's `-progress` output update the media time in `activity`.
+fn drain_pipe<R: Read>(
+    mut pipe: R,
+    mut echo: Option<Box<dyn Write + Send>>,
+    activity: &Activity,
+    parse_progress: bool,
+) -> Vec<u8> {
+    let mut captured = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let mut line_start = 0;
+    loop {
+        match pipe.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                activity.touch();
+                if let Some(echo) = echo.as_mut() {
+                    let _ = echo.write_all(&chunk[..n]);
+                    let _ = echo.flush();
+                }
+                captured.extend_from_slice(&chunk[..n]);
+                if parse_progress {
+                    while let Some(end) = captured[line_start..].iter().position(|b| *b == b'\n') {
+                        let line = String::from_utf8_lossy(&captured[line_start..line_start + end]);
+                        if let Some(Ok(us)) = line
+                            .trim()
+                            .strip_prefix("out_time_us=")
+                            .map(|v| v.parse::<u64>())
+                        {
+                            activity.media_time_us.store(us, Ordering::Relaxed);
+                        }
+                        line_start += end + 1;
+                    }
+                }
+            }
+        }
+    }
+    captured
+}
+
+/// Run a child process with stdin closed and both output pipes drained on background threads,
+/// so neither an interactive prompt nor a full pipe buffer can block the run.
+/// With `echo`, the child's output is also forwarded to our own stdout/stderr as it arrives.
+/// If the cancellation token of the current `ChildSettings` fires while waiting, the child is
+/// killed. A child that writes nothing for the stall timeout is reported, and killed if the
+/// settings say so.
+pub fn run_child(program: &str, args: &[String], echo: bool) -> Result<ChildOutput, FFmpegError> {
+    run_child_with_progress(program, args, echo, None)
+}
+
+/// Like `run_child`, for an ffmpeg child started with `-progress pipe:1`: `progress` is called
+/// on this thread with the media time (in seconds) ffmpeg has processed so far.
+fn run_child_with_progress(
+    program: &str,
+    args: &[String],
+    echo: bool,
+    progress: Option<&dyn Fn(f64)>,
+) -> Result<ChildOutput, FFmpegError> {
+    let settings = ChildSettings::current();
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // Keep Ctrl+C in the terminal from reaching the child, so a requested stop lets the
+    // running stage finish; an abort kills the child instead
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    debug!(target: LOG, "Running {} {}", program, args.join(" "));
+    let started = std::time::Instant::now();
+    let mut child = command.spawn().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            FFmpegError::CommandNotFound(program.to_string())
+        } else {
+            FFmpegError::Io(e)
+        }
+    })?;
+
+    let activity = Arc::new(Activity::new());
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let parse_progress = progress.is_some();
+    let stdout_activity = Arc::clone(&activity);
+    let stdout_reader = thread::spawn(move || {
+        stdout
+            .map(|pipe| {
+                let echo: Option<Box<dyn Write + Send>> = echo.then(|| Box::new(io::stdout()) as _);
+                drain_pipe(pipe, echo, &stdout_activity, parse_progress)
+            })
+            .unwrap_or_default()
+    });
+    let stderr_activity = Arc::clone(&activity);
+    let stderr_reader = thread::spawn(move || {
+        stderr
+            .map(|pipe| {
+                let echo: Option<Box<dyn Write + Send>> = echo.then(|| Box::new(io::stderr()) as _);
+                drain_pipe(pipe, echo, &stderr_activity, false)
+            })
+            .unwrap_or_default()
+    });
+
+    let stall_timeout_ms = settings
+        .stall_timeout
+        .map_or(0, |timeout| timeout.as_millis() as u64);
+    let mut stall_warnings = 0;
+    let mut reported_us = 0;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if let Some(progress) = progress {
+            let media_time_us = activity.media_time_us.load(Ordering::Relaxed);
+            if media_time_us != reported_us {
+                reported_us = media_time_us;
+                progress(media_time_us as f64 / 1_000_000.0);
+            }
+        }
+        if abort_requested() || settings.cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = stdout_reader.join();
+            let _ = stderr_reader.join();
+            info!(target: LOG, "Killed {} on cancellation", program);
+            return Err(FFmpegError::Cancelled);
+        }
+        let idle_ms = activity.idle_ms();
+        if stall_timeout_ms > 0 && idle_ms >= stall_timeout_ms * (stall_warnings + 1) {
+            if settings.abort_on_stall {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = stdout_reader.join();
+                let stderr = stderr_reader.join().unwrap_or_default();
+                let last_output = String::from_utf8_lossy(&stderr)
+                    .lines()
+                    .rev()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("none")
+                    .trim()
+                    .to_string();
+                error!(
+                    target: LOG,
+                    "Killed {} after {} s without output: {} {}",
+                    program,
+                    idle_ms / 1000,
+                    program,
+                    args.join(" ")
+                );
+                return Err(FFmpegError::Stalled {
+                    command: format!("{} {}", program, args.join(" ")),
+                    seconds: idle_ms / 1000,
+                    last_output,
+                });
+            }
+            stall_warnings += 1;
+            warn!(target: LOG, "{} has produced no output for {} s", program, idle_ms / 1000);
+            warn!(
+                "⚠️ {}",
+                tf(
+                    Msg::Stalled,
+                    &[
+                        ("program", program.to_string()),
+                        ("seconds", (idle_ms / 1000).to_string())
+                    ]
+                )
+            );
+        }
+        thread::sleep(CHILD_POLL_INTERVAL);
+    };
+    let output = ChildOutput {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    };
+    if status.success() {
+        debug!(target: LOG, "{} finished in {:.1} s", program, started.elapsed().as_secs_f64());
+    } else {
+        warn!(
+            target: LOG,
+            "{} exited with {} after {:.1} s: {} {}\n    {}",
+            program,
+            status,
+            started.elapsed().as_secs_f64(),
+            program,
+            args.join(" "),
+            output.last_error_line()
+        );
+    }
+    Ok(output)
+}
+
+/// Run ffmpeg with `-nostdin` and the configured thread limit, capturing its output.
+pub fn ffmpeg_output(args: &[&str], echo: bool) -> Result<ChildOutput, FFmpegError> {
+    let mut full_args = vec!["-nostdin".to_string()];
+    full_args.extend(with_thread_limit(args));
+    run_child(&ffmpeg_executable(), &full_args, echo)
+}
+
+/// Run ffmpeg like `run_ffmpeg`, calling `progress` with the media time (in seconds) it has
+/// processed so far. With `debug`, ffmpeg's own output is shown instead.
+pub fn run_ffmpeg_with_progress(
+    args: &[&str],
+    debug: bool,
+    progress: &dyn Fn(f64),
+) -> Result<(), FFmpegError> {
+    if debug {
+        return run_ffmpeg(args, debug);
+    }
+    let mut full_args = vec![
+        "-nostdin".to_string(),
+        "-progress".to_string(),
+        "pipe:1".to_string(),
+        "-nostats".to_string(),
+    ];
+    full_args.extend(with_thread_limit(args));
+    let output = run_child_with_progress(&ffmpeg_executable(), &full_args, false, Some(progress))?;
+    if !output.status.success() {
+        return Err(FFmpegError::CommandFailed(
+            args.join(" "),
+            format!("FFmpeg failed: {}", output.last_error_line()),
+        ));
+    }
+    Ok(())
+}
+
+pub fn run_ffmpeg(args: &[&str], debug: bool) -> Result<(), FFmpegError> {
+    let output = ffmpeg_output(args, debug)?;
+    if !output.status.success() {
         return Err(FFmpegError::CommandFailed(
             args.join(" "),
-            "FFmpeg failed".to_string(),
+            format!("FFmpeg failed: {}", output.last_error_line()),
         ));
     }
     Ok(())
@@ -85,7 +536,7 @@ pub fn check_ffmpeg_version(ignore_check: bool) -> Result<(), FFmpegError> {
         return Ok(());
     }
 
-    let output = Command::new("ffmpeg").arg("-version").output()?;
+    let output = Command::new(ffmpeg_executable()).arg("-version").output()?;
     if !output.status.success() {
         return Err(FFmpegError::FFmpegVersionCheckFailed);
     }
@@ -123,6 +574,7 @@ pub fn check_ffmpeg_version(ignore_check: bool) -> Result<(), FFmpegError> {
 pub fn check_dependency(cmd: &str) -> Result<(), FFmpegError> {
     match Command::new(cmd)
         .arg("-version")
+        .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status()
@@ -140,15 +592,17 @@ pub fn check_dependency(cmd: &str) -> Result<(), FFmpegError> {
 
 pub fn check_ffmpeg_installation() -> FFmpegCheckResult {
     let mut result = FFmpegCheckResult {
+        ffmpeg_path: ffmpeg_executable(),
         ffmpeg_available: false,
         ffmpeg_version: None,
+        ffprobe_path: ffprobe_executable(),
         ffprobe_available: false,
         ebur128_filter_available: false,
         error: None,
     };
 
     // Check if ffmpeg is available
-    match Command::new("ffmpeg").arg("-version").output() {
+    match Command::new(ffmpeg_executable()).arg("-version").output() {
         Ok(output) => {
             if output.status.success() {
                 result.ffmpeg_available = true;
@@ -180,7 +634,7 @@ pub fn check_ffmpeg_installation() -> FFmpegCheckResult {
         }
         Err(e) => {
             if e.kind() == io::ErrorKind::NotFound {
-                result.error = Some("FFmpeg not found in PATH".to_string());
+                result.error = Some(format!("`{}` not found", result.ffmpeg_path));
             } else {
                 result.error = Some(format!("Failed to check FFmpeg: {}", e));
             }
@@ -188,7 +642,7 @@ pub fn check_ffmpeg_installation() -> FFmpegCheckResult {
     }
 
     // Check if ffprobe is available
-    match Command::new("ffprobe").arg("-version").output() {
+    match Command::new(ffprobe_executable()).arg("-version").output() {
         Ok(output) => {
             result.ffprobe_available = output.status.success();
         }
@@ -198,7 +652,7 @@ pub fn check_ffmpeg_installation() -> FFmpegCheckResult {
     }
 
     // Check for required filter
-    match Command::new("ffmpeg")
+    match Command::new(ffmpeg_executable())
         .args(&["-hide_banner", "-filters"])
         .output()
     {
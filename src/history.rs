@@ -0,0 +1,181 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::{DelayRamp, SplitPoint};
+use crate::ffmpeg::ffprobe_executable;
+
+/// Track tag holding the edit history as a JSON array of `EditRecord`s.
+pub const HISTORY_TAG: &str = "SYNC_NUDGER_HISTORY";
+
+/// Containers that keep custom track tags. Other outputs get a sidecar file instead.
+const TAGGABLE_EXTENSIONS: &[&str] = &["mkv", "mka", "mks", "webm"];
+
+/// One sync-nudger run applied to an audio track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditRecord {
+    /// sync-nudger version that made the edit
+    pub version: String,
+    /// Unix time (seconds) of the edit
+    pub applied_at: u64,
+    /// File name of the input the track was taken from
+    pub source: String,
+    /// Absolute stream index of the track in the source
+    pub stream: usize,
+    /// Delay of the first segment in milliseconds
+    pub initial_delay: f64,
+    #[serde(default)]
+    pub splits: Vec<SplitPoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ramps: Vec<DelayRamp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift_ppm: Option<f64>,
+}
+
+impl EditRecord {
+    pub fn new(source: &str, stream: usize, initial_delay: f64, splits: Vec<SplitPoint>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            applied_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            source: Path::new(source)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| source.to_string()),
+            stream,
+            initial_delay,
+            splits,
+            ramps: Vec::new(),
+            drift_ppm: None,
+        }
+    }
+
+    /// `applied_at` as a UTC date and time (`YYYY-MM-DD HH:MM`).
+    pub fn applied_at_utc(&self) -> String {
+        format_utc(self.applied_at)
+    }
+
+    /// The splits and delays of the edit on one line, e.g. `+250.000 ms, 1200.000s: +40.000 ms`.
+    pub fn describe_edit(&self) -> String {
+        let mut parts = vec![format!("{:+.3} ms", self.initial_delay)];
+        parts.extend(
+            self.splits
+                .iter()
+                .map(|split| format!("{:.3}s: {:+.3} ms", split.time, split.delay)),
+        );
+        parts.extend(self.ramps.iter().map(|ramp| {
+            format!(
+                "{:.3}-{:.3}s: {:+.3} ms (ramp)",
+                ramp.start, ramp.end, ramp.delay
+            )
+        }));
+        if let Some(ppm) = self.drift_ppm {
+            parts.push(format!("drift {:+.3} ppm", ppm));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Unix time (seconds) as a UTC date and time (`YYYY-MM-DD HH:MM`).
+pub fn format_utc(unix_seconds: u64) -> String {
+    let days = (unix_seconds / 86_400) as i64;
+    let minutes = (unix_seconds % 86_400) / 60;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Whether the container of `output` keeps custom track tags.
+pub fn supports_track_tags(output: &str) -> bool {
+    Path::new(output)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| TAGGABLE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Sidecar file that holds the history of an output whose container cannot keep track tags.
+pub fn sidecar_path(output: &str) -> String {
+    format!("{}.sync-nudger.json", output)
+}
+
+/// Edit history stored in the tags of an audio stream (by absolute index), oldest first.
+pub fn read_track_history(input: &str, stream: usize) -> Result<Vec<EditRecord>> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            &stream.to_string(),
+            "-show_entries",
+            "stream_tags",
+            "-of",
+            "json",
+            input,
+        ])
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "ffprobe could not read the tags of {}: {}",
+            input,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let tag = json["streams"][0]["tags"].as_object().and_then(|tags| {
+        tags.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(HISTORY_TAG))
+            .and_then(|(_, value)| value.as_str())
+    });
+    match tag {
+        Some(tag) => Ok(serde_json::from_str(tag)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Edit history stored in the sidecar of `output`, if there is one.
+pub fn read_sidecar_history(output: &str) -> Result<Vec<EditRecord>> {
+    let path = sidecar_path(output);
+    if !Path::new(&path).is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+/// Edit history of an audio stream (by absolute index) from its tags, or from the sidecar of
+/// the file if the tags hold none and the sidecar's last edit was made to that stream.
+pub fn read_history(input: &str, stream: usize) -> Result<Vec<EditRecord>> {
+    let history = read_track_history(input, stream)?;
+    if !history.is_empty() {
+        return Ok(history);
+    }
+    let sidecar = read_sidecar_history(input)?;
+    if sidecar.last().is_some_and(|record| record.stream == stream) {
+        Ok(sidecar)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+/// The tag that stores `history` on a track.
+pub fn history_tag(history: &[EditRecord]) -> Result<(String, String)> {
+    Ok((HISTORY_TAG.to_string(), serde_json::to_string(history)?))
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::{info, warn};
+
+use crate::logging::LOG;
+use crate::progress::Stage;
+
+/// User commands run around a job, from `--pre-hook`/`--post-hook` or the `hooks` of a task file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct TaskHooks {
+    /// Runs before any work; a failing pre hook cancels the job
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre: Option<String>,
+    /// Runs after the job, whether it succeeded or not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post: Option<String>,
+    /// Run after a stage finishes, keyed by stage name (e.g. `encoding`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub stages: BTreeMap<String, String>,
+}
+
+impl TaskHooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_none() && self.post.is_none() && self.stages.is_empty()
+    }
+
+    /// Fail on a stage hook for a stage that does not exist.
+    pub fn validate(&self) -> Result<()> {
+        for name in self.stages.keys() {
+            if !Stage::ALL.iter().any(|stage| stage.as_str() == name) {
+                bail!(
+                    "Unknown stage '{}' in the task file hooks (expected one of: {}).",
+                    name,
+                    Stage::ALL.map(Stage::as_str).join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The command to run after `stage`, if any.
+    pub fn stage(&self, stage: Stage) -> Option<&str> {
+        self.stages.get(stage.as_str()).map(String::as_str)
+    }
+}
+
+/// The job a hook runs for. It is passed as `SYNC_NUDGER_*` environment variables and as JSON
+/// on stdin (and in `SYNC_NUDGER_JOB`).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HookContext {
+    /// `pre`, `post` or the name of the finished stage
+    pub event: String,
+    pub input: String,
+    pub output: String,
+    /// The stream as given on the command line or in the task file
+    pub stream: Option<String>,
+    pub task_file: Option<String>,
+    /// `succeeded`, `failed`, `stopped` or `cancelled` (post hook only)
+    pub status: Option<String>,
+    /// Why the job failed (post hook only)
+    pub error: Option<String>,
+}
+
+/// Run `command` through the shell with the job context. Fails if it cannot be started or
+/// exits unsuccessfully.
+pub fn run_hook(command: &str, context: &HookContext) -> Result<()> {
+    let json = serde_json::to_string(context)?;
+    info!(target: LOG, "Running the {} hook: {}", context.event, command);
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .env("SYNC_NUDGER_EVENT", &context.event)
+        .env("SYNC_NUDGER_INPUT", &context.input)
+        .env("SYNC_NUDGER_OUTPUT", &context.output)
+        .env(
+            "SYNC_NUDGER_STREAM",
+            context.stream.as_deref().unwrap_or(""),
+        )
+        .env(
+            "SYNC_NUDGER_TASK_FILE",
+            context.task_file.as_deref().unwrap_or(""),
+        )
+        .env(
+            "SYNC_NUDGER_STATUS",
+            context.status.as_deref().unwrap_or(""),
+        )
+        .env("SYNC_NUDGER_ERROR", context.error.as_deref().unwrap_or(""))
+        .env("SYNC_NUDGER_JOB", &json)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run the {} hook '{}'", context.event, command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that does not read its stdin closes the pipe early, which is fine
+        let _ = stdin.write_all(json.as_bytes());
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        warn!(target: LOG, "The {} hook exited with {}", context.event, status);
+        bail!(
+            "The {} hook '{}' exited with {}",
+            context.event,
+            command,
+            status
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,1547 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::util::render_template;
+
+/// Language of the messages printed by the tool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+    Ja,
+}
+
+impl Lang {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+            Lang::Ja => "ja",
+        }
+    }
+
+    /// The language of the first locale variable that is set (LC_ALL, LC_MESSAGES, LANG), or
+    /// English for any other language.
+    pub fn from_locale() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| {
+                let code = value.split(['_', '.', '@', '-']).next().unwrap_or_default();
+                parse_lang(code).ok()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub fn parse_lang(s: &str) -> Result<Lang, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "en" | "english" => Ok(Lang::En),
+        "de" | "german" | "deutsch" => Ok(Lang::De),
+        "ja" | "jp" | "japanese" => Ok(Lang::Ja),
+        _ => Err(format!(
+            "unsupported language '{}' (expected en, de or ja)",
+            s
+        )),
+    }
+}
+
+static LANG: Mutex<Lang> = Mutex::new(Lang::En);
+
+/// Print every subsequent message in `lang`.
+pub fn set_lang(lang: Lang) {
+    if let Ok(mut current) = LANG.lock() {
+        *current = lang;
+    }
+}
+
+/// The language messages are printed in.
+pub fn lang() -> Lang {
+    LANG.lock().map(|lang| *lang).unwrap_or_default()
+}
+
+/// A translated message. Placeholders in `{name}` form are filled in by [`tf`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    ProceedPlan,
+    ProceedAllFiles,
+    ProceedAllEpisodes,
+    YesProvided,
+    Aborting,
+    Processing,
+    BatchSummary,
+    ProposedPlan,
+    SimulatedTimeline,
+    JobDetails,
+    SampleEncode,
+    WrotePlan,
+    WroteTask,
+    DryRunStop,
+    DryRunStopPlan,
+    SampleEncodeStop,
+    ResolvedOnly,
+    Extracting,
+    ResolvingSplits,
+    Splitting,
+    AdjustingLengths,
+    Converting,
+    ExportedTrack,
+    ShiftSubtitles,
+    ShiftChapters,
+    NoChapters,
+    Remuxing,
+    StartsMatch,
+    TempSpace,
+    ProcessingComplete,
+    HeaderSource,
+    HeaderResolvedSplit,
+    HeaderDelay,
+    HeaderLabel,
+    HeaderConfidence,
+    HeaderSegment,
+    HeaderSourceSeconds,
+    HeaderAdjustment,
+    HeaderOutputSeconds,
+    HeaderParameter,
+    HeaderValue,
+    HeaderStage,
+    HeaderTempSpace,
+    HeaderChange,
+    BatchApplying,
+    ProcessedFiles,
+    LoadedSplitMap,
+    RunningPreHook,
+    RunningPostHook,
+    StreamPlan,
+    StreamUnchanged,
+    NoPlanChanged,
+    ServerRefreshed,
+    ServerRefreshFailed,
+    ConvertingFrames,
+    SyncPointsDrift,
+    DeterministicMode,
+    StopFileHint,
+    ResumingRun,
+    NothingToResume,
+    EvictedRuns,
+    IncompatibleStreams,
+    AutoMkv,
+    OriginalCodec,
+    LooksLikeCommentary,
+    DefaultStreamHint,
+    IgnoringBitrate,
+    LosslessCodec,
+    UserBitrate,
+    DetectedBitrate,
+    HistoryUnreadable,
+    AlreadyNudged,
+    DelaysOnTop,
+    ReusingExtracted,
+    CheckingLayout,
+    RemappingChannels,
+    MeasuringNoiseFloor,
+    NoiseFloor,
+    SampleSilent,
+    AligningReference,
+    MatchedTransients,
+    LoudnessCorrelated,
+    KeepingDelayReference,
+    MatchingOnset,
+    OnsetMatched,
+    KeepingDelayOnset,
+    AlreadyInSync,
+    ReusingSplits,
+    FindingQuietest,
+    FoundQuietestPeak,
+    FoundQuietest,
+    FoundSplitPoint,
+    MovedZeroCrossing,
+    TrimNotSilent,
+    RenderingWaveforms,
+    ExportingPreviews,
+    EncodingSample,
+    BelowConfidence,
+    ReusingConcatenated,
+    CorrectingDrift,
+    ReusingEncoded,
+    PaddingHead,
+    CuttingTail,
+    VerifiedTrack,
+    BitmapSubtitle,
+    ShiftedSubtitleStream,
+    RefreshedStats,
+    RemuxingProfile,
+    WroteProfile,
+    WroteHistorySidecar,
+    RecordedHistory,
+    WroteSubtitles,
+    StreamsMoved,
+    MaybeOutOfSync,
+    StartTimesUnchecked,
+    KeptTemp,
+    VideoInSync,
+    CheckingFfmpeg,
+    FfmpegFound,
+    FfmpegExecutable,
+    FfmpegVersion,
+    FfmpegCompatible,
+    FfmpegTooOld,
+    FfmpegTestedVersion,
+    FfmpegTestedWith,
+    FfmpegVersionUnparsed,
+    FfmpegNotFound,
+    InstallFfmpeg,
+    FfprobeFound,
+    FfprobeNotFound,
+    Ebur128Available,
+    Ebur128Missing,
+    Ebur128Needed,
+    FfmpegCheckComplete,
+    CheckingEnvironment,
+    DoctorReport,
+    NoProblems,
+    BuildsPassed,
+    ApplyingSeason,
+    ResolvingEpisode,
+    SkippingInSync,
+    FileFailed,
+    ProcessedEpisodes,
+    ProcessingDir,
+    Watching,
+    LeavingQueued,
+    PriorityFallback,
+    StoppedProcessing,
+    FileInSync,
+    MovedFile,
+    MoveFailed,
+    WatchStopped,
+    ComparingStreams,
+    MeasuringLoudness,
+    EstimatingOffset,
+    DelayFirstHint,
+    NoConstantOffset,
+    ReadingDelays,
+    DelaysFromReport,
+    UntiedDelays,
+    WroteTaskFiles,
+    ProjectExists,
+    CreatedProject,
+    ProjectNextSteps,
+    ProjectDir,
+    ProjectInput,
+    ProjectTask,
+    ProjectCreated,
+    NoAttempts,
+    RemovedDir,
+    RemovedTempDirs,
+    NoTempDirs,
+    TempDirsTotal,
+    ProjectAttempt,
+    AttemptRecorded,
+    AutoStream,
+    DetectingOffset,
+    SuggestedDelay,
+    CheckingStreamChannels,
+    FixChannelsHint,
+    ScanningArtifacts,
+    NoArtifacts,
+    FoundArtifacts,
+    ComparingWithVideo,
+    AudioStartsAfter,
+    AudioStartsBefore,
+    AudioEndsBefore,
+    AudioRunsPast,
+    AudioMatchesVideo,
+    CloseGapHint,
+    LookingForSilences,
+    NoSilences,
+    SilencesAtEdges,
+    FoundRangeCandidates,
+    LookingForBlack,
+    NoBreaks,
+    FoundBreaks,
+    SearchingQuietPoints,
+    RangeNoiseFloor,
+    RangeSilent,
+    SpeechSkipped,
+    SplitRangeHint,
+    NoChannelIssues,
+    InspectingStreams,
+    NoAudioStreams,
+    ContainerDuration,
+    StreamHistoryUnreadable,
+    AppliedEdits,
+    HistoryReadFrom,
+    TracksAlreadyNudged,
+    IndexHint,
+    ContainerRejected,
+    Stalled,
+    StopFileFound,
+    Unknown,
+    Layout,
+    AudioShorter,
+    AudioLonger,
+    Lossless,
+    Testing,
+    TestingThorough,
+    NoPointAbove,
+    NoPointAbovePeak,
+    MeasuredPoints,
+    MeasuredPointsRejected,
+    TempSpaceAfter,
+    StatusDone,
+    StageExtracting,
+    StageAligning,
+    StageResolving,
+    StageSplitting,
+    StageConcatenating,
+    StageAdjusting,
+    StageEncoding,
+    StageRemuxing,
+    ProgressDoneIn,
+    ProgressEta,
+    HeaderApplied,
+    HeaderArtifact,
+    HeaderBitrate,
+    HeaderBlack,
+    HeaderChannel,
+    HeaderChannels,
+    HeaderChapter,
+    HeaderCheck,
+    HeaderCodec,
+    HeaderContentStarts,
+    HeaderDetail,
+    HeaderDifference,
+    HeaderDir,
+    HeaderDrift,
+    HeaderDuration,
+    HeaderDurationSeconds,
+    HeaderEdit,
+    HeaderEnd,
+    HeaderEpisode,
+    HeaderError,
+    HeaderFinishedStages,
+    HeaderFirst,
+    HeaderFrom,
+    HeaderIndex,
+    HeaderInitialDelay,
+    HeaderInput,
+    HeaderInputStart,
+    HeaderLanguage,
+    HeaderLastUsed,
+    HeaderLength,
+    HeaderLevel,
+    HeaderLoudness,
+    HeaderMeasurement,
+    HeaderNewStart,
+    HeaderOutput,
+    HeaderOutputStart,
+    HeaderProperty,
+    HeaderRank,
+    HeaderReason,
+    HeaderResult,
+    HeaderSampleRate,
+    HeaderSecond,
+    HeaderSection,
+    HeaderShift,
+    HeaderSize,
+    HeaderSplitPoints,
+    HeaderSplitsAndDelays,
+    HeaderStart,
+    HeaderStartSeconds,
+    HeaderStarted,
+    HeaderStatus,
+    HeaderStream,
+    HeaderSuggestedOption,
+    HeaderTaskFile,
+    HeaderTime,
+    HeaderTitle,
+    HeaderTrack,
+    HeaderTruePeak,
+    HeaderType,
+    HeaderVersion,
+    HeaderZeroCrossings,
+    HeaderContainerDelta,
+}
+
+impl Msg {
+    /// The message in English, German and Japanese.
+    fn texts(self) -> [&'static str; 3] {
+        match self {
+            Msg::ProceedPlan => [
+                "Proceed with this plan? [y/N]",
+                "Mit diesem Plan fortfahren? [j/N]",
+                "この計画で続行しますか？ [y/N]",
+            ],
+            Msg::ProceedAllFiles => [
+                "Proceed with the same plan for all files? [y/N]",
+                "Mit demselben Plan für alle Dateien fortfahren? [j/N]",
+                "すべてのファイルに同じ計画で続行しますか？ [y/N]",
+            ],
+            Msg::ProceedAllEpisodes => [
+                "Proceed with this plan for all episodes? [y/N]",
+                "Mit diesem Plan für alle Episoden fortfahren? [j/N]",
+                "すべてのエピソードにこの計画で続行しますか？ [y/N]",
+            ],
+            Msg::YesProvided => [
+                "--yes flag provided, proceeding without confirmation.",
+                "--yes angegeben, fahre ohne Bestätigung fort.",
+                "--yes が指定されたため、確認せずに続行します。",
+            ],
+            Msg::Aborting => [
+                "Aborting operation.",
+                "Vorgang abgebrochen.",
+                "処理を中止しました。",
+            ],
+            Msg::Processing => ["Processing {file}", "Verarbeite {file}", "{file} を処理中"],
+            Msg::BatchSummary => ["Batch Summary:", "Zusammenfassung:", "一括処理の概要:"],
+            Msg::ProposedPlan => [
+                "Proposed Splitting Plan:",
+                "Vorgeschlagener Schnittplan:",
+                "分割計画案:",
+            ],
+            Msg::SimulatedTimeline => [
+                "Simulated Timeline:",
+                "Simulierte Zeitachse:",
+                "シミュレーションしたタイムライン:",
+            ],
+            Msg::JobDetails => ["Job Details:", "Auftragsdetails:", "ジョブの詳細:"],
+            Msg::SampleEncode => ["Sample Encode:", "Probe-Encode:", "サンプルエンコード:"],
+            Msg::WrotePlan => [
+                "Wrote plan to {path}",
+                "Plan nach {path} geschrieben",
+                "計画を {path} に書き出しました",
+            ],
+            Msg::WroteTask => [
+                "Wrote task to {path}",
+                "Task-Datei nach {path} geschrieben",
+                "タスクを {path} に書き出しました",
+            ],
+            Msg::DryRunStop => [
+                "Dry run: stopping before processing; nothing was re-encoded or written.",
+                "Probelauf: Stopp vor der Verarbeitung; nichts wurde neu kodiert oder geschrieben.",
+                "ドライラン: 処理の前に停止しました。再エンコードも書き込みも行っていません。",
+            ],
+            Msg::DryRunStopPlan => [
+                "Dry run: stopping before processing; nothing was re-encoded and the output was not written, the plan is in {path}.",
+                "Probelauf: Stopp vor der Verarbeitung; nichts wurde neu kodiert und die Ausgabe nicht geschrieben, der Plan steht in {path}.",
+                "ドライラン: 処理の前に停止しました。再エンコードも出力の書き込みも行っていません。計画は {path} にあります。",
+            ],
+            Msg::SampleEncodeStop => [
+                "Sample encode: stopping before processing; the output was not written.",
+                "Probe-Encode: Stopp vor der Verarbeitung; die Ausgabe wurde nicht geschrieben.",
+                "サンプルエンコード: 処理の前に停止しました。出力は書き込まれていません。",
+            ],
+            Msg::ResolvedOnly => [
+                "Resolved the plan; writing the task file without processing.",
+                "Plan ermittelt; schreibe die Task-Datei ohne Verarbeitung.",
+                "計画を解決しました。処理せずにタスクファイルを書き出します。",
+            ],
+            Msg::Extracting => [
+                "Extracting target audio track to a temporary {format} file...",
+                "Extrahiere die Zielaudiospur in eine temporäre {format}-Datei...",
+                "対象の音声トラックを一時 {format} ファイルに抽出中...",
+            ],
+            Msg::ResolvingSplits => [
+                "Resolving split points...",
+                "Ermittle Schnittpunkte...",
+                "分割点を解決中...",
+            ],
+            Msg::Splitting => [
+                "Splitting audio into parts...",
+                "Teile das Audio in Abschnitte...",
+                "音声を分割中...",
+            ],
+            Msg::AdjustingLengths => [
+                "Adjusting Audio Lengths...",
+                "Passe Audiolängen an...",
+                "音声の長さを調整中...",
+            ],
+            Msg::Converting => [
+                "Converting Audio Back to Original Codec...",
+                "Wandle das Audio zurück in den Originalcodec...",
+                "音声を元のコーデックに変換中...",
+            ],
+            Msg::ExportedTrack => [
+                "Exported corrected audio track to {path}",
+                "Korrigierte Audiospur nach {path} exportiert",
+                "補正した音声トラックを {path} に書き出しました",
+            ],
+            Msg::ShiftSubtitles => [
+                "Shift Subtitle Streams..",
+                "Verschiebe Untertitelspuren..",
+                "字幕ストリームをずらしています..",
+            ],
+            Msg::ShiftChapters => [
+                "Shift Chapters..",
+                "Verschiebe Kapitel..",
+                "チャプターをずらしています..",
+            ],
+            Msg::NoChapters => [
+                "The input has no chapters to shift",
+                "Die Eingabe hat keine Kapitel zum Verschieben",
+                "入力にはずらすチャプターがありません",
+            ],
+            Msg::Remuxing => [
+                "Remux Audio Back in Place of the Original..",
+                "Muxe das Audio anstelle des Originals zurück..",
+                "元の音声と置き換えてリマックス中..",
+            ],
+            Msg::StartsMatch => [
+                "Stream start times of the output match the input",
+                "Die Startzeiten der Spuren in der Ausgabe stimmen mit der Eingabe überein",
+                "出力のストリーム開始時刻は入力と一致しています",
+            ],
+            Msg::TempSpace => [
+                "Temp Space Usage:",
+                "Belegter temporärer Speicher:",
+                "一時領域の使用量:",
+            ],
+            Msg::ProcessingComplete => [
+                "Processing complete! Output: {output}",
+                "Verarbeitung abgeschlossen! Ausgabe: {output}",
+                "処理が完了しました！ 出力: {output}",
+            ],
+            Msg::HeaderSource => ["Source", "Quelle", "ソース"],
+            Msg::HeaderResolvedSplit => ["Resolved Split (s)", "Schnittpunkt (s)", "分割点 (秒)"],
+            Msg::HeaderDelay => ["Delay (ms)", "Verzögerung (ms)", "遅延 (ms)"],
+            Msg::HeaderLabel => ["Label", "Bezeichnung", "ラベル"],
+            Msg::HeaderConfidence => ["Confidence", "Konfidenz", "信頼度"],
+            Msg::HeaderSegment => ["Segment", "Abschnitt", "セグメント"],
+            Msg::HeaderSourceSeconds => ["Source (s)", "Quelle (s)", "ソース (秒)"],
+            Msg::HeaderAdjustment => [
+                "Silence / Trim (s)",
+                "Stille / Kürzung (s)",
+                "無音 / カット (秒)",
+            ],
+            Msg::HeaderOutputSeconds => ["Output (s)", "Ausgabe (s)", "出力 (秒)"],
+            Msg::HeaderParameter => ["Parameter", "Parameter", "パラメーター"],
+            Msg::HeaderValue => ["Value", "Wert", "値"],
+            Msg::HeaderStage => ["Stage", "Schritt", "ステージ"],
+            Msg::HeaderTempSpace => ["Temp Space Used", "Belegter Speicher", "一時領域の使用量"],
+            Msg::HeaderChange => ["Change", "Änderung", "増減"],
+            Msg::BatchApplying => [
+                "Applying the same plan to {count} files, writing to {dir}",
+                "Wende denselben Plan auf {count} Dateien an, Ausgabe nach {dir}",
+                "{count} 個のファイルに同じ計画を適用し、{dir} に書き出します",
+            ],
+            Msg::ProcessedFiles => [
+                "Processed {count} files into {dir}",
+                "{count} Dateien nach {dir} verarbeitet",
+                "{count} 個のファイルを処理し、{dir} に書き出しました",
+            ],
+            Msg::LoadedSplitMap => [
+                "Loaded the delays and splits from split map {path}",
+                "Verzögerungen und Schnittpunkte aus der Split-Map {path} geladen",
+                "分割マップ {path} から遅延と分割点を読み込みました",
+            ],
+            Msg::RunningPreHook => [
+                "Running the pre hook: {command}",
+                "Führe den Pre-Hook aus: {command}",
+                "事前フックを実行中: {command}",
+            ],
+            Msg::RunningPostHook => [
+                "Running the post hook: {command}",
+                "Führe den Post-Hook aus: {command}",
+                "事後フックを実行中: {command}",
+            ],
+            Msg::StreamPlan => [
+                "Stream plan {number} of {count}: audio stream #{stream} ({codec})",
+                "Stream-Plan {number} von {count}: Audiospur #{stream} ({codec})",
+                "ストリーム計画 {number}/{count}: 音声ストリーム #{stream} ({codec})",
+            ],
+            Msg::StreamUnchanged => [
+                "Audio stream #{stream} is left unchanged",
+                "Audiospur #{stream} bleibt unverändert",
+                "音声ストリーム #{stream} は変更しません",
+            ],
+            Msg::NoPlanChanged => [
+                "No stream plan changed its stream; nothing to remux",
+                "Kein Stream-Plan hat seine Spur geändert; nichts zu remuxen",
+                "どのストリーム計画もストリームを変更しなかったため、リマックスは不要です",
+            ],
+            Msg::ServerRefreshed => [
+                "Asked {server} to refresh {path}",
+                "{server} wurde gebeten, {path} zu aktualisieren",
+                "{server} に {path} の更新を依頼しました",
+            ],
+            Msg::ServerRefreshFailed => [
+                "Could not refresh the file on {server}: {error}",
+                "Die Datei konnte auf {server} nicht aktualisiert werden: {error}",
+                "{server} でファイルを更新できませんでした: {error}",
+            ],
+            Msg::ConvertingFrames => [
+                "Converting frame counts at {fps} fps",
+                "Rechne Frame-Angaben mit {fps} fps um",
+                "フレーム数を {fps} fps で換算します",
+            ],
+            Msg::SyncPointsDrift => [
+                "Sync points {first_time}s: {first_delay} ms and {second_time}s: {second_delay} ms give a drift of {drift} ppm and an initial delay of {delay} ms",
+                "Die Sync-Punkte {first_time}s: {first_delay} ms und {second_time}s: {second_delay} ms ergeben eine Drift von {drift} ppm und eine Anfangsverzögerung von {delay} ms",
+                "同期点 {first_time}秒: {first_delay} ms と {second_time}秒: {second_delay} ms から、ドリフト {drift} ppm と初期遅延 {delay} ms を求めました",
+            ],
+            Msg::DeterministicMode => [
+                "Deterministic mode: ffmpeg runs with one thread and writes bitexact output",
+                "Deterministischer Modus: ffmpeg läuft mit einem Thread und schreibt bitgenaue Ausgabe",
+                "決定論的モード: ffmpeg を 1 スレッドで実行し、ビット単位で同一の出力を書き出します",
+            ],
+            Msg::StopFileHint => [
+                "Create {path} (or press Ctrl+C) to stop after the current stage",
+                "Lege {path} an (oder drücke Strg+C), um nach dem aktuellen Schritt anzuhalten",
+                "現在のステージの後で停止するには {path} を作成してください (または Ctrl+C を押してください)",
+            ],
+            Msg::ResumingRun => [
+                "Resuming the failed run in {dir} ({stages})",
+                "Setze den fehlgeschlagenen Lauf in {dir} fort ({stages})",
+                "{dir} の失敗した実行を再開します ({stages})",
+            ],
+            Msg::NothingToResume => [
+                "No failed run of this job to resume, starting from the beginning",
+                "Kein fehlgeschlagener Lauf dieses Auftrags zum Fortsetzen, beginne von vorn",
+                "再開できる失敗した実行がないため、最初から始めます",
+            ],
+            Msg::EvictedRuns => [
+                "Removed {count} temp dir(s) of failed runs unused for {days} days or beyond {size} in total ({freed} freed)",
+                "{count} temporäre(s) Verzeichnis(se) fehlgeschlagener Läufe entfernt, die {days} Tage ungenutzt waren oder insgesamt über {size} hinausgingen ({freed} freigegeben)",
+                "{days} 日間使われていないか合計 {size} を超えた、失敗した実行の一時ディレクトリを {count} 個削除しました ({freed} 解放)",
+            ],
+            Msg::IncompatibleStreams => [
+                "The output container cannot hold these streams:",
+                "Der Ausgabecontainer kann diese Spuren nicht aufnehmen:",
+                "出力コンテナーはこれらのストリームを格納できません:",
+            ],
+            Msg::AutoMkv => [
+                "--auto-mkv: writing {path} instead",
+                "--auto-mkv: schreibe stattdessen {path}",
+                "--auto-mkv: 代わりに {path} を書き出します",
+            ],
+            Msg::OriginalCodec => [
+                "Original audio codec: {codec}",
+                "Ursprünglicher Audiocodec: {codec}",
+                "元の音声コーデック: {codec}",
+            ],
+            Msg::LooksLikeCommentary => [
+                "Audio stream #{stream} ({title}) looks like commentary or audio description, not the main mix.",
+                "Audiospur #{stream} ({title}) sieht nach Kommentar oder Audiodeskription aus, nicht nach der Hauptmischung.",
+                "音声ストリーム #{stream} ({title}) はメインの音声ではなく、コメンタリーか音声解説のようです。",
+            ],
+            Msg::DefaultStreamHint => [
+                "The default audio stream is #{stream} ({language}, {title}); pass --stream {stream} (or --stream auto) to process it instead.",
+                "Die Standard-Audiospur ist #{stream} ({language}, {title}); gib --stream {stream} (oder --stream auto) an, um stattdessen sie zu verarbeiten.",
+                "既定の音声ストリームは #{stream} ({language}, {title}) です。そちらを処理するには --stream {stream} (または --stream auto) を指定してください。",
+            ],
+            Msg::IgnoringBitrate => [
+                "Ignoring bitrate {bitrate}: {codec} is lossless and is encoded back losslessly",
+                "Ignoriere die Bitrate {bitrate}: {codec} ist verlustfrei und wird verlustfrei zurückkodiert",
+                "ビットレート {bitrate} を無視します: {codec} はロスレスのため、ロスレスで再エンコードします",
+            ],
+            Msg::LosslessCodec => [
+                "{codec} is lossless; the edited track is encoded back to {codec} without a bitrate",
+                "{codec} ist verlustfrei; die bearbeitete Spur wird ohne Bitrate zurück nach {codec} kodiert",
+                "{codec} はロスレスです。編集したトラックはビットレートを指定せずに {codec} へ再エンコードします",
+            ],
+            Msg::UserBitrate => [
+                "Using user-provided bitrate: {bitrate}",
+                "Verwende die angegebene Bitrate: {bitrate}",
+                "指定されたビットレートを使用します: {bitrate}",
+            ],
+            Msg::DetectedBitrate => [
+                "Automatically detected bitrate: {bitrate}",
+                "Automatisch erkannte Bitrate: {bitrate}",
+                "自動検出したビットレート: {bitrate}",
+            ],
+            Msg::HistoryUnreadable => [
+                "Could not read the edit history of the track: {error}",
+                "Der Bearbeitungsverlauf der Spur konnte nicht gelesen werden: {error}",
+                "トラックの編集履歴を読み取れませんでした: {error}",
+            ],
+            Msg::AlreadyNudged => [
+                "Audio stream #{stream} was already nudged by sync-nudger {count} time(s), last on {date} UTC ({edit}).",
+                "Audiospur #{stream} wurde bereits {count}-mal mit sync-nudger bearbeitet, zuletzt am {date} UTC ({edit}).",
+                "音声ストリーム #{stream} は sync-nudger で既に {count} 回調整されています。最後は {date} UTC ({edit}) です。",
+            ],
+            Msg::DelaysOnTop => [
+                "The delays of this run are applied on top of the earlier ones; make sure they are relative to the current track.",
+                "Die Verzögerungen dieses Laufs kommen zu den früheren hinzu; sie müssen sich auf die aktuelle Spur beziehen.",
+                "今回の遅延は以前の遅延に上乗せされます。現在のトラックを基準にした値であることを確認してください。",
+            ],
+            Msg::ReusingExtracted => [
+                "Reusing the extracted audio of the failed run",
+                "Verwende das extrahierte Audio des fehlgeschlagenen Laufs wieder",
+                "失敗した実行で抽出した音声を再利用します",
+            ],
+            Msg::CheckingLayout => [
+                "Checking channel layout...",
+                "Prüfe die Kanalbelegung...",
+                "チャンネルレイアウトを確認中...",
+            ],
+            Msg::RemappingChannels => [
+                "Remapping channels with: {pan}",
+                "Ordne die Kanäle neu zu mit: {pan}",
+                "チャンネルを再割り当てします: {pan}",
+            ],
+            Msg::MeasuringNoiseFloor => [
+                "Measuring noise floor...",
+                "Messe das Grundrauschen...",
+                "ノイズフロアを測定中...",
+            ],
+            Msg::NoiseFloor => [
+                "Noise floor {floor} LUFS, silence threshold set to {threshold} LUFS",
+                "Grundrauschen {floor} LUFS, Stilleschwelle auf {threshold} LUFS gesetzt",
+                "ノイズフロア {floor} LUFS、無音しきい値を {threshold} LUFS に設定しました",
+            ],
+            Msg::SampleSilent => [
+                "The measured sample is digital silence; keeping silence threshold {threshold} LUFS",
+                "Die gemessene Probe ist digitale Stille; behalte die Stilleschwelle von {threshold} LUFS bei",
+                "測定したサンプルはデジタル無音です。無音しきい値 {threshold} LUFS を維持します",
+            ],
+            Msg::AligningReference => [
+                "Aligning against reference: {reference}",
+                "Richte an der Referenz aus: {reference}",
+                "リファレンスに合わせています: {reference}",
+            ],
+            Msg::MatchedTransients => [
+                "Matched {matches} transients (target: {target}, reference: {reference}), offset {offset} ms",
+                "{matches} Transienten zugeordnet (Ziel: {target}, Referenz: {reference}), Versatz {offset} ms",
+                "{matches} 個のトランジェントが一致しました (対象: {target}、リファレンス: {reference})。オフセット {offset} ms",
+            ],
+            Msg::LoudnessCorrelated => [
+                "Too few sync marks; cross-correlated the loudness instead (correlation {correlation}), offset {offset} ms",
+                "Zu wenige Sync-Marken; stattdessen die Lautheit kreuzkorreliert (Korrelation {correlation}), Versatz {offset} ms",
+                "同期マークが少なすぎるため、代わりにラウドネスを相互相関しました (相関 {correlation})。オフセット {offset} ms",
+            ],
+            Msg::KeepingDelayReference => [
+                "Keeping provided initial delay of {delay} ms (reference suggests {suggested} ms)",
+                "Behalte die angegebene Anfangsverzögerung von {delay} ms (die Referenz ergibt {suggested} ms)",
+                "指定された初期遅延 {delay} ms を維持します (リファレンスからは {suggested} ms)",
+            ],
+            Msg::MatchingOnset => [
+                "Matching first onset against audio stream {stream}",
+                "Gleiche den ersten Einsatz mit Audiospur {stream} ab",
+                "最初の立ち上がりを音声ストリーム {stream} と照合中",
+            ],
+            Msg::OnsetMatched => [
+                "First onset at {target} s (target) and {reference} s (stream {stream}), offset {offset} ms",
+                "Erster Einsatz bei {target} s (Ziel) und {reference} s (Spur {stream}), Versatz {offset} ms",
+                "最初の立ち上がりは {target} 秒 (対象) と {reference} 秒 (ストリーム {stream})。オフセット {offset} ms",
+            ],
+            Msg::KeepingDelayOnset => [
+                "Keeping provided initial delay of {delay} ms (onset suggests {suggested} ms)",
+                "Behalte die angegebene Anfangsverzögerung von {delay} ms (der Einsatz ergibt {suggested} ms)",
+                "指定された初期遅延 {delay} ms を維持します (立ち上がりからは {suggested} ms)",
+            ],
+            Msg::AlreadyInSync => [
+                "Already in sync: measured offset {offset} ms is within ±{threshold} ms, nothing to do.",
+                "Bereits synchron: der gemessene Versatz von {offset} ms liegt innerhalb von ±{threshold} ms, nichts zu tun.",
+                "既に同期しています: 測定したオフセット {offset} ms は ±{threshold} ms 以内のため、処理は不要です。",
+            ],
+            Msg::ReusingSplits => [
+                "Reusing the split points resolved by the failed run",
+                "Verwende die vom fehlgeschlagenen Lauf ermittelten Schnittpunkte wieder",
+                "失敗した実行で解決した分割点を再利用します",
+            ],
+            Msg::FindingQuietest => [
+                "Finding quietest point in range {start}s - {end}s",
+                "Suche die leiseste Stelle im Bereich {start}s - {end}s",
+                "{start}秒 - {end}秒 の範囲で最も静かな点を探しています",
+            ],
+            Msg::FoundQuietestPeak => [
+                "Found quietest point at {time}s (Loudness: {loudness} LUFS, True Peak: {peak} dBFS)",
+                "Leiseste Stelle bei {time}s gefunden (Lautheit: {loudness} LUFS, True Peak: {peak} dBFS)",
+                "最も静かな点を {time}秒 に見つけました (ラウドネス: {loudness} LUFS、トゥルーピーク: {peak} dBFS)",
+            ],
+            Msg::FoundQuietest => [
+                "Found quietest point at {time}s (Loudness: {loudness} LUFS)",
+                "Leiseste Stelle bei {time}s gefunden (Lautheit: {loudness} LUFS)",
+                "最も静かな点を {time}秒 に見つけました (ラウドネス: {loudness} LUFS)",
+            ],
+            Msg::FoundSplitPoint => [
+                "Found split point at {time}s ({detector} detector)",
+                "Schnittpunkt bei {time}s gefunden (Detektor {detector})",
+                "分割点を {time}秒 に見つけました ({detector} 検出器)",
+            ],
+            Msg::MovedZeroCrossing => [
+                "Moved to the nearest zero crossing (from {from}s to {to}s)",
+                "Zum nächsten Nulldurchgang verschoben (von {from}s auf {to}s)",
+                "最も近いゼロクロスに移動しました ({from}秒 → {to}秒)",
+            ],
+            Msg::TrimNotSilent => [
+                "Warning: The first {seconds} s trimmed by the initial delay are not silent (peak {peak} dBFS).",
+                "Warnung: Die ersten {seconds} s, die die Anfangsverzögerung abschneidet, sind nicht still (Spitze {peak} dBFS).",
+                "警告: 初期遅延でカットされる最初の {seconds} 秒は無音ではありません (ピーク {peak} dBFS)。",
+            ],
+            Msg::RenderingWaveforms => [
+                "Rendering waveforms of ±{seconds}s around {count} split point(s) into: {dir}",
+                "Zeichne Wellenformen von ±{seconds}s um {count} Schnittpunkt(e) nach: {dir}",
+                "{count} 個の分割点の前後 ±{seconds}秒 の波形を描画中: {dir}",
+            ],
+            Msg::ExportingPreviews => [
+                "Exporting ±{seconds}s of audio around {count} split point(s), with and without the edit, into: {dir}",
+                "Exportiere ±{seconds}s Audio um {count} Schnittpunkt(e), mit und ohne Bearbeitung, nach: {dir}",
+                "{count} 個の分割点の前後 ±{seconds}秒 の音声を、編集あり・なしで書き出し中: {dir}",
+            ],
+            Msg::EncodingSample => [
+                "Encoding a sample of {start}s - {end}s with {codec} at {bitrate}...",
+                "Kodiere eine Probe von {start}s - {end}s mit {codec} bei {bitrate}...",
+                "{start}秒 - {end}秒 のサンプルを {codec} ({bitrate}) でエンコード中...",
+            ],
+            Msg::BelowConfidence => [
+                "Below the minimum confidence of {confidence}: {splits}",
+                "Unter der Mindestkonfidenz von {confidence}: {splits}",
+                "最低信頼度 {confidence} を下回っています: {splits}",
+            ],
+            Msg::ReusingConcatenated => [
+                "Reusing the split and concatenated audio of the failed run",
+                "Verwende das geteilte und zusammengefügte Audio des fehlgeschlagenen Laufs wieder",
+                "失敗した実行で分割・結合した音声を再利用します",
+            ],
+            Msg::CorrectingDrift => [
+                "Correcting clock drift of {ppm} ppm...",
+                "Korrigiere eine Taktdrift von {ppm} ppm...",
+                "{ppm} ppm のクロックドリフトを補正中...",
+            ],
+            Msg::ReusingEncoded => [
+                "Reusing the encoded audio of the failed run",
+                "Verwende das kodierte Audio des fehlgeschlagenen Laufs wieder",
+                "失敗した実行でエンコードした音声を再利用します",
+            ],
+            Msg::PaddingHead => [
+                "Padding {seconds} s of silence before the audio, where the video starts first",
+                "Füge {seconds} s Stille vor dem Audio ein, wo das Video zuerst beginnt",
+                "映像が先に始まるため、音声の前に {seconds} 秒の無音を追加します",
+            ],
+            Msg::CuttingTail => [
+                "Cutting {seconds} s of audio that runs past the end of the video",
+                "Schneide {seconds} s Audio ab, die über das Ende des Videos hinausgehen",
+                "映像の終わりを超える {seconds} 秒の音声をカットします",
+            ],
+            Msg::VerifiedTrack => [
+                "Verified encoded track: {codec}, {channels} channels, {rate} Hz, {duration} s",
+                "Kodierte Spur geprüft: {codec}, {channels} Kanäle, {rate} Hz, {duration} s",
+                "エンコードしたトラックを検証しました: {codec}、{channels} チャンネル、{rate} Hz、{duration} 秒",
+            ],
+            Msg::BitmapSubtitle => [
+                "Subtitle stream #{stream} ({codec}) is a bitmap format and is kept unchanged.",
+                "Untertitelspur #{stream} ({codec}) ist ein Bitmap-Format und bleibt unverändert.",
+                "字幕ストリーム #{stream} ({codec}) はビットマップ形式のため、変更しません。",
+            ],
+            Msg::ShiftedSubtitleStream => [
+                "Shifted subtitle stream #{stream} ({codec}): {cues} cue(s), {dropped} dropped",
+                "Untertitelspur #{stream} ({codec}) verschoben: {cues} Einträge, {dropped} verworfen",
+                "字幕ストリーム #{stream} ({codec}) をずらしました: {cues} 件、削除 {dropped} 件",
+            ],
+            Msg::RefreshedStats => [
+                "Refreshed container duration and track statistics ({tool})",
+                "Containerdauer und Spurstatistiken aktualisiert ({tool})",
+                "コンテナーの長さとトラック統計を更新しました ({tool})",
+            ],
+            Msg::RemuxingProfile => [
+                "Remuxing the {offset} ms profile to {path}...",
+                "Remuxe das Profil mit {offset} ms nach {path}...",
+                "{offset} ms のプロファイルを {path} にリマックス中...",
+            ],
+            Msg::WroteProfile => [
+                "Wrote {path} with the audio shifted by a further {offset} ms",
+                "{path} mit um weitere {offset} ms verschobenem Audio geschrieben",
+                "音声をさらに {offset} ms ずらした {path} を書き出しました",
+            ],
+            Msg::WroteHistorySidecar => [
+                "Wrote edit history to {path} (the output container cannot hold track tags)",
+                "Bearbeitungsverlauf nach {path} geschrieben (der Ausgabecontainer kann keine Spur-Tags aufnehmen)",
+                "編集履歴を {path} に書き出しました (出力コンテナーはトラックタグを保持できません)",
+            ],
+            Msg::RecordedHistory => [
+                "Recorded edit history in the track tags",
+                "Bearbeitungsverlauf in den Spur-Tags gespeichert",
+                "編集履歴をトラックタグに記録しました",
+            ],
+            Msg::WroteSubtitles => [
+                "Wrote re-timed subtitles to {path} ({cues} cue(s), {dropped} dropped)",
+                "Neu getaktete Untertitel nach {path} geschrieben ({cues} Einträge, {dropped} verworfen)",
+                "タイミングを調整した字幕を {path} に書き出しました ({cues} 件、削除 {dropped} 件)",
+            ],
+            Msg::StreamsMoved => [
+                "The muxer moved these streams against the others:",
+                "Der Muxer hat diese Spuren gegenüber den anderen verschoben:",
+                "マルチプレクサーがこれらのストリームを他のストリームに対してずらしました:",
+            ],
+            Msg::MaybeOutOfSync => [
+                "The output may still be out of sync. Remux it with mkvmerge, or run again with --avoid-negative-ts disabled (or make_zero), and check the start times with ffprobe.",
+                "Die Ausgabe ist möglicherweise weiterhin asynchron. Remuxe sie mit mkvmerge oder starte erneut mit --avoid-negative-ts disabled (oder make_zero) und prüfe die Startzeiten mit ffprobe.",
+                "出力はまだ同期がずれている可能性があります。mkvmerge でリマックスするか、--avoid-negative-ts disabled (または make_zero) で再実行し、ffprobe で開始時刻を確認してください。",
+            ],
+            Msg::StartTimesUnchecked => [
+                "Could not check the stream start times of the output: {error}",
+                "Die Startzeiten der Spuren in der Ausgabe konnten nicht geprüft werden: {error}",
+                "出力のストリーム開始時刻を確認できませんでした: {error}",
+            ],
+            Msg::KeptTemp => [
+                "Kept the intermediate files in {dir}",
+                "Zwischendateien in {dir} behalten",
+                "中間ファイルを {dir} に残しました",
+            ],
+            Msg::VideoInSync => [
+                "An initial delay of 0 ms leaves the video in sync, nothing to do",
+                "Eine Anfangsverzögerung von 0 ms lässt das Video synchron, nichts zu tun",
+                "初期遅延が 0 ms のため映像は同期したままです。処理は不要です",
+            ],
+            Msg::CheckingFfmpeg => [
+                "Checking FFmpeg installation...",
+                "Prüfe die FFmpeg-Installation...",
+                "FFmpeg のインストールを確認中...",
+            ],
+            Msg::FfmpegFound => [
+                "FFmpeg found:",
+                "FFmpeg gefunden:",
+                "FFmpeg が見つかりました:",
+            ],
+            Msg::FfmpegExecutable => [
+                "Executable: {path}",
+                "Programm: {path}",
+                "実行ファイル: {path}",
+            ],
+            Msg::FfmpegVersion => [
+                "Version: {major}.{minor}.{patch}",
+                "Version: {major}.{minor}.{patch}",
+                "バージョン: {major}.{minor}.{patch}",
+            ],
+            Msg::FfmpegCompatible => [
+                "Status: ✅ Compatible (minimum required: 4.0.0)",
+                "Status: ✅ Kompatibel (mindestens erforderlich: 4.0.0)",
+                "状態: ✅ 対応 (最低要件: 4.0.0)",
+            ],
+            Msg::FfmpegTooOld => [
+                "Status: ❌ Too old (minimum required: 4.0.0)",
+                "Status: ❌ Zu alt (mindestens erforderlich: 4.0.0)",
+                "状態: ❌ 古すぎます (最低要件: 4.0.0)",
+            ],
+            Msg::FfmpegTestedVersion => [
+                "Note: This is the tested version",
+                "Hinweis: Dies ist die getestete Version",
+                "注: テスト済みのバージョンです",
+            ],
+            Msg::FfmpegTestedWith => [
+                "Note: Tested with version 7.1.x",
+                "Hinweis: Getestet mit Version 7.1.x",
+                "注: バージョン 7.1.x でテストしています",
+            ],
+            Msg::FfmpegVersionUnparsed => [
+                "Could not parse FFmpeg version from output",
+                "Die FFmpeg-Version konnte nicht aus der Ausgabe gelesen werden",
+                "出力から FFmpeg のバージョンを読み取れませんでした",
+            ],
+            Msg::FfmpegNotFound => [
+                "FFmpeg not found: {path}",
+                "FFmpeg nicht gefunden: {path}",
+                "FFmpeg が見つかりません: {path}",
+            ],
+            Msg::InstallFfmpeg => [
+                "Please install FFmpeg and ensure it's accessible from the command line, or point --ffmpeg-path at it",
+                "Bitte installiere FFmpeg und stelle sicher, dass es auf der Kommandozeile erreichbar ist, oder gib es mit --ffmpeg-path an",
+                "FFmpeg をインストールしてコマンドラインから使えるようにするか、--ffmpeg-path で指定してください",
+            ],
+            Msg::FfprobeFound => [
+                "FFprobe found and working ({path})",
+                "FFprobe gefunden und funktionsfähig ({path})",
+                "FFprobe が見つかり、動作しています ({path})",
+            ],
+            Msg::FfprobeNotFound => [
+                "FFprobe not found: {path}",
+                "FFprobe nicht gefunden: {path}",
+                "FFprobe が見つかりません: {path}",
+            ],
+            Msg::Ebur128Available => [
+                "Required filter 'ebur128' is available",
+                "Der benötigte Filter 'ebur128' ist verfügbar",
+                "必要なフィルター 'ebur128' は利用できます",
+            ],
+            Msg::Ebur128Missing => [
+                "Required filter 'ebur128' not found",
+                "Der benötigte Filter 'ebur128' wurde nicht gefunden",
+                "必要なフィルター 'ebur128' が見つかりません",
+            ],
+            Msg::Ebur128Needed => [
+                "This filter is needed for loudness analysis",
+                "Dieser Filter wird für die Lautheitsanalyse benötigt",
+                "このフィルターはラウドネス解析に必要です",
+            ],
+            Msg::FfmpegCheckComplete => [
+                "FFmpeg check complete!",
+                "FFmpeg-Prüfung abgeschlossen!",
+                "FFmpeg の確認が完了しました！",
+            ],
+            Msg::CheckingEnvironment => [
+                "Checking the installation and environment...",
+                "Prüfe Installation und Umgebung...",
+                "インストールと環境を確認中...",
+            ],
+            Msg::DoctorReport => [
+                "Report (paste this into a bug report):",
+                "Bericht (zum Einfügen in einen Fehlerbericht):",
+                "レポート (バグ報告に貼り付けてください):",
+            ],
+            Msg::NoProblems => [
+                "No problems found ({warnings} warnings).",
+                "Keine Probleme gefunden ({warnings} Warnungen).",
+                "問題は見つかりませんでした (警告 {warnings} 件)。",
+            ],
+            Msg::BuildsPassed => [
+                "All {count} ffmpeg builds passed.",
+                "Alle {count} ffmpeg-Builds bestanden.",
+                "{count} 個の ffmpeg ビルドがすべて合格しました。",
+            ],
+            Msg::ApplyingSeason => [
+                "Applying {task} to {count} episodes in {dir}",
+                "Wende {task} auf {count} Episoden in {dir} an",
+                "{dir} の {count} 個のエピソードに {task} を適用します",
+            ],
+            Msg::ResolvingEpisode => [
+                "Resolving {file}",
+                "Ermittle den Plan für {file}",
+                "{file} の計画を解決中",
+            ],
+            Msg::SkippingInSync => [
+                "Skipping {file}: already in sync",
+                "Überspringe {file}: bereits synchron",
+                "{file} をスキップします: 既に同期しています",
+            ],
+            Msg::FileFailed => [
+                "{file} failed: {error}",
+                "{file} fehlgeschlagen: {error}",
+                "{file} が失敗しました: {error}",
+            ],
+            Msg::ProcessedEpisodes => [
+                "Processed {count} episodes into {dir} ({skipped} already in sync, skipped)",
+                "{count} Episoden nach {dir} verarbeitet ({skipped} bereits synchron, übersprungen)",
+                "{count} 個のエピソードを処理し、{dir} に書き出しました ({skipped} 個は同期済みのためスキップ)",
+            ],
+            Msg::ProcessingDir => [
+                "Processing the files in {dir} with {task}",
+                "Verarbeite die Dateien in {dir} mit {task}",
+                "{dir} のファイルを {task} で処理します",
+            ],
+            Msg::Watching => [
+                "Watching {dir} with {task} (every {interval} s, stop with Ctrl+C)",
+                "Überwache {dir} mit {task} (alle {interval} s, beenden mit Strg+C)",
+                "{dir} を {task} で監視中 ({interval} 秒ごと、Ctrl+C で停止)",
+            ],
+            Msg::LeavingQueued => [
+                "Leaving {count} queued files for the next run",
+                "Lasse {count} wartende Dateien für den nächsten Lauf liegen",
+                "待機中の {count} 個のファイルを次回の実行に残します",
+            ],
+            Msg::PriorityFallback => [
+                "{error} Using priority 0.",
+                "{error} Verwende Priorität 0.",
+                "{error} 優先度 0 を使用します。",
+            ],
+            Msg::StoppedProcessing => [
+                "Stopped processing {file}",
+                "Verarbeitung von {file} angehalten",
+                "{file} の処理を停止しました",
+            ],
+            Msg::FileInSync => [
+                "{file} is already in sync, not processed",
+                "{file} ist bereits synchron und wurde nicht verarbeitet",
+                "{file} は既に同期しているため、処理しませんでした",
+            ],
+            Msg::MovedFile => [
+                "Moved {file} to {dir}",
+                "{file} nach {dir} verschoben",
+                "{file} を {dir} に移動しました",
+            ],
+            Msg::MoveFailed => [
+                "Could not move {file}: {error}",
+                "{file} konnte nicht verschoben werden: {error}",
+                "{file} を移動できませんでした: {error}",
+            ],
+            Msg::WatchStopped => [
+                "Stopped after processing {count} files; run the same command again to continue with the files left in {dir}",
+                "Nach {count} verarbeiteten Dateien angehalten; führe denselben Befehl erneut aus, um mit den übrigen Dateien in {dir} fortzufahren",
+                "{count} 個のファイルを処理した後で停止しました。{dir} に残ったファイルを続けて処理するには、同じコマンドを再度実行してください",
+            ],
+            Msg::ComparingStreams => [
+                "Comparing audio stream {first_stream} of {first} with audio stream {second_stream} of {second}",
+                "Vergleiche Audiospur {first_stream} von {first} mit Audiospur {second_stream} von {second}",
+                "{first} の音声ストリーム {first_stream} と {second} の音声ストリーム {second_stream} を比較中",
+            ],
+            Msg::MeasuringLoudness => [
+                "Measuring loudness of {file}...",
+                "Messe die Lautheit von {file}...",
+                "{file} のラウドネスを測定中...",
+            ],
+            Msg::EstimatingOffset => [
+                "Estimating constant offset...",
+                "Schätze den konstanten Versatz...",
+                "一定のオフセットを推定中...",
+            ],
+            Msg::DelayFirstHint => [
+                "Delaying the first file's stream by {offset} ms lines it up with the second.",
+                "Eine Verzögerung der Spur der ersten Datei um {offset} ms bringt sie mit der zweiten in Deckung.",
+                "1 つ目のファイルのストリームを {offset} ms 遅らせると 2 つ目と揃います。",
+            ],
+            Msg::NoConstantOffset => [
+                "No constant offset detected: {error}",
+                "Kein konstanter Versatz erkannt: {error}",
+                "一定のオフセットを検出できませんでした: {error}",
+            ],
+            Msg::ReadingDelays => [
+                "Reading delays from: {path}",
+                "Lese Verzögerungen aus: {path}",
+                "遅延を読み込み中: {path}",
+            ],
+            Msg::DelaysFromReport => [
+                "Delays from the {format} report:",
+                "Verzögerungen aus dem {format}-Bericht:",
+                "{format} レポートの遅延:",
+            ],
+            Msg::UntiedDelays => [
+                "Some delays could not be tied to a stream; pass --stream (or --language) when processing them.",
+                "Einige Verzögerungen ließen sich keiner Spur zuordnen; gib beim Verarbeiten --stream (oder --language) an.",
+                "一部の遅延をストリームに対応付けられませんでした。処理時に --stream (または --language) を指定してください。",
+            ],
+            Msg::WroteTaskFiles => [
+                "Wrote {count} task file(s). Run them with `sync-nudger process -t <task file>`.",
+                "{count} Task-Datei(en) geschrieben. Führe sie mit `sync-nudger process -t <Task-Datei>` aus.",
+                "{count} 個のタスクファイルを書き出しました。`sync-nudger process -t <タスクファイル>` で実行してください。",
+            ],
+            Msg::ProjectExists => [
+                "'{dir}' already holds a project, --input is ignored",
+                "'{dir}' enthält bereits ein Projekt, --input wird ignoriert",
+                "'{dir}' には既にプロジェクトがあるため、--input は無視します",
+            ],
+            Msg::CreatedProject => [
+                "Created project in {dir}",
+                "Projekt in {dir} angelegt",
+                "{dir} にプロジェクトを作成しました",
+            ],
+            Msg::ProjectNextSteps => [
+                "Edit {task} and run `sync-nudger project run {dir}`",
+                "Bearbeite {task} und führe `sync-nudger project run {dir}` aus",
+                "{task} を編集し、`sync-nudger project run {dir}` を実行してください",
+            ],
+            Msg::ProjectDir => ["Project: {dir}", "Projekt: {dir}", "プロジェクト: {dir}"],
+            Msg::ProjectInput => ["Input: {input}", "Eingabe: {input}", "入力: {input}"],
+            Msg::ProjectTask => ["Task: {task}", "Task: {task}", "タスク: {task}"],
+            Msg::ProjectCreated => [
+                "Created: {date} UTC",
+                "Angelegt: {date} UTC",
+                "作成日時: {date} UTC",
+            ],
+            Msg::NoAttempts => [
+                "No attempts yet",
+                "Noch keine Versuche",
+                "まだ試行はありません",
+            ],
+            Msg::RemovedDir => [
+                "Removed {dir} ({size})",
+                "{dir} entfernt ({size})",
+                "{dir} を削除しました ({size})",
+            ],
+            Msg::RemovedTempDirs => [
+                "Removed {count} temp dir(s), {size} freed",
+                "{count} temporäre(s) Verzeichnis(se) entfernt, {size} freigegeben",
+                "一時ディレクトリを {count} 個削除し、{size} を解放しました",
+            ],
+            Msg::NoTempDirs => [
+                "No temp dirs of failed runs",
+                "Keine temporären Verzeichnisse fehlgeschlagener Läufe",
+                "失敗した実行の一時ディレクトリはありません",
+            ],
+            Msg::TempDirsTotal => [
+                "{count} temp dir(s), {size} in total",
+                "{count} temporäre(s) Verzeichnis(se), {size} insgesamt",
+                "一時ディレクトリ {count} 個、合計 {size}",
+            ],
+            Msg::ProjectAttempt => [
+                "Attempt {number} of project {dir}",
+                "Versuch {number} von Projekt {dir}",
+                "プロジェクト {dir} の試行 {number}",
+            ],
+            Msg::AttemptRecorded => [
+                "Attempt {number} recorded in {dir}",
+                "Versuch {number} in {dir} festgehalten",
+                "試行 {number} を {dir} に記録しました",
+            ],
+            Msg::AutoStream => [
+                "Stream auto: using audio stream {stream} of {file} ({reason})",
+                "Stream auto: verwende Audiospur {stream} von {file} ({reason})",
+                "Stream auto: {file} の音声ストリーム {stream} を使用します ({reason})",
+            ],
+            Msg::DetectingOffset => [
+                "Detecting start offset of audio stream {stream} in: {file}",
+                "Ermittle den Startversatz von Audiospur {stream} in: {file}",
+                "音声ストリーム {stream} の開始オフセットを検出中: {file}",
+            ],
+            Msg::SuggestedDelay => [
+                "Suggested initial delay: --initial-delay {delay}",
+                "Vorgeschlagene Anfangsverzögerung: --initial-delay {delay}",
+                "推奨する初期遅延: --initial-delay {delay}",
+            ],
+            Msg::CheckingStreamChannels => [
+                "Checking channels of audio stream {stream} in: {file}",
+                "Prüfe die Kanäle von Audiospur {stream} in: {file}",
+                "音声ストリーム {stream} のチャンネルを確認中: {file}",
+            ],
+            Msg::FixChannelsHint => [
+                "--fix-channels would apply: {pan}",
+                "--fix-channels würde anwenden: {pan}",
+                "--fix-channels で適用される内容: {pan}",
+            ],
+            Msg::ScanningArtifacts => [
+                "Scanning audio stream {stream} for DC offset, clipping and clicks in: {file}",
+                "Untersuche Audiospur {stream} auf Gleichspannungsversatz, Übersteuerung und Knackser in: {file}",
+                "音声ストリーム {stream} の DC オフセット、クリッピング、クリックを検査中: {file}",
+            ],
+            Msg::NoArtifacts => [
+                "No DC offset, clipping or clicks found.",
+                "Kein Gleichspannungsversatz, keine Übersteuerung und keine Knackser gefunden.",
+                "DC オフセット、クリッピング、クリックは見つかりませんでした。",
+            ],
+            Msg::FoundArtifacts => [
+                "Found {dc} DC offset chunk(s), {clipping} clipped passage(s) and {clicks} click(s).",
+                "{dc} Abschnitt(e) mit Gleichspannungsversatz, {clipping} übersteuerte Passage(n) und {clicks} Knackser gefunden.",
+                "DC オフセット {dc} 箇所、クリッピング {clipping} 箇所、クリック {clicks} 箇所が見つかりました。",
+            ],
+            Msg::ComparingWithVideo => [
+                "Comparing audio stream {stream} with the video in: {file}",
+                "Vergleiche Audiospur {stream} mit dem Video in: {file}",
+                "音声ストリーム {stream} を映像と比較中: {file}",
+            ],
+            Msg::AudioStartsAfter => [
+                "The audio starts {seconds} s after the video; the picture runs without sound until then.",
+                "Das Audio beginnt {seconds} s nach dem Video; bis dahin läuft das Bild ohne Ton.",
+                "音声は映像の {seconds} 秒後に始まります。それまでは音声なしで映像が流れます。",
+            ],
+            Msg::AudioStartsBefore => [
+                "The audio starts {seconds} s before the video.",
+                "Das Audio beginnt {seconds} s vor dem Video.",
+                "音声は映像の {seconds} 秒前に始まります。",
+            ],
+            Msg::AudioEndsBefore => [
+                "The audio ends {seconds} s before the video.",
+                "Das Audio endet {seconds} s vor dem Video.",
+                "音声は映像の {seconds} 秒前に終わります。",
+            ],
+            Msg::AudioRunsPast => [
+                "The audio runs {seconds} s past the end of the video.",
+                "Das Audio läuft {seconds} s über das Ende des Videos hinaus.",
+                "音声は映像の終わりを {seconds} 秒超えて続きます。",
+            ],
+            Msg::AudioMatchesVideo => [
+                "The audio starts and ends with the video.",
+                "Das Audio beginnt und endet mit dem Video.",
+                "音声は映像と同時に始まり、同時に終わります。",
+            ],
+            Msg::CloseGapHint => [
+                "Add {options} when processing this stream to close the gap.",
+                "Gib beim Verarbeiten dieser Spur {options} an, um die Lücke zu schließen.",
+                "このストリームを処理するときに {options} を指定すると、ずれが解消されます。",
+            ],
+            Msg::LookingForSilences => [
+                "Looking for silences of at least {seconds} s below {noise} dBFS in audio stream {stream} of: {file}",
+                "Suche Stille von mindestens {seconds} s unter {noise} dBFS in Audiospur {stream} von: {file}",
+                "{noise} dBFS 未満で {seconds} 秒以上の無音を音声ストリーム {stream} で探しています: {file}",
+            ],
+            Msg::NoSilences => [
+                "No silences found. Try a shorter --min-silence or a higher --noise level.",
+                "Keine Stille gefunden. Versuche ein kürzeres --min-silence oder einen höheren --noise-Pegel.",
+                "無音は見つかりませんでした。--min-silence を短くするか、--noise レベルを上げてください。",
+            ],
+            Msg::SilencesAtEdges => [
+                "The only silences are at the start or end of the stream.",
+                "Die einzigen Stillen liegen am Anfang oder Ende der Spur.",
+                "無音はストリームの先頭か末尾にしかありません。",
+            ],
+            Msg::FoundRangeCandidates => [
+                "Found {count} split range candidates. Keep the ones where the sync changes, set their delays and pass them to process:",
+                "{count} Kandidaten für Schnittbereiche gefunden. Behalte die, an denen sich die Synchronität ändert, setze ihre Verzögerungen und übergib sie an process:",
+                "分割範囲の候補が {count} 個見つかりました。同期が変わる箇所を残して遅延を設定し、process に渡してください:",
+            ],
+            Msg::LookingForBlack => [
+                "Looking for black video of at least {seconds} s in: {file}",
+                "Suche schwarzes Bild von mindestens {seconds} s in: {file}",
+                "{seconds} 秒以上の黒画面を探しています: {file}",
+            ],
+            Msg::NoBreaks => [
+                "No breaks found ({black} black intervals, {silences} silences). Try a shorter --min-black or --min-silence.",
+                "Keine Pausen gefunden ({black} Schwarzbilder, {silences} Stillen). Versuche ein kürzeres --min-black oder --min-silence.",
+                "区切りは見つかりませんでした (黒画面 {black} 区間、無音 {silences} 区間)。--min-black か --min-silence を短くしてください。",
+            ],
+            Msg::FoundBreaks => [
+                "Found {count} breaks. Set the delay after each one and pass them to process:",
+                "{count} Pausen gefunden. Setze die Verzögerung nach jeder und übergib sie an process:",
+                "区切りが {count} 個見つかりました。それぞれの後の遅延を設定し、process に渡してください:",
+            ],
+            Msg::SearchingQuietPoints => [
+                "Searching audio stream {stream} for quiet points between {start}s and {end}s in: {file}",
+                "Suche leise Stellen in Audiospur {stream} zwischen {start}s und {end}s in: {file}",
+                "音声ストリーム {stream} の {start}秒 から {end}秒 で静かな点を探しています: {file}",
+            ],
+            Msg::RangeNoiseFloor => [
+                "Noise floor of the range: {floor} LUFS, silence threshold {threshold} LUFS",
+                "Grundrauschen des Bereichs: {floor} LUFS, Stilleschwelle {threshold} LUFS",
+                "範囲のノイズフロア: {floor} LUFS、無音しきい値 {threshold} LUFS",
+            ],
+            Msg::RangeSilent => [
+                "The range holds only digital silence; keeping the silence threshold of {threshold} LUFS",
+                "Der Bereich enthält nur digitale Stille; behalte die Stilleschwelle von {threshold} LUFS bei",
+                "範囲にはデジタル無音しかありません。無音しきい値 {threshold} LUFS を維持します",
+            ],
+            Msg::SpeechSkipped => [
+                "{count} audible point(s) were inside speech and skipped by --avoid-speech.",
+                "{count} hörbare Stelle(n) lagen in Sprache und wurden von --avoid-speech übersprungen.",
+                "{count} 個の可聴点は発話中のため、--avoid-speech によりスキップしました。",
+            ],
+            Msg::SplitRangeHint => [
+                "With the same threshold, `process --split-range {start}:{end}:<delay>` splits at {time}s.",
+                "Mit derselben Schwelle schneidet `process --split-range {start}:{end}:<delay>` bei {time}s.",
+                "同じしきい値では、`process --split-range {start}:{end}:<delay>` は {time}秒 で分割します。",
+            ],
+            Msg::NoChannelIssues => [
+                "No channel layout issues found.",
+                "Keine Probleme mit der Kanalbelegung gefunden.",
+                "チャンネルレイアウトの問題は見つかりませんでした。",
+            ],
+            Msg::InspectingStreams => [
+                "Inspecting audio streams in: {file}",
+                "Untersuche die Audiospuren in: {file}",
+                "音声ストリームを調べています: {file}",
+            ],
+            Msg::NoAudioStreams => [
+                "No audio streams found in the input file.",
+                "Keine Audiospuren in der Eingabedatei gefunden.",
+                "入力ファイルに音声ストリームが見つかりません。",
+            ],
+            Msg::ContainerDuration => [
+                "Container duration: {duration}s",
+                "Containerdauer: {duration}s",
+                "コンテナーの長さ: {duration}秒",
+            ],
+            Msg::StreamHistoryUnreadable => [
+                "Could not read the edit history of stream {stream}: {error}",
+                "Der Bearbeitungsverlauf von Spur {stream} konnte nicht gelesen werden: {error}",
+                "ストリーム {stream} の編集履歴を読み取れませんでした: {error}",
+            ],
+            Msg::AppliedEdits => [
+                "Edits applied by sync-nudger:",
+                "Von sync-nudger vorgenommene Bearbeitungen:",
+                "sync-nudger が適用した編集:",
+            ],
+            Msg::HistoryReadFrom => [
+                "Edit history read from {path}",
+                "Bearbeitungsverlauf aus {path} gelesen",
+                "編集履歴を {path} から読み込みました",
+            ],
+            Msg::TracksAlreadyNudged => [
+                "These tracks were already nudged; new delays are applied on top of the recorded ones.",
+                "Diese Spuren wurden bereits bearbeitet; neue Verzögerungen kommen zu den gespeicherten hinzu.",
+                "これらのトラックは既に調整されています。新しい遅延は記録済みの遅延に上乗せされます。",
+            ],
+            Msg::IndexHint => [
+                "Use the 'Index' value with --stream to select an audio stream for processing.",
+                "Gib den Wert aus 'Index' mit --stream an, um eine Audiospur zur Verarbeitung auszuwählen.",
+                "処理する音声ストリームを選ぶには、'インデックス' の値を --stream に指定してください。",
+            ],
+            Msg::ContainerRejected => [
+                "Container for '{target}' rejected {codec} audio, exporting to {fallback} instead",
+                "Der Container für '{target}' hat {codec}-Audio abgelehnt, exportiere stattdessen nach {fallback}",
+                "'{target}' のコンテナーが {codec} 音声を受け付けなかったため、代わりに {fallback} に書き出します",
+            ],
+            Msg::Stalled => [
+                "{program} has produced no output for {seconds} s and may be stalled (a network-mounted input can hang while demuxing). Still waiting; use --abort-on-stall to stop instead.",
+                "{program} hat seit {seconds} s keine Ausgabe erzeugt und hängt womöglich (eine Eingabe auf einem Netzlaufwerk kann beim Demuxen hängen). Warte weiter; --abort-on-stall bricht stattdessen ab.",
+                "{program} は {seconds} 秒間出力がなく、停止している可能性があります (ネットワーク上の入力はデマルチプレクス中に止まることがあります)。待機を続けます。代わりに停止するには --abort-on-stall を使用してください。",
+            ],
+            Msg::StopFileFound => [
+                "Found {path}, stopping after the current stage",
+                "{path} gefunden, halte nach dem aktuellen Schritt an",
+                "{path} が見つかったため、現在のステージの後で停止します",
+            ],
+            Msg::Unknown => ["unknown", "unbekannt", "不明"],
+            Msg::Layout => [
+                "Layout: {layout}",
+                "Belegung: {layout}",
+                "レイアウト: {layout}",
+            ],
+            Msg::AudioShorter => [
+                "Audio stream {stream} is {seconds} s shorter than the container; the audio may be delayed, cut or drifting",
+                "Audiospur {stream} ist {seconds} s kürzer als der Container; das Audio ist womöglich verzögert, gekürzt oder driftet",
+                "音声ストリーム {stream} はコンテナーより {seconds} 秒短いため、音声が遅れている、切れている、またはドリフトしている可能性があります",
+            ],
+            Msg::AudioLonger => [
+                "Audio stream {stream} is {seconds} s longer than the container; the audio may be delayed, cut or drifting",
+                "Audiospur {stream} ist {seconds} s länger als der Container; das Audio ist womöglich verzögert, gekürzt oder driftet",
+                "音声ストリーム {stream} はコンテナーより {seconds} 秒長いため、音声が遅れている、切れている、またはドリフトしている可能性があります",
+            ],
+            Msg::Lossless => ["lossless", "verlustfrei", "ロスレス"],
+            Msg::Testing => [
+                "Testing {build}...",
+                "Teste {build}...",
+                "{build} をテスト中...",
+            ],
+            Msg::TestingThorough => [
+                "Testing {build} (thorough)...",
+                "Teste {build} (gründlich)...",
+                "{build} を詳細にテスト中...",
+            ],
+            Msg::NoPointAbove => [
+                "No point in the range is above the silence threshold of {threshold} LUFS.",
+                "Keine Stelle im Bereich liegt über der Stilleschwelle von {threshold} LUFS.",
+                "範囲内に無音しきい値 {threshold} LUFS を超える点はありません。",
+            ],
+            Msg::NoPointAbovePeak => [
+                "No point in the range is above the silence threshold of {threshold} LUFS with a true peak at or below {peak} dBFS ({rejected} rejected by --max-peak).",
+                "Keine Stelle im Bereich liegt über der Stilleschwelle von {threshold} LUFS mit einem True Peak von höchstens {peak} dBFS ({rejected} von --max-peak verworfen).",
+                "範囲内に無音しきい値 {threshold} LUFS を超え、トゥルーピークが {peak} dBFS 以下の点はありません (--max-peak により {rejected} 個を除外)。",
+            ],
+            Msg::MeasuredPoints => [
+                "{count} measured point(s) above {threshold} LUFS.",
+                "{count} gemessene Stelle(n) über {threshold} LUFS.",
+                "{threshold} LUFS を超える測定点が {count} 個あります。",
+            ],
+            Msg::MeasuredPointsRejected => [
+                "{count} measured point(s) above {threshold} LUFS, {rejected} rejected by --max-peak.",
+                "{count} gemessene Stelle(n) über {threshold} LUFS, {rejected} von --max-peak verworfen.",
+                "{threshold} LUFS を超える測定点が {count} 個あり、--max-peak により {rejected} 個を除外しました。",
+            ],
+            Msg::TempSpaceAfter => [
+                "Temp space after {stage}: {size} ({change})",
+                "Temporärer Speicher nach {stage}: {size} ({change})",
+                "{stage} 後の一時領域: {size} ({change})",
+            ],
+            Msg::StatusDone => ["Done", "Fertig", "完了"],
+            Msg::StageExtracting => ["Extracting", "Extrahieren", "抽出"],
+            Msg::StageAligning => ["Aligning", "Ausrichten", "位置合わせ"],
+            Msg::StageResolving => ["Resolving", "Auflösen", "解決"],
+            Msg::StageSplitting => ["Splitting", "Schneiden", "分割"],
+            Msg::StageConcatenating => ["Concatenating", "Verbinden", "結合"],
+            Msg::StageAdjusting => ["Adjusting", "Anpassen", "調整"],
+            Msg::StageEncoding => ["Encoding", "Kodieren", "エンコード"],
+            Msg::StageRemuxing => ["Remuxing", "Remuxen", "リマックス"],
+            Msg::ProgressDoneIn => ["done in {time}", "fertig in {time}", "{time} で完了"],
+            Msg::ProgressEta => ["ETA {time}", "noch {time}", "残り {time}"],
+            Msg::HeaderApplied => ["Applied (UTC)", "Angewendet (UTC)", "適用日時 (UTC)"],
+            Msg::HeaderArtifact => ["Artifact", "Artefakt", "アーティファクト"],
+            Msg::HeaderBitrate => ["Bitrate", "Bitrate", "ビットレート"],
+            Msg::HeaderBlack => ["Black (s)", "Schwarz (s)", "黒画面 (秒)"],
+            Msg::HeaderChannel => ["Channel", "Kanal", "チャンネル"],
+            Msg::HeaderChannels => ["Channels", "Kanäle", "チャンネル数"],
+            Msg::HeaderChapter => ["Chapter", "Kapitel", "チャプター"],
+            Msg::HeaderCheck => ["Check", "Prüfung", "チェック"],
+            Msg::HeaderCodec => ["Codec", "Codec", "コーデック"],
+            Msg::HeaderContentStarts => [
+                "Content Starts (s)",
+                "Inhalt beginnt (s)",
+                "内容の開始 (秒)",
+            ],
+            Msg::HeaderDetail => ["Detail", "Detail", "詳細"],
+            Msg::HeaderDifference => ["Difference", "Differenz", "差"],
+            Msg::HeaderDir => ["Dir", "Verzeichnis", "ディレクトリ"],
+            Msg::HeaderDrift => ["Drift (ppm)", "Drift (ppm)", "ドリフト (ppm)"],
+            Msg::HeaderDuration => ["Duration", "Dauer", "長さ"],
+            Msg::HeaderDurationSeconds => ["Duration (s)", "Dauer (s)", "長さ (秒)"],
+            Msg::HeaderEdit => ["Edit", "Bearbeitung", "編集"],
+            Msg::HeaderEnd => ["End (s)", "Ende (s)", "終了 (秒)"],
+            Msg::HeaderEpisode => ["Episode", "Episode", "エピソード"],
+            Msg::HeaderError => ["Error", "Fehler", "エラー"],
+            Msg::HeaderFinishedStages => {
+                ["Finished Stages", "Fertige Schritte", "完了したステージ"]
+            }
+            Msg::HeaderFirst => ["First", "Erste", "1 つ目"],
+            Msg::HeaderFrom => ["From", "Herkunft", "取得元"],
+            Msg::HeaderIndex => ["Index", "Index", "インデックス"],
+            Msg::HeaderInitialDelay => [
+                "Initial Delay (ms)",
+                "Anfangsverzögerung (ms)",
+                "初期遅延 (ms)",
+            ],
+            Msg::HeaderInput => ["Input", "Eingabe", "入力"],
+            Msg::HeaderInputStart => ["Input Start", "Start Eingabe", "入力の開始"],
+            Msg::HeaderLanguage => ["Language", "Sprache", "言語"],
+            Msg::HeaderLastUsed => ["Last Used (UTC)", "Zuletzt benutzt (UTC)", "最終使用 (UTC)"],
+            Msg::HeaderLength => ["Length (s)", "Länge (s)", "長さ (秒)"],
+            Msg::HeaderLevel => ["Level (dBFS)", "Pegel (dBFS)", "レベル (dBFS)"],
+            Msg::HeaderLoudness => ["Loudness (LUFS)", "Lautheit (LUFS)", "ラウドネス (LUFS)"],
+            Msg::HeaderMeasurement => ["Measurement", "Messung", "測定項目"],
+            Msg::HeaderNewStart => ["New Start", "Neuer Start", "新しい開始"],
+            Msg::HeaderOutput => ["Output", "Ausgabe", "出力"],
+            Msg::HeaderOutputStart => ["Output Start", "Start Ausgabe", "出力の開始"],
+            Msg::HeaderProperty => ["Property", "Eigenschaft", "項目"],
+            Msg::HeaderRank => ["Rank", "Rang", "順位"],
+            Msg::HeaderReason => ["Reason", "Grund", "理由"],
+            Msg::HeaderResult => ["Result", "Ergebnis", "結果"],
+            Msg::HeaderSampleRate => ["Sample Rate", "Abtastrate", "サンプルレート"],
+            Msg::HeaderSecond => ["Second", "Zweite", "2 つ目"],
+            Msg::HeaderSection => ["Section", "Bereich", "区分"],
+            Msg::HeaderShift => ["Shift", "Verschiebung", "シフト"],
+            Msg::HeaderSize => ["Size", "Größe", "サイズ"],
+            Msg::HeaderSplitPoints => [
+                "Split Points (s:ms)",
+                "Schnittpunkte (s:ms)",
+                "分割点 (秒:ms)",
+            ],
+            Msg::HeaderSplitsAndDelays => [
+                "Splits and Delays",
+                "Schnitte und Verzögerungen",
+                "分割と遅延",
+            ],
+            Msg::HeaderStart => ["Start", "Start", "開始"],
+            Msg::HeaderStartSeconds => ["Start (s)", "Start (s)", "開始 (秒)"],
+            Msg::HeaderStarted => ["Started (UTC)", "Gestartet (UTC)", "開始日時 (UTC)"],
+            Msg::HeaderStatus => ["Status", "Status", "状態"],
+            Msg::HeaderStream => ["Stream", "Spur", "ストリーム"],
+            Msg::HeaderSuggestedOption => [
+                "Suggested option",
+                "Vorgeschlagene Option",
+                "推奨オプション",
+            ],
+            Msg::HeaderTaskFile => ["Task File", "Task-Datei", "タスクファイル"],
+            Msg::HeaderTime => ["Time (s)", "Zeit (s)", "時刻 (秒)"],
+            Msg::HeaderTitle => ["Title", "Titel", "タイトル"],
+            Msg::HeaderTrack => ["Track", "Spur", "トラック"],
+            Msg::HeaderTruePeak => [
+                "True Peak (dBFS)",
+                "True Peak (dBFS)",
+                "トゥルーピーク (dBFS)",
+            ],
+            Msg::HeaderType => ["Type", "Typ", "種類"],
+            Msg::HeaderVersion => ["Version", "Version", "バージョン"],
+            Msg::HeaderZeroCrossings => ["Zero Crossings/s", "Nulldurchgänge/s", "ゼロクロス/秒"],
+            Msg::HeaderContainerDelta => ["Δ Container", "Δ Container", "Δ コンテナー"],
+        }
+    }
+}
+
+/// A message in the current language.
+pub fn t(msg: Msg) -> &'static str {
+    let [en, de, ja] = msg.texts();
+    match lang() {
+        Lang::En => en,
+        Lang::De => de,
+        Lang::Ja => ja,
+    }
+}
+
+/// A message in the current language with its `{name}` placeholders filled in from `values`.
+pub fn tf(msg: Msg, values: &[(&str, String)]) -> String {
+    render_template(t(msg), values).unwrap_or_else(|_| t(msg).to_string())
+}
+
+/// Whether an answer to a [y/N] prompt means yes, in English or the current language.
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    match lang() {
+        Lang::De => matches!(answer.as_str(), "y" | "yes" | "j" | "ja"),
+        Lang::Ja => matches!(answer.as_str(), "y" | "yes" | "はい"),
+        Lang::En => matches!(answer.as_str(), "y" | "yes"),
+    }
+}
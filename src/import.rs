@@ -0,0 +1,400 @@
+use crate::cli::StreamSpecifier;
+use crate::task::Task;
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::fmt;
+
+/// Tools whose delay reports can be turned into tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// mkvmerge command lines or option files with `--sync` track delays
+    Mkvtoolnix,
+    /// eac3to command lines and logs (`+120ms`) and demuxed files named `... DELAY -56ms.ac3`
+    Eac3to,
+    /// ffmpeg command lines shifting an input with `-itsoffset`
+    Ffmpeg,
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportFormat::Mkvtoolnix => write!(f, "MKVToolNix"),
+            ImportFormat::Eac3to => write!(f, "eac3to"),
+            ImportFormat::Ffmpeg => write!(f, "ffmpeg"),
+        }
+    }
+}
+
+/// One delay found in an imported report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedCorrection {
+    /// Media file the delay applies to, if the report names it
+    pub input: Option<String>,
+    /// Audio stream the delay applies to, if it can be mapped to a stream of the input
+    pub stream: Option<StreamSpecifier>,
+    /// Delay in milliseconds (positive delays the audio)
+    pub delay_ms: f64,
+    /// Clock drift correction in parts per million (MKVToolNix `--sync` ratios)
+    pub drift_ppm: Option<f64>,
+    /// The part of the report the delay was read from
+    pub origin: String,
+}
+
+impl ImportedCorrection {
+    /// A task applying this correction; every other field is left to the task defaults.
+    pub fn to_task(&self) -> Task {
+        Task {
+            input: self.input.clone(),
+            stream: self.stream,
+            initial_delay: Some(self.delay_ms),
+            drift_ppm: self.drift_ppm,
+            ..Task::default()
+        }
+    }
+}
+
+/// Guess the tool that wrote a report from its contents.
+pub fn detect_format(contents: &str) -> Option<ImportFormat> {
+    if contents.contains("--sync") || contents.contains("mkvmerge") {
+        Some(ImportFormat::Mkvtoolnix)
+    } else if contents.contains("-itsoffset") {
+        Some(ImportFormat::Ffmpeg)
+    } else if contents.contains("eac3to") || contents.contains("DELAY") {
+        Some(ImportFormat::Eac3to)
+    } else {
+        None
+    }
+}
+
+/// Read every delay from a report of a sync tool, detecting the tool from the contents.
+pub fn parse_corrections(contents: &str) -> Result<(ImportFormat, Vec<ImportedCorrection>)> {
+    let Some(format) = detect_format(contents) else {
+        bail!(
+            "Could not recognise the report. Supported are mkvmerge command lines or option files (--sync), eac3to command lines and logs, and ffmpeg command lines with -itsoffset."
+        );
+    };
+    let corrections = match format {
+        ImportFormat::Mkvtoolnix => parse_mkvmerge(&command_tokens(contents)?)?,
+        ImportFormat::Eac3to => parse_eac3to(contents)?,
+        ImportFormat::Ffmpeg => parse_ffmpeg(&command_tokens(contents)?)?,
+    };
+    Ok((format, corrections))
+}
+
+/// Arguments of a command line, or of an mkvmerge option file (a JSON array of strings).
+fn command_tokens(contents: &str) -> Result<Vec<String>> {
+    if let Ok(tokens) = serde_json::from_str::<Vec<String>>(contents) {
+        return Ok(tokens);
+    }
+    let joined = ["\\\r\n", "\\\n", "^\r\n", "^\n"]
+        .iter()
+        .fold(contents.to_string(), |text, continuation| {
+            text.replace(continuation, " ")
+        });
+    shell_words(&joined)
+}
+
+/// Split a command line into words like a shell: whitespace separates words and quotes group
+/// them. A backslash outside single quotes only escapes whitespace, quotes and backslashes, so
+/// Windows paths such as `C:\Movies\a.mkv` stay intact.
+fn shell_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\')
+                if chars
+                    .clone()
+                    .next()
+                    .is_some_and(|next| next.is_whitespace() || "\"'\\".contains(next)) =>
+            {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        bail!("Unterminated quote in the command line.");
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// mkvmerge options that take no value; every other option is followed by one.
+const MKVMERGE_FLAGS: &[&str] = &[
+    "-A",
+    "-D",
+    "-S",
+    "-B",
+    "-T",
+    "-M",
+    "-q",
+    "-v",
+    "-w",
+    "--no-audio",
+    "--no-video",
+    "--no-subtitles",
+    "--no-buttons",
+    "--no-track-tags",
+    "--no-global-tags",
+    "--no-chapters",
+    "--no-attachments",
+    "--no-date",
+    "--quiet",
+    "--verbose",
+    "--webm",
+    "--gui-mode",
+    "--disable-lacing",
+    "--disable-track-statistics-tags",
+    "--enable-durations",
+    "--clusters-in-meta-seek",
+    "--no-cues",
+    "--identify-verbose",
+    "--flush-on-close",
+    "--abort-on-warnings",
+];
+
+/// Delays from mkvmerge `--sync TID:d[,o[/p]]` options. Options apply to the next source
+/// file, and mkvmerge track IDs of Matroska files are ffprobe stream indices.
+fn parse_mkvmerge(tokens: &[String]) -> Result<Vec<ImportedCorrection>> {
+    let sync =
+        Regex::new(r"^(-?\d+):(-?\d+(?:\.\d+)?)(?:,(\d+(?:\.\d+)?)(?:/(\d+(?:\.\d+)?))?)?$")?;
+    let mut corrections = Vec::new();
+    let mut pending: Vec<ImportedCorrection> = Vec::new();
+    // The first word is the executable unless the tokens come from an option file
+    let start = usize::from(tokens.first().is_some_and(|t| t.contains("mkvmerge")));
+    let mut i = start;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match token.as_str() {
+            "--sync" | "-y" => {
+                let Some(value) = tokens.get(i + 1) else {
+                    bail!("--sync without a value.");
+                };
+                let Some(cap) = sync.captures(value) else {
+                    bail!("Cannot read the mkvmerge sync value '{}'.", value);
+                };
+                let tid: i64 = cap[1].parse()?;
+                let numerator: Option<f64> = cap.get(3).map(|m| m.as_str().parse()).transpose()?;
+                let denominator: f64 = cap.get(4).map_or(Ok(1.0), |m| m.as_str().parse())?;
+                let drift_ppm = numerator
+                    .map(|n| ((n / denominator - 1.0) * 1e9).round() / 1000.0)
+                    .filter(|ppm| *ppm != 0.0);
+                pending.push(ImportedCorrection {
+                    input: None,
+                    stream: usize::try_from(tid).ok().map(StreamSpecifier::Index),
+                    delay_ms: cap[2].parse()?,
+                    drift_ppm,
+                    origin: format!("{} {}", token, value),
+                });
+                i += 2;
+            }
+            "(" | ")" | "+" => i += 1,
+            t if MKVMERGE_FLAGS.contains(&t) => i += 1,
+            t if t.starts_with('-') || t.starts_with('@') => i += 2,
+            source => {
+                for mut correction in pending.drain(..) {
+                    correction.input = Some(source.to_string());
+                    corrections.push(correction);
+                }
+                i += 1;
+            }
+        }
+    }
+    corrections.append(&mut pending);
+    Ok(corrections)
+}
+
+/// Delays from eac3to: `+120ms`/`-56ms` options on a command line (logs repeat it on their
+/// `command line:` line) and `DELAY -56ms` in the names of demuxed files.
+fn parse_eac3to(contents: &str) -> Result<Vec<ImportedCorrection>> {
+    let delay_option = Regex::new(r"^([+-]\d+(?:\.\d+)?)ms$")?;
+    let track = Regex::new(r"^(\d+):$")?;
+    let file_delay = Regex::new(r"(?i)DELAY\s*([+-]?\d+(?:\.\d+)?)\s*ms")?;
+    let mut corrections = Vec::new();
+    for line in contents.lines() {
+        let command = line
+            .trim()
+            .strip_prefix("command line:")
+            .unwrap_or(line)
+            .trim();
+        let words = shell_words(command).unwrap_or_default();
+        if words
+            .first()
+            .is_some_and(|w| w.to_ascii_lowercase().contains("eac3to"))
+        {
+            let source = words.get(1).cloned();
+            let mut current_track = None;
+            for word in &words[1..] {
+                if let Some(cap) = track.captures(word) {
+                    current_track = Some(cap[1].to_string());
+                } else if let Some(cap) = delay_option.captures(word) {
+                    let origin = match &current_track {
+                        Some(track) => format!("eac3to track {}: {}", track, word),
+                        None => format!("eac3to {}", word),
+                    };
+                    corrections.push(ImportedCorrection {
+                        input: source.clone(),
+                        // eac3to numbers tracks in its own listing, which ffprobe does not share
+                        stream: None,
+                        delay_ms: cap[1].parse()?,
+                        drift_ppm: None,
+                        origin,
+                    });
+                }
+            }
+            continue;
+        }
+        // A demuxed file: the delay belongs to its only audio stream. Unquoted names with
+        // spaces (as in a directory listing) span the whole line.
+        if let Some(cap) = file_delay.captures(line) {
+            let input = words
+                .iter()
+                .find(|w| file_delay.is_match(w))
+                .cloned()
+                .unwrap_or_else(|| line.trim().to_string());
+            corrections.push(ImportedCorrection {
+                input: Some(input),
+                stream: Some(StreamSpecifier::Audio(0)),
+                delay_ms: cap[1].parse()?,
+                drift_ppm: None,
+                origin: cap[0].to_string(),
+            });
+        }
+    }
+    Ok(corrections)
+}
+
+/// Parse an ffmpeg duration: `[-][HH:]MM:SS[.m...]` or `[-]S[.m...]` with an optional
+/// `s`, `ms` or `us` unit. Returns seconds.
+fn parse_ffmpeg_duration(value: &str) -> Option<f64> {
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let seconds = if rest.contains(':') {
+        rest.split(':').try_fold(0.0, |total, part| {
+            part.parse::<f64>().ok().map(|p| total * 60.0 + p)
+        })?
+    } else if let Some(ms) = rest.strip_suffix("ms") {
+        ms.parse::<f64>().ok()? / 1000.0
+    } else if let Some(us) = rest.strip_suffix("us") {
+        us.parse::<f64>().ok()? / 1_000_000.0
+    } else {
+        rest.strip_suffix('s').unwrap_or(rest).parse().ok()?
+    };
+    Some(if negative { -seconds } else { seconds })
+}
+
+/// Audio stream of a `-map` stream specifier (after the input index), if it names one.
+fn map_stream(spec: &str) -> Option<StreamSpecifier> {
+    match spec {
+        "a" => Some(StreamSpecifier::Audio(0)),
+        _ => match spec.strip_prefix("a:") {
+            Some(n) => n.parse().ok().map(StreamSpecifier::Audio),
+            None => spec.parse().ok().map(StreamSpecifier::Index),
+        },
+    }
+}
+
+/// Delays from ffmpeg `-itsoffset` options. The offset of the input an audio stream is mapped
+/// from, relative to the input the video comes from, is the delay of that audio stream.
+fn parse_ffmpeg(tokens: &[String]) -> Result<Vec<ImportedCorrection>> {
+    let mut inputs: Vec<(String, f64)> = Vec::new();
+    let mut maps: Vec<(usize, String)> = Vec::new();
+    let mut offset = 0.0;
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i].as_str() {
+            "-itsoffset" => {
+                let value = tokens.get(i + 1).map(String::as_str).unwrap_or_default();
+                let Some(seconds) = parse_ffmpeg_duration(value) else {
+                    bail!("Cannot read the -itsoffset value '{}'.", value);
+                };
+                offset = seconds;
+                i += 2;
+            }
+            "-i" => {
+                if let Some(file) = tokens.get(i + 1) {
+                    inputs.push((file.clone(), offset));
+                }
+                offset = 0.0;
+                i += 2;
+            }
+            "-map" => {
+                // Negative maps (`-map -0:a:1`) only remove streams
+                if let Some((index, spec)) = tokens
+                    .get(i + 1)
+                    .filter(|m| !m.starts_with('-'))
+                    .and_then(|m| m.split_once(':'))
+                    && let Ok(index) = index.parse()
+                {
+                    maps.push((index, spec.to_string()));
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    let offset_of = |index: usize| inputs.get(index).map_or(0.0, |(_, offset)| *offset);
+    let video_offset = offset_of(
+        maps.iter()
+            .find(|(_, spec)| spec == "v" || spec.starts_with("v:"))
+            .map_or(0, |(index, _)| *index),
+    );
+
+    let mut corrections = Vec::new();
+    if maps.is_empty() {
+        for (file, offset) in inputs.iter().skip(1).filter(|(_, offset)| *offset != 0.0) {
+            corrections.push(ImportedCorrection {
+                input: Some(file.clone()),
+                stream: None,
+                delay_ms: (offset - video_offset) * 1000.0,
+                drift_ppm: None,
+                origin: format!("-itsoffset {}", offset),
+            });
+        }
+        return Ok(corrections);
+    }
+    for (index, spec) in &maps {
+        let Some(stream) = map_stream(spec) else {
+            continue;
+        };
+        let delay = offset_of(*index) - video_offset;
+        if delay == 0.0 {
+            continue;
+        }
+        let Some((file, _)) = inputs.get(*index) else {
+            bail!("-map {}:{} refers to a missing input.", index, spec);
+        };
+        corrections.push(ImportedCorrection {
+            input: Some(file.clone()),
+            stream: Some(stream),
+            delay_ms: delay * 1000.0,
+            drift_ppm: None,
+            origin: format!("-map {}:{} shifted by {} s", index, spec, delay),
+        });
+    }
+    Ok(corrections)
+}
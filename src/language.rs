@@ -0,0 +1,141 @@
+use tracing::warn;
+
+/// ISO 639-1, ISO 639-2/T and ISO 639-2/B codes of the languages commonly found in media files.
+/// The bibliographic (B) code is what Matroska and ffmpeg write, so it is used as the normal form.
+const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("af", "afr", "afr"),
+    ("am", "amh", "amh"),
+    ("ar", "ara", "ara"),
+    ("az", "aze", "aze"),
+    ("be", "bel", "bel"),
+    ("bg", "bul", "bul"),
+    ("bn", "ben", "ben"),
+    ("bo", "bod", "tib"),
+    ("bs", "bos", "bos"),
+    ("ca", "cat", "cat"),
+    ("cs", "ces", "cze"),
+    ("cy", "cym", "wel"),
+    ("da", "dan", "dan"),
+    ("de", "deu", "ger"),
+    ("el", "ell", "gre"),
+    ("en", "eng", "eng"),
+    ("eo", "epo", "epo"),
+    ("es", "spa", "spa"),
+    ("et", "est", "est"),
+    ("eu", "eus", "baq"),
+    ("fa", "fas", "per"),
+    ("fi", "fin", "fin"),
+    ("fr", "fra", "fre"),
+    ("ga", "gle", "gle"),
+    ("gl", "glg", "glg"),
+    ("gu", "guj", "guj"),
+    ("he", "heb", "heb"),
+    ("hi", "hin", "hin"),
+    ("hr", "hrv", "hrv"),
+    ("hu", "hun", "hun"),
+    ("hy", "hye", "arm"),
+    ("id", "ind", "ind"),
+    ("is", "isl", "ice"),
+    ("it", "ita", "ita"),
+    ("ja", "jpn", "jpn"),
+    ("ka", "kat", "geo"),
+    ("kk", "kaz", "kaz"),
+    ("km", "khm", "khm"),
+    ("kn", "kan", "kan"),
+    ("ko", "kor", "kor"),
+    ("la", "lat", "lat"),
+    ("lo", "lao", "lao"),
+    ("lt", "lit", "lit"),
+    ("lv", "lav", "lav"),
+    ("mi", "mri", "mao"),
+    ("mk", "mkd", "mac"),
+    ("ml", "mal", "mal"),
+    ("mn", "mon", "mon"),
+    ("mr", "mar", "mar"),
+    ("ms", "msa", "may"),
+    ("my", "mya", "bur"),
+    ("nb", "nob", "nob"),
+    ("ne", "nep", "nep"),
+    ("nl", "nld", "dut"),
+    ("nn", "nno", "nno"),
+    ("no", "nor", "nor"),
+    ("pa", "pan", "pan"),
+    ("pl", "pol", "pol"),
+    ("pt", "por", "por"),
+    ("ro", "ron", "rum"),
+    ("ru", "rus", "rus"),
+    ("si", "sin", "sin"),
+    ("sk", "slk", "slo"),
+    ("sl", "slv", "slv"),
+    ("sq", "sqi", "alb"),
+    ("sr", "srp", "srp"),
+    ("sv", "swe", "swe"),
+    ("sw", "swa", "swa"),
+    ("ta", "tam", "tam"),
+    ("te", "tel", "tel"),
+    ("th", "tha", "tha"),
+    ("tl", "tgl", "tgl"),
+    ("tr", "tur", "tur"),
+    ("uk", "ukr", "ukr"),
+    ("ur", "urd", "urd"),
+    ("uz", "uzb", "uzb"),
+    ("vi", "vie", "vie"),
+    ("yi", "yid", "yid"),
+    ("zh", "zho", "chi"),
+    ("zu", "zul", "zul"),
+];
+
+/// Common non-standard tags written by some tools.
+const ALIASES: &[(&str, &str)] = &[("jp", "jpn"), ("cn", "chi"), ("gr", "gre"), ("cz", "cze")];
+
+/// Special ISO 639-2 codes that are valid as-is.
+const SPECIAL: &[&str] = &["und", "mul", "mis", "zxx"];
+
+/// Normalize a language tag (`ja`, `jpn`, `JA-jp`, `jp`) to its ISO 639-2/B code.
+/// Region and script subtags are ignored. Returns `None` for unknown codes.
+pub fn normalize_language(tag: &str) -> Option<&'static str> {
+    let primary = tag
+        .trim()
+        .split(['-', '_'])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if let Some(code) = SPECIAL.iter().find(|c| **c == primary) {
+        return Some(code);
+    }
+    if let Some((_, code)) = ALIASES.iter().find(|(alias, _)| *alias == primary) {
+        return Some(code);
+    }
+    LANGUAGES
+        .iter()
+        .find(|(iso1, t, b)| *iso1 == primary || *t == primary || *b == primary)
+        .map(|(_, _, b)| *b)
+}
+
+/// Normalize a language tag read from `source`, warning and keeping it unchanged if it is unknown.
+/// An empty tag stays empty.
+pub fn normalize_or_warn(tag: &str, source: &str) -> String {
+    if let Some(warning) = unknown_language_warning(tag, source) {
+        warn!("⚠️ {}", warning);
+    }
+    normalize_or_keep(tag)
+}
+
+/// Normalize a language tag, keeping it unchanged if it is unknown. An empty tag stays empty.
+pub fn normalize_or_keep(tag: &str) -> String {
+    let tag = tag.trim();
+    normalize_language(tag)
+        .map(str::to_string)
+        .unwrap_or_else(|| tag.to_string())
+}
+
+/// The warning for a tag read from `source` that [`normalize_or_keep`] keeps because it is unknown.
+pub fn unknown_language_warning(tag: &str, source: &str) -> Option<String> {
+    let tag = tag.trim();
+    (!tag.is_empty() && normalize_language(tag).is_none()).then(|| {
+        format!(
+            "Unknown language tag '{}' on {}, keeping it as-is",
+            tag, source
+        )
+    })
+}
@@ -0,0 +1,57 @@
+//! Fix audio sync problems in video files: delay, split and re-time one audio stream with
+//! ffmpeg, then remux it with every other stream unchanged.
+//!
+//! [`SyncNudger`] runs a complete job described by a [`Plan`] with [`SyncOptions`] and returns a
+//! [`SyncReport`]. The modules below expose the individual stages (probing, split resolution,
+//! split/delay/concat/convert/remux) for tools that need finer control.
+
+pub mod analysis;
+mod app;
+pub mod audio_metadata;
+pub mod audio_processing;
+pub mod breaks;
+pub mod channels;
+pub mod chapters;
+pub mod checkpoint;
+mod cli;
+pub mod container;
+pub mod detector;
+pub mod doctor;
+pub mod exit_code;
+pub mod extents;
+pub mod ffmpeg;
+pub mod history;
+pub mod hooks;
+pub mod i18n;
+pub mod import;
+pub mod language;
+pub mod logging;
+pub mod media_server;
+mod nudger;
+#[cfg(feature = "async")]
+pub mod pipeline_async;
+pub mod plan;
+pub mod porcelain;
+pub mod progress;
+pub mod project;
+pub mod qc;
+pub mod season;
+#[cfg(feature = "selftest")]
+pub mod selftest;
+pub mod silence;
+pub mod split_map;
+pub mod subtitles;
+pub mod task;
+pub mod temp_usage;
+pub mod util;
+pub mod vad;
+pub mod watch;
+
+pub use app::{run, run_with_hooks};
+pub use cli::{
+    AvoidNegativeTs, Cli, DelayRamp, DetectorKind, FrameUnits, IntermediateFormat, MediaServerKind,
+    ProfileOutput, SplitPoint, SplitRange, StreamSpecifier, SyncPoint,
+};
+pub use nudger::{Plan, SyncNudger, SyncOptions, SyncReport, TrackReport};
+pub use progress::{CancellationToken, PipelineHooks, ProgressCallback, Stage, StopRequest};
+pub use task::Task;
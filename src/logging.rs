@@ -0,0 +1,119 @@
+//! Output and log of a run, through `tracing`.
+//!
+//! Status messages (`info!`, `warn!` and `error!` events of this crate) are printed to stdout
+//! as they are; with `--quiet`, only warnings and errors are, to stderr and without the emoji
+//! they start with. Events with the [`LOG`]
+//! target are the record of a run: the ffmpeg commands it started, how they ended, and why a
+//! job failed. They go to stderr with `-v` (up to info) and `-vv` (everything). `--log-file`
+//! gets both, with time and level.
+
+use std::fs::OpenOptions;
+use std::io;
+use std::sync::Mutex;
+
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::{FmtContext, FormattedFields};
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, fmt};
+
+/// Target of the records of a run, which the status output leaves out.
+pub const LOG: &str = "sync_nudger::log";
+
+/// Formats a status message as its fields alone, with `plain` leaving out the emoji each of its
+/// lines starts with.
+struct StatusFormat {
+    plain: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for StatusFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut message = FormattedFields::<N>::new(String::new());
+        ctx.format_fields(message.as_writer(), event)?;
+        if !self.plain {
+            return writeln!(writer, "{}", message.fields);
+        }
+        for line in message.fields.lines() {
+            writeln!(writer, "{}", strip_emoji(line))?;
+        }
+        Ok(())
+    }
+}
+
+/// `line` without the emoji (and the space after it) that follows its indentation.
+fn strip_emoji(line: &str) -> String {
+    let text = line.trim_start();
+    let indent = &line[..line.len() - text.len()];
+    let rest = text.trim_start_matches(is_emoji);
+    if rest.len() == text.len() {
+        return line.to_string();
+    }
+    format!("{}{}", indent, rest.trim_start())
+}
+
+/// Pictographs and symbols the status messages start with, with their variation selector and
+/// joiner. Box drawing and block characters are left alone, as tables are made of them.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2190}'..='\u{24FF}'
+            | '\u{25A0}'..='\u{2BFF}'
+            | '\u{1F000}'..='\u{1FAFF}'
+            | '\u{FE0F}'
+            | '\u{200D}'
+    )
+}
+
+/// Install the subscriber for `verbosity` (the number of `-v` flags), `--quiet` and an optional
+/// log file, which is appended to so several runs can share it. A subscriber installed earlier,
+/// e.g. by a program embedding the library, is kept.
+pub fn init(verbosity: u8, quiet: bool, log_file: Option<&str>) -> io::Result<()> {
+    let file = log_file
+        .map(|path| OpenOptions::new().create(true).append(true).open(path))
+        .transpose()?;
+    // Quiet runs keep stdout for the output paths
+    let (writer, level) = if quiet {
+        (BoxMakeWriter::new(io::stderr), "warn")
+    } else {
+        (BoxMakeWriter::new(io::stdout), "info")
+    };
+    let status = fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .event_format(StatusFormat { plain: quiet })
+        .with_filter(EnvFilter::new(format!("sync_nudger={},{}=off", level, LOG)));
+    let records = fmt::layer()
+        .with_writer(io::stderr)
+        .with_target(false)
+        .with_ansi(false)
+        .with_filter(EnvFilter::new(match verbosity {
+            0 => "off".to_string(),
+            1 => format!("{}=info", LOG),
+            _ => format!("{}=debug", LOG),
+        }));
+    // Write errors are dropped, so a full disk does not fail the run it is recording
+    let file = file.map(|file| {
+        fmt::layer()
+            .with_writer(Mutex::new(file))
+            .with_target(false)
+            .with_ansi(false)
+            .with_filter(EnvFilter::new("sync_nudger=debug"))
+    });
+    let _ = tracing_subscriber::registry()
+        .with(status)
+        .with(records)
+        .with(file)
+        .try_init();
+    Ok(())
+}
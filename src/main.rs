@@ -1,15 +1,17 @@
-mod app;
-mod audio_metadata;
-mod audio_processing;
-mod cli;
-mod ffmpeg;
-mod task;
-mod util;
-
-use anyhow::Result;
 use clap::Parser;
+use std::process::ExitCode;
+use sync_nudger::Cli;
+use sync_nudger::exit_code::{ExitReason, RunError};
 
-fn main() -> Result<()> {
-    let args = cli::Args::parse();
-    app::run(args)
+fn main() -> ExitCode {
+    match sync_nudger::run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // Declining the plan is already reported by the prompt
+            if !matches!(e.downcast_ref::<RunError>(), Some(RunError::Aborted)) {
+                eprintln!("Error: {:?}", e);
+            }
+            ExitCode::from(ExitReason::of(&e).code())
+        }
+    }
 }
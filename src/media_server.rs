@@ -0,0 +1,145 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::cli::MediaServerKind;
+
+/// Environment variable read for the API token when none is given.
+pub const SERVER_TOKEN_ENV: &str = "SYNC_NUDGER_SERVER_TOKEN";
+/// How long a request to the media server may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A Jellyfin or Plex server that is asked to refresh the fixed file after a successful run.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MediaServer {
+    pub kind: MediaServerKind,
+    /// Base URL, e.g. `http://localhost:8096`
+    pub url: String,
+    /// API token (Jellyfin API key or Plex token). Never written to task files; falls back to
+    /// `SYNC_NUDGER_SERVER_TOKEN`.
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+    /// `LOCAL=SERVER` path prefixes, for servers that see the media under another path
+    /// (e.g. in a container)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_map: Option<String>,
+}
+
+impl MediaServer {
+    /// Path of `file` as the server sees it.
+    pub fn server_path(&self, file: &Path) -> Result<String> {
+        let local = std::fs::canonicalize(file)
+            .with_context(|| format!("Could not resolve {}", file.display()))?;
+        let local = local.to_string_lossy().to_string();
+        let Some(map) = &self.path_map else {
+            return Ok(local);
+        };
+        let Some((from, to)) = map.split_once('=') else {
+            bail!("Invalid path map '{}', expected LOCAL=SERVER.", map);
+        };
+        Ok(match local.strip_prefix(from) {
+            Some(rest) => format!("{}{}", to, rest),
+            None => local,
+        })
+    }
+
+    fn token(&self) -> Result<String> {
+        match self
+            .token
+            .clone()
+            .or_else(|| std::env::var(SERVER_TOKEN_ENV).ok())
+        {
+            Some(token) if !token.is_empty() => Ok(token),
+            _ => bail!(
+                "No API token for the {} server. Pass --server-token or set {}.",
+                self.kind,
+                SERVER_TOKEN_ENV
+            ),
+        }
+    }
+
+    /// Ask the server to rescan `file`, returning the path it was told about.
+    pub fn refresh(&self, file: &Path) -> Result<String> {
+        let path = self.server_path(file)?;
+        let token = self.token()?;
+        let url = self.url.trim_end_matches('/');
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        match self.kind {
+            MediaServerKind::Jellyfin => {
+                let body = serde_json::json!({
+                    "Updates": [{ "Path": path, "UpdateType": "Modified" }]
+                })
+                .to_string();
+                let endpoint = format!("{}/Library/Media/Updated", url);
+                agent
+                    .post(&endpoint)
+                    .header("X-Emby-Token", &token)
+                    .content_type("application/json")
+                    .send(&body)
+                    .with_context(|| format!("POST {} failed", endpoint))?;
+            }
+            MediaServerKind::Plex => {
+                let endpoint = format!("{}/library/sections", url);
+                let sections = agent
+                    .get(&endpoint)
+                    .header("X-Plex-Token", &token)
+                    .header("Accept", "application/xml")
+                    .call()
+                    .and_then(|mut response| response.body_mut().read_to_string())
+                    .with_context(|| format!("GET {} failed", endpoint))?;
+                let section = plex_section_for(&sections, &path)?.ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No Plex library contains '{}'. Check --server-path-map.",
+                        path
+                    )
+                })?;
+                let folder = Path::new(&path)
+                    .parent()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or(path.clone());
+                let endpoint = format!("{}/library/sections/{}/refresh", url, section);
+                agent
+                    .get(&endpoint)
+                    .header("X-Plex-Token", &token)
+                    .query("path", &folder)
+                    .call()
+                    .with_context(|| format!("GET {} failed", endpoint))?;
+            }
+        }
+        Ok(path)
+    }
+}
+
+/// Key of the Plex library section whose folder holds `path` (the deepest one), from the XML
+/// of `/library/sections`.
+pub fn plex_section_for(sections_xml: &str, path: &str) -> Result<Option<String>> {
+    let document = roxmltree::Document::parse(sections_xml)
+        .context("Could not read the library sections of the Plex server")?;
+    let mut best: Option<(usize, &str)> = None;
+    for directory in document
+        .descendants()
+        .filter(|node| node.has_tag_name("Directory"))
+    {
+        let Some(section) = directory.attribute("key") else {
+            continue;
+        };
+        let folders = directory
+            .children()
+            .filter(|node| node.has_tag_name("Location"))
+            .filter_map(|location| location.attribute("path"));
+        for folder in folders {
+            let folder = folder.trim_end_matches('/');
+            let inside = path
+                .strip_prefix(folder)
+                .is_some_and(|rest| rest.starts_with('/'));
+            if inside && best.is_none_or(|(len, _)| folder.len() > len) {
+                best = Some((folder.len(), section));
+            }
+        }
+    }
+    Ok(best.map(|(_, section)| section.to_string()))
+}
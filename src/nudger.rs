@@ -0,0 +1,171 @@
+use anyhow::Result;
+
+use crate::app::run_job;
+use crate::checkpoint::{DEFAULT_CACHE_MAX_AGE_DAYS, DEFAULT_CACHE_MAX_SIZE};
+use crate::cli::SplitPoint;
+use crate::progress::PipelineHooks;
+use crate::task::Task;
+
+/// A sync job: the same fields as a task file. Build one in code or load it with `Task::load`.
+pub type Plan = Task;
+
+/// How a job runs, as opposed to what it does (the `Plan`). The command line maps its options
+/// into this; in code, change the fields you need on `SyncOptions::default()`.
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// Resolve and check the plan, then stop without writing the output
+    pub dry_run: bool,
+    /// Only resolve the plan (split ranges, reference alignment) and return it
+    pub resolve_only: bool,
+    /// Continue the last failed run of the same job from its last finished stage
+    pub resume: bool,
+    /// Echo every ffmpeg command and its output
+    pub debug: bool,
+    /// Run with an ffmpeg older than the minimum version
+    pub ignore_ffmpeg_version: bool,
+    /// Write the resolved plan as a task file; `Some(None)` puts it next to the input
+    pub write_task_file: Option<Option<String>>,
+    /// Write the plan and job details to this file, as Markdown (.md) or JSON (.json)
+    pub plan_out: Option<String>,
+    /// Render a waveform image around each split point into this directory
+    pub review_dir: Option<String>,
+    /// Write audio previews of each split point into this directory
+    pub export_previews: Option<String>,
+    /// Also export the corrected track on its own; `Some(None)` puts it next to the output
+    pub export_audio: Option<Option<String>>,
+    /// Keep the intermediate files in this directory; `Some(None)` puts them next to the output
+    pub keep_temp: Option<Option<String>>,
+    /// Temp dirs of failed runs unused for this many days are removed
+    pub cache_max_age: f64,
+    /// The least recently used temp dirs of failed runs are removed beyond this many bytes
+    pub cache_max_size: u64,
+    /// Stop at the next stage boundary once this file exists
+    pub stop_file: Option<String>,
+    /// Apply the plan without asking for confirmation; only the command line asks
+    pub(crate) yes: bool,
+    /// Print the output path on its own line (`--quiet`)
+    pub(crate) quiet: bool,
+    /// Encode a sample of this many seconds instead of the whole track
+    pub(crate) sample_encode: Option<f64>,
+    /// Stop before the remux and copy the finished track into this directory (one stream plan)
+    pub(crate) track_output: Option<String>,
+    /// The task file the plan was loaded from, for the hooks
+    pub(crate) task_file: Option<String>,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            resolve_only: false,
+            resume: false,
+            debug: false,
+            ignore_ffmpeg_version: false,
+            write_task_file: None,
+            plan_out: None,
+            review_dir: None,
+            export_previews: None,
+            export_audio: None,
+            keep_temp: None,
+            cache_max_age: DEFAULT_CACHE_MAX_AGE_DAYS,
+            cache_max_size: DEFAULT_CACHE_MAX_SIZE,
+            stop_file: None,
+            yes: true,
+            quiet: false,
+            sample_encode: None,
+            track_output: None,
+            task_file: None,
+        }
+    }
+}
+
+/// What a job did.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// The file written, or `None` if the job wrote none: a dry or resolve-only run, a track
+    /// that is already in sync, or a declined plan
+    pub output: Option<String>,
+    /// The resolved plan of every corrected audio stream (none when only the video is shifted)
+    pub tracks: Vec<TrackReport>,
+}
+
+/// The resolved plan of one audio stream.
+#[derive(Debug, Clone)]
+pub struct TrackReport {
+    /// Index of the stream among all streams of the input
+    pub stream: usize,
+    /// Initial delay in ms, including one measured against a reference or onset
+    pub initial_delay: f64,
+    /// The split points and their delays, with the ones found in split ranges
+    pub splits: Vec<SplitPoint>,
+    /// Corrected clock drift in ppm
+    pub drift_ppm: Option<f64>,
+}
+
+/// Runs a sync job from code, without going through the command line.
+///
+/// ```no_run
+/// use sync_nudger::{Plan, SplitPoint, StreamSpecifier, SyncNudger};
+///
+/// let plan = Plan {
+///     input: Some("movie.mkv".to_string()),
+///     output: Some("movie.synced.mkv".to_string()),
+///     stream: Some(StreamSpecifier::Index(1)),
+///     initial_delay: Some(250.0),
+///     splits: vec![SplitPoint {
+///         time: 1200.0,
+///         delay: 40.0,
+///         label: None,
+///         frames: Default::default(),
+///     }],
+///     ..Plan::default()
+/// };
+/// let report = SyncNudger::new(plan).run()?;
+/// println!("Wrote {:?}", report.output);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// The plan is confirmed automatically; progress and cancellation go through `PipelineHooks`.
+/// Messages are `tracing` events, shown only if the program installs a subscriber.
+pub struct SyncNudger {
+    plan: Plan,
+    options: SyncOptions,
+    hooks: PipelineHooks,
+}
+
+impl SyncNudger {
+    pub fn new(plan: Plan) -> Self {
+        Self {
+            plan,
+            options: SyncOptions::default(),
+            hooks: PipelineHooks::default(),
+        }
+    }
+
+    /// Run with `options` instead of the defaults.
+    pub fn with_options(mut self, options: SyncOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Report progress to and take cancellation from `hooks`.
+    pub fn with_hooks(mut self, hooks: PipelineHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Echo every ffmpeg command and its output.
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.options.debug = debug;
+        self
+    }
+
+    pub fn plan(&self) -> &Plan {
+        &self.plan
+    }
+
+    /// Run the job. A cancelled run kills the running ffmpeg child and removes its temp files.
+    pub fn run(&self) -> Result<SyncReport> {
+        run_job(self.plan.clone(), &self.options, &self.hooks)
+    }
+}
@@ -0,0 +1,295 @@
+//! Async variants of the pipeline stages, built on `tokio::process`.
+//! Each stage builds its ffmpeg arguments (segment plan, single-pass edit, crossfades, trims)
+//! with the same functions as its blocking counterpart in `audio_processing`; only spawning
+//! ffmpeg is async, and ffprobe calls run on the blocking thread pool. Dropping a future kills
+//! the ffmpeg child it is waiting on, which is how a run is cancelled from async code.
+//!
+//! Every stage takes the `ChildSettings` entered on the thread that calls it (not the one that
+//! polls the future), so enter the settings of a job before calling its stages.
+
+use anyhow::Result;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+
+use crate::audio_metadata::{StreamFormat, build_stream_map_args, get_sample_rate};
+use crate::audio_processing::{
+    Delay, RemuxOptions, RunOptions, SourceAudio, check_segment_consistency, concat_args,
+    convert_args, drift_args, edit_args, extract_args, fit_length_args, intermediate_duration,
+    intermediate_file, pad_head_args, plan_segments, remux_args, str_args,
+};
+use crate::ffmpeg::{ChildSettings, FFmpegError};
+use crate::util::path_to_str;
+
+/// Run the ffmpeg of `children` with `-nostdin` and its thread limit without blocking the
+/// runtime.
+pub async fn run_ffmpeg(
+    args: &[String],
+    debug: bool,
+    children: &ChildSettings,
+) -> Result<(), FFmpegError> {
+    let program = children.ffmpeg();
+    let mut full_args = vec!["-nostdin".to_string()];
+    full_args.extend(children.limit_threads(&str_args(args)));
+    let output = Command::new(&program)
+        .args(&full_args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                FFmpegError::CommandNotFound(program)
+            } else {
+                FFmpegError::Io(e)
+            }
+        })?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if debug {
+        eprint!("{}", stderr);
+    }
+    if !output.status.success() {
+        let reason = stderr
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("")
+            .trim();
+        return Err(FFmpegError::CommandFailed(
+            args.join(" "),
+            format!("FFmpeg failed: {}", reason),
+        ));
+    }
+    Ok(())
+}
+
+/// Run a blocking probe on the blocking thread pool with `children` entered there.
+async fn probe<T: Send + 'static>(
+    children: &ChildSettings,
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let children = children.clone();
+    tokio::task::spawn_blocking(move || {
+        let _children = children.enter();
+        f()
+    })
+    .await?
+}
+
+/// Sample rate of an intermediate file.
+async fn sample_rate(path: &Path, children: &ChildSettings) -> Result<u32> {
+    let path = path_to_str(path)?.to_string();
+    probe(children, move || get_sample_rate(&path)).await
+}
+
+/// Extract a specific audio stream from a media file to an intermediate file.
+pub fn extract_audio_stream_to_flac(
+    input: &str,
+    stream: usize,
+    output_path: &Path,
+    run: &RunOptions,
+) -> impl Future<Output = Result<()>> {
+    let children = ChildSettings::current();
+    async move {
+        run_ffmpeg(
+            &extract_args(input, stream, output_path, run)?,
+            run.debug,
+            &children,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Split and delay audio segments according to split points and delays, one segment at a time.
+/// Returns the resulting split files in order.
+pub fn split_and_delay_audio(
+    flac_path: &Path,
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
+    tmpdir: &Path,
+    run: &RunOptions,
+) -> impl Future<Output = Result<Vec<PathBuf>>> {
+    let children = ChildSettings::current();
+    async move {
+        let path = flac_path.to_path_buf();
+        let source = probe(&children, move || SourceAudio::probe(&path)).await?;
+        let mut split_files = Vec::new();
+        for job in plan_segments(flac_path, split_points, delays, tempos, tmpdir, source, run)? {
+            run_ffmpeg(&job.cut_args, run.debug, &children).await?;
+            if let Some(delay_args) = &job.delay_args {
+                run_ffmpeg(delay_args, run.debug, &children).await?;
+                std::fs::remove_file(&job.part)?;
+            }
+            split_files.push(job.output);
+        }
+        Ok(split_files)
+    }
+}
+
+/// Concatenate audio segments after checking that they match. Returns the path to the final
+/// intermediate file.
+pub fn concat_audio_segments(
+    split_files: &[PathBuf],
+    tmpdir: &Path,
+    run: &RunOptions,
+) -> impl Future<Output = Result<PathBuf>> {
+    let children = ChildSettings::current();
+    async move {
+        let files = split_files.to_vec();
+        probe(&children, move || check_segment_consistency(&files)).await?;
+        let final_flac = intermediate_file(tmpdir, "target_audio_final", run.intermediate);
+        run_ffmpeg(
+            &concat_args(split_files, &final_flac, run)?,
+            run.debug,
+            &children,
+        )
+        .await?;
+        Ok(final_flac)
+    }
+}
+
+/// Cut, delay and concatenate every segment with one ffmpeg run, without writing a file per
+/// segment. Returns the path to the edited intermediate file.
+pub fn edit_audio(
+    flac_path: &Path,
+    split_points: &[f64],
+    delays: &[Delay],
+    tempos: &[f64],
+    tmpdir: &Path,
+    run: &RunOptions,
+) -> impl Future<Output = Result<PathBuf>> {
+    let children = ChildSettings::current();
+    async move {
+        let path = flac_path.to_path_buf();
+        let source = probe(&children, move || SourceAudio::probe(&path)).await?;
+        let final_flac = intermediate_file(tmpdir, "target_audio_final", run.intermediate);
+        let args = edit_args(
+            flac_path,
+            split_points,
+            delays,
+            tempos,
+            source,
+            &final_flac,
+            run,
+        )?;
+        run_ffmpeg(&args, run.debug, &children).await?;
+        Ok(final_flac)
+    }
+}
+
+/// Apply a uniform clock drift correction of `ppm` parts per million to an intermediate file.
+pub fn correct_drift(
+    input_path: &Path,
+    output_path: &Path,
+    ppm: f64,
+    run: &RunOptions,
+) -> impl Future<Output = Result<()>> {
+    let children = ChildSettings::current();
+    async move {
+        let sample_rate = sample_rate(input_path, &children).await?;
+        let args = drift_args(input_path, output_path, sample_rate, ppm, run)?;
+        run_ffmpeg(&args, run.debug, &children).await?;
+        Ok(())
+    }
+}
+
+/// Put `seconds` of silence (rounded to whole samples) before the audio at input_path, writing
+/// to output_path.
+pub fn pad_audio_head(
+    input_path: &Path,
+    output_path: &Path,
+    seconds: f64,
+    run: &RunOptions,
+) -> impl Future<Output = Result<()>> {
+    let children = ChildSettings::current();
+    async move {
+        let sample_rate = sample_rate(input_path, &children).await?;
+        let args = pad_head_args(input_path, output_path, sample_rate, seconds, run)?;
+        run_ffmpeg(&args, run.debug, &children).await?;
+        Ok(())
+    }
+}
+
+/// Trim or pad the audio at input_path to match target_duration (seconds), writing to output_path.
+pub fn fit_audio_to_length(
+    input_path: &Path,
+    output_path: &Path,
+    target_duration: f64,
+    run: &RunOptions,
+) -> impl Future<Output = Result<()>> {
+    let children = ChildSettings::current();
+    async move {
+        let path = input_path.to_path_buf();
+        let input_duration = probe(&children, move || intermediate_duration(&path)).await?;
+        match fit_length_args(
+            input_path,
+            output_path,
+            input_duration,
+            target_duration,
+            run,
+        )? {
+            Some(args) => run_ffmpeg(&args, run.debug, &children).await?,
+            None => {
+                std::fs::copy(input_path, output_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert intermediate audio to the target codec and bitrate (None for lossless codecs).
+pub fn convert_audio_codec(
+    input_flac: &Path,
+    codec: &str,
+    bitrate: Option<&str>,
+    format: &StreamFormat,
+    output_path: &Path,
+    run: &RunOptions,
+) -> impl Future<Output = Result<()>> {
+    let children = ChildSettings::current();
+    async move {
+        run_ffmpeg(
+            &convert_args(input_flac, codec, bitrate, format, output_path, run)?,
+            run.debug,
+            &children,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Remux the new audio stream in place of the original audio stream in the input file.
+/// The input is probed on the blocking thread pool.
+pub fn remux_audio_stream(
+    input: &str,
+    new_audio: &Path,
+    output: &str,
+    audio_stream_idx: usize,
+    options: RemuxOptions<'_>,
+    run: &RunOptions,
+) -> impl Future<Output = Result<()>> {
+    let children = ChildSettings::current();
+    async move {
+        let probe_input = input.to_string();
+        let map_args = probe(&children, move || {
+            build_stream_map_args(&probe_input, audio_stream_idx)
+        })
+        .await?;
+        let args = remux_args(
+            input,
+            new_audio,
+            output,
+            &map_args,
+            audio_stream_idx,
+            options,
+            run,
+        )?;
+        run_ffmpeg(&args, run.debug, &children).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{Result, bail};
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::i18n::{Msg, t};
+
+/// One row of the proposed splitting plan.
+#[derive(Debug, Serialize)]
+pub struct PlanRow {
+    pub source: String,
+    pub resolved_split: String,
+    pub delay: String,
+    pub label: String,
+    /// Confidence of an automatically found split point, from 0 to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+/// One parameter of the job details.
+#[derive(Debug, Serialize)]
+pub struct PlanDetail {
+    pub parameter: String,
+    pub value: String,
+}
+
+/// Where one segment lands in the output, as simulated before processing.
+#[derive(Debug, Serialize)]
+pub struct PlanSegment {
+    pub segment: String,
+    pub source: String,
+    pub adjustment: String,
+    pub output: String,
+}
+
+/// The plan shown for confirmation, kept as plain rows so it can also be exported for review.
+#[derive(Debug, Default, Serialize)]
+pub struct PlanReport {
+    pub splits: Vec<PlanRow>,
+    pub segments: Vec<PlanSegment>,
+    pub details: Vec<PlanDetail>,
+    /// Confidence of a measured initial delay, from 0 to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset_confidence: Option<f64>,
+}
+
+/// A confidence score as shown in the tables, or a dash for values given by hand.
+pub fn format_confidence(confidence: Option<f64>) -> String {
+    confidence.map_or_else(|| "-".to_string(), |c| format!("{:.2}", c))
+}
+
+impl PlanReport {
+    pub fn add_split(
+        &mut self,
+        source: String,
+        resolved_split: String,
+        delay: String,
+        label: String,
+        confidence: Option<f64>,
+    ) {
+        self.splits.push(PlanRow {
+            source,
+            resolved_split,
+            delay,
+            label,
+            confidence,
+        });
+    }
+
+    pub fn add_segment(
+        &mut self,
+        segment: String,
+        source: String,
+        adjustment: String,
+        output: String,
+    ) {
+        self.segments.push(PlanSegment {
+            segment,
+            source,
+            adjustment,
+            output,
+        });
+    }
+
+    pub fn add_detail(&mut self, parameter: &str, value: impl Into<String>) {
+        self.details.push(PlanDetail {
+            parameter: parameter.to_string(),
+            value: value.into(),
+        });
+    }
+
+    pub fn split_table(&self) -> Table {
+        let mut table = Table::new();
+        table
+            .set_header(vec![
+                t(Msg::HeaderSource),
+                t(Msg::HeaderResolvedSplit),
+                t(Msg::HeaderDelay),
+                t(Msg::HeaderLabel),
+                t(Msg::HeaderConfidence),
+            ])
+            .load_preset(UTF8_FULL);
+        for row in &self.splits {
+            table.add_row(vec![
+                row.source.clone(),
+                row.resolved_split.clone(),
+                row.delay.clone(),
+                row.label.clone(),
+                format_confidence(row.confidence),
+            ]);
+        }
+        table
+    }
+
+    pub fn segment_table(&self) -> Table {
+        let mut table = Table::new();
+        table
+            .set_header(vec![
+                t(Msg::HeaderSegment),
+                t(Msg::HeaderSourceSeconds),
+                t(Msg::HeaderAdjustment),
+                t(Msg::HeaderOutputSeconds),
+            ])
+            .load_preset(UTF8_FULL);
+        for row in &self.segments {
+            table.add_row(vec![
+                &row.segment,
+                &row.source,
+                &row.adjustment,
+                &row.output,
+            ]);
+        }
+        table
+    }
+
+    pub fn details_table(&self) -> Table {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .set_header(vec![t(Msg::HeaderParameter), t(Msg::HeaderValue)]);
+        for detail in &self.details {
+            table.add_row(vec![&detail.parameter, &detail.value]);
+        }
+        table
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let escape = |s: &str| s.replace('|', "\\|");
+        let mut md = String::from("# Proposed Splitting Plan\n\n");
+        md.push_str(
+            "| Source | Resolved Split (s) | Delay (ms) | Label | Confidence |\n|---|---|---|---|---|\n",
+        );
+        for row in &self.splits {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                escape(&row.source),
+                escape(&row.resolved_split),
+                escape(&row.delay),
+                escape(&row.label),
+                format_confidence(row.confidence)
+            ));
+        }
+        if !self.segments.is_empty() {
+            md.push_str("\n# Simulated Timeline\n\n");
+            md.push_str(
+                "| Segment | Source (s) | Silence / Trim (s) | Output (s) |\n|---|---|---|---|\n",
+            );
+            for row in &self.segments {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    escape(&row.segment),
+                    escape(&row.source),
+                    escape(&row.adjustment),
+                    escape(&row.output)
+                ));
+            }
+        }
+        md.push_str("\n# Job Details\n\n| Parameter | Value |\n|---|---|\n");
+        for detail in &self.details {
+            md.push_str(&format!(
+                "| {} | {} |\n",
+                escape(&detail.parameter),
+                escape(&detail.value)
+            ));
+        }
+        md
+    }
+
+    /// Write the plan as Markdown (`.md`) or JSON (`.json`), chosen by the extension of `path`.
+    pub fn write(&self, path: &str) -> Result<()> {
+        let contents = match Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("md") | Some("markdown") => self.to_markdown(),
+            Some("json") => serde_json::to_string_pretty(self)?,
+            _ => bail!(
+                "Cannot tell the plan format from '{}'. Use a .md or .json file.",
+                path
+            ),
+        };
+        crate::util::write_file_atomic(path, contents.as_bytes())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! `--porcelain` output: a line-oriented format for scripts that stays the same across releases.
+//!
+//! Every line is one record: a record name and its fields, separated by tabs. Tabs, newlines,
+//! carriage returns and backslashes inside a field are written as `\t`, `\n`, `\r` and `\\`.
+//! Numbers use a `.` as the decimal separator and no unit. The first line is
+//! `version <PORCELAIN_VERSION>` and the last one is `status ok` or `status error <message>`.
+//! Records may gain fields at the end and new record names may appear within a version;
+//! anything else bumps the version.
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Version of the porcelain format, written on the first line.
+pub const PORCELAIN_VERSION: u32 = 1;
+
+/// Where porcelain records go, once enabled.
+static PORCELAIN: Mutex<Option<File>> = Mutex::new(None);
+
+/// Switch to porcelain output until the returned guard is dropped. Stdout then carries only
+/// porcelain records; the human-readable output is moved to stderr. Only available on Unix,
+/// where the descriptors can be swapped.
+#[must_use = "porcelain output ends when the guard is dropped"]
+pub fn enable() -> io::Result<PorcelainGuard> {
+    let out = redirect_stdout()?;
+    *PORCELAIN.lock().unwrap_or_else(|e| e.into_inner()) = Some(out);
+    emit("version", &[&PORCELAIN_VERSION]);
+    Ok(PorcelainGuard(()))
+}
+
+/// Ends porcelain output when dropped: stdout is the original one again and `emit` does nothing.
+#[derive(Debug)]
+pub struct PorcelainGuard(());
+
+impl Drop for PorcelainGuard {
+    fn drop(&mut self) {
+        let out = PORCELAIN.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(out) = out {
+            restore_stdout(out);
+        }
+    }
+}
+
+/// Whether `--porcelain` is active.
+pub fn enabled() -> bool {
+    PORCELAIN
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some()
+}
+
+/// Write one record; does nothing without `--porcelain`.
+pub fn emit(record: &str, fields: &[&dyn Display]) {
+    let mut porcelain = PORCELAIN.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(out) = porcelain.as_mut() else {
+        return;
+    };
+    let mut line = record.to_string();
+    for field in fields {
+        line.push('\t');
+        line.push_str(&escape(&field.to_string()));
+    }
+    line.push('\n');
+    // A closed pipe on the reading side is the script's business, not a failed run
+    let _ = out.write_all(line.as_bytes());
+}
+
+/// Escape tabs, newlines and backslashes so a field stays on its line.
+pub fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(unix)]
+fn redirect_stdout() -> io::Result<File> {
+    use std::os::fd::FromRawFd;
+
+    io::stdout().flush()?;
+    // SAFETY: only the standard descriptors are duplicated; the copy of stdout is owned by the
+    // returned File from here on
+    unsafe {
+        let saved = libc::dup(1);
+        if saved < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::dup2(2, 1) < 0 {
+            let error = io::Error::last_os_error();
+            libc::close(saved);
+            return Err(error);
+        }
+        Ok(File::from_raw_fd(saved))
+    }
+}
+
+/// Point stdout back at `saved`, the copy taken by `redirect_stdout`, and close the copy.
+#[cfg(unix)]
+fn restore_stdout(saved: File) {
+    use std::os::fd::AsRawFd;
+
+    let _ = io::stdout().flush();
+    // SAFETY: `saved` is an open descriptor owned by this function until it is dropped below
+    unsafe {
+        libc::dup2(saved.as_raw_fd(), 1);
+    }
+}
+
+#[cfg(not(unix))]
+fn restore_stdout(_saved: File) {}
+
+#[cfg(not(unix))]
+fn redirect_stdout() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--porcelain is only available on Unix",
+    ))
+}
@@ -0,0 +1,328 @@
+use crate::ffmpeg::FFmpegError;
+use crate::i18n::{Msg, t, tf};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// A stop at the next stage boundary was requested (first Ctrl+C or SIGTERM).
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+/// The running stage should be abandoned right away (second Ctrl+C or SIGTERM).
+static ABORT_REQUESTED: AtomicBool = AtomicBool::new(false);
+/// Waiting for an answer at a prompt, where Ctrl+C quits at once as usual.
+static PROMPTING: AtomicBool = AtomicBool::new(false);
+
+/// Width of the terminal progress bar in characters.
+const BAR_WIDTH: usize = 30;
+/// The bar is redrawn after at least this much progress (as a fraction of the stage).
+const REDRAW_STEP: f64 = 0.005;
+
+/// Pipeline stages reported to progress callbacks, in execution order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Extracting,
+    Aligning,
+    Resolving,
+    Splitting,
+    Concatenating,
+    Adjusting,
+    Encoding,
+    Remuxing,
+}
+
+impl Stage {
+    /// Every stage, in execution order.
+    pub const ALL: [Stage; 8] = [
+        Stage::Extracting,
+        Stage::Aligning,
+        Stage::Resolving,
+        Stage::Splitting,
+        Stage::Concatenating,
+        Stage::Adjusting,
+        Stage::Encoding,
+        Stage::Remuxing,
+    ];
+
+    /// Lowercase name, as used for the stage hooks of a task file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Extracting => "extracting",
+            Stage::Aligning => "aligning",
+            Stage::Resolving => "resolving",
+            Stage::Splitting => "splitting",
+            Stage::Concatenating => "concatenating",
+            Stage::Adjusting => "adjusting",
+            Stage::Encoding => "encoding",
+            Stage::Remuxing => "remuxing",
+        }
+    }
+
+    /// Name shown in progress output, in the current language.
+    pub fn label(self) -> &'static str {
+        t(match self {
+            Stage::Extracting => Msg::StageExtracting,
+            Stage::Aligning => Msg::StageAligning,
+            Stage::Resolving => Msg::StageResolving,
+            Stage::Splitting => Msg::StageSplitting,
+            Stage::Concatenating => Msg::StageConcatenating,
+            Stage::Adjusting => Msg::StageAdjusting,
+            Stage::Encoding => Msg::StageEncoding,
+            Stage::Remuxing => Msg::StageRemuxing,
+        })
+    }
+}
+
+/// Called with the current stage and the fraction (0.0 - 1.0) of that stage completed.
+pub type ProgressCallback = Box<dyn Fn(Stage, f64) + Send + Sync>;
+
+/// Stage currently drawn by `terminal_progress`.
+struct BarState {
+    stage: Stage,
+    started: Instant,
+    drawn: f64,
+}
+
+/// Progress callback that draws a bar with an ETA on stderr for the long-running stages.
+pub fn terminal_progress() -> ProgressCallback {
+    let state: Mutex<Option<BarState>> = Mutex::new(None);
+    Box::new(move |stage, fraction| {
+        if !matches!(
+            stage,
+            Stage::Extracting
+                | Stage::Splitting
+                | Stage::Concatenating
+                | Stage::Encoding
+                | Stage::Remuxing
+        ) {
+            return;
+        }
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        let mut stderr = std::io::stderr().lock();
+        match state.as_mut() {
+            Some(bar) if bar.stage == stage => {
+                if fraction < 1.0 && fraction - bar.drawn < REDRAW_STEP {
+                    return;
+                }
+            }
+            current => {
+                if fraction >= 1.0 || fraction <= 0.0 {
+                    return;
+                }
+                // A stage that stopped short of 1.0 keeps its last line
+                if current.is_some() {
+                    let _ = writeln!(stderr);
+                }
+                *state = Some(BarState {
+                    stage,
+                    started: Instant::now(),
+                    drawn: 0.0,
+                });
+            }
+        }
+        let Some(bar) = state.as_mut() else {
+            return;
+        };
+        bar.drawn = fraction;
+        let elapsed = bar.started.elapsed();
+        let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+        let bar_text = format!(
+            "{}{}",
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH - filled.min(BAR_WIDTH))
+        );
+        if fraction >= 1.0 {
+            let _ = writeln!(
+                stderr,
+                "\r{:<13} [{}] 100% {}",
+                stage.label(),
+                bar_text,
+                tf(Msg::ProgressDoneIn, &[("time", format_clock(elapsed))])
+            );
+            *state = None;
+        } else {
+            let eta = elapsed.mul_f64((1.0 - fraction) / fraction);
+            let _ = write!(
+                stderr,
+                "\r{:<13} [{}] {:>3.0}% {}",
+                stage.label(),
+                bar_text,
+                fraction * 100.0,
+                tf(Msg::ProgressEta, &[("time", format_clock(eta))])
+            );
+        }
+        let _ = stderr.flush();
+    })
+}
+
+fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Cloneable flag that cancels a running pipeline. Cancelling kills the running ffmpeg
+/// child and makes the pipeline stop at the next stage boundary.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Cloneable request to stop the running job at the next stage boundary, keeping the finished
+/// stages for `--resume`: made by hand or by the job's stop file appearing.
+#[derive(Debug, Clone, Default)]
+pub struct StopRequest {
+    requested: Arc<AtomicBool>,
+    file: Arc<Mutex<Option<PathBuf>>>,
+}
+
+impl StopRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether a stop was requested. A stop file that appeared counts once and is removed, so
+    /// the resumed run does not stop right away.
+    pub fn is_requested(&self) -> bool {
+        if let Ok(file) = self.file.lock()
+            && let Some(path) = file.as_ref()
+            && path.exists()
+        {
+            let _ = std::fs::remove_file(path);
+            info!(
+                "\n⏸️ {}",
+                tf(Msg::StopFileFound, &[("path", path.display().to_string())])
+            );
+            self.request();
+        }
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Start a job: forget a stop requested for an earlier one and watch for `stop_file`.
+    pub(crate) fn start_job(&self, stop_file: Option<&Path>) {
+        self.requested.store(false, Ordering::SeqCst);
+        if let Ok(mut file) = self.file.lock() {
+            *file = stop_file.map(Path::to_path_buf);
+        }
+    }
+}
+
+/// Hooks a host application passes to the pipeline to follow and control a run.
+#[derive(Default)]
+pub struct PipelineHooks {
+    pub progress: Option<ProgressCallback>,
+    pub cancel: CancellationToken,
+    pub stop: StopRequest,
+}
+
+impl PipelineHooks {
+    /// Report progress within a stage.
+    pub fn report(&self, stage: Stage, fraction: f64) {
+        if let Some(progress) = &self.progress {
+            progress(stage, fraction.clamp(0.0, 1.0));
+        }
+    }
+
+    /// Fail with `FFmpegError::Cancelled` if cancellation was requested, or with
+    /// `FFmpegError::Stopped` if a stop at this stage boundary was requested.
+    pub fn check_cancelled(&self) -> Result<(), FFmpegError> {
+        if self.cancel.is_cancelled() || abort_requested() {
+            Err(FFmpegError::Cancelled)
+        } else if self.stop.is_requested() || stop_requested() {
+            Err(FFmpegError::Stopped)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Forget a stop or abort requested by a signal in an earlier run of the command line tool.
+pub fn clear_stop_request() {
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    ABORT_REQUESTED.store(false, Ordering::SeqCst);
+}
+
+/// Whether Ctrl+C or SIGTERM requested a stop at the next stage boundary.
+pub fn stop_requested() -> bool {
+    STOP_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Whether the running ffmpeg child should be killed right away.
+pub fn abort_requested() -> bool {
+    ABORT_REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Marks a prompt: while the guard lives, Ctrl+C quits at once instead of requesting a stop.
+pub struct PromptGuard;
+
+impl PromptGuard {
+    pub fn new() -> Self {
+        PROMPTING.store(true, Ordering::SeqCst);
+        PromptGuard
+    }
+}
+
+impl Default for PromptGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PromptGuard {
+    fn drop(&mut self) {
+        PROMPTING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Turn Ctrl+C and SIGTERM into a stop at the next stage boundary. A second signal abandons the
+/// running stage, a third quits at once. Signals are left alone on other platforms.
+pub fn install_stop_signals() {
+    #[cfg(unix)]
+    {
+        extern "C" fn on_signal(_: libc::c_int) {
+            // Only async-signal-safe calls here: atomics, write(2) and _exit(2)
+            let message: &[u8] =
+                if PROMPTING.load(Ordering::SeqCst) || ABORT_REQUESTED.load(Ordering::SeqCst) {
+                    // SAFETY: _exit is async-signal-safe
+                    unsafe { libc::_exit(130) }
+                } else if STOP_REQUESTED.swap(true, Ordering::SeqCst) {
+                    ABORT_REQUESTED.store(true, Ordering::SeqCst);
+                    "\n⏹️ Abandoning the current stage (press Ctrl+C again to quit at once)\n"
+                        .as_bytes()
+                } else {
+                    "\n⏸️ Stopping after the current stage (press Ctrl+C again to abandon it)\n"
+                        .as_bytes()
+                };
+            // SAFETY: write is async-signal-safe and the buffer outlives the call
+            unsafe {
+                libc::write(2, message.as_ptr().cast(), message.len());
+            }
+        }
+        let handler = on_signal as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only touches atomics and async-signal-safe functions
+        unsafe {
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+            libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+        }
+    }
+}
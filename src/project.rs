@@ -0,0 +1,182 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::task::Task;
+use crate::util::{path_to_str, write_file_atomic};
+
+/// File in a project directory that records the input and the attempts.
+pub const PROJECT_FILE: &str = "project.json";
+
+/// The task of a project, edited between attempts.
+pub const PROJECT_TASK_FILE: &str = "task.json";
+
+/// Outcome of an attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptStatus {
+    /// Started but never finished (killed or still running)
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl std::fmt::Display for AttemptStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AttemptStatus::Running => "running",
+            AttemptStatus::Succeeded => "succeeded",
+            AttemptStatus::Failed => "failed",
+        })
+    }
+}
+
+/// One `project run`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    pub number: u32,
+    /// Unix time (seconds) the attempt started
+    pub started_at: u64,
+    pub status: AttemptStatus,
+    /// Whether the attempt only printed the plan
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Contents of [`PROJECT_FILE`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectMeta {
+    pub input: String,
+    /// Unix time (seconds) the project was created
+    pub created_at: u64,
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+}
+
+/// A directory that keeps everything about fixing one file: the task, the analysis cache (the
+/// stages of failed runs, for `--resume`), preview clips and every attempt with the task it ran.
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub dir: PathBuf,
+    pub meta: ProjectMeta,
+}
+
+impl Project {
+    /// Create a project for `input` in `dir`, with a task that writes the output into the project.
+    pub fn create(dir: &Path, input: &str) -> Result<Self> {
+        if dir.join(PROJECT_FILE).exists() {
+            bail!("'{}' already holds a project.", dir.display());
+        }
+        if !Path::new(input).is_file() {
+            bail!("Input file '{}' does not exist.", input);
+        }
+        fs::create_dir_all(dir)?;
+        let input = fs::canonicalize(input)?;
+        let extension = input
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_else(|| "mkv".to_string());
+        let task = Task {
+            input: Some(path_to_str(&input)?.to_string()),
+            output: Some(path_to_str(&dir.join(format!("output.{}", extension)))?.to_string()),
+            ..Task::default()
+        };
+        let task_path = dir.join(PROJECT_TASK_FILE);
+        let task_path = path_to_str(&task_path)?;
+        write_file_atomic(task_path, task.to_file_string(task_path)?.as_bytes())?;
+        let project = Self {
+            dir: dir.to_path_buf(),
+            meta: ProjectMeta {
+                input: path_to_str(&input)?.to_string(),
+                created_at: unix_now(),
+                attempts: Vec::new(),
+            },
+        };
+        project.save()?;
+        Ok(project)
+    }
+
+    pub fn open(dir: &Path) -> Result<Self> {
+        let path = dir.join(PROJECT_FILE);
+        let Ok(contents) = fs::read_to_string(&path) else {
+            bail!(
+                "'{}' is not a project (no {}); create one with `project open {} --input <FILE>`.",
+                dir.display(),
+                PROJECT_FILE,
+                dir.display()
+            );
+        };
+        let meta = serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Could not read '{}': {}", path.display(), e))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            meta,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.meta)?;
+        write_file_atomic(path_to_str(&self.dir.join(PROJECT_FILE))?, json.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn task_path(&self) -> PathBuf {
+        self.dir.join(PROJECT_TASK_FILE)
+    }
+
+    /// Temp dir of the runs of this project, kept between attempts.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.dir.join("cache")
+    }
+
+    pub fn previews_dir(&self, attempt: u32) -> PathBuf {
+        self.dir
+            .join("previews")
+            .join(format!("attempt-{:03}", attempt))
+    }
+
+    pub fn attempt_dir(&self, attempt: u32) -> PathBuf {
+        self.dir.join("attempts").join(format!("{:03}", attempt))
+    }
+
+    /// Record a new attempt and keep a copy of the task it runs in its directory.
+    pub fn begin_attempt(&mut self, dry_run: bool) -> Result<u32> {
+        let number = self.meta.attempts.last().map_or(1, |a| a.number + 1);
+        let dir = self.attempt_dir(number);
+        fs::create_dir_all(&dir)?;
+        fs::create_dir_all(self.cache_dir())?;
+        fs::copy(self.task_path(), dir.join(PROJECT_TASK_FILE))?;
+        self.meta.attempts.push(Attempt {
+            number,
+            started_at: unix_now(),
+            status: AttemptStatus::Running,
+            dry_run,
+            error: None,
+        });
+        self.save()?;
+        Ok(number)
+    }
+
+    pub fn finish_attempt(&mut self, number: u32, result: &Result<()>) -> Result<()> {
+        if let Some(attempt) = self.meta.attempts.iter_mut().find(|a| a.number == number) {
+            match result {
+                Ok(()) => attempt.status = AttemptStatus::Succeeded,
+                Err(e) => {
+                    attempt.status = AttemptStatus::Failed;
+                    attempt.error = Some(format!("{:#}", e));
+                }
+            }
+        }
+        self.save()
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,182 @@
+use anyhow::Result;
+
+use crate::channels::{channel_names, decode_pcm_channels};
+
+/// The stream is decoded in chunks of this many seconds to keep memory bounded.
+const QC_CHUNK_SECONDS: f64 = 60.0;
+/// Mean sample value of a chunk above this (about -46 dBFS) counts as DC offset.
+const DC_OFFSET_LIMIT: f64 = 0.005;
+/// Samples at or above this magnitude count as full scale.
+const CLIP_LEVEL: f32 = 0.999;
+/// Minimum run of consecutive full-scale samples to count as clipping.
+const MIN_CLIP_RUN: usize = 3;
+/// Minimum second-difference jump for a click.
+const CLICK_MIN_JUMP: f32 = 0.2;
+/// A click jumps this many times above the average second difference of the preceding window.
+const CLICK_RATIO: f32 = 20.0;
+/// Length of the window that gives the background level for click detection.
+const CLICK_WINDOW_MS: f64 = 10.0;
+/// Events of the same kind closer than this are reported once.
+const EVENT_REFRACTORY_SECONDS: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    DcOffset,
+    Clipping,
+    Click,
+}
+
+impl std::fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArtifactKind::DcOffset => write!(f, "DC offset"),
+            ArtifactKind::Clipping => write!(f, "Clipping"),
+            ArtifactKind::Click => write!(f, "Click"),
+        }
+    }
+}
+
+/// A defect found in the audio, at `time` seconds from the start of the stream.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub time: f64,
+    /// The channel it was found in, for clipping; the other kinds are found in the mix
+    pub channel: Option<String>,
+    pub detail: String,
+}
+
+/// Scan an audio stream at its own sample rate for DC offset, clipping and clicks. `map` is an
+/// ffmpeg stream specifier for input 0. Clipping is looked for in each channel, DC offset and
+/// clicks in the mean of the channels. DC offset is reported per chunk, clipping and clicks at
+/// the sample where they occur.
+pub fn scan_artifacts(
+    input: &str,
+    map: &str,
+    layout: &str,
+    channel_count: usize,
+    sample_rate: u32,
+    duration: f64,
+) -> Result<Vec<Artifact>> {
+    let mut artifacts: Vec<Artifact> = Vec::new();
+    let rate = sample_rate as f64;
+    let click_window = ((CLICK_WINDOW_MS / 1000.0) * rate).max(1.0) as usize;
+    let channel_count = channel_count.max(1);
+    let names = channel_names(layout, channel_count);
+    // Length and start time of the full-scale run each channel is in, kept across chunks
+    let mut runs = vec![(0usize, 0.0f64); channel_count];
+    let mut chunk_start = 0.0;
+    while chunk_start < duration {
+        let planes = decode_pcm_channels(
+            input,
+            map,
+            channel_count,
+            chunk_start,
+            QC_CHUNK_SECONDS,
+            sample_rate,
+        )?;
+        if planes[0].is_empty() {
+            break;
+        }
+
+        for ((plane, name), (run, run_start)) in planes.iter().zip(&names).zip(&mut runs) {
+            for (i, sample) in plane.iter().enumerate() {
+                if sample.abs() >= CLIP_LEVEL {
+                    if *run == 0 {
+                        *run_start = chunk_start + i as f64 / rate;
+                    }
+                    *run += 1;
+                    continue;
+                }
+                push_clipping(&mut artifacts, name, *run, *run_start);
+                *run = 0;
+            }
+        }
+
+        let samples: Vec<f32> = (0..planes[0].len())
+            .map(|i| planes.iter().map(|p| p[i]).sum::<f32>() / channel_count as f32)
+            .collect();
+
+        let mean = samples.iter().map(|s| *s as f64).sum::<f64>() / samples.len() as f64;
+        if mean.abs() > DC_OFFSET_LIMIT {
+            artifacts.push(Artifact {
+                kind: ArtifactKind::DcOffset,
+                time: chunk_start,
+                channel: None,
+                detail: format!(
+                    "mean {:+.4} ({:.1} dBFS) over {:.0} s",
+                    mean,
+                    20.0 * mean.abs().log10(),
+                    samples.len() as f64 / rate
+                ),
+            });
+        }
+
+        let second_diff: Vec<f32> = samples
+            .windows(3)
+            .map(|w| (w[2] - 2.0 * w[1] + w[0]).abs())
+            .collect();
+        let mut background: f32 = second_diff.iter().take(click_window).sum();
+        for i in click_window..second_diff.len() {
+            let average = background / click_window as f32;
+            let jump = second_diff[i];
+            if jump > CLICK_MIN_JUMP && jump > CLICK_RATIO * average {
+                push_event(
+                    &mut artifacts,
+                    ArtifactKind::Click,
+                    None,
+                    chunk_start + (i + 2) as f64 / rate,
+                    format!(
+                        "jump {:.2} ({:.0}x the surrounding level)",
+                        jump,
+                        jump / average.max(1e-6)
+                    ),
+                );
+            }
+            background += jump - second_diff[i - click_window];
+        }
+
+        chunk_start += QC_CHUNK_SECONDS;
+    }
+    // A stream that ends at full scale
+    for ((run, run_start), name) in runs.into_iter().zip(&names) {
+        push_clipping(&mut artifacts, name, run, run_start);
+    }
+    Ok(artifacts)
+}
+
+/// Record a run of `run` full-scale samples in `channel` if it is long enough to be clipping.
+fn push_clipping(artifacts: &mut Vec<Artifact>, channel: &str, run: usize, start: f64) {
+    if run >= MIN_CLIP_RUN {
+        push_event(
+            artifacts,
+            ArtifactKind::Clipping,
+            Some(channel.to_string()),
+            start,
+            format!("{} consecutive full-scale samples in {}", run, channel),
+        );
+    }
+}
+
+/// Record an event unless one of the same kind in the same channel was just reported.
+fn push_event(
+    artifacts: &mut Vec<Artifact>,
+    kind: ArtifactKind,
+    channel: Option<String>,
+    time: f64,
+    detail: String,
+) {
+    let repeated = artifacts
+        .iter()
+        .rev()
+        .find(|a| a.kind == kind && a.channel == channel)
+        .is_some_and(|a| time - a.time < EVENT_REFRACTORY_SECONDS);
+    if !repeated {
+        artifacts.push(Artifact {
+            kind,
+            time,
+            channel,
+            detail,
+        });
+    }
+}
@@ -0,0 +1,335 @@
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::analysis::{DEFAULT_IN_SYNC_THRESHOLD_MS, align_by_onset};
+use crate::audio_metadata::{
+    find_audio_stream_by_tags, get_audio_stream_duration, get_file_duration, probe_audio_stream,
+    resolve_stream,
+};
+use crate::audio_processing::{
+    RunOptions, extract_audio_stream_to_flac, intermediate_file, measure_noise_floor,
+};
+use crate::chapters::read_chapters;
+use crate::cli::{FrameUnits, SplitPoint, StreamSpecifier, parse_timecode};
+use crate::detector::{build_detector, find_split_point};
+use crate::language::normalize_or_warn;
+use crate::task::Task;
+
+/// File extensions treated as episodes when scanning a season directory.
+const EPISODE_EXTENSIONS: &[&str] = &["mkv", "mp4", "m4v", "mov", "avi", "ts", "m2ts", "webm"];
+
+/// A time in a season template, resolved separately for every episode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplateTime {
+    /// Seconds from the start (`754.2`)
+    Seconds(f64),
+    /// Percentage of the episode duration (`"50%"`)
+    Percent(f64),
+    /// Start of a chapter, counting from 1, plus an offset in seconds (`"chapter:3"`, `"chapter:3+12.5"`)
+    Chapter { number: usize, offset: f64 },
+}
+
+/// Template files may give a time as a number of seconds or as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTemplateTime {
+    Seconds(f64),
+    Text(String),
+}
+
+impl TryFrom<RawTemplateTime> for TemplateTime {
+    type Error = String;
+
+    fn try_from(raw: RawTemplateTime) -> Result<Self, Self::Error> {
+        match raw {
+            RawTemplateTime::Seconds(seconds) => Ok(TemplateTime::Seconds(seconds)),
+            RawTemplateTime::Text(text) => parse_template_time(&text),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TemplateTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        RawTemplateTime::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parse `754.2`, `50%`, `chapter:3` or `chapter:3+12.5` / `chapter:3-4`.
+pub fn parse_template_time(s: &str) -> Result<TemplateTime, String> {
+    let s = s.trim();
+    if let Some(percent) = s.strip_suffix('%') {
+        let percent: f64 = percent
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid percentage '{}'", s))?;
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!("Percentage '{}' must be between 0% and 100%", s));
+        }
+        return Ok(TemplateTime::Percent(percent));
+    }
+    if let Some(rest) = s.strip_prefix("chapter:") {
+        let (number, offset) = match rest.find(['+', '-']) {
+            Some(pos) => {
+                let offset: f64 = rest[pos..]
+                    .parse()
+                    .map_err(|_| format!("Invalid chapter offset in '{}'", s))?;
+                (&rest[..pos], offset)
+            }
+            None => (rest, 0.0),
+        };
+        let number: usize = number
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid chapter number in '{}'", s))?;
+        if number == 0 {
+            return Err(format!("Chapters are counted from 1 in '{}'", s));
+        }
+        return Ok(TemplateTime::Chapter { number, offset });
+    }
+    parse_timecode(s).map(TemplateTime::Seconds).map_err(|_| {
+        format!(
+            "Invalid time '{}'. Use seconds, a timecode (1:23:45.2), a percentage (50%) or a chapter (chapter:3+12.5)",
+            s
+        )
+    })
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateSplit {
+    pub time: TemplateTime,
+    /// Delay in milliseconds (can be fractional, e.g., 200.5)
+    pub delay: f64,
+    /// Note shown in the plan, e.g. "reel change"
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateRange {
+    #[serde(rename = "startTime")]
+    pub start: TemplateTime,
+    #[serde(rename = "endTime")]
+    pub end: TemplateTime,
+    /// Delay in milliseconds (can be fractional, e.g., 200.5)
+    pub delay: f64,
+    /// Note shown in the plan, e.g. "reel change"
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// A task file whose split times are relative to each episode. All other fields are copied
+/// into every episode's task as-is.
+#[derive(Debug, Deserialize)]
+pub struct SeasonTemplate {
+    #[serde(default)]
+    pub splits: Vec<TemplateSplit>,
+    #[serde(default)]
+    pub split_ranges: Vec<TemplateRange>,
+    #[serde(flatten)]
+    pub base: Task,
+}
+
+impl SeasonTemplate {
+    /// Load a template as JSON, YAML or TOML, like a task file.
+    pub fn load(path: &str) -> Result<Self> {
+        crate::task::read_task_file(path)
+    }
+}
+
+/// An episode with its template resolved to absolute split points.
+pub struct ResolvedEpisode {
+    pub input: String,
+    pub task_path: String,
+    pub duration: f64,
+    /// The measured offset is below the in-sync threshold and nothing else is planned
+    pub in_sync: bool,
+    pub task: Task,
+}
+
+/// Whether a path is a file with one of the video extensions treated as episodes.
+pub fn is_episode_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| EPISODE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// List the episodes of a season directory, sorted by file name.
+/// Nothing below `exclude` (the output directory) is picked up.
+pub fn find_episodes(dir: &str, exclude: &Path) -> Result<Vec<PathBuf>> {
+    let mut episodes = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_episode_file(&path) && !path.starts_with(exclude) {
+            episodes.push(path);
+        }
+    }
+    episodes.sort();
+    if episodes.is_empty() {
+        bail!("No episodes found in '{}'.", dir);
+    }
+    Ok(episodes)
+}
+
+/// Start times (in seconds) of the chapters of a media file, in order.
+pub fn get_chapter_starts(input: &str) -> Result<Vec<f64>> {
+    Ok(read_chapters(input)?.iter().map(|c| c.start).collect())
+}
+
+/// Resolve a template time against an episode's duration and chapters.
+pub fn resolve_time(time: TemplateTime, duration: f64, chapters: &[f64]) -> Result<f64> {
+    let seconds = match time {
+        TemplateTime::Seconds(seconds) => seconds,
+        TemplateTime::Percent(percent) => duration * percent / 100.0,
+        TemplateTime::Chapter { number, offset } => match chapters.get(number - 1) {
+            Some(start) => start + offset,
+            None => bail!(
+                "Chapter {} does not exist (the episode has {} chapters).",
+                number,
+                chapters.len()
+            ),
+        },
+    };
+    if seconds < 0.0 || seconds > duration {
+        bail!(
+            "Resolved time {:.3}s is outside the episode (duration {:.3}s).",
+            seconds,
+            duration
+        );
+    }
+    Ok(seconds)
+}
+
+/// Resolve the template for one episode: template times become absolute, and split ranges are
+/// narrowed to the quietest point of this episode's audio, which is extracted to `tmpdir`.
+pub fn resolve_episode(
+    template: &SeasonTemplate,
+    episode: &Path,
+    output: &Path,
+    tmpdir: &Path,
+    debug: bool,
+) -> Result<ResolvedEpisode> {
+    let input = crate::util::path_to_str(episode)?;
+    let base = &template.base;
+    let stream = match (base.stream, &base.language, &base.stream_title) {
+        (Some(stream_spec), _, _) => probe_audio_stream(input, stream_spec)?.absolute_index,
+        (None, None, None) => {
+            bail!("The template must set 'stream', 'language' or 'stream_title'.")
+        }
+        (None, language, title) => {
+            let language = language
+                .as_ref()
+                .map(|l| normalize_or_warn(l, "the template"));
+            find_audio_stream_by_tags(input, language.as_deref(), title.as_deref())?
+        }
+    };
+    let duration = match get_audio_stream_duration(input, stream)? {
+        Some(duration) => duration,
+        None => get_file_duration(input)?,
+    };
+    let chapters = get_chapter_starts(input)?;
+
+    let mut splits: Vec<SplitPoint> = Vec::new();
+    for split in &template.splits {
+        splits.push(SplitPoint {
+            time: resolve_time(split.time, duration, &chapters)?,
+            delay: split.delay,
+            label: split.label.clone(),
+            frames: FrameUnits::default(),
+        });
+    }
+    if !template.split_ranges.is_empty() {
+        let run = RunOptions {
+            debug,
+            ..RunOptions::default()
+        };
+        let flac_path = intermediate_file(tmpdir, "season_episode", run.intermediate);
+        extract_audio_stream_to_flac(input, stream, &flac_path, &run, &|_| {})?;
+        let mut silence_threshold = base.silence_threshold.unwrap_or(-95.0);
+        if let Some(margin) = base.auto_threshold
+            && let Some(floor) = measure_noise_floor(&flac_path)?
+        {
+            silence_threshold = floor + margin;
+        }
+        let zero_crossing = base.zero_crossing.unwrap_or(true);
+        let detector = build_detector(
+            base.detector.unwrap_or_default(),
+            silence_threshold,
+            base.max_peak,
+            base.avoid_speech.unwrap_or(false),
+            base.detector_command.as_deref(),
+        )?;
+        for range in &template.split_ranges {
+            let start = resolve_time(range.start, duration, &chapters)?;
+            let end = resolve_time(range.end, duration, &chapters)?;
+            if end <= start {
+                bail!(
+                    "Split range resolves to {:.3}s - {:.3}s, which is empty.",
+                    start,
+                    end
+                );
+            }
+            let result = find_split_point(
+                detector.as_ref(),
+                &flac_path,
+                start,
+                end,
+                zero_crossing,
+                debug,
+            )?;
+            splits.push(SplitPoint {
+                time: result.time,
+                delay: range.delay,
+                label: range.label.clone(),
+                frames: FrameUnits::default(),
+            });
+        }
+        std::fs::remove_file(&flac_path)?;
+    }
+    splits.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    // Without a fixed initial delay, a cheap onset estimate decides the delay and whether
+    // the episode is already in sync
+    let mut initial_delay = base.initial_delay;
+    let mut in_sync = false;
+    if let (Some(onset_stream), None) = (base.match_onset, base.initial_delay) {
+        let alignment = align_by_onset(
+            input,
+            &format!("0:{}", stream),
+            input,
+            &resolve_stream(input, onset_stream)?.to_map(),
+        )?;
+        let threshold = base
+            .in_sync_threshold
+            .unwrap_or(DEFAULT_IN_SYNC_THRESHOLD_MS);
+        in_sync = alignment.offset_ms.abs() < threshold
+            && splits.is_empty()
+            && base.ramps.is_empty()
+            && base.drift_ppm.is_none()
+            && base.stretch.is_none()
+            && base.sync_points.is_empty();
+        initial_delay = Some(alignment.offset_ms);
+    }
+
+    let task = Task {
+        input: Some(input.to_string()),
+        output: Some(crate::util::path_to_str(output)?.to_string()),
+        stream: base.stream.or(Some(StreamSpecifier::Index(stream))),
+        initial_delay,
+        splits,
+        split_ranges: Vec::new(),
+        ..base.clone()
+    };
+    let task_path = episode.with_extension("json").to_string_lossy().to_string();
+    Ok(ResolvedEpisode {
+        input: input.to_string(),
+        task_path,
+        duration,
+        in_sync,
+        task,
+    })
+}
@@ -0,0 +1,414 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::analysis::integrated_loudness;
+use crate::audio_metadata::{get_audio_stream_duration, get_file_duration};
+use crate::audio_processing::{
+    RunOptions, extract_audio_stream_to_flac, find_quietest_point, intermediate_file, str_args,
+};
+use crate::doctor::CheckStatus;
+use crate::ffmpeg::{
+    ChildSettings, check_ffmpeg_installation, ffmpeg_executable, ffprobe_executable, run_ffmpeg,
+};
+use crate::util::path_to_str;
+
+/// Length of the generated fixture in seconds.
+pub const FIXTURE_DURATION: f64 = 30.0;
+/// Span in which the fixture's tone drops to a whisper; a split range around it must resolve
+/// inside it.
+pub const FIXTURE_QUIET: (f64, f64) = (9.5, 10.5);
+/// Absolute index of the fixture's audio stream (after the video stream).
+const FIXTURE_AUDIO_STREAM: usize = 1;
+/// Delay the pipeline run applies after the split, in milliseconds.
+const PIPELINE_DELAY_MS: f64 = 200.0;
+/// Allowed difference between expected and measured durations; one AC-3 frame is 32 ms.
+const DURATION_TOLERANCE: f64 = 0.05;
+
+/// An ffmpeg executable and the ffprobe next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfmpegBuild {
+    pub ffmpeg: String,
+    pub ffprobe: String,
+}
+
+impl FfmpegBuild {
+    /// The build of `ffmpeg`, with the ffprobe of the same name next to it (`ffmpeg-6` pairs
+    /// with `ffprobe-6`).
+    pub fn for_ffmpeg(ffmpeg: &str) -> Self {
+        let path = Path::new(ffmpeg);
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(ffmpeg)
+            .replacen("ffmpeg", "ffprobe", 1);
+        let ffprobe = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                parent.join(name).to_string_lossy().to_string()
+            }
+            _ => name,
+        };
+        Self {
+            ffmpeg: ffmpeg.to_string(),
+            ffprobe,
+        }
+    }
+}
+
+/// The builds to test: the given ffmpeg executables, or else the configured one and every
+/// versioned ffmpeg on PATH (e.g. `ffmpeg-6` or `ffmpeg7`) that has a matching ffprobe.
+pub fn detect_builds(ffmpeg: &[String]) -> Vec<FfmpegBuild> {
+    if !ffmpeg.is_empty() {
+        return ffmpeg.iter().map(|f| FfmpegBuild::for_ffmpeg(f)).collect();
+    }
+    let configured = FfmpegBuild {
+        ffmpeg: ffmpeg_executable(),
+        ffprobe: ffprobe_executable(),
+    };
+    let mut seen: HashSet<PathBuf> = find_executable(&configured.ffmpeg).into_iter().collect();
+    let mut builds = vec![configured];
+    let versioned = regex::Regex::new(r"^ffmpeg[-_.]?\d[\w.]*?(\.exe)?$").expect("valid regex");
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| versioned.is_match(name))
+            .collect();
+        names.sort();
+        for name in names {
+            let build = FfmpegBuild::for_ffmpeg(&dir.join(&name).to_string_lossy());
+            let canonical = std::fs::canonicalize(&build.ffmpeg).ok();
+            if Path::new(&build.ffprobe).is_file() && canonical.is_some_and(|c| seen.insert(c)) {
+                builds.push(build);
+            }
+        }
+    }
+    builds
+}
+
+/// Where `program` resolves to: the path itself if it names one, else the first match on PATH.
+fn find_executable(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return std::fs::canonicalize(path).ok();
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| std::fs::canonicalize(candidate).ok())
+}
+
+/// One line of the `selftest` report.
+#[derive(Debug, Clone)]
+pub struct SelftestCheck {
+    /// The ffmpeg executable the check ran with
+    pub build: String,
+    pub name: &'static str,
+    pub value: String,
+    pub status: CheckStatus,
+}
+
+/// Collects the checks of one build.
+struct BuildChecks<'a> {
+    build: String,
+    checks: &'a mut Vec<SelftestCheck>,
+}
+
+impl BuildChecks<'_> {
+    /// Record the outcome of a check; returns whether it passed.
+    fn record<T>(
+        &mut self,
+        name: &'static str,
+        result: &Result<T>,
+        value: impl Fn(&T) -> String,
+    ) -> bool {
+        let (value, status) = match result {
+            Ok(ok) => (value(ok), CheckStatus::Ok),
+            Err(e) => (format!("{:#}", e), CheckStatus::Error),
+        };
+        self.checks.push(SelftestCheck {
+            build: self.build.clone(),
+            name,
+            value,
+            status,
+        });
+        status == CheckStatus::Ok
+    }
+
+    fn skip(&mut self, name: &'static str, reason: &str) {
+        self.checks.push(SelftestCheck {
+            build: self.build.clone(),
+            name,
+            value: reason.to_string(),
+            status: CheckStatus::Info,
+        });
+    }
+}
+
+/// Runs the full pipeline with the given command line (as for `sync-nudger process`).
+pub type PipelineRunner<'a> = &'a dyn Fn(&[String]) -> Result<()>;
+
+/// Test one ffmpeg build in `dir`: its version, a generated fixture, duration and loudness
+/// parsing and the quiet-point search. With `thorough`, the fixture is also split at a quiet
+/// point and delayed through the full pipeline, once in a single pass and once with a file per
+/// segment, and the resolved split and output durations are checked.
+pub fn run_build(
+    build: &FfmpegBuild,
+    dir: &Path,
+    thorough: bool,
+    debug: bool,
+    run_pipeline: PipelineRunner,
+) -> Vec<SelftestCheck> {
+    let _children = ChildSettings {
+        ffmpeg_path: Some(build.ffmpeg.clone()),
+        ffprobe_path: Some(build.ffprobe.clone()),
+        ..ChildSettings::current()
+    }
+    .enter();
+    let mut checks = Vec::new();
+    let mut section = BuildChecks {
+        build: build.ffmpeg.clone(),
+        checks: &mut checks,
+    };
+
+    if !section.record("Version", &check_version(), |v| v.clone()) {
+        return checks;
+    }
+    let fixture = generate_fixture(dir, debug);
+    if !section.record("Fixture", &fixture, |_| {
+        format!("{:.0} s of video and AC-3 audio", FIXTURE_DURATION)
+    }) {
+        return checks;
+    }
+    let Ok(fixture) = fixture else {
+        return checks;
+    };
+    section.record("Duration", &check_duration(&fixture), |d| {
+        format!("{:.3} s", d)
+    });
+    section.record("Loudness", &check_loudness(&fixture), |l| {
+        format!("{:.1} LUFS", l)
+    });
+    section.record(
+        "Quiet point",
+        &check_quiet_point(&fixture, dir, debug),
+        |t| format!("{:.3} s", t),
+    );
+
+    if !thorough {
+        section.skip("Pipeline", "skipped (run with --thorough)");
+        return checks;
+    }
+    let single = run_fixture_pipeline(&fixture, dir, "single_pass", &[], run_pipeline);
+    let ok = section.record("Pipeline", &single, |(split, duration)| {
+        format!("split at {:.3} s, audio {:.3} s", split, duration)
+    });
+    let segmented = run_fixture_pipeline(
+        &fixture,
+        dir,
+        "segment_files",
+        &["--segment-files"],
+        run_pipeline,
+    );
+    let segmented = segmented.and_then(|(split, duration)| match &single {
+        Ok((_, single_duration)) if ok && (duration - single_duration).abs() > 0.001 => bail!(
+            "audio is {:.3} s, {:.3} s in a single pass",
+            duration,
+            single_duration
+        ),
+        _ => Ok((split, duration)),
+    });
+    section.record(
+        "Pipeline (--segment-files)",
+        &segmented,
+        |(split, duration)| format!("split at {:.3} s, audio {:.3} s", split, duration),
+    );
+    checks
+}
+
+fn check_version() -> Result<String> {
+    let result = check_ffmpeg_installation();
+    if !result.ffmpeg_available {
+        bail!(
+            "{} not usable: {}",
+            result.ffmpeg_path,
+            result.error.as_deref().unwrap_or("not found")
+        );
+    }
+    if !result.ffprobe_available {
+        bail!("{} not found", result.ffprobe_path);
+    }
+    let version = result
+        .ffmpeg_version
+        .context("the version could not be parsed")?;
+    let version_str = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    if !version.is_compatible {
+        bail!("{} is too old (minimum required: 4.0.0)", version_str);
+    }
+    if !result.ebur128_filter_available {
+        bail!("{} has no ebur128 filter", version_str);
+    }
+    Ok(version_str)
+}
+
+/// Generate a short video with a 440 Hz stereo tone that drops to a whisper in
+/// `FIXTURE_QUIET`, using only encoders built into every ffmpeg.
+fn generate_fixture(dir: &Path, debug: bool) -> Result<PathBuf> {
+    let path = dir.join("fixture.mkv");
+    let video = format!("testsrc=size=160x120:rate=25:duration={}", FIXTURE_DURATION);
+    let audio = format!(
+        "aevalsrc='0.5*sin(2*PI*440*t)*if(between(t,{},{}),0.002,1)':s=48000:c=stereo:d={}",
+        FIXTURE_QUIET.0, FIXTURE_QUIET.1, FIXTURE_DURATION
+    );
+    let args = vec![
+        "-y".to_string(),
+        "-f".to_string(),
+        "lavfi".to_string(),
+        "-i".to_string(),
+        video,
+        "-f".to_string(),
+        "lavfi".to_string(),
+        "-i".to_string(),
+        audio,
+        "-map".to_string(),
+        "0:v".to_string(),
+        "-map".to_string(),
+        "1:a".to_string(),
+        "-c:v".to_string(),
+        "mpeg4".to_string(),
+        "-c:a".to_string(),
+        "ac3".to_string(),
+        "-b:a".to_string(),
+        "192k".to_string(),
+        path_to_str(&path)?.to_string(),
+    ];
+    run_ffmpeg(&str_args(&args), debug)?;
+    Ok(path)
+}
+
+/// Audio duration of `path`, from the stream or else the container.
+fn audio_duration(path: &Path) -> Result<f64> {
+    let path = path_to_str(path)?;
+    match get_audio_stream_duration(path, FIXTURE_AUDIO_STREAM)? {
+        Some(duration) => Ok(duration),
+        None => get_file_duration(path),
+    }
+}
+
+fn check_duration(fixture: &Path) -> Result<f64> {
+    let duration = audio_duration(fixture)?;
+    if (duration - FIXTURE_DURATION).abs() > DURATION_TOLERANCE {
+        bail!("read {:.3} s, expected {:.3} s", duration, FIXTURE_DURATION);
+    }
+    Ok(duration)
+}
+
+fn check_loudness(fixture: &Path) -> Result<f64> {
+    let loudness = integrated_loudness(
+        path_to_str(fixture)?,
+        &format!("0:{}", FIXTURE_AUDIO_STREAM),
+    )?;
+    if !loudness.is_finite() || !(-40.0..0.0).contains(&loudness) {
+        bail!("read {} LUFS, expected a loud tone", loudness);
+    }
+    Ok(loudness)
+}
+
+fn check_quiet_point(fixture: &Path, dir: &Path, debug: bool) -> Result<f64> {
+    let run = RunOptions {
+        debug,
+        ..RunOptions::default()
+    };
+    let audio = intermediate_file(dir, "fixture_audio", run.intermediate);
+    extract_audio_stream_to_flac(
+        path_to_str(fixture)?,
+        FIXTURE_AUDIO_STREAM,
+        &audio,
+        &run,
+        &|_| {},
+    )?;
+    let quietest = find_quietest_point(
+        &audio,
+        FIXTURE_QUIET.0 - 1.5,
+        FIXTURE_QUIET.1 + 1.5,
+        -95.0,
+        None,
+        false,
+        debug,
+    )?;
+    check_in_quiet_span(quietest.time)?;
+    Ok(quietest.time)
+}
+
+fn check_in_quiet_span(time: f64) -> Result<()> {
+    if !(FIXTURE_QUIET.0..=FIXTURE_QUIET.1).contains(&time) {
+        bail!(
+            "found {:.3} s, expected {:.1}-{:.1} s",
+            time,
+            FIXTURE_QUIET.0,
+            FIXTURE_QUIET.1
+        );
+    }
+    Ok(())
+}
+
+/// Split the fixture in a range around its quiet span and delay the rest through the full
+/// pipeline. Returns the resolved split point and the output's audio duration.
+fn run_fixture_pipeline(
+    fixture: &Path,
+    dir: &Path,
+    name: &str,
+    extra_args: &[&str],
+    run_pipeline: PipelineRunner,
+) -> Result<(f64, f64)> {
+    let output = dir.join(format!("{}.mkv", name));
+    let plan = dir.join(format!("{}_plan.json", name));
+    let mut args: Vec<String> = [
+        "sync-nudger",
+        "--input",
+        path_to_str(fixture)?,
+        "--output",
+        path_to_str(&output)?,
+        "--stream",
+        &FIXTURE_AUDIO_STREAM.to_string(),
+        "--split-range",
+        &format!(
+            "{}:{}:{}",
+            FIXTURE_QUIET.0 - 1.5,
+            FIXTURE_QUIET.1 + 1.5,
+            PIPELINE_DELAY_MS
+        ),
+        "--plan-out",
+        path_to_str(&plan)?,
+        "--ignore-ffmpeg-version",
+        "--yes",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    args.extend(extra_args.iter().map(|arg| arg.to_string()));
+    run_pipeline(&args)?;
+
+    let plan: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&plan)?)?;
+    let split: f64 = plan["splits"]
+        .as_array()
+        .and_then(|splits| splits.first())
+        .and_then(|split| split["resolved_split"].as_str())
+        .and_then(|split| split.parse().ok())
+        .context("the plan has no resolved split")?;
+    check_in_quiet_span(split)?;
+
+    let duration = audio_duration(&output)?;
+    let expected = FIXTURE_DURATION + PIPELINE_DELAY_MS / 1000.0;
+    if (duration - expected).abs() > DURATION_TOLERANCE {
+        bail!(
+            "output audio is {:.3} s, expected {:.3} s",
+            duration,
+            expected
+        );
+    }
+    Ok((split, duration))
+}
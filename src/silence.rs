@@ -0,0 +1,69 @@
+use anyhow::{Result, bail};
+use regex::Regex;
+
+use crate::ffmpeg::ffmpeg_output;
+
+/// An interval of silence in an audio stream, in seconds from its start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Silence {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Silence {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Find the intervals where an audio stream stays below `noise_db` (dBFS) for at least
+/// `min_duration` seconds, with ffmpeg's `silencedetect` filter. `map` is an ffmpeg stream
+/// specifier for input 0. A silence still running at the end of the stream ends at
+/// `stream_end` (or is dropped if the end is unknown).
+pub fn detect_silences(
+    input: &str,
+    map: &str,
+    noise_db: f64,
+    min_duration: f64,
+    stream_end: Option<f64>,
+) -> Result<Vec<Silence>> {
+    let filter = format!("silencedetect=noise={}dB:d={}", noise_db, min_duration);
+    let output = ffmpeg_output(
+        &["-i", input, "-map", map, "-af", &filter, "-f", "null", "-"],
+        false,
+    )?;
+    if !output.status.success() {
+        bail!(
+            "Could not detect silence in '{}': {}",
+            input,
+            output.last_error_line()
+        );
+    }
+    parse_silencedetect(&String::from_utf8_lossy(&output.stderr), stream_end)
+}
+
+/// Collect the `silence_start` / `silence_end` pairs `silencedetect` logs to stderr.
+pub fn parse_silencedetect(stderr: &str, stream_end: Option<f64>) -> Result<Vec<Silence>> {
+    let event = Regex::new(r"silence_(start|end): (-?[0-9.]+)")?;
+    let mut silences = Vec::new();
+    let mut start = None;
+    for caps in event.captures_iter(stderr) {
+        let Ok(time) = caps[2].parse::<f64>() else {
+            continue;
+        };
+        match &caps[1] {
+            "start" => start = Some(time.max(0.0)),
+            _ => {
+                if let Some(start) = start.take() {
+                    silences.push(Silence { start, end: time });
+                }
+            }
+        }
+    }
+    if let (Some(start), Some(end)) = (start, stream_end)
+        && end > start
+    {
+        silences.push(Silence { start, end });
+    }
+    Ok(silences)
+}
@@ -0,0 +1,93 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::cli::{DelayRamp, SplitPoint, SplitRange, parse_ramp, parse_split, parse_split_range};
+use crate::task::read_task_file;
+
+/// The initial delay, split points, split ranges and ramps of a job, loaded with `--split-map`.
+/// Written like the same fields of a task file, or in the line format of [`parse_split_map`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SplitMap {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_delay: Option<f64>,
+    #[serde(default)]
+    pub splits: Vec<SplitPoint>,
+    #[serde(default)]
+    pub split_ranges: Vec<SplitRange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ramps: Vec<DelayRamp>,
+}
+
+impl SplitMap {
+    /// Load a split map: `.txt` and `.map` files in the line format, anything else as JSON, YAML
+    /// or TOML like a task file.
+    pub fn load(path: &str) -> Result<Self> {
+        let line_format = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("txt") || e.eq_ignore_ascii_case("map"));
+        let map = if line_format {
+            let contents = std::fs::read_to_string(path)?;
+            parse_split_map(&contents)
+                .map_err(|e| anyhow::anyhow!("Could not read split map '{}': {}", path, e))?
+        } else {
+            read_task_file(path)?
+        };
+        if map.initial_delay.is_none()
+            && map.splits.is_empty()
+            && map.split_ranges.is_empty()
+            && map.ramps.is_empty()
+        {
+            bail!("The split map '{}' holds no delays or splits.", path);
+        }
+        Ok(map)
+    }
+}
+
+/// Parse the line format of a split map: one entry per line, written like the matching option.
+///
+/// ```text
+/// # PAL DVD against the BD
+/// delay 250
+/// split 20:31.5:+40
+/// range 41:10:41:40:-40:label=reel change
+/// ramp 3600:3660:+25
+/// ```
+///
+/// Empty lines and lines starting with `#` are skipped.
+pub fn parse_split_map(contents: &str) -> Result<SplitMap> {
+    let mut map = SplitMap::default();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        let parsed = match keyword.to_ascii_lowercase().as_str() {
+            "delay" | "initial_delay" => {
+                if map.initial_delay.is_some() {
+                    bail!("line {}: the initial delay is given twice", number + 1);
+                }
+                value
+                    .parse::<f64>()
+                    .map(|delay| map.initial_delay = Some(delay))
+                    .map_err(|e| format!("invalid delay '{}': {}", value, e))
+            }
+            "split" => parse_split(value).map(|split| map.splits.push(split)),
+            "range" | "split_range" => {
+                parse_split_range(value).map(|range| map.split_ranges.push(range))
+            }
+            "ramp" => parse_ramp(value).map(|ramp| map.ramps.push(ramp)),
+            _ => Err(format!(
+                "unknown entry '{}', expected delay, split, range or ramp",
+                keyword
+            )),
+        };
+        if let Err(e) = parsed {
+            bail!("line {}: {}", number + 1, e);
+        }
+    }
+    Ok(map)
+}
@@ -0,0 +1,355 @@
+use crate::audio_processing::SimulatedSegment;
+use crate::ffmpeg::{ffprobe_executable, run_ffmpeg};
+use crate::util::path_to_str;
+use anyhow::{Result, bail};
+use regex::{Captures, Regex};
+use std::path::Path;
+use std::process::Command;
+
+/// Subtitle codecs that are plain text with timestamps and can be re-timed cue by cue.
+const TEXT_SUBTITLE_CODECS: &[&str] =
+    &["subrip", "srt", "ass", "ssa", "mov_text", "webvtt", "text"];
+
+/// How source times move through the delay plan: the segments of `simulate_segments`,
+/// followed by the clock drift correction of the whole track.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    segments: Vec<SimulatedSegment>,
+    stretch: f64,
+}
+
+impl Timeline {
+    pub fn new(segments: Vec<SimulatedSegment>, drift_ppm: Option<f64>) -> Self {
+        Self {
+            segments,
+            stretch: 1.0 + drift_ppm.unwrap_or(0.0) / 1_000_000.0,
+        }
+    }
+
+    /// Output time of source time `t`, or None if the audio at `t` is trimmed away.
+    /// Times past the last segment move with it.
+    pub fn map(&self, t: f64) -> Option<f64> {
+        let Some(segment) = self
+            .segments
+            .iter()
+            .find(|s| t < s.source_end)
+            .or(self.segments.last())
+        else {
+            return Some(t * self.stretch);
+        };
+        let local = (t - segment.source_start).max(0.0) / segment.tempo;
+        if local < segment.trim {
+            return None;
+        }
+        Some((segment.output_start + segment.silence + local - segment.trim) * self.stretch)
+    }
+
+    /// Output time of source time `t`, or where the audio resumes if `t` is trimmed away.
+    pub fn map_clamped(&self, t: f64) -> f64 {
+        self.map(t).unwrap_or_else(|| {
+            let segment = self
+                .segments
+                .iter()
+                .find(|s| t < s.source_end)
+                .or(self.segments.last());
+            segment
+                .map(|s| (s.output_start + s.silence) * self.stretch)
+                .unwrap_or(t * self.stretch)
+        })
+    }
+
+    /// Output times of a cue shown from `start` to `end`. A cue whose start is trimmed away
+    /// begins where the audio resumes; a cue that is trimmed away completely is dropped.
+    pub fn map_cue(&self, start: f64, end: f64) -> Option<(f64, f64)> {
+        let new_end = self.map(end)?;
+        let new_start = self.map_clamped(start);
+        (new_end > new_start).then_some((new_start, new_end))
+    }
+}
+
+/// How many cues of a subtitle file were moved and how many were dropped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetimeStats {
+    pub cues: usize,
+    pub dropped: usize,
+}
+
+fn srt_time(caps: &Captures, first: usize) -> f64 {
+    let part = |i: usize| caps[first + i].parse::<f64>().unwrap_or(0.0);
+    part(0) * 3600.0 + part(1) * 60.0 + part(2) + part(3) / 1000.0
+}
+
+fn format_srt_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Re-time every cue of a SubRip file. Dropped cues are removed and the rest renumbered.
+pub fn retime_srt(contents: &str, timeline: &Timeline) -> (String, RetimeStats) {
+    let timing = Regex::new(
+        r"^\s*(\d+):(\d{2}):(\d{2})[,.](\d{3})\s*-->\s*(\d+):(\d{2}):(\d{2})[,.](\d{3})(.*)$",
+    )
+    .unwrap();
+    let mut stats = RetimeStats::default();
+    let mut output = String::new();
+    let normalized = contents
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n");
+    for block in normalized.split("\n\n").filter(|b| !b.trim().is_empty()) {
+        let lines: Vec<&str> = block.trim_matches('\n').lines().collect();
+        let Some(timing_line) = lines.iter().position(|l| timing.is_match(l)) else {
+            continue;
+        };
+        let caps = timing.captures(lines[timing_line]).unwrap();
+        stats.cues += 1;
+        let Some((start, end)) = timeline.map_cue(srt_time(&caps, 1), srt_time(&caps, 5)) else {
+            stats.dropped += 1;
+            continue;
+        };
+        output.push_str(&format!(
+            "{}\n{} --> {}{}\n",
+            stats.cues - stats.dropped,
+            format_srt_time(start),
+            format_srt_time(end),
+            &caps[9]
+        ));
+        for line in &lines[timing_line + 1..] {
+            output.push_str(line);
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    (output, stats)
+}
+
+fn ass_time(caps: &Captures, first: usize) -> f64 {
+    let part = |i: usize| caps[first + i].parse::<f64>().unwrap_or(0.0);
+    part(0) * 3600.0 + part(1) * 60.0 + part(2) + part(3) / 100.0
+}
+
+fn format_ass_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// Re-time the `Dialogue` and `Comment` events of an ASS/SSA script; every other line is kept.
+pub fn retime_ass(contents: &str, timeline: &Timeline) -> (String, RetimeStats) {
+    let event = Regex::new(
+        r"^((?:Dialogue|Comment):\s*[^,]*,)(\d+):(\d{2}):(\d{2})\.(\d{2}),(\d+):(\d{2}):(\d{2})\.(\d{2}),(.*)$",
+    )
+    .unwrap();
+    let mut stats = RetimeStats::default();
+    let mut output = String::new();
+    for line in contents.lines() {
+        if let Some(caps) = event.captures(line) {
+            stats.cues += 1;
+            match timeline.map_cue(ass_time(&caps, 2), ass_time(&caps, 6)) {
+                Some((start, end)) => output.push_str(&format!(
+                    "{}{},{},{}",
+                    &caps[1],
+                    format_ass_time(start),
+                    format_ass_time(end),
+                    &caps[10]
+                )),
+                None => {
+                    stats.dropped += 1;
+                    continue;
+                }
+            }
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+    (output, stats)
+}
+
+fn subtitle_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+/// Whether a subtitle file can be re-timed: SubRip or ASS/SSA, judged by its extension.
+pub fn is_supported_subtitle_file(path: &Path) -> bool {
+    matches!(
+        subtitle_extension(path).as_deref(),
+        Some("srt") | Some("ass") | Some("ssa")
+    )
+}
+
+/// Where the re-timed copy of an external subtitle file is written: next to `output` and
+/// named after it, keeping whatever follows the input's name in the subtitle's name, so
+/// `movie.en.srt` becomes `movie.synced.en.srt` for `movie.synced.mkv`.
+pub fn external_subtitle_output(subtitle: &str, input: &str, output: &str) -> String {
+    let file_name = Path::new(subtitle)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let input_stem = Path::new(input)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let suffix = match file_name.strip_prefix(&input_stem) {
+        Some(rest) if !input_stem.is_empty() && rest.starts_with('.') => rest.to_string(),
+        _ => format!(".{}", file_name),
+    };
+    let output = Path::new(output);
+    let output_stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    output
+        .with_file_name(format!("{}{}", output_stem, suffix))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Re-time a SubRip (`.srt`) or ASS/SSA (`.ass`, `.ssa`) file, chosen by its extension.
+pub fn retime_subtitle_file(
+    input: &Path,
+    output: &Path,
+    timeline: &Timeline,
+) -> Result<RetimeStats> {
+    let contents = std::fs::read_to_string(input)?;
+    let (retimed, stats) = match subtitle_extension(input).as_deref() {
+        Some("srt") => retime_srt(&contents, timeline),
+        Some("ass") | Some("ssa") => retime_ass(&contents, timeline),
+        _ => bail!(
+            "Cannot re-time '{}': only SubRip (.srt) and ASS/SSA (.ass, .ssa) subtitles are supported.",
+            input.display()
+        ),
+    };
+    crate::util::write_file_atomic(path_to_str(output)?, retimed.as_bytes())?;
+    Ok(stats)
+}
+
+/// A subtitle stream of a media file.
+#[derive(Debug, Clone)]
+pub struct SubtitleStream {
+    pub index: usize,
+    pub codec: String,
+    /// Dispositions set on the stream, e.g. `default+forced`, or `0` for none
+    pub disposition: String,
+}
+
+impl SubtitleStream {
+    /// Whether the stream is text that can be re-timed (bitmap subtitles such as PGS cannot).
+    pub fn is_text(&self) -> bool {
+        TEXT_SUBTITLE_CODECS.contains(&self.codec.as_str())
+    }
+
+    /// Extension of the file the stream is extracted to for re-timing.
+    fn extract_extension(&self) -> &'static str {
+        match self.codec.as_str() {
+            "ass" | "ssa" => "ass",
+            _ => "srt",
+        }
+    }
+}
+
+/// List the subtitle streams of a media file.
+pub fn list_subtitle_streams(input: &str) -> Result<Vec<SubtitleStream>> {
+    let output = Command::new(ffprobe_executable())
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "s",
+            "-show_entries",
+            "stream=index,codec_name:stream_disposition=default,forced,hearing_impaired",
+            "-of",
+            "csv=p=0",
+            input,
+        ])
+        .output()?;
+    let mut streams = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts: Vec<_> = line.split(',').collect();
+        if parts.len() >= 2
+            && let Ok(index) = parts[0].parse::<usize>()
+        {
+            let flags: Vec<&str> = ["default", "forced", "hearing_impaired"]
+                .iter()
+                .zip(parts[2..].iter())
+                .filter(|(_, value)| **value == "1")
+                .map(|(name, _)| *name)
+                .collect();
+            streams.push(SubtitleStream {
+                index,
+                codec: parts[1].to_string(),
+                disposition: if flags.is_empty() {
+                    "0".to_string()
+                } else {
+                    flags.join("+")
+                },
+            });
+        }
+    }
+    Ok(streams)
+}
+
+/// An embedded subtitle stream replaced by a re-timed file when remuxing.
+#[derive(Debug, Clone)]
+pub struct ReplacedSubtitle {
+    /// Index of the stream in the input (and in the output, which keeps the stream order)
+    pub index: usize,
+    pub path: std::path::PathBuf,
+    /// Encoder for the re-timed stream
+    pub codec: String,
+    pub disposition: String,
+}
+
+/// Extract a text subtitle stream, re-time it and return it ready for the remux.
+pub fn retime_embedded_subtitle(
+    input: &str,
+    stream: &SubtitleStream,
+    timeline: &Timeline,
+    tmpdir: &Path,
+    debug: bool,
+) -> Result<(ReplacedSubtitle, RetimeStats)> {
+    let extension = stream.extract_extension();
+    let extracted = tmpdir.join(format!("subtitle_{}.{}", stream.index, extension));
+    let retimed = tmpdir.join(format!("subtitle_{}_retimed.{}", stream.index, extension));
+    run_ffmpeg(
+        &[
+            "-y",
+            "-i",
+            input,
+            "-map",
+            &format!("0:{}", stream.index),
+            "-c:s",
+            if extension == "ass" { "ass" } else { "srt" },
+            path_to_str(&extracted)?,
+        ],
+        debug,
+    )?;
+    let stats = retime_subtitle_file(&extracted, &retimed, timeline)?;
+    // Matroska takes the extracted formats as they are; MP4 needs its own text codec back
+    let codec = match stream.codec.as_str() {
+        "mov_text" => "mov_text",
+        "webvtt" => "webvtt",
+        _ => "copy",
+    };
+    Ok((
+        ReplacedSubtitle {
+            index: stream.index,
+            path: retimed,
+            codec: codec.to_string(),
+            disposition: stream.disposition.clone(),
+        },
+        stats,
+    ))
+}
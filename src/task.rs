@@ -1,32 +1,228 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::path::Path;
 
-#[derive(Debug, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct Task {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<String>,
-    pub stream: Option<usize>,
+    pub stream: Option<crate::cli::StreamSpecifier>,
+    /// Several audio streams edited with the same delays and splits and remuxed in one pass
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<crate::cli::StreamSpecifier>,
+    /// Language of the audio stream to process, used when no stream is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Text in the title of the audio stream to process, used when no stream is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_title: Option<String>,
     pub initial_delay: Option<f64>,
     #[serde(default)]
     pub splits: Vec<crate::cli::SplitPoint>,
     #[serde(default)]
     pub split_ranges: Vec<crate::cli::SplitRange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ramps: Vec<crate::cli::DelayRamp>,
+    /// Clock drift correction in parts per million applied to the whole track
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift_ppm: Option<f64>,
+    /// Length ratio the whole track is stretched by, converted to `drift_ppm`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stretch: Option<f64>,
+    /// Frame rate used for values given in frames on the command line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f64>,
+    /// Two measured delays the drift and initial delay are derived from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sync_points: Vec<crate::cli::SyncPoint>,
     pub bitrate: Option<String>,
     pub silence_threshold: Option<f64>,
+    /// Margin (dB) above the measured noise floor used as the silence threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_threshold: Option<f64>,
+    /// Highest true peak (dBFS) allowed at a split point chosen from a range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_peak: Option<f64>,
+    /// If true, split points chosen from a range stay out of speech
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avoid_speech: Option<bool>,
+    /// If false, split points chosen from a range are not moved to the nearest zero crossing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zero_crossing: Option<bool>,
+    /// Strategy that picks the split point inside a range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detector: Option<crate::cli::DetectorKind>,
+    /// Program run by the external detector
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detector_command: Option<String>,
+    /// Commands run before and after the job and after each stage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<crate::hooks::TaskHooks>,
+    /// Media server asked to refresh the output after a successful run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_server: Option<crate::media_server::MediaServer>,
+    /// Extra outputs with the corrected track shifted by a constant offset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profile_outputs: Vec<crate::cli::ProfileOutput>,
     /// If true, fit the edited audio stream to the original length (trim or pad with silence at the end as needed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fit_length: Option<bool>,
+    /// If true, fill a gap between the video start and the later audio start with silence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pad_head: Option<bool>,
+    /// If true, cut audio that runs past the end of the video
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trim_tail: Option<bool>,
+    /// Maximum number of threads per ffmpeg process
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ffmpeg_threads: Option<usize>,
+    /// Number of segments split and delayed at the same time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    /// If true, produce bit-identical audio on every run of the same task
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deterministic: Option<bool>,
+    /// If true, edit the track through one temporary file per segment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment_files: Option<bool>,
+    /// Crossfade at every join in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crossfade: Option<f64>,
+    /// Codec of the temporary files the track is edited in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intermediate: Option<crate::cli::IntermediateFormat>,
+    /// Minutes without ffmpeg output before a process counts as stalled (0 disables the check)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stall_timeout: Option<f64>,
+    /// If true, stop the run when an ffmpeg process stalls instead of only warning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abort_on_stall: Option<bool>,
+    /// ffmpeg executable to use instead of the one from PATH
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ffmpeg_path: Option<String>,
+    /// ffprobe executable to use instead of the one from PATH
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ffprobe_path: Option<String>,
+    /// Directory for temp files instead of the system temp dir
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_dir: Option<String>,
+    /// If true, switch the output to Matroska when its container cannot hold every input stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_mkv: Option<bool>,
+    /// If true, refresh container duration and track statistics after remuxing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_stats: Option<bool>,
+    /// If true, record the applied edits as track tags or in a sidecar file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_history: Option<bool>,
+    /// If true, keep the original audio stream next to the corrected one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_original: Option<bool>,
+    /// If true, the initial delay is applied to the video streams instead of the audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_video: Option<bool>,
+    /// Independent plans for several streams of the input, each with its own stream, delays and
+    /// splits, written in one remux. Options not set in a plan come from the task itself.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stream_plans: Vec<Task>,
+    /// Title of the corrected stream when the original is kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corrected_title: Option<String>,
+    /// Title template of the corrected stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// ffmpeg -avoid_negative_ts mode of the remux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avoid_negative_ts: Option<crate::cli::AvoidNegativeTs>,
+    /// ffmpeg -muxdelay of the remux, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muxdelay: Option<f64>,
+    /// ffmpeg -muxpreload of the remux, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muxpreload: Option<f64>,
+    /// If true, keep the input timestamps in the remux (ffmpeg -copyts)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copyts: Option<bool>,
+    /// Embedded subtitle streams re-timed with the delay plan; empty shifts every text subtitle stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_subtitles: Option<Vec<usize>>,
+    /// If true, move the chapters with the delay plan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shift_chapters: Option<bool>,
+    /// External subtitle files re-timed with the delay plan and written next to the output
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtitles: Vec<String>,
+    /// If true, fix swapped or polarity-inverted channels before editing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix_channels: Option<bool>,
+    /// Reference media file used to derive the initial delay from matching transients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_stream: Option<crate::cli::StreamSpecifier>,
+    /// Audio stream of the input whose first onset the target is lined up with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_onset: Option<crate::cli::StreamSpecifier>,
+    /// Measured offsets (in milliseconds) below this count as already in sync
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_sync_threshold: Option<f64>,
+    /// Lowest confidence of an automatic suggestion that is applied without review
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f64>,
 }
 
 impl Task {
+    /// Load a task file as JSON, YAML (`.yaml`, `.yml`) or TOML (`.toml`), chosen by its extension.
     pub fn load(path: Option<&str>) -> anyhow::Result<Option<Self>> {
         if let Some(path) = path {
-            let contents = std::fs::read_to_string(path)?;
-            let task: Task = serde_json::from_str(&contents)?;
-            Ok(Some(task))
+            Ok(Some(read_task_file(path)?))
         } else {
             Ok(None)
         }
     }
+
+    /// Serialize the task in the format of `path` (see `load`).
+    pub fn to_file_string(&self, path: &str) -> anyhow::Result<String> {
+        Ok(match TaskFormat::of(path) {
+            TaskFormat::Json => serde_json::to_string_pretty(self)?,
+            TaskFormat::Yaml => serde_yaml::to_string(self)?,
+            TaskFormat::Toml => toml::to_string_pretty(self)?,
+        })
+    }
+}
+
+/// File formats accepted for task files and season templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl TaskFormat {
+    /// Format of a file by its extension; anything unknown is read as JSON.
+    fn of(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("yaml") | Some("yml") => TaskFormat::Yaml,
+            Some("toml") => TaskFormat::Toml,
+            _ => TaskFormat::Json,
+        }
+    }
+}
+
+/// Read a task file (or anything with the same layout) in the format of its extension.
+pub fn read_task_file<T: DeserializeOwned>(path: &str) -> anyhow::Result<T> {
+    let contents = std::fs::read_to_string(path)?;
+    let parsed = match TaskFormat::of(path) {
+        TaskFormat::Json => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+        TaskFormat::Yaml => serde_yaml::from_str(&contents).map_err(anyhow::Error::from),
+        TaskFormat::Toml => toml::from_str(&contents).map_err(anyhow::Error::from),
+    };
+    parsed.map_err(|e| anyhow::anyhow!("Could not read task file '{}': {}", path, e))
 }
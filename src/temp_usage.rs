@@ -0,0 +1,118 @@
+use comfy_table::{Table, presets::UTF8_FULL};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::i18n::{Msg, t, tf};
+use crate::progress::Stage;
+
+/// Total size in bytes of all files below `path`. Missing or unreadable entries count as empty.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Format a byte count with a binary unit (e.g. `1.42 GiB`).
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{:.2} {}", value, unit)
+}
+
+/// Tracks the space used by the temp dir after each stage and the peak seen during the run.
+pub struct TempUsage {
+    dir: PathBuf,
+    peak: Cell<u64>,
+    stages: RefCell<Vec<(Stage, u64)>>,
+}
+
+impl TempUsage {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            peak: Cell::new(0),
+            stages: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Measure the temp dir now, updating the peak. Called within long stages to catch
+    /// files that only exist for part of the stage.
+    pub fn sample(&self) -> u64 {
+        let size = dir_size(&self.dir);
+        self.peak.set(self.peak.get().max(size));
+        size
+    }
+
+    /// Measure the temp dir after `stage` finished and print how much it holds.
+    pub fn record(&self, stage: Stage) {
+        let size = self.sample();
+        let previous = self.stages.borrow().last().map(|(_, s)| *s).unwrap_or(0);
+        self.stages.borrow_mut().push((stage, size));
+        let change = format!(
+            "{}{}",
+            if size >= previous { "+" } else { "-" },
+            format_bytes(size.abs_diff(previous))
+        );
+        info!(
+            "  💾 {}",
+            tf(
+                Msg::TempSpaceAfter,
+                &[
+                    ("stage", stage.label().to_string()),
+                    ("size", format_bytes(size)),
+                    ("change", change)
+                ]
+            )
+        );
+    }
+
+    pub fn peak(&self) -> u64 {
+        self.peak.get()
+    }
+
+    /// Table of the space in use after every recorded stage, with the change per stage and the peak.
+    pub fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).set_header(vec![
+            t(Msg::HeaderStage),
+            t(Msg::HeaderTempSpace),
+            t(Msg::HeaderChange),
+        ]);
+        let mut previous = 0;
+        for (stage, size) in self.stages.borrow().iter() {
+            let sign = if *size >= previous { "+" } else { "-" };
+            table.add_row(vec![
+                stage.label().to_string(),
+                format_bytes(*size),
+                format!("{}{}", sign, format_bytes(size.abs_diff(previous))),
+            ]);
+            previous = *size;
+        }
+        table.add_row(vec![
+            "Peak".to_string(),
+            format_bytes(self.peak()),
+            String::new(),
+        ]);
+        table
+    }
+}
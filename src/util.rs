@@ -1,7 +1,282 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the directory for temp files when none is configured.
+pub const TEMP_DIR_ENV: &str = "SYNC_NUDGER_TEMP_DIR";
 
 /// Helper to convert a Path to &str, returning an error if not valid UTF-8.
 pub fn path_to_str(path: &Path) -> anyhow::Result<&str> {
     path.to_str()
         .ok_or_else(|| anyhow::anyhow!("Invalid path (not UTF-8)"))
 }
+
+/// Resolve symlinks and relative components of a path. A file that does not exist yet
+/// is resolved through its parent directory.
+fn resolve_path(path: &Path) -> Option<PathBuf> {
+    if let Ok(resolved) = std::fs::canonicalize(path) {
+        return Some(resolved);
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(std::fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
+
+/// Whether two paths name the same file, across symlinks, hard links, relative vs absolute
+/// paths and case-insensitive filesystems.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    // Existing files are compared by identity, which also covers case-insensitive filesystems
+    #[cfg(unix)]
+    if let (Ok(meta_a), Ok(meta_b)) = (std::fs::metadata(a), std::fs::metadata(b)) {
+        use std::os::unix::fs::MetadataExt;
+        return meta_a.dev() == meta_b.dev() && meta_a.ino() == meta_b.ino();
+    }
+    match (resolve_path(a), resolve_path(b)) {
+        (Some(a), Some(b)) if cfg!(windows) => a
+            .to_string_lossy()
+            .eq_ignore_ascii_case(&b.to_string_lossy()),
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Fail if writing `target` would overwrite one of the `sources` (label, path) the run reads from.
+pub fn ensure_not_overwriting(
+    target: &str,
+    what: &str,
+    sources: &[(&str, &str)],
+) -> anyhow::Result<()> {
+    for (label, source) in sources {
+        if same_file(Path::new(target), Path::new(source)) {
+            anyhow::bail!(
+                "The {} '{}' is the same file as the {} '{}'. Choose a different path.",
+                what,
+                target,
+                label,
+                source
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A random value for unique file names, different on every call and in every process.
+fn random_suffix() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
+    }
+    hasher.finish()
+}
+
+/// The directory temp dirs are created in: `configured` (--temp-dir or the task's `temp_dir`),
+/// else `$SYNC_NUDGER_TEMP_DIR`, else the system temp dir.
+pub fn temp_root(configured: Option<&str>) -> PathBuf {
+    configured
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var(TEMP_DIR_ENV)
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        })
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Like `temp_root`, but fails if the directory does not exist or is not writable.
+pub fn usable_temp_root(configured: Option<&str>) -> anyhow::Result<PathBuf> {
+    let root = temp_root(configured);
+    check_writable_dir(&root).map_err(|e| anyhow::anyhow!("Unusable temp dir: {}", e))?;
+    Ok(root)
+}
+
+/// Make sure a directory exists and files can be created in it.
+pub fn check_writable_dir(dir: &Path) -> anyhow::Result<()> {
+    if !dir.exists() {
+        anyhow::bail!("'{}' does not exist.", dir.display());
+    }
+    if !dir.is_dir() {
+        anyhow::bail!("'{}' is not a directory.", dir.display());
+    }
+    let probe = dir.join(format!(
+        ".sync_nudger_write_test_{}_{:016x}",
+        std::process::id(),
+        random_suffix()
+    ));
+    std::fs::write(&probe, b"")
+        .map_err(|e| anyhow::anyhow!("'{}' is not writable: {}", dir.display(), e))?;
+    std::fs::remove_file(&probe)?;
+    Ok(())
+}
+
+/// Create a new, empty temp dir for one run in `root`. The name holds the PID and a random
+/// component, and creation fails rather than reusing a dir, so concurrent instances never share
+/// one.
+pub fn create_temp_dir(root: &Path) -> std::io::Result<PathBuf> {
+    loop {
+        let dir = root.join(format!(
+            "sync_nudger_{}_{:016x}",
+            std::process::id(),
+            random_suffix()
+        ));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Create a new, empty temp dir in `root` with a predictable name for a deterministic run: the
+/// dirs of a process are numbered, starting at the first free number. The name holds the PID like that
+/// of `create_temp_dir`, so other instances can tell the run is still in progress.
+pub fn create_numbered_temp_dir(root: &Path) -> std::io::Result<PathBuf> {
+    for n in 1.. {
+        let dir = root.join(format!("sync_nudger_{}_run_{:04}", std::process::id(), n));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+/// Move a directory with everything in it. Falls back to copying when the target is on
+/// another filesystem.
+pub fn move_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to)?;
+    std::fs::remove_dir_all(from)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// A uniquely named hidden sibling of `target` to write it through. It keeps the extension of
+/// `target`, so ffmpeg picks the same muxer for it.
+fn staging_sibling(target: &Path) -> PathBuf {
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let extension = target
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    target.with_file_name(format!(
+        ".{}.{}_{:016x}.tmp{}",
+        file_name,
+        std::process::id(),
+        random_suffix(),
+        extension
+    ))
+}
+
+/// Write a file through a uniquely named sibling and rename it into place, so readers and
+/// other instances writing the same path never see a partially written file.
+pub fn write_file_atomic(path: &str, contents: &[u8]) -> std::io::Result<()> {
+    let target = Path::new(path);
+    let staging = staging_sibling(target);
+    std::fs::write(&staging, contents)?;
+    std::fs::rename(&staging, target).inspect_err(|_| {
+        let _ = std::fs::remove_file(&staging);
+    })
+}
+
+/// An output that is written under a hidden sibling name and renamed into place once it has
+/// been checked, so a crash or a failed mux never leaves a partial file at the destination for
+/// media scanners to pick up. Dropped without [`StagedFile::commit`], the sibling is removed.
+pub struct StagedFile {
+    target: PathBuf,
+    staging: String,
+    committed: bool,
+}
+
+impl StagedFile {
+    pub fn new(target: &str) -> anyhow::Result<Self> {
+        let staging = staging_sibling(Path::new(target));
+        Ok(Self {
+            target: PathBuf::from(target),
+            staging: path_to_str(&staging)?.to_string(),
+            committed: false,
+        })
+    }
+
+    /// Where the output is written until it is committed.
+    pub fn path(&self) -> &str {
+        &self.staging
+    }
+
+    /// Move the finished output to its destination, replacing any file there.
+    pub fn commit(mut self) -> std::io::Result<()> {
+        std::fs::rename(&self.staging, &self.target)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.staging);
+        }
+    }
+}
+
+/// Fill the `{name}` placeholders of a template with `values` (name, value). `{{` and `}}`
+/// stand for literal braces; an unknown or unclosed placeholder is an error.
+pub fn render_template(template: &str, values: &[(&str, String)]) -> anyhow::Result<String> {
+    let mut rendered = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                rendered.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                rendered.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            anyhow::bail!("Unclosed placeholder '{{{}' in '{}'", name, template)
+                        }
+                    }
+                }
+                match values.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) => rendered.push_str(value),
+                    None => anyhow::bail!(
+                        "Unknown placeholder '{{{}}}' in '{}'. Available: {}",
+                        name,
+                        template,
+                        values
+                            .iter()
+                            .map(|(key, _)| format!("{{{}}}", key))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                }
+            }
+            _ => rendered.push(c),
+        }
+    }
+    Ok(rendered)
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+
+use crate::analysis::decode_pcm_mono_at;
+
+/// Audio is decoded at this rate for speech detection; it covers the speech band and keeps the
+/// zero-crossing rates comparable between sources.
+const VAD_SAMPLE_RATE: u32 = 16_000;
+/// Length of one analysis frame in seconds.
+const FRAME_SECONDS: f64 = 0.02;
+/// A frame must be this many dB above the quietest frames of the range to count as voiced.
+const SPEECH_ABOVE_FLOOR_DB: f64 = 12.0;
+/// Frames quieter than this (dBFS) never count as speech.
+const SPEECH_MIN_LEVEL_DB: f64 = -55.0;
+/// Voiced speech crosses zero in fewer than this share of samples; hiss and cymbals cross more.
+const SPEECH_MAX_ZCR: f64 = 0.25;
+/// Voiced frames in a row needed to start a speech span, so single thumps are not speech.
+const MIN_SPEECH_FRAMES: usize = 3;
+/// Pauses shorter than this between speech spans (breaths, short gaps between words) belong
+/// to the speech.
+const SPEECH_HANGOVER_SECONDS: f64 = 0.35;
+/// Margin added around every speech span in seconds.
+const SPEECH_MARGIN_SECONDS: f64 = 0.1;
+
+/// Find the spans of `audio_path` between `start` and `end` that sound like speech, with an
+/// energy and zero-crossing heuristic: frames well above the quietest frames of the range with
+/// a voiced zero-crossing rate, joined across breath-length pauses. Times are in seconds from
+/// the start of the file.
+pub fn speech_spans(audio_path: &str, start: f64, end: f64) -> Result<Vec<(f64, f64)>> {
+    let samples = decode_pcm_mono_at(audio_path, "0:a:0", start, end - start, VAD_SAMPLE_RATE)?;
+    let frame_len = (FRAME_SECONDS * VAD_SAMPLE_RATE as f64) as usize;
+    let frames: Vec<(f64, f64)> = samples
+        .chunks_exact(frame_len)
+        .map(|frame| {
+            let energy = frame.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / frame_len as f64;
+            let crossings = frame
+                .windows(2)
+                .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+                .count();
+            (
+                10.0 * energy.max(1e-12).log10(),
+                crossings as f64 / frame_len as f64,
+            )
+        })
+        .collect();
+    if frames.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // The tenth percentile of the frame levels stands in for the noise floor of the range
+    let mut levels: Vec<f64> = frames.iter().map(|(level, _)| *level).collect();
+    levels.sort_by(|a, b| a.total_cmp(b));
+    let floor = levels[levels.len() / 10];
+    let voiced: Vec<bool> = frames
+        .iter()
+        .map(|(level, zcr)| {
+            *level > floor + SPEECH_ABOVE_FLOOR_DB
+                && *level > SPEECH_MIN_LEVEL_DB
+                && *zcr < SPEECH_MAX_ZCR
+        })
+        .collect();
+
+    let frame_time = |i: usize| start + i as f64 * FRAME_SECONDS;
+    let mut spans: Vec<(f64, f64)> = Vec::new();
+    let mut run_start = None;
+    for (i, is_voiced) in voiced.iter().chain(std::iter::once(&false)).enumerate() {
+        match (*is_voiced, run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(first)) => {
+                run_start = None;
+                if i - first < MIN_SPEECH_FRAMES {
+                    continue;
+                }
+                let (span_start, span_end) = (frame_time(first), frame_time(i));
+                match spans.last_mut() {
+                    Some(last) if span_start - last.1 < SPEECH_HANGOVER_SECONDS => {
+                        last.1 = span_end
+                    }
+                    _ => spans.push((span_start, span_end)),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(spans
+        .into_iter()
+        .map(|(s, e)| (s - SPEECH_MARGIN_SECONDS, e + SPEECH_MARGIN_SECONDS))
+        .collect())
+}
+
+/// Whether `time` falls inside one of `spans`.
+pub fn in_speech(spans: &[(f64, f64)], time: f64) -> bool {
+    spans
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&time))
+}
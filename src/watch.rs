@@ -0,0 +1,133 @@
+use anyhow::{Result, anyhow, bail};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::season::is_episode_file;
+
+/// Extension of the optional file next to a watched media file that holds its priority.
+pub const PRIORITY_EXTENSION: &str = "priority";
+
+/// Polls a directory for media files that have finished arriving.
+pub struct FolderWatcher {
+    dir: PathBuf,
+    /// Size of every pending file at the previous poll
+    sizes: HashMap<PathBuf, u64>,
+    /// Files already handed out, which are not returned again even if they stay in the directory
+    handled: HashSet<PathBuf>,
+}
+
+impl FolderWatcher {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            sizes: HashMap::new(),
+            handled: HashSet::new(),
+        }
+    }
+
+    /// List the new media files, sorted by file name. With `settle`, a file is only returned once
+    /// its size is the same as at the previous poll, so files still being copied in wait for a
+    /// later poll; without it, every new file is returned right away.
+    pub fn poll(&mut self, settle: bool) -> Result<Vec<PathBuf>> {
+        let mut sizes = HashMap::new();
+        let mut ready = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !is_episode_file(&path) || self.handled.contains(&path) {
+                continue;
+            }
+            // Gone again since it was listed, e.g. a temp file or one moved out by another tool
+            let Ok(meta) = fs::metadata(&path) else {
+                continue;
+            };
+            let size = meta.len();
+            if !settle || (size > 0 && self.sizes.get(&path) == Some(&size)) {
+                ready.push(path);
+            } else {
+                sizes.insert(path, size);
+            }
+        }
+        self.sizes = sizes;
+        ready.sort();
+        self.handled.extend(ready.iter().cloned());
+        Ok(ready)
+    }
+}
+
+/// Priority of a watched file, read from `<name>.priority` next to it: an integer, higher runs
+/// first. Files without one have priority 0.
+pub fn job_priority(path: &Path) -> Result<i64> {
+    let priority_path = path.with_extension(PRIORITY_EXTENSION);
+    match fs::read_to_string(&priority_path) {
+        Ok(contents) => contents.trim().parse().map_err(|_| {
+            anyhow!(
+                "'{}' must hold an integer priority (got '{}').",
+                priority_path.display(),
+                contents.trim()
+            )
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Files waiting to be processed, highest priority first and in arrival order within a priority.
+#[derive(Debug, Default)]
+pub struct JobQueue {
+    jobs: BinaryHeap<(i64, Reverse<u64>, PathBuf)>,
+    next: u64,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, path: PathBuf, priority: i64) {
+        self.jobs.push((priority, Reverse(self.next), path));
+        self.next += 1;
+    }
+
+    /// The next file to process and its priority.
+    pub fn pop(&mut self) -> Option<(PathBuf, i64)> {
+        self.jobs.pop().map(|(priority, _, path)| (path, priority))
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
+
+/// Move a file into a directory, creating the directory if needed. Falls back to copying when
+/// the directory is on another filesystem. An existing file of the same name is never replaced.
+pub fn move_into(path: &Path, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let target = dir.join(path.file_name().unwrap_or_default());
+    if target.exists() {
+        bail!("'{}' already exists.", target.display());
+    }
+    if fs::rename(path, &target).is_err() {
+        fs::copy(path, &target)?;
+        fs::remove_file(path)?;
+    }
+    Ok(target)
+}
+
+/// Hand a file to a watched directory with the given priority, for programs that queue jobs
+/// instead of copying files in by hand. The priority file is written before the file arrives,
+/// so the watcher never sees the file without it.
+pub fn submit(path: &Path, dir: &Path, priority: i64) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = Path::new(path.file_name().unwrap_or_default());
+    let priority_path = dir.join(name.with_extension(PRIORITY_EXTENSION));
+    fs::write(&priority_path, format!("{}\n", priority))?;
+    move_into(path, dir).inspect_err(|_| {
+        let _ = fs::remove_file(&priority_path);
+    })
+}